@@ -0,0 +1,40 @@
+//! Compiles the built-in XDP program of the AF_XDP backend (`prog.c`) into `OUT_DIR`,
+//! where `src/af_xdp/wrapper.rs` embeds it from.
+//!
+//! Without a working clang (e.g. on docs.rs) the build goes on with an empty object and
+//! a warning: sockets using the built-in program then fail to open, while those loading
+//! an `XdpProgram::Object` keep working.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=prog.c");
+    println!("cargo:rerun-if-env-changed=CLANG");
+    if env::var_os("CARGO_FEATURE_AF_XDP").is_none() {
+        return;
+    }
+
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("prog.o");
+    let clang = env::var("CLANG").unwrap_or_else(|_| "clang".to_string());
+    let mut cmd = Command::new(&clang);
+    cmd.args(["-O2", "-g", "-target", "bpf"]);
+    // The libbpf headers (`bpf/bpf_helpers.h`) installed by libbpf-sys.
+    if let Some(include) = env::var_os("DEP_BPF_INCLUDE") {
+        cmd.arg("-I").arg(include);
+    }
+    cmd.args(["-c", "prog.c", "-o"]).arg(&out);
+
+    let error = match cmd.status() {
+        Ok(status) if status.success() => return,
+        Ok(status) => format!("{clang} failed to compile prog.c ({status})"),
+        Err(e) => format!("cannot run {clang} to compile prog.c: {e}"),
+    };
+    println!(
+        "cargo:warning={error}; the built-in XDP program is unavailable, set CLANG to a \
+         clang with the BPF target to build it"
+    );
+    fs::write(&out, []).expect("cannot write prog.o");
+}
@@ -9,6 +9,8 @@
 #include <rte_mbuf.h>
 #include <rte_ether.h>
 #include <rte_bus_vdev.h>
+#include <rte_flow.h>
+#include <netinet/in.h>
 //#include <dev_driver.h>
 //#include <bus_driver.h>
 //#include <rte_dev.h>
@@ -76,6 +78,120 @@ int rust_rte_eth_tx_queue_count(uint16_t port_id, uint16_t queue_id)
     return rte_eth_tx_queue_count(port_id, queue_id);
 }
 
+int rust_rte_eth_link_info(uint16_t port_id, uint32_t *speed, int *full_duplex, int *up)
+{
+    struct rte_eth_link link;
+    int ret = rte_eth_link_get_nowait(port_id, &link);
+    if (ret < 0)
+        return ret;
+    *speed = link.link_speed;
+    *full_duplex = link.link_duplex == RTE_ETH_LINK_FULL_DUPLEX;
+    *up = link.link_status == RTE_ETH_LINK_UP;
+    return 0;
+}
+
+int rust_rte_eth_macaddr_get(uint16_t port_id, uint8_t *mac)
+{
+    struct rte_ether_addr addr;
+    int ret = rte_eth_macaddr_get(port_id, &addr);
+    if (ret < 0)
+        return ret;
+    memcpy(mac, addr.addr_bytes, RTE_ETHER_ADDR_LEN);
+    return 0;
+}
+
+int rust_rte_eth_dev_set_mc_addr_list(uint16_t port_id, const uint8_t *macs, uint32_t nb_mc_addr)
+{
+    struct rte_ether_addr addrs[nb_mc_addr > 0 ? nb_mc_addr : 1];
+    for (uint32_t i = 0; i < nb_mc_addr; i++)
+        memcpy(addrs[i].addr_bytes, macs + i * RTE_ETHER_ADDR_LEN, RTE_ETHER_ADDR_LEN);
+    return rte_eth_dev_set_mc_addr_list(port_id, addrs, nb_mc_addr);
+}
+
+// Steers the packets matching the given fields to `queue`: NULL addresses, zero ports
+// and a zero protocol match anything.
+int rust_rte_flow_steer(uint16_t port_id, uint16_t queue, int ipv6, uint8_t proto,
+                        const uint8_t *src, const uint8_t *dst, uint16_t src_port,
+                        uint16_t dst_port, struct rte_flow **flow)
+{
+    struct rte_flow_attr attr = { .ingress = 1 };
+    struct rte_flow_item_ipv4 ip4 = { 0 }, ip4_mask = { 0 };
+    struct rte_flow_item_ipv6 ip6 = { 0 }, ip6_mask = { 0 };
+    struct rte_flow_item_tcp tcp = { 0 }, tcp_mask = { 0 };
+    struct rte_flow_item_udp udp = { 0 }, udp_mask = { 0 };
+    struct rte_flow_item pattern[4];
+    struct rte_flow_error error;
+    memset(pattern, 0, sizeof(pattern));
+
+    pattern[0].type = RTE_FLOW_ITEM_TYPE_ETH;
+    if (ipv6) {
+        if (src) {
+            memcpy(&ip6.hdr.src_addr, src, 16);
+            memset(&ip6_mask.hdr.src_addr, 0xff, 16);
+        }
+        if (dst) {
+            memcpy(&ip6.hdr.dst_addr, dst, 16);
+            memset(&ip6_mask.hdr.dst_addr, 0xff, 16);
+        }
+        ip6.hdr.proto = proto;
+        ip6_mask.hdr.proto = proto ? 0xff : 0;
+        pattern[1].type = RTE_FLOW_ITEM_TYPE_IPV6;
+        pattern[1].spec = &ip6;
+        pattern[1].mask = &ip6_mask;
+    } else {
+        if (src) {
+            memcpy(&ip4.hdr.src_addr, src, 4);
+            ip4_mask.hdr.src_addr = UINT32_MAX;
+        }
+        if (dst) {
+            memcpy(&ip4.hdr.dst_addr, dst, 4);
+            ip4_mask.hdr.dst_addr = UINT32_MAX;
+        }
+        ip4.hdr.next_proto_id = proto;
+        ip4_mask.hdr.next_proto_id = proto ? 0xff : 0;
+        pattern[1].type = RTE_FLOW_ITEM_TYPE_IPV4;
+        pattern[1].spec = &ip4;
+        pattern[1].mask = &ip4_mask;
+    }
+    switch (proto) {
+    case IPPROTO_TCP:
+        tcp.hdr.src_port = rte_cpu_to_be_16(src_port);
+        tcp.hdr.dst_port = rte_cpu_to_be_16(dst_port);
+        tcp_mask.hdr.src_port = src_port ? UINT16_MAX : 0;
+        tcp_mask.hdr.dst_port = dst_port ? UINT16_MAX : 0;
+        pattern[2].type = RTE_FLOW_ITEM_TYPE_TCP;
+        pattern[2].spec = &tcp;
+        pattern[2].mask = &tcp_mask;
+        break;
+    case IPPROTO_UDP:
+        udp.hdr.src_port = rte_cpu_to_be_16(src_port);
+        udp.hdr.dst_port = rte_cpu_to_be_16(dst_port);
+        udp_mask.hdr.src_port = src_port ? UINT16_MAX : 0;
+        udp_mask.hdr.dst_port = dst_port ? UINT16_MAX : 0;
+        pattern[2].type = RTE_FLOW_ITEM_TYPE_UDP;
+        pattern[2].spec = &udp;
+        pattern[2].mask = &udp_mask;
+        break;
+    default:
+        pattern[2].type = RTE_FLOW_ITEM_TYPE_END;
+    }
+    pattern[3].type = RTE_FLOW_ITEM_TYPE_END;
+
+    struct rte_flow_action_queue action_queue = { .index = queue };
+    struct rte_flow_action actions[] = {
+        { .type = RTE_FLOW_ACTION_TYPE_QUEUE, .conf = &action_queue },
+        { .type = RTE_FLOW_ACTION_TYPE_END },
+    };
+    *flow = rte_flow_create(port_id, &attr, pattern, actions, &error);
+    return *flow ? 0 : -rte_errno;
+}
+
+int rust_rte_flow_destroy(uint16_t port_id, struct rte_flow *flow)
+{
+    struct rte_flow_error error;
+    return rte_flow_destroy(port_id, flow, &error);
+}
+
 // rte_mbuf
 
 void rust_rte_mbuf_prefetch_part1(struct rte_mbuf *m)
@@ -473,6 +589,11 @@ void *rust_rte_pktmbuf_mtod(struct rte_mbuf *m)
     return rte_pktmbuf_mtod(m, void *);
 }
 
+struct rte_mbuf *rust_rte_pktmbuf_next(struct rte_mbuf *m)
+{
+    return m->next;
+}
+
 
 //void rust_rte_mempool_put_bulk(struct rte_mbuf **mbs, uint32_t count)
 //{
@@ -34,7 +34,7 @@ fn bench_flume_sp() {
     let start = Instant::now();
     let mut count = 0;
     while count < ITERS {
-        if let Ok(_) = rx.recv() {
+        if rx.recv().is_ok() {
             count += 1;
         }
     }
@@ -62,7 +62,7 @@ fn bench_flume_mp(threads: usize) {
     let start = Instant::now();
     let mut count = 0;
     while count < total_iters {
-        if let Ok(_) = rx.recv() {
+        if rx.recv().is_ok() {
             count += 1;
         }
     }
@@ -85,7 +85,7 @@ fn bench_crossbeam_sp() {
     let t = thread::spawn(move || {
         for i in 0..ITERS {
             loop {
-                if let Ok(_) = q_prod.push(new_token(i as usize)) {
+                if q_prod.push(new_token(i as usize)).is_ok() {
                     break;
                 }
                 // Backoff or yield?
@@ -97,7 +97,7 @@ fn bench_crossbeam_sp() {
     let start = Instant::now();
     let mut count = 0;
     while count < ITERS {
-        if let Some(_) = q.pop() {
+        if q.pop().is_some() {
             count += 1;
         } else {
             thread::yield_now();
@@ -120,7 +120,7 @@ fn bench_crossbeam_mp(threads: usize) {
         handles.push(thread::spawn(move || {
             for i in 0..iter_per_thread {
                 loop {
-                    if let Ok(_) = q_prod.push(new_token(i as usize)) {
+                    if q_prod.push(new_token(i as usize)).is_ok() {
                         break;
                     }
                     thread::yield_now();
@@ -132,7 +132,7 @@ fn bench_crossbeam_mp(threads: usize) {
     let start = Instant::now();
     let mut count = 0;
     while count < total_iters {
-        if let Some(_) = q.pop() {
+        if q.pop().is_some() {
             count += 1;
         } else {
             thread::yield_now();
@@ -169,7 +169,7 @@ fn bench_mpsc_sp() {
     let start = Instant::now();
     let mut count = 0;
     while count < ITERS {
-        if let Some(_) = cons.pop() {
+        if cons.pop().is_some() {
             count += 1;
         } else {
             thread::yield_now();
@@ -200,7 +200,7 @@ fn bench_mpsc_mp(threads: usize) {
     let start = Instant::now();
     let mut count = 0;
     while count < total_iters {
-        if let Some(_) = cons.pop() {
+        if cons.pop().is_some() {
             count += 1;
         } else {
             thread::yield_now();
@@ -234,7 +234,7 @@ fn bench_std_mpsc_sp() {
     let start = Instant::now();
     let mut count = 0;
     while count < ITERS {
-        if let Ok(_) = rx.recv() {
+        if rx.recv().is_ok() {
             count += 1;
         }
     }
@@ -262,7 +262,7 @@ fn bench_std_mpsc_mp(threads: usize) {
     let start = Instant::now();
     let mut count = 0;
     while count < total_iters {
-        if let Ok(_) = rx.recv() {
+        if rx.recv().is_ok() {
             count += 1;
         }
     }
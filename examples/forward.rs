@@ -13,7 +13,7 @@ use std::time::Duration;
 
 #[cfg(feature = "af-xdp")]
 use nethuns_rs::af_xdp;
-use nethuns_rs::api::Socket;
+use nethuns_rs::api::{self, Socket};
 #[cfg(feature = "netmap")]
 use nethuns_rs::netmap;
 #[cfg(feature = "pcap")]
@@ -118,6 +118,14 @@ pub fn main() -> Result<()> {
         Framework::Netmap(netmap_args) => {
             let flags = netmap::NetmapFlags {
                 extra_buf: netmap_args.extra_buf,
+                promiscuous: false,
+                allmulti: false,
+                flush_policy: nethuns_rs::api::FlushPolicy::Manual,
+                tx_rate: nethuns_rs::api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                snaplen: None,
+                timestamp_source: nethuns_rs::api::TimestampSource::Realtime,
             };
             run_forwarder::<netmap::Sock>(flags, &args, term)
         }
@@ -125,11 +133,34 @@ pub fn main() -> Result<()> {
         Framework::AfXdp(af_xdp_args) => {
             let flags = af_xdp::AfXdpFlags {
                 bind_flags: af_xdp_args.bind_flags,
+                zero_copy: false,
+                need_wakeup: true,
+                busy_poll: None,
                 xdp_flags: af_xdp_args.xdp_flags,
+                xdp_program: af_xdp::XdpProgram::Builtin,
+                hw_offload: false,
                 num_frames: 4096,
                 frame_size: 2048,
+                hugepages: false,
+                frame_headroom: 0,
+                unaligned_chunks: false,
                 tx_size: 2048,
+                tx_frames: None,
                 rx_size: 2048,
+                rx_metadata: false,
+                rx_timestamp: false,
+                rx_vlan: false,
+                promiscuous: false,
+                allmulti: false,
+                flush_policy: nethuns_rs::api::FlushPolicy::Manual,
+                refill_policy: af_xdp::RefillPolicy::OnEmpty,
+                completion_batch: 64,
+                tx_rate: nethuns_rs::api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                snaplen: None,
+                timestamp_source: nethuns_rs::api::TimestampSource::None,
+                fcs: None,
             };
             run_forwarder::<af_xdp::Sock>(flags, &args, term)
         }
@@ -138,11 +169,27 @@ pub fn main() -> Result<()> {
             let flags = pcap::PcapFlags {
                 snaplen: pcap_args.snaplen,
                 promiscuous: pcap_args.promiscuous,
+                allmulti: false,
                 timeout_ms: pcap_args.timeout_ms,
                 immediate: pcap_args.immediate,
                 filter: pcap_args.filter.clone(),
                 buffer_size: pcap_args.buffer_size,
                 buffer_count: pcap_args.buffer_count,
+                pool_policy: pcap::PoolPolicy::default(),
+                direction: nethuns_rs::api::Direction::In,
+                hw_timestamps: false,
+                timestamp_type: None,
+                timestamp_source: nethuns_rs::api::TimestampSource::Realtime,
+                tx_rate: nethuns_rs::api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                fcs: None,
+                monitor_mode: false,
+                nonblocking: false,
+                nanosecond_timestamps: false,
+                remote_auth: None,
+                synthesize_ethernet: false,
+                optimize_filter: true,
             };
             run_forwarder::<pcap::Sock>(flags, &args, term)
         }
@@ -186,25 +233,20 @@ where
         });
     }
 
-    while !term.load(Ordering::SeqCst) {
-        let (packet, _meta) = match in_socket.recv() {
-            Ok((p, m)) => (p, m),
-            Err(e) => {
-                eprintln!("Receive error: {:?}", e);
-                continue;
+    let stats = api::run_rx_loop(
+        &in_socket,
+        &term,
+        api::ErrorPolicy::Ignore,
+        |packet, _meta| {
+            total_rcv.fetch_add(1, Ordering::SeqCst);
+            while out_socket.send(&packet).is_err() {
+                out_socket.flush();
             }
-        };
-        total_rcv.fetch_add(1, Ordering::SeqCst);
-
-        loop {
-            match out_socket.send(&packet) {
-                Ok(_) => break,
-                Err(_e) => {
-                    out_socket.flush();
-                }
-            }
-        }
-        total_fwd.fetch_add(1, Ordering::SeqCst);
+            total_fwd.fetch_add(1, Ordering::SeqCst);
+        },
+    )?;
+    if stats.errors > 0 {
+        eprintln!("{} receive errors", stats.errors);
     }
 
     Ok(())
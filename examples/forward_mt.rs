@@ -133,7 +133,7 @@ where
                 let payload = token.consume(&ctx);
 
                 // Forward the packet
-                if let Err(e) = out_socket.send(&payload) {
+                if let Err(_e) = out_socket.send(&payload) {
                     // eprintln!("Send error: {:?}", e);
                     continue;
                 }
@@ -193,6 +193,14 @@ pub fn main() -> Result<()> {
         Framework::Netmap(netmap_args) => {
             let flags = netmap::NetmapFlags {
                 extra_buf: netmap_args.extra_buf,
+                promiscuous: false,
+                allmulti: false,
+                flush_policy: nethuns_rs::api::FlushPolicy::Manual,
+                tx_rate: nethuns_rs::api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                snaplen: None,
+                timestamp_source: nethuns_rs::api::TimestampSource::Realtime,
             };
             run_queue::<netmap::Sock>(flags, &args, term)?;
         }
@@ -200,11 +208,34 @@ pub fn main() -> Result<()> {
         Framework::AfXdp(af_xdp_args) => {
             let flags = af_xdp::AfXdpFlags {
                 bind_flags: af_xdp_args.bind_flags,
+                zero_copy: false,
+                need_wakeup: true,
+                busy_poll: None,
                 xdp_flags: af_xdp_args.xdp_flags,
+                xdp_program: af_xdp::XdpProgram::Builtin,
+                hw_offload: false,
                 num_frames: 4096,
                 frame_size: 2048,
+                hugepages: false,
+                frame_headroom: 0,
+                unaligned_chunks: false,
                 tx_size: 2048,
+                tx_frames: None,
                 rx_size: 2048,
+                rx_metadata: false,
+                rx_timestamp: false,
+                rx_vlan: false,
+                promiscuous: false,
+                allmulti: false,
+                flush_policy: nethuns_rs::api::FlushPolicy::Manual,
+                refill_policy: af_xdp::RefillPolicy::OnEmpty,
+                completion_batch: 64,
+                tx_rate: nethuns_rs::api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                snaplen: None,
+                timestamp_source: nethuns_rs::api::TimestampSource::None,
+                fcs: None,
             };
             run_queue::<af_xdp::Sock>(flags, &args, term)?;
         }
@@ -213,11 +244,27 @@ pub fn main() -> Result<()> {
             let flags = pcap::PcapFlags {
                 snaplen: pcap_args.snaplen,
                 promiscuous: pcap_args.promiscuous,
+                allmulti: false,
                 timeout_ms: pcap_args.timeout_ms,
                 immediate: pcap_args.immediate,
                 filter: pcap_args.filter.clone(),
                 buffer_size: pcap_args.buffer_size,
                 buffer_count: pcap_args.buffer_count,
+                pool_policy: pcap::PoolPolicy::default(),
+                direction: nethuns_rs::api::Direction::In,
+                hw_timestamps: false,
+                timestamp_type: None,
+                timestamp_source: nethuns_rs::api::TimestampSource::Realtime,
+                tx_rate: nethuns_rs::api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                fcs: None,
+                monitor_mode: false,
+                nonblocking: false,
+                nanosecond_timestamps: false,
+                remote_auth: None,
+                synthesize_ethernet: false,
+                optimize_filter: true,
             };
             run_queue::<pcap::Sock>(flags, &args, term)?;
         }
@@ -5,7 +5,7 @@
 use anyhow::{Result, bail};
 use clap::{Parser, Subcommand};
 use etherparse::{NetHeaders, PacketHeaders};
-use nethuns_rs::api::{Flags, Socket, Token};
+use nethuns_rs::api::Socket;
 use std::net::{Ipv4Addr, Ipv6Addr};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
@@ -253,13 +253,16 @@ fn run<Sock: Socket + 'static>(flags: Sock::Flags, args: &Args) -> Result<()> {
             for _ in 0..1000 {
                 for (i, socket) in sockets.iter_mut().enumerate() {
                     let res: Result<()> = (|| {
-                        let (token, meta) = socket.recv_token()?;
+                        let (token, _meta) = socket.recv_token()?;
                         local_counters[i] += 1;
                         if local_counters[i] == BULK {
                             totals[i].fetch_add(local_counters[i], Ordering::Relaxed);
                             local_counters[i] = 0;
                         }
                         let payload = ctxs[j].packet(token);
+                        if debug && let Ok(info) = print_addrs(&payload) {
+                            println!("Socket {}: {}", i, info);
+                        }
                         j = (j + 1) % ctxs.len();
                         Ok(())
                     })();
@@ -285,6 +288,14 @@ pub fn main() -> Result<()> {
         Framework::Netmap(netmap_args) => {
             let flags = netmap::NetmapFlags {
                 extra_buf: netmap_args.extra_buf,
+                promiscuous: false,
+                allmulti: false,
+                flush_policy: nethuns_rs::api::FlushPolicy::Manual,
+                tx_rate: nethuns_rs::api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                snaplen: None,
+                timestamp_source: nethuns_rs::api::TimestampSource::Realtime,
             };
             run::<netmap::Sock>(flags, &args)?;
         }
@@ -292,11 +303,34 @@ pub fn main() -> Result<()> {
         Framework::AfXdp(af_xdp_args) => {
             let flags = af_xdp::AfXdpFlags {
                 bind_flags: af_xdp_args.bind_flags,
+                zero_copy: false,
+                need_wakeup: true,
+                busy_poll: None,
                 xdp_flags: af_xdp_args.xdp_flags,
+                xdp_program: af_xdp::XdpProgram::Builtin,
+                hw_offload: false,
                 num_frames: 4096,
                 frame_size: 2048,
+                hugepages: false,
+                frame_headroom: 0,
+                unaligned_chunks: false,
                 tx_size: 2048,
+                tx_frames: Some(0),
                 rx_size: 2048,
+                rx_metadata: false,
+                rx_timestamp: false,
+                rx_vlan: false,
+                promiscuous: false,
+                allmulti: false,
+                flush_policy: nethuns_rs::api::FlushPolicy::Manual,
+                refill_policy: af_xdp::RefillPolicy::OnEmpty,
+                completion_batch: 64,
+                tx_rate: nethuns_rs::api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                snaplen: None,
+                timestamp_source: nethuns_rs::api::TimestampSource::None,
+                fcs: None,
             };
             run::<af_xdp::Sock>(flags, &args)?;
         }
@@ -306,6 +340,18 @@ pub fn main() -> Result<()> {
                 num_mbufs: dpdk_args.num_mbufs,
                 mbuf_cache_size: dpdk_args.mbuf_cache_size,
                 mbuf_default_buf_size: dpdk_args.mbuf_default_buf_size as u16,
+                hw_timestamps: false,
+                promiscuous: false,
+                allmulti: false,
+                flush_policy: nethuns_rs::api::FlushPolicy::Manual,
+                tx_rate: nethuns_rs::api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                tx_checksum: false,
+                tso: false,
+                keep_crc: false,
+                snaplen: None,
+                timestamp_source: nethuns_rs::api::TimestampSource::None,
             };
             run::<dpdk::Sock>(flags, &args)?;
         }
@@ -314,14 +360,31 @@ pub fn main() -> Result<()> {
             let flags = pcap::PcapFlags {
                 snaplen: pcap_args.snaplen,
                 promiscuous: pcap_args.promiscuous,
+                allmulti: false,
                 timeout_ms: pcap_args.timeout_ms,
                 immediate: pcap_args.immediate,
                 filter: pcap_args.filter.clone(),
                 buffer_size: pcap_args.buffer_size,
                 buffer_count: pcap_args.buffer_count,
+                pool_policy: pcap::PoolPolicy::default(),
+                direction: nethuns_rs::api::Direction::InOut,
+                hw_timestamps: false,
+                timestamp_type: None,
+                timestamp_source: nethuns_rs::api::TimestampSource::Realtime,
+                tx_rate: nethuns_rs::api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                fcs: None,
+                monitor_mode: false,
+                nonblocking: false,
+                nanosecond_timestamps: false,
+                remote_auth: None,
+                synthesize_ethernet: false,
+                optimize_filter: true,
             };
             run::<pcap::Sock>(flags, &args)?;
         }
+        #[allow(unreachable_patterns)]
         _ => bail!("Unsupported framework"),
     }
     Ok(())
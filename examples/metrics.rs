@@ -0,0 +1,84 @@
+//! Prometheus exporter example.
+//!
+//! Receives on one socket and serves the counters nethuns registers with the
+//! `metrics` facade in the Prometheus text format, e.g.
+//! `curl http://127.0.0.1:9000/metrics`. Build with `--features metrics`.
+use anyhow::Result;
+use clap::Parser;
+use metrics_exporter_prometheus::PrometheusBuilder;
+use nethuns_rs::api::{AnySocket, Backend, SocketOptions};
+use nethuns_rs::errors::Error;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Network interface name.
+    #[clap(short, long)]
+    interface: String,
+
+    /// Backend to open the interface with.
+    #[clap(short, long, default_value = "pcap")]
+    backend: Backend,
+
+    /// Queue index to bind (defaults to backend choice).
+    #[clap(long)]
+    queue: Option<usize>,
+
+    /// Address the exporter listens on.
+    #[clap(short, long, default_value = "127.0.0.1:9000")]
+    listen: String,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let handle = PrometheusBuilder::new().install_recorder()?;
+
+    // The recorder must be installed before the socket registers its counters.
+    let socket = AnySocket::create(
+        args.backend,
+        &args.interface,
+        args.queue,
+        &SocketOptions::default(),
+    )?;
+
+    let listener = TcpListener::bind(&args.listen)?;
+    println!("Serving metrics on http://{}/metrics", args.listen);
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            // Every request gets the metrics, whatever the path.
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request);
+            let body = handle.render();
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        }
+    });
+
+    let term = Arc::new(AtomicBool::new(false));
+    {
+        let term = term.clone();
+        ctrlc::set_handler(move || {
+            term.store(true, Ordering::SeqCst);
+        })
+        .expect("Error setting Ctrl-C handler");
+    }
+
+    while !term.load(Ordering::Relaxed) {
+        // The counters are updated by the socket itself: just drain the packets.
+        match socket.recv_timeout(Duration::from_millis(100)) {
+            Ok(_) | Err(Error::WouldBlock) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
@@ -17,7 +17,7 @@ use clap::{Parser, Subcommand};
 use etherparse::PacketBuilder;
 #[cfg(feature = "af-xdp")]
 use nethuns_rs::af_xdp;
-use nethuns_rs::api::Socket;
+use nethuns_rs::api::{Flags, Socket, SocketOptions, TxRate};
 #[cfg(feature = "dpdk")]
 use nethuns_rs::dpdk;
 #[cfg(feature = "netmap")]
@@ -29,7 +29,7 @@ use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 #[derive(Parser, Debug, Clone)]
 #[clap(author, version, about, long_about = None)]
@@ -254,13 +254,23 @@ fn run_tx<Sock: Socket + 'static>(flags: Sock::Flags, args: &Args) -> Result<()>
 
     let pkt_template = build_packet_template(args)?;
 
-    let mut sockets = Vec::with_capacity(args.sockets);
-    for i in 0..args.sockets {
-        let portspec = if args.sockets > 1 {
-            args.interface.clone()
+    // The sockets pace themselves: each thread sends at the full rate, a single thread
+    // shares it among its sockets.
+    let mut flags = flags;
+    if args.rate > 0 {
+        let per_socket = if args.multithreading {
+            args.rate
         } else {
-            args.interface.clone()
+            (args.rate / args.sockets as u64).max(1)
         };
+        let mut options = SocketOptions::default();
+        options.tx_rate = Some(TxRate::Packets(per_socket));
+        flags.apply(&options);
+    }
+
+    let mut sockets = Vec::with_capacity(args.sockets);
+    for i in 0..args.sockets {
+        let portspec = args.interface.clone();
         let socket = Sock::create(&portspec, args.queue.or(Some(i)), flags.clone())?;
         sockets.push(socket);
     }
@@ -290,44 +300,27 @@ fn run_tx<Sock: Socket + 'static>(flags: Sock::Flags, args: &Args) -> Result<()>
 
     let mut handles = Vec::new();
 
-    let rate_pps = args.rate;
-    let spacing = if rate_pps > 0 {
-        Some(Duration::from_secs_f64(1.0 / rate_pps as f64))
-    } else {
-        None
-    };
-
     if args.multithreading {
         for (sock_id, sock) in sockets.into_iter().enumerate() {
             let term = term.clone();
             let counter = totals[sock_id].clone();
             let pkt = pkt_template.clone();
-            let spacing = spacing.clone();
             let count_limit = args.count;
             let handle = thread::spawn(move || {
                 let mut sent: u64 = 0;
                 let mut local_queue = 0;
-                let mut next_ts = Instant::now();
 
                 while !term.load(Ordering::Acquire) {
                     if count_limit > 0 && sent >= count_limit {
                         break;
                     }
 
-                    if let Some(d) = spacing {
-                        let now = Instant::now();
-                        if now < next_ts {
-                            std::thread::sleep(next_ts - now);
-                        }
-                        next_ts += d;
-                    }
-
                     match sock.send(&pkt) {
                         Ok(_) => {
                             sent += 1;
                             local_queue += 1;
                             if local_queue >= FLUSH_EVERY {
-                                let _ = sock.flush();
+                                sock.flush();
                                 local_queue = 0;
                             }
                             counter.fetch_add(1, Ordering::Relaxed);
@@ -335,7 +328,7 @@ fn run_tx<Sock: Socket + 'static>(flags: Sock::Flags, args: &Args) -> Result<()>
                         Err(_err) => {}
                     }
                 }
-                let _ = sock.flush();
+                sock.flush();
             });
             handles.push(handle);
         }
@@ -345,7 +338,6 @@ fn run_tx<Sock: Socket + 'static>(flags: Sock::Flags, args: &Args) -> Result<()>
         let args = args.clone();
         let handle = thread::spawn(move || {
             let mut sent_total = 0u64;
-            let mut next_ts = Instant::now();
             loop {
                 if term.load(Ordering::Acquire) {
                     break;
@@ -355,19 +347,12 @@ fn run_tx<Sock: Socket + 'static>(flags: Sock::Flags, args: &Args) -> Result<()>
                         term.store(true, Ordering::SeqCst);
                         break;
                     }
-                    if let Some(d) = spacing {
-                        let now = Instant::now();
-                        if now < next_ts {
-                            std::thread::sleep(next_ts - now);
-                        }
-                        next_ts += d;
-                    }
                     match sock.send(&pkt_template) {
                         Ok(_) => {
                             sent_total += 1;
                             totals_ref[idx].fetch_add(1, Ordering::Relaxed);
-                            if sent_total % FLUSH_EVERY as u64 == 0 {
-                                let _ = sock.flush();
+                            if sent_total.is_multiple_of(FLUSH_EVERY as u64) {
+                                sock.flush();
                             }
                         }
                         Err(_err) => {}
@@ -375,7 +360,7 @@ fn run_tx<Sock: Socket + 'static>(flags: Sock::Flags, args: &Args) -> Result<()>
                 }
             }
             for sock in &mut sockets {
-                let _ = sock.flush();
+                sock.flush();
             }
         });
         handles.push(handle);
@@ -397,6 +382,14 @@ fn main() -> Result<()> {
         Framework::Netmap(nm) => {
             let flags = netmap::NetmapFlags {
                 extra_buf: nm.extra_buf,
+                promiscuous: false,
+                allmulti: false,
+                flush_policy: nethuns_rs::api::FlushPolicy::Manual,
+                tx_rate: nethuns_rs::api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                snaplen: None,
+                timestamp_source: nethuns_rs::api::TimestampSource::Realtime,
             };
             run_tx::<netmap::Sock>(flags, &args)?;
         }
@@ -404,11 +397,34 @@ fn main() -> Result<()> {
         Framework::AfXdp(xdp) => {
             let flags = af_xdp::AfXdpFlags {
                 bind_flags: xdp.bind_flags,
+                zero_copy: false,
+                need_wakeup: true,
+                busy_poll: None,
                 xdp_flags: xdp.xdp_flags,
+                xdp_program: af_xdp::XdpProgram::Builtin,
+                hw_offload: false,
                 num_frames: 4096 * 8,
                 frame_size: 2048,
+                hugepages: false,
+                frame_headroom: 0,
+                unaligned_chunks: false,
                 tx_size: 2048,
+                tx_frames: None,
                 rx_size: 2048,
+                rx_metadata: false,
+                rx_timestamp: false,
+                rx_vlan: false,
+                promiscuous: false,
+                allmulti: false,
+                flush_policy: nethuns_rs::api::FlushPolicy::Manual,
+                refill_policy: af_xdp::RefillPolicy::OnEmpty,
+                completion_batch: 64,
+                tx_rate: nethuns_rs::api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                snaplen: None,
+                timestamp_source: nethuns_rs::api::TimestampSource::None,
+                fcs: None,
             };
             run_tx::<af_xdp::Sock>(flags, &args)?;
         }
@@ -418,6 +434,18 @@ fn main() -> Result<()> {
                 num_mbufs: dp.num_mbufs,
                 mbuf_cache_size: dp.mbuf_cache_size,
                 mbuf_default_buf_size: dp.mbuf_default_buf_size as u16,
+                hw_timestamps: false,
+                promiscuous: false,
+                allmulti: false,
+                flush_policy: nethuns_rs::api::FlushPolicy::Manual,
+                tx_rate: nethuns_rs::api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                tx_checksum: false,
+                tso: false,
+                keep_crc: false,
+                snaplen: None,
+                timestamp_source: nethuns_rs::api::TimestampSource::None,
             };
             run_tx::<dpdk::Sock>(flags, &args)?;
         }
@@ -426,11 +454,27 @@ fn main() -> Result<()> {
             let flags = pcap::PcapFlags {
                 snaplen: pcap.snaplen,
                 promiscuous: pcap.promiscuous,
+                allmulti: false,
                 timeout_ms: pcap.timeout_ms,
                 immediate: pcap.immediate,
                 filter: None,
                 buffer_size: pcap.buffer_size,
                 buffer_count: pcap.buffer_count,
+                pool_policy: pcap::PoolPolicy::default(),
+                direction: nethuns_rs::api::Direction::InOut,
+                hw_timestamps: false,
+                timestamp_type: None,
+                timestamp_source: nethuns_rs::api::TimestampSource::Realtime,
+                tx_rate: nethuns_rs::api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                fcs: None,
+                monitor_mode: false,
+                nonblocking: false,
+                nanosecond_timestamps: false,
+                remote_auth: None,
+                synthesize_ethernet: false,
+                optimize_filter: true,
             };
             run_tx::<pcap::Sock>(flags, &args)?;
         }
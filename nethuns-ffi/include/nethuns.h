@@ -0,0 +1,64 @@
+/* C interface of nethuns-rs. */
+
+#ifndef NETHUNS_H
+#define NETHUNS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define NETHUNS_ERRBUF_SIZE 512
+
+typedef struct nethuns_socket nethuns_socket_t;
+
+/* Zero (or a negative promisc) keeps the backend default. */
+struct nethuns_socket_options {
+    uint32_t rx_ring;
+    uint32_t tx_ring;
+    uint32_t snaplen;
+    uint32_t buffer_size;
+    uint32_t num_buffers;
+    int promisc;
+};
+
+struct nethuns_pkthdr {
+    uint64_t tstamp; /* nanoseconds, 0 if not timestamped */
+    uint32_t caplen;
+    uint32_t len;
+};
+
+struct nethuns_stat {
+    uint64_t rx_packets;
+    uint64_t rx_bytes;
+    uint64_t rx_dropped;
+    uint64_t rx_errors;
+    uint64_t tx_packets;
+    uint64_t tx_bytes;
+    uint64_t tx_errors;
+};
+
+/* backend: "pcap", "af-xdp", "netmap" or "dpdk"; queue < 0 binds any queue.
+ * Returns NULL on failure, with the reason in errbuf. */
+nethuns_socket_t *nethuns_open(const char *backend, const char *dev, int queue,
+                               const struct nethuns_socket_options *opt, char *errbuf);
+void nethuns_close(nethuns_socket_t *s);
+
+/* Returns 1 with a packet, 0 if none is ready, -1 on error.
+ * The frame stays valid until the next call on s. */
+int nethuns_recv(nethuns_socket_t *s, struct nethuns_pkthdr *hdr, const uint8_t **frame);
+int nethuns_send(nethuns_socket_t *s, const uint8_t *packet, size_t len);
+void nethuns_flush(nethuns_socket_t *s);
+int nethuns_stats(nethuns_socket_t *s, struct nethuns_stat *stats);
+int nethuns_set_filter(nethuns_socket_t *s, const char *expr);
+
+/* Message of the last failed call on s. */
+const char *nethuns_error(const nethuns_socket_t *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* NETHUNS_H */
@@ -0,0 +1,328 @@
+//! C bindings for nethuns-rs.
+//!
+//! Exposes the backend-independent socket API to C applications, with names modeled on
+//! the original nethuns library. The declarations are in `include/nethuns.h`; the
+//! backends are selected with the same features as the `nethuns_rs` crate.
+
+#![allow(non_camel_case_types)]
+
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::ptr;
+
+use nethuns_rs::api::{AnySocket, Backend, Metadata, SocketOptions};
+use nethuns_rs::errors::Error;
+
+/// Size of the error buffer passed to [`nethuns_open`].
+pub const NETHUNS_ERRBUF_SIZE: usize = 512;
+
+/// Frame buffer size used when the backend does not report a maximum frame size.
+const DEFAULT_FRAME_SIZE: usize = 65536;
+
+/// Options of [`nethuns_open`]; zero (or a negative `promisc`) keeps the backend
+/// default.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct nethuns_socket_options {
+    pub rx_ring: u32,
+    pub tx_ring: u32,
+    pub snaplen: u32,
+    pub buffer_size: u32,
+    pub num_buffers: u32,
+    pub promisc: c_int,
+}
+
+/// Header of a packet returned by [`nethuns_recv`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct nethuns_pkthdr {
+    /// Timestamp in nanoseconds, 0 if the packet was not timestamped.
+    pub tstamp: u64,
+    /// Bytes available in the frame.
+    pub caplen: u32,
+    /// Length of the packet on the wire.
+    pub len: u32,
+}
+
+/// Socket counters filled in by [`nethuns_stats`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct nethuns_stat {
+    pub rx_packets: u64,
+    pub rx_bytes: u64,
+    pub rx_dropped: u64,
+    pub rx_errors: u64,
+    pub tx_packets: u64,
+    pub tx_bytes: u64,
+    pub tx_errors: u64,
+}
+
+/// An open socket, opaque to C.
+pub struct nethuns_socket {
+    sock: AnySocket,
+    /// The last received frame, valid until the next `nethuns_recv`.
+    frame: Vec<u8>,
+    /// Message of the last failed call.
+    error: CString,
+}
+
+impl nethuns_socket {
+    fn set_error(&mut self, err: &Error) {
+        self.error = error_string(err);
+    }
+}
+
+fn invalid_input(msg: &'static str) -> Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, msg).into()
+}
+
+fn error_string(err: impl std::fmt::Display) -> CString {
+    CString::new(err.to_string().replace('\0', " ")).unwrap_or_default()
+}
+
+/// Copies `msg` into `errbuf`, truncated to [`NETHUNS_ERRBUF_SIZE`].
+///
+/// # Safety
+///
+/// `errbuf` is null or points to at least `NETHUNS_ERRBUF_SIZE` writable bytes.
+unsafe fn write_errbuf(errbuf: *mut c_char, msg: impl std::fmt::Display) {
+    if errbuf.is_null() {
+        return;
+    }
+    let msg = error_string(msg);
+    let bytes = msg.as_bytes();
+    let len = bytes.len().min(NETHUNS_ERRBUF_SIZE - 1);
+    unsafe {
+        ptr::copy_nonoverlapping(bytes.as_ptr() as *const c_char, errbuf, len);
+        *errbuf.add(len) = 0;
+    }
+}
+
+fn open(
+    backend: &CStr,
+    dev: &CStr,
+    queue: c_int,
+    opt: Option<&nethuns_socket_options>,
+) -> Result<nethuns_socket, Error> {
+    let invalid = |what: &str| {
+        Error::from(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("invalid {what}"),
+        ))
+    };
+    let backend: Backend = backend
+        .to_str()
+        .map_err(|_| invalid("backend name"))?
+        .parse()?;
+    let dev = dev.to_str().map_err(|_| invalid("device name"))?;
+    let queue = usize::try_from(queue).ok();
+
+    let mut options = SocketOptions::default();
+    if let Some(opt) = opt {
+        let nonzero = |v: u32| (v != 0).then_some(v);
+        options.rx_ring = nonzero(opt.rx_ring);
+        options.tx_ring = nonzero(opt.tx_ring);
+        options.snaplen = nonzero(opt.snaplen);
+        options.buffer_size = nonzero(opt.buffer_size);
+        options.num_buffers = nonzero(opt.num_buffers);
+        options.promiscuous = (opt.promisc >= 0).then_some(opt.promisc != 0);
+    }
+
+    let sock = AnySocket::create(backend, dev, queue, &options)?;
+    let frame_size = match sock.capabilities().max_frame_size {
+        0 => DEFAULT_FRAME_SIZE,
+        size => size,
+    };
+    Ok(nethuns_socket {
+        sock,
+        frame: Vec::with_capacity(frame_size),
+        error: CString::default(),
+    })
+}
+
+/// Opens a socket on queue `queue` of `dev` (any queue if negative) with the backend
+/// named `backend` (`"pcap"`, `"af-xdp"`, `"netmap"` or `"dpdk"`).
+///
+/// `opt` may be null. Returns null on failure, with the reason in `errbuf` if it is
+/// not null.
+///
+/// # Safety
+///
+/// `backend` and `dev` are NUL-terminated strings, `opt` is null or valid, and
+/// `errbuf` is null or points to at least `NETHUNS_ERRBUF_SIZE` writable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nethuns_open(
+    backend: *const c_char,
+    dev: *const c_char,
+    queue: c_int,
+    opt: *const nethuns_socket_options,
+    errbuf: *mut c_char,
+) -> *mut nethuns_socket {
+    if backend.is_null() || dev.is_null() {
+        unsafe { write_errbuf(errbuf, "backend and device must not be null") };
+        return ptr::null_mut();
+    }
+    let (backend, dev) = unsafe { (CStr::from_ptr(backend), CStr::from_ptr(dev)) };
+    match open(backend, dev, queue, unsafe { opt.as_ref() }) {
+        Ok(sock) => Box::into_raw(Box::new(sock)),
+        Err(err) => {
+            unsafe { write_errbuf(errbuf, err) };
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Closes a socket returned by [`nethuns_open`].
+///
+/// # Safety
+///
+/// `s` is null or a socket returned by `nethuns_open` that was not closed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nethuns_close(s: *mut nethuns_socket) {
+    if !s.is_null() {
+        drop(unsafe { Box::from_raw(s) });
+    }
+}
+
+/// Receives a packet without blocking.
+///
+/// Returns 1 and fills in `hdr` and `frame` if a packet was received, 0 if none is
+/// ready, and -1 on error. The frame stays valid until the next call on `s`.
+///
+/// # Safety
+///
+/// `s` is an open socket not used concurrently, `hdr` and `frame` are valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nethuns_recv(
+    s: *mut nethuns_socket,
+    hdr: *mut nethuns_pkthdr,
+    frame: *mut *const u8,
+) -> c_int {
+    let s = unsafe { &mut *s };
+    let res = s.sock.try_recv().map(|(payload, meta)| {
+        s.frame.clear();
+        s.frame.extend_from_slice(&payload);
+        (payload.len(), meta)
+    });
+    let (len, meta) = match res {
+        Ok(pkt) => pkt,
+        Err(Error::WouldBlock | Error::NoPacket) => return 0,
+        Err(err) => {
+            s.set_error(&err);
+            return -1;
+        }
+    };
+    unsafe {
+        *hdr = nethuns_pkthdr {
+            tstamp: meta.timestamp().map_or(0, |ts| ts.as_nanos()),
+            caplen: len as u32,
+            len: meta.wirelen(),
+        };
+        *frame = s.frame.as_ptr();
+    }
+    1
+}
+
+/// Queues a packet for transmission.
+///
+/// Returns 0 on success and -1 on error, e.g. when the TX ring is full.
+///
+/// # Safety
+///
+/// `s` is an open socket not used concurrently, `packet` points to `len` bytes or is
+/// NULL with a `len` of 0.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nethuns_send(
+    s: *mut nethuns_socket,
+    packet: *const u8,
+    len: usize,
+) -> c_int {
+    let s = unsafe { &mut *s };
+    let packet = if packet.is_null() {
+        if len != 0 {
+            s.set_error(&invalid_input("NULL packet with a non-zero length"));
+            return -1;
+        }
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(packet, len) }
+    };
+    match s.sock.send(packet) {
+        Ok(()) => 0,
+        Err(err) => {
+            s.set_error(&err);
+            -1
+        }
+    }
+}
+
+/// Pushes the queued packets to the NIC.
+///
+/// # Safety
+///
+/// `s` is an open socket not used concurrently.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nethuns_flush(s: *mut nethuns_socket) {
+    unsafe { &*s }.sock.flush();
+}
+
+/// Reads the socket counters into `stats`. Returns 0 on success and -1 on error.
+///
+/// # Safety
+///
+/// `s` is an open socket not used concurrently, `stats` is valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nethuns_stats(s: *mut nethuns_socket, stats: *mut nethuns_stat) -> c_int {
+    let s = unsafe { &mut *s };
+    match s.sock.stats() {
+        Ok(st) => {
+            unsafe {
+                *stats = nethuns_stat {
+                    rx_packets: st.rx_packets,
+                    rx_bytes: st.rx_bytes,
+                    rx_dropped: st.rx_dropped,
+                    rx_errors: st.rx_errors,
+                    tx_packets: st.tx_packets,
+                    tx_bytes: st.tx_bytes,
+                    tx_errors: st.tx_errors,
+                };
+            }
+            0
+        }
+        Err(err) => {
+            s.set_error(&err);
+            -1
+        }
+    }
+}
+
+/// Installs a BPF filter in `pcap-filter(7)` syntax; an empty string removes it.
+/// Returns 0 on success and -1 on error, including when `expr` is not valid UTF-8.
+///
+/// # Safety
+///
+/// `s` is an open socket not used concurrently, `expr` is a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nethuns_set_filter(s: *mut nethuns_socket, expr: *const c_char) -> c_int {
+    let s = unsafe { &mut *s };
+    let Ok(expr) = unsafe { CStr::from_ptr(expr) }.to_str() else {
+        s.set_error(&invalid_input("filter expression is not valid UTF-8"));
+        return -1;
+    };
+    match s.sock.set_filter(expr) {
+        Ok(()) => 0,
+        Err(err) => {
+            s.set_error(&err);
+            -1
+        }
+    }
+}
+
+/// Returns the message of the last failed call on `s`, valid until the next failure.
+///
+/// # Safety
+///
+/// `s` is an open socket.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nethuns_error(s: *const nethuns_socket) -> *const c_char {
+    unsafe { &*s }.error.as_ptr()
+}
@@ -55,6 +55,20 @@ impl BufferPool {
 
         ring
     }
+
+    /// Size in bytes of every buffer of the pool.
+    pub fn buf_size(&self) -> usize {
+        let port = unsafe { &*self.port.get() };
+        unsafe { (*port.a_ring).nr_buf_size as usize }
+    }
+
+    /// Identifier of the netmap memory region the buffers belong to.
+    ///
+    /// Buffer indexes are only meaningful among ports of the same region.
+    pub fn mem_id(&self) -> u16 {
+        let port = unsafe { &*self.port.get() };
+        unsafe { (*port.inner).reg.nr_mem_id }
+    }
 }
 
 pub struct Port {
@@ -107,6 +121,22 @@ impl Port {
         res
     }
 
+    // # Safety
+    // `buf_idx` must be an extra buffer of this port that is not in use anywhere
+    pub unsafe fn release_extra_buffer(&mut self, buf_idx: u32) {
+        unsafe {
+            let a_ring = self.a_ring;
+            let size = (*a_ring).nr_buf_size;
+            let buf_ofs = (*a_ring).buf_ofs;
+            let next = (a_ring as *mut u8)
+                .add(buf_ofs as usize)
+                .add(buf_idx as usize * size as usize) as *mut u32;
+            // The list is threaded through the first word of each buffer.
+            *next = (*(*self.inner).nifp).ni_bufs_head;
+            (*(*self.inner).nifp).ni_bufs_head = buf_idx;
+        }
+    }
+
     pub fn open(portspec: &str, n_extrabuf: u32) -> Result<Self> {
         if !allocate_port(portspec) {
             return Err(Error::OpenError("Port is already allocated"));
@@ -493,6 +523,11 @@ impl Receiver {
         ReceiverIterMut { rx: self }
     }
 
+    /// File descriptor of the underlying port, readable when RX slots are available.
+    pub fn fd(&self) -> i32 {
+        unsafe { (*(*self.port.get()).inner).fd }
+    }
+
     // # Safety
     // Caller should guarantee that no slots are in use when calling this method
     pub unsafe fn reset(&mut self) {
@@ -512,6 +547,12 @@ impl Receiver {
             p.rx_sync();
         }
     }
+
+    // # Safety
+    // `buf_idx` must be an extra buffer of this port that is not in use anywhere
+    pub unsafe fn release_extra_buffer(&mut self, buf_idx: u32) {
+        unsafe { (*self.port.get()).release_extra_buffer(buf_idx) }
+    }
 }
 
 pub struct RxBuf<'a> {
@@ -617,6 +658,37 @@ impl<'a> Drop for TxBuf<'a> {
     }
 }
 
+/// A TX slot taken with [`Transmitter::reserve`], identified by its position so that it
+/// does not keep the transmitter borrowed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TxReservation {
+    pub ring_idx: u16,
+    pub slot_idx: u32,
+    pub buf_idx: u32,
+}
+
+impl<'a> TxBuf<'a> {
+    /// Puts `buf_idx` in the slot to send `len` bytes of it, and returns the index of
+    /// the buffer it replaces.
+    ///
+    /// # Safety
+    /// `buf_idx` must be a buffer of the same memory region that is not in use anywhere
+    /// else; the returned buffer is owned by the caller.
+    pub unsafe fn swap_buffer(self, buf_idx: u32, len: u16) -> u32 {
+        let old = self.slot.buf_idx();
+        unsafe {
+            self.slot.update(|data| {
+                data.buf_idx = buf_idx;
+                data.len = len;
+                data.flags = NS_BUF_CHANGED as u16;
+            });
+        }
+        // Dropping the slot would clear NS_BUF_CHANGED before the next sync.
+        std::mem::forget(self);
+        old
+    }
+}
+
 impl<'a> TransmitterIterMut<'a> {
     pub fn sync(&mut self) {
         let p = unsafe { &*self.tx.port.get() };
@@ -681,6 +753,26 @@ impl Transmitter {
         TransmitterIterMut { tx: self }
     }
 
+    /// Returns whether some TX ring still holds slots the NIC has not sent yet.
+    pub fn pending(&self) -> bool {
+        self.in_flight() > 0
+    }
+
+    /// Returns the number of slots queued on the TX rings that the NIC had not sent
+    /// at the last TX sync.
+    pub fn in_flight(&self) -> usize {
+        let p = unsafe { &*self.port.get() };
+        (p.first_tx_ring() as usize..=self.last_tx_ring)
+            .filter_map(|idx| p.tx_ring_at(idx))
+            .map(|ring| unsafe {
+                let ring = ring.inner;
+                // The kernel owns the slots from tail + 1 up to head.
+                let n = (*ring).num_slots;
+                ((*ring).head + n - (*ring).tail - 1) as usize % n as usize
+            })
+            .sum()
+    }
+
     // # Safety
     // Caller should guarantee that no slots are in use when calling this method
     pub unsafe fn reset(&mut self) {
@@ -691,6 +783,59 @@ impl Transmitter {
         }
     }
 
+    /// Takes the next free TX slot, like `iter_mut().next()`, and returns its position
+    /// instead of a borrow of the transmitter.
+    pub fn reserve(&mut self) -> Option<TxReservation> {
+        let (ring_idx, buf_idx) = {
+            let buf = self.iter_mut().next()?;
+            (buf.ring_idx, buf.slot.buf_idx())
+        };
+        let p = unsafe { &*self.port.get() };
+        let ring = p.tx_ring_at(ring_idx as usize)?.inner;
+        // The iterator moved the head past the slot.
+        let slot_idx = unsafe {
+            if (*ring).head == 0 {
+                (*ring).num_slots - 1
+            } else {
+                (*ring).head - 1
+            }
+        };
+        Some(TxReservation {
+            ring_idx,
+            slot_idx,
+            buf_idx,
+        })
+    }
+
+    // # Safety
+    // `res` must come from `reserve`, with no sync in between
+    pub unsafe fn commit(&mut self, res: TxReservation, len: u16) {
+        let p = unsafe { &*self.port.get() };
+        if let Some(ring) = p.tx_ring_at(res.ring_idx as usize)
+            && let Some(slot) = ring.slot_slice().get(res.slot_idx as usize)
+        {
+            unsafe {
+                slot.update(|data| {
+                    data.len = len;
+                    data.flags = 0;
+                });
+            }
+        }
+    }
+
+    // # Safety
+    // `res` must be the last slot returned by `reserve`, with no sync in between
+    pub unsafe fn unreserve(&mut self, res: TxReservation) {
+        let p = unsafe { &*self.port.get() };
+        if let Some(ring) = p.tx_ring_at(res.ring_idx as usize) {
+            let ring = ring.inner;
+            unsafe {
+                (*ring).head = res.slot_idx;
+                (*ring).cur = res.slot_idx;
+            }
+        }
+    }
+
     // # Safety
     // Caller should guarantee that no slots are in use when calling this method
     pub unsafe fn sync(&mut self) {
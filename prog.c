@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: GPL-2.0
+/*
+ * Built-in XDP program of the AF_XDP backend, compiled by build.rs and embedded in
+ * the crate.
+ *
+ * Redirects each packet to the socket bound to its RX queue, which sits at the index
+ * of the queue in `xsks_map`. `queue_map` holds an action per queue and
+ * `steer_config` global flags, both set from userspace through
+ * `af_xdp::XdpSteering`. IPv4 packets from the sources in `blocklist` are dropped
+ * before reaching any socket.
+ *
+ * build.rs runs (with `CLANG` overriding the compiler):
+ *     clang -O2 -g -target bpf -c prog.c -o $OUT_DIR/prog.o
+ */
+#include <linux/bpf.h>
+#include <linux/if_ether.h>
+#include <linux/ip.h>
+#include <bpf/bpf_helpers.h>
+#include <bpf/bpf_endian.h>
+
+#define MAX_QUEUES 256
+#define MAX_BLOCKED 65536
+
+/* Values of `queue_map`; must match `QueueAction` in src/af_xdp/mod.rs. */
+#define QUEUE_REDIRECT 0
+#define QUEUE_PASS 1
+#define QUEUE_DROP 2
+
+/* Bits of `steer_config`. */
+#define STEER_DROP_NON_IP (1U << 0)
+
+struct {
+	__uint(type, BPF_MAP_TYPE_XSKMAP);
+	__uint(max_entries, MAX_QUEUES);
+	__type(key, __u32);
+	__type(value, __u32);
+} xsks_map SEC(".maps");
+
+struct {
+	__uint(type, BPF_MAP_TYPE_ARRAY);
+	__uint(max_entries, MAX_QUEUES);
+	__type(key, __u32);
+	__type(value, __u32);
+} queue_map SEC(".maps");
+
+struct {
+	__uint(type, BPF_MAP_TYPE_ARRAY);
+	__uint(max_entries, 1);
+	__type(key, __u32);
+	__type(value, __u32);
+} steer_config SEC(".maps");
+
+/* IPv4 source addresses, in network byte order; the values are unused. */
+struct {
+	__uint(type, BPF_MAP_TYPE_HASH);
+	__uint(max_entries, MAX_BLOCKED);
+	__type(key, __u32);
+	__type(value, __u32);
+} blocklist SEC(".maps");
+
+struct vlan_hdr {
+	__be16 tci;
+	__be16 proto;
+};
+
+/*
+ * Returns the EtherType of the frame, behind at most one VLAN tag, and sets `l3` to
+ * the network header; 0 if the frame is too short.
+ */
+static __always_inline __be16 l3_proto(struct xdp_md *ctx, void **l3)
+{
+	void *data = (void *)(long)ctx->data;
+	void *data_end = (void *)(long)ctx->data_end;
+	struct ethhdr *eth = data;
+	__be16 proto;
+
+	if ((void *)(eth + 1) > data_end)
+		return 0;
+	proto = eth->h_proto;
+	*l3 = eth + 1;
+	if (proto == bpf_htons(ETH_P_8021Q) || proto == bpf_htons(ETH_P_8021AD)) {
+		struct vlan_hdr *vlan = (void *)(eth + 1);
+
+		if ((void *)(vlan + 1) > data_end)
+			return 0;
+		proto = vlan->proto;
+		*l3 = vlan + 1;
+	}
+	return proto;
+}
+
+/* Whether the IPv4 packet at `l3` comes from a source in `blocklist`. */
+static __always_inline int is_blocked(struct xdp_md *ctx, void *l3)
+{
+	void *data_end = (void *)(long)ctx->data_end;
+	struct iphdr *ip = l3;
+
+	if ((void *)(ip + 1) > data_end)
+		return 0;
+	return bpf_map_lookup_elem(&blocklist, &ip->saddr) != NULL;
+}
+
+SEC("xdp")
+int xdp_sock_prog(struct xdp_md *ctx)
+{
+	__u32 queue = ctx->rx_queue_index;
+	__u32 zero = 0;
+	__u32 *flags, *action;
+	void *l3 = NULL;
+	__be16 proto = l3_proto(ctx, &l3);
+	int ip = proto == bpf_htons(ETH_P_IP) || proto == bpf_htons(ETH_P_IPV6);
+
+	flags = bpf_map_lookup_elem(&steer_config, &zero);
+	if (flags && (*flags & STEER_DROP_NON_IP) && !ip)
+		return XDP_DROP;
+	if (proto == bpf_htons(ETH_P_IP) && is_blocked(ctx, l3))
+		return XDP_DROP;
+
+	action = bpf_map_lookup_elem(&queue_map, &queue);
+	if (action && *action == QUEUE_PASS)
+		return XDP_PASS;
+	if (action && *action == QUEUE_DROP)
+		return XDP_DROP;
+
+	/* Queues without a socket leave their packets to the kernel stack. */
+	return bpf_redirect_map(&xsks_map, queue, XDP_PASS);
+}
+
+char _license[] SEC("license") = "GPL";
@@ -1,17 +1,41 @@
 mod wrapper;
 use crate::api::Result;
-use crate::api::{self, Token};
+use crate::api::{self, RxSocket, Token, TxSocket};
 use crate::errors::Error;
 use libc::{self, _SC_PAGESIZE, sysconf};
+use libxdp_sys::xdp_desc;
 use std::alloc::{self, Layout};
 use std::cell::{Cell, RefCell, UnsafeCell};
 use std::io::{self, ErrorKind};
 use std::mem::ManuallyDrop;
+use std::net::Ipv4Addr;
+use std::os::fd::{AsRawFd, RawFd};
+use std::path::PathBuf;
 use std::ptr::NonNull;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicU32, Ordering};
-use wrapper::{TxSlot, Umem, XdpDescData, XskSocket};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use wrapper::{
+    CompletionQueue, FillQueue, SharedUmemRef, TxSlot as DescSlot, Umem, XdpAttachment,
+    XdpDescData, XskRx, XskSocket, XskTx,
+};
+
+/// Plain old data that can be stored in the maps of an XDP program; see [`XdpMaps`].
+pub use aya::Pod;
+
 const RX_BATCH_SIZE: usize = 32;
+/// Size of the RX hash hint the XDP program stores in front of the packet data.
+const RX_HASH_HINT_SIZE: usize = size_of::<u32>();
+/// Size of the RX timestamp hint the XDP program stores in front of the hash hint.
+const RX_TIMESTAMP_HINT_SIZE: usize = size_of::<u64>();
+/// Size of the RX VLAN hint the XDP program stores in front of the timestamp hint.
+const RX_VLAN_HINT_SIZE: usize = 2 * size_of::<u16>();
+/// Headroom the kernel reserves in front of the packet data of every RX frame.
+const XDP_PACKET_HEADROOM: usize = 256;
+/// RX descriptor option: more frames of the same packet follow (needs `XDP_USE_SG`).
+const XDP_PKT_CONTD: u32 = 1;
+/// Flag of the `steer_config` map of the built-in XDP program.
+const STEER_DROP_NON_IP: u32 = 1 << 0;
 
 pub fn resultify(x: i32) -> io::Result<u32> {
     match x >= 0 {
@@ -24,6 +48,8 @@ pub fn resultify(x: i32) -> io::Result<u32> {
 pub struct Ctx {
     buffer: UmemArea,
     producer: RefCell<mpsc::Producer<api::BufferDesc>>,
+    /// Frames released by `OwnedPacket`s, shared by every clone.
+    returns: api::ReturnQueue,
     index: u32,
 }
 
@@ -35,11 +61,17 @@ impl Ctx {
         let res = Self {
             buffer: buffer_pool, //: Arc::new(buffer_pool),
             producer: RefCell::new(producer),
+            returns: api::ReturnQueue::new(nbufs),
             index: counter,
         };
         (res, cons)
     }
 
+    /// Moves the frames released by `OwnedPacket`s to the free frames of the socket.
+    fn reclaim(&self) {
+        self.returns.reclaim(&mut self.producer.borrow_mut());
+    }
+
     unsafe fn buffer(&self, idx: api::BufferDesc, size: usize) -> *mut [u8] {
         let (ptr, _) = self.buffer.raw_parts();
         let offset = usize::from(idx);
@@ -57,6 +89,10 @@ impl api::Context for Ctx {
         self.producer.borrow_mut().push(buf_idx);
     }
 
+    fn release_detached(&self, buf_idx: api::BufferDesc) {
+        self.returns.push(buf_idx);
+    }
+
     unsafe fn unsafe_buffer(&self, buf_idx: api::BufferDesc, size: usize) -> *mut [u8] {
         unsafe { self.buffer(buf_idx, size) }
     }
@@ -79,50 +115,98 @@ struct StatsRecord {
 pub struct UmemArea {
     mem: Arc<UnsafeCell<NonNull<u8>>>,
     size: usize,
+    /// Size of the pages backing the area.
+    page_size: usize,
 }
 
 unsafe impl Send for UmemArea {}
 
 impl UmemArea {
+    /// Allocates an area of `packet_buffer_size` bytes, from huge pages if `hugepages`
+    /// is set and the system has enough of them, from regular pages otherwise.
     #[allow(clippy::arc_with_non_send_sync)]
-    fn new(packet_buffer_size: usize) -> Result<UmemArea> {
-        let packet_buffer = alloc_page_aligned(packet_buffer_size)?;
+    fn new(packet_buffer_size: usize, hugepages: bool) -> Result<UmemArea> {
+        let huge = if hugepages {
+            alloc_huge_pages(packet_buffer_size)
+                .inspect_err(|e| {
+                    trace_event!(
+                        debug,
+                        backend = "af_xdp",
+                        error = %e,
+                        "no huge pages for the UMEM, falling back to regular pages"
+                    );
+                })
+                .ok()
+        } else {
+            None
+        };
+        let (packet_buffer, page_size) = match huge {
+            Some(huge) => huge,
+            None => (
+                alloc_page_aligned(packet_buffer_size)?,
+                unsafe { sysconf(_SC_PAGESIZE) } as usize,
+            ),
+        };
 
         let mem = Arc::new(UnsafeCell::new(packet_buffer));
 
         Ok(Self {
             mem,
             size: packet_buffer_size,
+            page_size,
         })
     }
 
+    /// Returns whether `other` is the same memory area.
+    fn same_area(&self, other: &UmemArea) -> bool {
+        Arc::ptr_eq(&self.mem, &other.mem)
+    }
+
     fn raw_parts(&self) -> (NonNull<u8>, usize) {
         unsafe { (*self.mem.get(), self.size) }
     }
 }
 
-/// Wraps the XDP UMEM info.
-/// Now handles fill/completion ring and frame addresses internally.
+/// Wraps the XDP UMEM fill ring.
+/// Now handles fill ring and frame addresses internally.
 struct UmemManager {
-    umem: Umem,
+    fill_queue: FillQueue,
     consumer: mpsc::Consumer<api::BufferDesc>,
+    frame_size: u32,
+    /// Refills that found the fill ring empty and no free frame.
+    underruns: u64,
 }
 
 impl UmemManager {
-    pub fn create_with_buffer(
-        umem: UmemArea,
+    pub fn new(
+        fill_queue: FillQueue,
         consumer: mpsc::Consumer<api::BufferDesc>,
-    ) -> Result<Self> {
-        Ok(Self {
-            umem: Umem::new(umem).map_err(Error::Generic)?,
+        frame_size: u32,
+    ) -> Self {
+        Self {
+            fill_queue,
             consumer,
-        })
+            frame_size,
+            underruns: 0,
+        }
+    }
+
+    /// Returns the number of frames the kernel has left in the fill ring.
+    fn fill_level(&mut self) -> u32 {
+        let mut ring = self.fill_queue.ring_mut();
+        let size = ring.size();
+        size - ring.nb_free(size)
     }
 
     /// Allocates one frame address from our free array.
-    fn alloc_frame(&mut self) -> Option<u32> {
-        // self.frames.pop()
-        self.consumer.pop().map(|idx| idx as u32)
+    fn alloc_frame(&mut self) -> Option<u64> {
+        // Released packets point past the start of their frame: the kernel masks the
+        // offset out of aligned chunks, but takes unaligned addresses as they are.
+        let frame_size = self.frame_size as usize;
+        self.consumer.pop().map(|idx| {
+            let addr = usize::from(idx);
+            (addr - addr % frame_size) as u64
+        })
     }
 
     // Lo userei quando fallisce in qualche modo la read o la write
@@ -138,15 +222,22 @@ impl UmemManager {
         } as u32;
 
         if wanted == 0 {
+            // An empty ring leaves the kernel with no frame to receive into.
+            let mut ring = self.fill_queue.ring_mut();
+            let size = ring.size();
+            if ring.nb_free(size) == size {
+                trace_verbose!(backend = "af_xdp", "fill ring underrun");
+                self.underruns += 1;
+            }
             return Ok(());
         }
 
-        let available = self.umem.ring_prod_mut().nb_free(wanted);
+        let available = self.fill_queue.ring_mut().nb_free(wanted);
         if available == 0 {
             return Ok(()); // ring is full or no space
         }
 
-        let (reserved, idx) = self.umem.ring_prod_mut().reserve(available);
+        let (reserved, idx) = self.fill_queue.ring_mut().reserve(available);
         if reserved != available {
             return Err(io::Error::other(
                 "refill_fill_ring: not enough descriptors reserved",
@@ -157,85 +248,470 @@ impl UmemManager {
             let addr = self
                 .alloc_frame()
                 .ok_or_else(|| io::Error::other("refill_fill_ring: no free frames"))?;
-            *self.umem.ring_prod_mut().get_addr(idx + i) = addr as u64;
+            *self.fill_queue.ring_mut().get_addr(idx + i) = addr;
         }
 
-        self.umem.ring_prod_mut().submit(available);
+        self.fill_queue.ring_mut().submit(available);
+        trace_event!(
+            trace,
+            backend = "af_xdp",
+            frames = available,
+            "fill ring refilled"
+        );
         Ok(())
     }
 } //
 
-fn complete_tx(xsk: &Sock) -> io::Result<()> {
-    let umem = &mut xsk.umem_manager.borrow_mut().umem;
-    let (completed, mut idx) = umem.ring_cons_mut().peek(64);
+/// Reaps up to `max` entries of the completion ring, returning the number of frames
+/// recycled.
+fn complete_tx(xsk: &TxSock, max: u32) -> u32 {
+    let mut cq = xsk.completion_queue.borrow_mut();
+    let mut ring = cq.ring_mut();
+    let (completed, mut idx) = ring.peek(max);
     if completed == 0 {
-        return Ok(());
+        return 0;
     }
     // For each completion, get the “addr” (which was the frame address) and
-    // recycle it into your free/producer ring so it can be used again.
+    // recycle it into the TX free list so it can be used again.
+    let mut free_frames = xsk.free_frames.borrow_mut();
     for _ in 0..completed {
-        let addr = umem.ring_cons_mut().get_addr(idx);
+        let addr = ring.get_addr(idx);
         idx += 1;
-        xsk.ctx
-            .producer
-            .borrow_mut()
-            .push(api::BufferDesc::from(addr as usize));
+        // Forwarded RX frames come back with the offset of the packet data.
+        free_frames.push(addr - addr % xsk.frame_size as u64);
     }
-    umem.ring_cons_mut().release(completed);
-    xsk.ctx.producer.borrow_mut().flush();
-
-    Ok(())
+    ring.release(completed);
+    completed
 }
 
 /// Wraps an AF_XDP socket.
 pub struct Sock {
+    rx: RxSock,
+    tx: TxSock,
+    prev_stats: Cell<StatsRecord>,
+    ifname: String,
+    /// Bound in zero-copy mode.
+    zero_copy: bool,
+}
+
+/// Receive half of an AF_XDP socket: the RX ring and the UMEM fill ring.
+pub struct RxSock {
     ctx: Ctx,
-    xsk: RefCell<XskSocket>,
-    outstanding_tx: u32,
+    xsk: RefCell<XskRx>,
     umem_manager: RefCell<UmemManager>,
     stats: Cell<StatsRecord>,
-    prev_stats: Cell<StatsRecord>,
+    queue: u32,
+    rx_metadata: bool,
+    rx_timestamp: bool,
+    rx_vlan: bool,
+    vlan_strip: bool,
+    fcs: api::RxFcs,
+    snaplen: Option<u32>,
+    clock: api::SoftwareClock,
+    filter: api::UserspaceFilter,
+    segments: api::SegmentTracker,
+    metrics: api::SocketMetrics,
+    /// Busy polling is enabled: every wakeup syscall runs the driver's NAPI loop.
+    busy_poll: bool,
+    refill_policy: RefillPolicy,
+}
+
+/// Transmit half of an AF_XDP socket: the TX ring and the UMEM completion ring.
+///
+/// TX frames come from a partition of the UMEM reserved at creation time, so the two
+/// halves never compete for the same free list.
+pub struct TxSock {
+    ctx: Ctx,
+    xsk: RefCell<XskTx>,
+    completion_queue: RefCell<CompletionQueue>,
+    free_frames: RefCell<Vec<u64>>,
+    /// Number of UMEM frames owned by the TX half.
+    tx_frames: usize,
+    frame_size: u32,
+    /// Bytes kept free in front of the packets built in TX slots.
+    headroom: u32,
+    stats: Cell<StatsRecord>,
+    flush_state: api::FlushState,
+    pacer: api::Pacer,
+    vlan_insert: Option<api::VlanTag>,
+    completions: api::TxCompletions,
+    metrics: api::SocketMetrics,
+    /// Busy polling is enabled: every wakeup syscall runs the driver's NAPI loop.
+    busy_poll: bool,
+    /// Most completions reaped by a flush.
+    completion_batch: u32,
 }
 
 impl Sock {
+    /// Opens one socket on each channel of `dev` that receives packets, as reported by
+    /// ethtool (`ethtool -l <if>`), in queue order.
+    ///
+    /// The sockets share the XDP program attached by the first one, each inserted into
+    /// its XSKMAP at the index of its queue, so the NIC's RSS decides which socket gets a
+    /// packet. The group is ready for [`spawn_per_queue`](api::spawn_per_queue):
+    ///
+    /// ```ignore
+    /// let group = af_xdp::Sock::create_all_queues("eth0", flags)?;
+    /// let workers = api::spawn_per_queue(group, |queue, socket| worker(queue, socket))?;
+    /// ```
+    pub fn create_all_queues(dev: &str, flags: AfXdpFlags) -> Result<api::SocketGroup<Sock>> {
+        let queues = api::netdev::rx_channels(dev)?;
+        api::SocketGroup::open(dev, queues, flags)
+    }
+
+    /// Returns a handle on the built-in XDP program attached to the interface, or
+    /// `None` if the socket uses another program; see [`XdpSteering`].
+    pub fn xdp_steering(&self) -> Option<XdpSteering> {
+        self.rx.xdp_steering()
+    }
+
+    /// Returns a handle on the maps of the XDP program attached to the interface, or
+    /// `None` if the socket uses an [`XdpProgram::Pinned`] one; see [`XdpMaps`].
+    pub fn xdp_maps(&self) -> Option<XdpMaps> {
+        self.rx.xdp_maps()
+    }
+
+    /// Returns the size of the pages backing the UMEM of the socket; see
+    /// [`AfXdpFlags::hugepages`].
+    pub fn umem_page_size(&self) -> usize {
+        self.rx.ctx.buffer.page_size
+    }
+
+    /// Returns the kernel's counters of the socket.
+    pub fn xdp_stats(&self) -> Result<XdpStats> {
+        Ok(xdp_statistics(self.as_raw_fd())?)
+    }
+
+    /// Hands the released frames back to the kernel; see [`RxSock::refill`].
+    pub fn refill(&self) -> Result<()> {
+        self.rx.refill()
+    }
+
+    /// Recycles the completed TX frames; see [`TxSock::reap_completions`].
+    pub fn reap_completions(&self) -> usize {
+        self.tx.reap_completions()
+    }
+
+    /// Returns the number of TX frames not completed yet; see
+    /// [`TxSock::outstanding_tx`].
+    pub fn outstanding_tx(&self) -> usize {
+        self.tx.outstanding_tx()
+    }
+
+    /// Returns whether the XDP program in front of the socket was offloaded to the
+    /// NIC; see [`AfXdpFlags::hw_offload`].
+    pub fn xdp_offloaded(&self) -> bool {
+        let xsk = self.rx.xsk.borrow();
+        xsk.program().is_some_and(|program| program.is_offloaded())
+    }
+}
+
+impl RxSock {
+    /// Returns the kernel's counters of the socket, shared with its TX half.
+    pub fn xdp_stats(&self) -> Result<XdpStats> {
+        Ok(xdp_statistics(self.as_raw_fd())?)
+    }
+
+    /// Returns a handle on the built-in XDP program attached to the interface, or
+    /// `None` if the socket uses another program; see [`XdpSteering`].
+    pub fn xdp_steering(&self) -> Option<XdpSteering> {
+        let xsk = self.xsk.borrow();
+        let program = xsk.program().filter(|program| program.is_builtin())?;
+        Some(XdpSteering {
+            program: program.clone(),
+        })
+    }
+
+    /// Returns a handle on the maps of the XDP program attached to the interface, or
+    /// `None` if the socket uses an [`XdpProgram::Pinned`] one; see [`XdpMaps`].
+    pub fn xdp_maps(&self) -> Option<XdpMaps> {
+        let xsk = self.xsk.borrow();
+        Some(XdpMaps {
+            program: xsk.program()?.clone(),
+        })
+    }
+
+    /// Hands the frames released by the application to the kernel through the fill
+    /// ring, and wakes the driver up if it waits for them; the only way frames go back
+    /// with [`RefillPolicy::Manual`].
+    pub fn refill(&self) -> Result<()> {
+        let rx = self.xsk.borrow();
+        Ok(self.refill_ring(&rx)?)
+    }
+
+    /// Refills the fill ring ahead of a receive, if the refill policy asks for it.
+    fn refill_ahead(&self, rx: &XskRx) -> io::Result<()> {
+        let due = match self.refill_policy {
+            RefillPolicy::Eager => true,
+            RefillPolicy::Below(frames) => self.umem_manager.borrow_mut().fill_level() < frames,
+            RefillPolicy::OnEmpty | RefillPolicy::Manual => false,
+        };
+        if due {
+            self.refill_ring(rx)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the next packet that passes the filter, or `None` if the ring is empty.
+    fn next_packet(&self) -> Result<Option<(Token, Meta)>> {
+        let mut rx = self.xsk.borrow_mut();
+        self.refill_ahead(&rx)?;
+        // Only a manual policy leaves an empty RX ring alone.
+        let mut refilled = self.refill_policy == RefillPolicy::Manual;
+        loop {
+            match rx.rx_mut().next() {
+                Some(slot) => {
+                    if let Some(pkt) = self.recv_inner(slot)? {
+                        return Ok(Some(pkt));
+                    }
+                }
+                None if !refilled => {
+                    self.refill_ring(&rx)?;
+                    refilled = true;
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Refills the fill ring of `rx`, and wakes the driver up if it waits for the new
+    /// frames.
+    fn refill_ring(&self, rx: &XskRx) -> io::Result<()> {
+        self.metrics.rx_sync();
+        self.ctx.reclaim();
+        let mut umem_manager = self.umem_manager.borrow_mut();
+        let underruns = umem_manager.underruns;
+        umem_manager.refill_fill_ring()?;
+        self.metrics.dropped(umem_manager.underruns - underruns);
+        let needs_wakeup =
+            rx.uses_need_wakeup() && umem_manager.fill_queue.ring_mut().needs_wakeup();
+        if self.busy_poll || needs_wakeup {
+            // Without the flag, the driver processes the fill ring on its own, unless
+            // busy polling prevents it from being scheduled.
+            unsafe {
+                libc::recvfrom(
+                    rx.fd(),
+                    std::ptr::null_mut(),
+                    0,
+                    libc::MSG_DONTWAIT,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                )
+            };
+        }
+        Ok(())
+    }
+
+    /// Turns a descriptor into a packet; frames rejected by the filter are released.
     #[inline(never)]
-    fn recv_inner(&self, slot: XdpDescData) -> Result<(Token, Meta)> {
+    fn recv_inner(&self, slot: XdpDescData) -> Result<Option<(Token, Meta)>> {
         let offset = slot.offset;
         let len = slot.len;
 
+        let buffer_pool = self.ctx.index;
+        let idx = api::BufferDesc::from(offset as usize);
+        let frame = unsafe { &mut *self.ctx.buffer(idx, len as usize) };
+        let more = slot.options & XDP_PKT_CONTD != 0;
+        let first = match self.segments.current() {
+            api::Segment::First => true,
+            api::Segment::Next => false,
+            api::Segment::Dropped => {
+                api::Context::release(&self.ctx, idx);
+                self.segments.advance(false, more);
+                return Ok(None);
+            }
+        };
+        if first && !self.filter.accepts(frame) {
+            api::Context::release(&self.ctx, idx);
+            self.segments.advance(false, more);
+            return Ok(None);
+        }
+        self.segments.advance(true, more);
+
         let mut stats = self.stats.get();
         stats.rx_bytes += len as u64;
-        stats.rx_packets += 1;
+        stats.rx_packets += first as u64;
         self.stats.set(stats);
+        self.metrics.rx(len as usize);
+
+        // The FCS ends the last frame of a packet.
+        let (_, len) = match more {
+            true => (len, len),
+            false => self.fcs.apply(len, len),
+        };
+        if !first {
+            // Only the first frame carries the headers and the program's hints.
+            let meta = Meta {
+                len,
+                wirelen: len,
+                queue: self.queue,
+                rss_hash: None,
+                timestamp: None,
+                hw_timestamp: false,
+                vlan: None,
+                fcs: !more && self.fcs.present(),
+                more,
+            };
+            return Ok(Some((Token::new(idx, buffer_pool, len), meta)));
+        }
+
+        // SAFETY: the kernel reserves XDP_PACKET_HEADROOM in front of every frame.
+        let (base, _) = self.ctx.buffer.raw_parts();
+        let data = unsafe { base.as_ptr().add(offset as usize) };
+        let stripped = if self.rx_vlan {
+            let hint =
+                unsafe { data.sub(RX_HASH_HINT_SIZE + RX_TIMESTAMP_HINT_SIZE + RX_VLAN_HINT_SIZE) };
+            let hint = unsafe { (hint as *const [u8; RX_VLAN_HINT_SIZE]).read() };
+            // A zero TPID means the NIC left the frame as it was.
+            match u16::from_be_bytes([hint[0], hint[1]]) {
+                0 => None,
+                tpid => Some(api::VlanTag {
+                    tpid,
+                    tci: u16::from_ne_bytes([hint[2], hint[3]]),
+                }),
+            }
+        } else {
+            None
+        };
+        let (len, vlan) = match stripped {
+            Some(tag) => (len, Some(tag)),
+            None if self.vlan_strip => match api::VlanTag::strip(frame) {
+                Some((tag, stripped)) => (stripped as u32, Some(tag)),
+                None => (len, None),
+            },
+            None => (len, api::VlanTag::parse(frame)),
+        };
+        let caplen = self.snaplen.map_or(len, |snaplen| len.min(snaplen));
 
-        let buffer_pool = self.ctx.index;
         let token = ManuallyDrop::new(Token {
-            idx: api::BufferDesc::from(offset as usize),
-            len,
+            idx,
+            len: caplen,
             buffer_pool,
         });
-        let meta = Meta {};
-        Ok((ManuallyDrop::into_inner(token), meta))
+        let rss_hash = if self.rx_metadata {
+            let hint = unsafe { data.sub(RX_HASH_HINT_SIZE) };
+            Some(unsafe { (hint as *const u32).read_unaligned() })
+        } else {
+            None
+        };
+        let hw_timestamp = if self.rx_timestamp {
+            let hint = unsafe { data.sub(RX_HASH_HINT_SIZE + RX_TIMESTAMP_HINT_SIZE) };
+            // Drivers store 0 when the NIC did not timestamp the packet.
+            match unsafe { (hint as *const u64).read_unaligned() } {
+                0 => None,
+                ns => Some(api::Timestamp::from_nanos(ns)),
+            }
+        } else {
+            None
+        };
+        let meta = Meta {
+            len: caplen,
+            wirelen: len,
+            queue: self.queue,
+            rss_hash,
+            timestamp: hw_timestamp.or_else(|| self.clock.now()),
+            hw_timestamp: hw_timestamp.is_some(),
+            vlan,
+            fcs: !more && self.fcs.present(),
+            more,
+        };
+        Ok(Some((ManuallyDrop::into_inner(token), meta)))
+    }
+}
+
+impl api::RxSocket for RxSock {
+    type Context = Ctx;
+    type Metadata = Meta;
+
+    fn recv_token(&self) -> Result<(Token, Self::Metadata)> {
+        Ok(self
+            .next_packet()?
+            .ok_or_else(|| io::Error::other("No packets"))?)
+    }
+
+    fn recv_packet(&self) -> Result<(api::Packet<'_, Ctx>, Meta)> {
+        let (token, mut meta) = self.recv_token()?;
+        let mut packet = api::Packet::from(token.consume(&self.ctx));
+        // The kernel only makes whole packets visible on the RX ring.
+        while meta.more {
+            let (token, next) = self.recv_token()?;
+            packet.push(token.consume(&self.ctx));
+            meta.more = next.more;
+            meta.fcs = next.fcs;
+        }
+        meta.len = packet.len() as u32;
+        meta.wirelen = meta.len;
+        Ok((packet, meta))
     }
 
-    fn send_inner<'a>(&self, mut slot: TxSlot<'a>, payload: &[u8]) -> Result<()> {
+    fn try_recv_token(&self) -> Result<(Token, Self::Metadata)> {
+        self.next_packet()?.ok_or(Error::WouldBlock)
+    }
+
+    fn recv_batch(&self, budget: usize) -> Result<Vec<(Token, Self::Metadata)>> {
+        let mut rx = self.xsk.borrow_mut();
+        self.refill_ahead(&rx)?;
+        let mut batch = Vec::with_capacity(budget);
+        api::fill_batch(&mut batch, rx.rx_mut().take(budget), |slot| {
+            self.recv_inner(slot)
+        })?;
+        if batch.is_empty() && budget > 0 && self.refill_policy != RefillPolicy::Manual {
+            self.refill_ring(&rx)?;
+            api::fill_batch(&mut batch, rx.rx_mut().take(budget), |slot| {
+                self.recv_inner(slot)
+            })?;
+            if batch.is_empty() {
+                return Err(io::Error::other("No packets").into());
+            }
+        }
+        Ok(batch)
+    }
+
+    fn set_filter(&self, expr: &str) -> Result<()> {
+        // Filtering runs in userspace: the XDP program only redirects to the socket.
+        self.filter.set(expr)
+    }
+
+    fn dropped(&self) -> api::Dropped {
+        api::Dropped {
+            fill_underruns: self.umem_manager.borrow().underruns,
+            ..Default::default()
+        }
+    }
+
+    fn context(&self) -> &Self::Context {
+        &self.ctx
+    }
+}
+
+impl TxSock {
+    fn send_inner<'a>(&self, mut slot: DescSlot<'a>, payload: &[u8]) -> Result<()> {
         let frame_addr = self
-            .umem_manager
+            .free_frames
             .borrow_mut()
-            .alloc_frame()
+            .pop()
             .ok_or_else(|| io::Error::other("No free frames for TX"))?;
 
-        // Assign the descriptor’s address
-        *slot.offset_mut() = frame_addr as u64;
-        *slot.len_mut() = payload.len() as u32;
-
         // Actually copy the packet into UMEM
         let buffer_index = api::BufferDesc::from(frame_addr as usize);
-        let buf = unsafe { self.ctx.buffer(buffer_index, payload.len()) };
+        let len = match self.vlan_insert {
+            Some(tag) => {
+                let buf = unsafe { &mut *self.ctx.buffer(buffer_index, payload.len() + 4) };
+                tag.write_tagged(payload, buf)
+                    .expect("buffer sized for the tag")
+            }
+            None => {
+                let buf = unsafe { self.ctx.buffer(buffer_index, payload.len()) };
+                unsafe {
+                    (*buf).copy_from_slice(payload);
+                }
+                payload.len()
+            }
+        };
 
-        unsafe {
-            (*buf).copy_from_slice(payload);
-        }
+        // Assign the descriptor’s address
+        *slot.offset_mut() = frame_addr;
+        *slot.len_mut() = len as u32;
 
         // Update stats, etc.
         // self.stats.update(|mut s| {
@@ -244,31 +720,39 @@ impl Sock {
         //     s
         // });
         let mut stats = self.stats.get();
-        stats.tx_bytes += payload.len() as u64;
+        stats.tx_bytes += len as u64;
         stats.tx_packets += 1;
         self.stats.set(stats);
+        self.metrics.tx(len);
 
         Ok(())
     }
-}
 
-impl api::Socket for Sock {
-    type Context = Ctx;
-    type Metadata = Meta;
-    type Flags = AfXdpFlags;
-    fn recv_token(&self) -> Result<(Token, Self::Metadata)> {
-        let mut rx = self.xsk.borrow_mut();
-        if let Some(slot) = rx.rx_mut().next() {
-            self.recv_inner(slot)
-        } else {
-            self.umem_manager.borrow_mut().refill_fill_ring()?;
-            let tmp = rx
-                .rx_mut()
-                .next()
-                .ok_or_else(|| io::Error::other("No packets"))?;
-            self.recv_inner(tmp)
+    /// Recycles every TX frame whose packet the kernel completed, returning their
+    /// number; the way frames come back when
+    /// [`completion_batch`](AfXdpFlags::completion_batch) is 0.
+    pub fn reap_completions(&self) -> usize {
+        complete_tx(self, u32::MAX) as usize
+    }
+
+    /// Returns the number of TX frames holding a packet the kernel has not completed
+    /// yet, or whose completion was not reaped.
+    pub fn outstanding_tx(&self) -> usize {
+        self.tx_frames - self.free_frames.borrow().len()
+    }
+
+    /// Records a queued packet of `len` bytes and flushes the TX ring if the pacing or
+    /// the flush policy asks for it.
+    fn queued(&self, len: usize) {
+        self.completions.queued();
+        if self.pacer.pace(len) | self.flush_state.queued() {
+            api::TxSocket::flush(self);
         }
     }
+}
+
+impl api::TxSocket for TxSock {
+    type Slot<'a> = TxSlot<'a>;
 
     fn send(&self, packet: &[u8]) -> Result<()> {
         if let Some(slot) = self.xsk.borrow_mut().tx_mut().iter().next() {
@@ -281,18 +765,79 @@ impl api::Socket for Sock {
                 return Err(Error::NoMemory);
             }
         }
+        self.queued(packet.len());
+        Ok(())
+    }
+
+    fn send_payload<C: api::Context>(&self, packet: api::Payload<'_, C>) -> Result<()> {
+        // Only sockets created from the same `SharedUmem` share the frames of a UMEM.
+        // A tag can only be inserted into a copy: the frame may have no headroom for it.
+        let Some(ctx) = api::same_backend::<Ctx, C>(&packet)
+            .filter(|ctx| ctx.buffer.same_area(&self.ctx.buffer) && self.vlan_insert.is_none())
+        else {
+            return self.send(&packet);
+        };
+        let mut xsk = self.xsk.borrow_mut();
+        let desc = match xsk.tx_mut().reserve() {
+            Some(desc) => desc,
+            None => {
+                drop(xsk);
+                self.flush();
+                xsk = self.xsk.borrow_mut();
+                xsk.tx_mut().reserve().ok_or(Error::NoMemory)?
+            }
+        };
+        let frame = self.free_frames.borrow_mut().pop();
+        let frame = match frame {
+            Some(frame) => frame,
+            None => {
+                complete_tx(self, u32::MAX);
+                match self.free_frames.borrow_mut().pop() {
+                    Some(frame) => frame,
+                    None => {
+                        // SAFETY: `desc` is the last descriptor reserved.
+                        unsafe { xsk.tx_mut().unreserve(desc) };
+                        return Err(Error::NoMemory);
+                    }
+                }
+            }
+        };
+        let token = packet.into_token();
+        unsafe {
+            (*desc).addr = usize::from(token.buffer_desc()) as u64;
+            (*desc).len = token.size();
+            (*desc).options = 0;
+        }
+        drop(xsk);
+        // The TX frame takes the place of the forwarded one in the fill ring.
+        api::Context::release(ctx, api::BufferDesc::from(frame as usize));
+        let mut stats = self.stats.get();
+        stats.tx_bytes += token.size() as u64;
+        stats.tx_packets += 1;
+        self.stats.set(stats);
+        self.metrics.tx(token.size() as usize);
+        self.queued(token.size() as usize);
         Ok(())
     }
 
     fn flush(&self) {
+        self.flush_state.flushed();
+        self.metrics.tx_flush();
         unsafe {
             self.xsk.borrow_mut().tx_mut().iter().sync();
         }
 
-        complete_tx(self).unwrap();
-        unsafe {
+        if self.completion_batch != 0 {
+            complete_tx(self, self.completion_batch);
+        }
+        let xsk = self.xsk.borrow();
+        if !self.busy_poll && !xsk.needs_wakeup() {
+            // The kernel is still draining the ring: no syscall needed.
+            return;
+        }
+        let rc = unsafe {
             libc::sendto(
-                self.xsk.borrow().fd(),
+                xsk.fd(),
                 std::ptr::null_mut(),
                 0,
                 libc::MSG_DONTWAIT,
@@ -300,67 +845,1349 @@ impl api::Socket for Sock {
                 0,
             )
         };
+        trace_event!(trace, backend = "af_xdp", "TX ring flushed");
+        if rc < 0 {
+            // EAGAIN, EBUSY and ENOBUFS only mean the kernel is still draining the ring.
+            let err = io::Error::last_os_error();
+            if !matches!(
+                err.raw_os_error(),
+                Some(libc::EAGAIN | libc::EBUSY | libc::ENOBUFS)
+            ) {
+                trace_event!(warn, backend = "af_xdp", error = %err, "TX wakeup failed");
+            }
+        }
+    }
+
+    fn send_with_cookie(&self, packet: &[u8], cookie: u64) -> Result<()> {
+        self.send(packet)?;
+        self.completions.tag_last(cookie);
+        Ok(())
+    }
+
+    fn tx_completions(&self, out: &mut Vec<u64>) -> usize {
+        // A TX frame is back in the free list once the kernel completed its packet.
+        self.reap_completions();
+        self.completions.drain(self.outstanding_tx(), out)
+    }
+
+    fn tx_reserve(&self, len: usize) -> Result<TxSlot<'_>> {
+        let headroom = self.headroom as usize;
+        if len > self.frame_size as usize - headroom {
+            return Err(Error::TooBigPacket(len));
+        }
+        let mut xsk = self.xsk.borrow_mut();
+        let desc = match xsk.tx_mut().reserve() {
+            Some(desc) => desc,
+            None => {
+                drop(xsk);
+                self.flush();
+                xsk = self.xsk.borrow_mut();
+                xsk.tx_mut().reserve().ok_or(Error::NoMemory)?
+            }
+        };
+        let frame = self.free_frames.borrow_mut().pop();
+        let frame = match frame {
+            Some(frame) => frame,
+            None => {
+                complete_tx(self, u32::MAX);
+                match self.free_frames.borrow_mut().pop() {
+                    Some(frame) => frame,
+                    None => {
+                        // SAFETY: `desc` is the last descriptor reserved.
+                        unsafe { xsk.tx_mut().unreserve(desc) };
+                        return Err(Error::NoMemory);
+                    }
+                }
+            }
+        };
+        let idx = api::BufferDesc::from(frame as usize + headroom);
+        let data = unsafe { &mut *self.ctx.buffer(idx, self.frame_size as usize - headroom) };
+        Ok(TxSlot {
+            sock: self,
+            xsk,
+            desc,
+            frame: Some(frame),
+            offset: headroom,
+            data,
+            checksum: api::TxChecksum::NONE,
+        })
+    }
+}
+
+/// Zero-copy TX slot of the AF_XDP backend: a TX descriptor and a UMEM frame.
+pub struct TxSlot<'a> {
+    sock: &'a TxSock,
+    xsk: std::cell::RefMut<'a, XskTx>,
+    desc: *mut xdp_desc,
+    frame: Option<u64>,
+    /// Offset of `data` in the frame.
+    offset: usize,
+    data: &'a mut [u8],
+    checksum: api::TxChecksum,
+}
+
+impl TxSlot<'_> {
+    /// Returns the number of bytes [`prepend`](Self::prepend) can still add in front
+    /// of the slot.
+    pub fn headroom(&self) -> usize {
+        self.offset
+    }
+
+    /// Grows the slot by `len` bytes at the front, taken from the frame headroom (see
+    /// [`AfXdpFlags::frame_headroom`]), so that the slot starts with `len` new bytes to
+    /// fill in followed by what it held, e.g. to put an encapsulation header in front
+    /// of a packet already built without moving it.
+    pub fn prepend(&mut self, len: usize) -> Result<()> {
+        if len > self.offset {
+            return Err(Error::TooBigPacket(self.data.len() + len));
+        }
+        self.offset -= len;
+        let frame = self.frame.expect("slot not committed");
+        let idx = api::BufferDesc::from(frame as usize + self.offset);
+        // SAFETY: the slot owns the whole frame.
+        self.data = unsafe { &mut *self.sock.ctx.buffer(idx, self.data.len() + len) };
+        Ok(())
+    }
+}
+
+impl std::ops::Deref for TxSlot<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.data
+    }
+}
+
+impl std::ops::DerefMut for TxSlot<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.data
+    }
+}
+
+impl api::TxSlot for TxSlot<'_> {
+    fn commit(mut self, len: usize) -> Result<()> {
+        if len > self.data.len() {
+            return Err(Error::TooBigPacket(len));
+        }
+        api::checksum::fill(&mut self.data[..len], self.checksum);
+        let len = match self.sock.vlan_insert {
+            Some(tag) => tag
+                .insert(self.data, len)
+                .ok_or(Error::TooBigPacket(len + 4))?,
+            None => len,
+        };
+        let frame = self.frame.take().expect("slot committed twice");
+        unsafe {
+            (*self.desc).addr = frame + self.offset as u64;
+            (*self.desc).len = len as u32;
+            (*self.desc).options = 0;
+        }
+        let mut stats = self.sock.stats.get();
+        stats.tx_bytes += len as u64;
+        stats.tx_packets += 1;
+        self.sock.stats.set(stats);
+        self.sock.metrics.tx(len);
+        // Release the TX ring before a possible flush.
+        let sock = self.sock;
+        drop(self);
+        sock.queued(len);
+        Ok(())
+    }
+
+    fn set_checksum(&mut self, csum: api::TxChecksum) {
+        self.checksum = csum;
+    }
+}
+
+impl Drop for TxSlot<'_> {
+    fn drop(&mut self) {
+        if let Some(frame) = self.frame.take() {
+            // SAFETY: the TX ring stayed borrowed, so `desc` is still the last one reserved.
+            unsafe { self.xsk.tx_mut().unreserve(self.desc) };
+            self.sock.free_frames.borrow_mut().push(frame);
+        }
+    }
+}
+
+impl api::Socket for Sock {
+    type Context = Ctx;
+    type Metadata = Meta;
+    type Flags = AfXdpFlags;
+    type Rx = RxSock;
+    type Tx = TxSock;
+    type Slot<'a> = TxSlot<'a>;
+
+    fn recv_token(&self) -> Result<(Token, Self::Metadata)> {
+        self.rx.recv_token()
+    }
+
+    fn try_recv_token(&self) -> Result<(Token, Self::Metadata)> {
+        self.rx.try_recv_token()
+    }
+
+    fn recv_token_timeout(&self, timeout: Duration) -> Result<(Token, Self::Metadata)> {
+        api::poll::recv_token_polling(self, self.as_raw_fd(), timeout)
+    }
+
+    fn recv_packet(&self) -> Result<(api::Packet<'_, Ctx>, Meta)> {
+        self.rx.recv_packet()
+    }
+
+    fn recv_batch(&self, budget: usize) -> Result<Vec<(Token, Self::Metadata)>> {
+        self.rx.recv_batch(budget)
+    }
+
+    fn send(&self, packet: &[u8]) -> Result<()> {
+        self.tx.send(packet)
+    }
+
+    fn send_payload<C: api::Context>(&self, packet: api::Payload<'_, C>) -> Result<()> {
+        self.tx.send_payload(packet)
+    }
+
+    fn flush(&self) {
+        self.tx.flush();
+    }
+
+    fn send_with_cookie(&self, packet: &[u8], cookie: u64) -> Result<()> {
+        self.tx.send_with_cookie(packet, cookie)
+    }
+
+    fn tx_completions(&self, out: &mut Vec<u64>) -> usize {
+        self.tx.tx_completions(out)
+    }
+
+    fn tx_reserve(&self, len: usize) -> Result<TxSlot<'_>> {
+        self.tx.tx_reserve(len)
+    }
+
+    fn set_filter(&self, expr: &str) -> Result<()> {
+        self.rx.set_filter(expr)
+    }
+
+    fn set_promiscuous(&self, enable: bool) -> Result<()> {
+        Ok(api::netdev::set_promiscuous(&self.ifname, enable)?)
+    }
+
+    fn join_multicast(&self, mac: [u8; 6]) -> Result<()> {
+        Ok(api::netdev::set_multicast(&self.ifname, mac, true)?)
+    }
+
+    fn leave_multicast(&self, mac: [u8; 6]) -> Result<()> {
+        Ok(api::netdev::set_multicast(&self.ifname, mac, false)?)
+    }
+
+    fn link_info(&self) -> Result<api::LinkInfo> {
+        api::link_info(&self.ifname)
+    }
+
+    fn steer(&self, spec: api::FlowSpec, queue: usize) -> Result<api::FlowRule> {
+        Ok(api::ntuple_insert(&self.ifname, &spec, queue)?)
+    }
+
+    fn unsteer(&self, rule: api::FlowRule) -> Result<()> {
+        Ok(api::ntuple_delete(&self.ifname, rule)?)
     }
 
     fn create(portspec: &str, queue: Option<usize>, flags: Self::Flags) -> Result<Self> {
+        SharedUmem::with_config(UmemConfig {
+            num_frames: flags.num_frames,
+            frame_size: flags.frame_size,
+            frame_headroom: flags.frame_headroom,
+            hugepages: flags.hugepages,
+            unaligned_chunks: flags.unaligned_chunks,
+        })?
+        .create(portspec, queue, flags)
+    }
+
+    fn create_group(portspec: &str, flags: Self::Flags) -> Result<api::SocketGroup<Self>> {
+        Self::create_all_queues(portspec, flags)
+    }
+
+    fn context(&self) -> &Self::Context {
+        &self.rx.ctx
+    }
+
+    fn stats(&self) -> Result<api::Stats> {
+        let rx = self.rx.stats.get();
+        let tx = self.tx.stats.get();
+        let xdp = xdp_statistics(self.as_raw_fd())?;
+        Ok(api::Stats {
+            rx_packets: rx.rx_packets,
+            rx_bytes: rx.rx_bytes,
+            rx_dropped: xdp.rx_dropped + xdp.rx_ring_full,
+            rx_errors: xdp.rx_invalid_descs,
+            tx_packets: tx.tx_packets,
+            tx_bytes: tx.tx_bytes,
+            tx_errors: xdp.tx_invalid_descs,
+        })
+    }
+
+    fn dropped(&self) -> api::Dropped {
+        api::RxSocket::dropped(&self.rx)
+    }
+
+    fn capabilities(&self) -> api::Capabilities {
+        // Frames live in the UMEM even when the driver falls back to copy mode.
+        api::Capabilities {
+            zero_copy_rx: true,
+            zero_copy_tx: true,
+            writable_rx: true,
+            hw_timestamps: self.rx.rx_timestamp,
+            multi_queue: true,
+            socket_groups: true,
+            vlan_strip: self.rx.vlan_strip || self.rx.rx_vlan,
+            tx_checksum: false,
+            tso: false,
+            kernel_filter: false,
+            zero_copy_driver: self.zero_copy,
+            max_frame_size: (self.tx.frame_size - self.tx.headroom) as usize - XDP_PACKET_HEADROOM,
+        }
+    }
+
+    fn close(self) -> Result<()> {
+        // Every TX frame is back in the free list once the kernel completed it.
+        api::drain_tx(|| {
+            api::TxSocket::flush(&self.tx);
+            self.tx.reap_completions();
+            Ok(self.tx.outstanding_tx() != 0)
+        })
+    }
+
+    fn split(self) -> (RxSock, TxSock) {
+        (self.rx, self.tx)
+    }
+}
+
+/// Layout of a [`SharedUmem`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct UmemConfig {
+    pub num_frames: u32,
+    pub frame_size: u32,
+    /// See [`AfXdpFlags::frame_headroom`].
+    pub frame_headroom: u32,
+    /// See [`AfXdpFlags::hugepages`].
+    pub hugepages: bool,
+    /// See [`AfXdpFlags::unaligned_chunks`].
+    pub unaligned_chunks: bool,
+}
+
+impl Default for UmemConfig {
+    fn default() -> Self {
+        Self {
+            num_frames: 4096,
+            frame_size: 2048,
+            frame_headroom: 0,
+            hugepages: false,
+            unaligned_chunks: false,
+        }
+    }
+}
+
+/// A UMEM shared by several AF_XDP sockets (`XDP_SHARED_UMEM`), on different queues of
+/// one or more interfaces.
+///
+/// Every socket created from it takes [`num_frames`](AfXdpFlags::num_frames) frames
+/// from the shared frame allocator for its rings (its
+/// [`frame_size`](AfXdpFlags::frame_size) and
+/// [`frame_headroom`](AfXdpFlags::frame_headroom) are ignored), and owns its own fill
+/// and completion rings. Since all the frames live in the same memory area, [`forward`]
+/// hands a packet received by one socket over to the TX ring of any other without
+/// copying it, e.g. in a multi-queue receiver or in a forwarder receiving on one queue
+/// and transmitting on another.
+///
+/// The frames of a socket are not returned to the allocator when it is dropped; the
+/// memory is freed once every socket of the UMEM is.
+///
+/// ```ignore
+/// let umem = af_xdp::SharedUmem::new(8192, 2048)?;
+/// let flags = af_xdp::AfXdpFlags { num_frames: 4096, ..Default::default() };
+/// let rx = umem.create("eth0", Some(0), flags.clone())?;
+/// let tx = umem.create("eth1", Some(0), flags)?;
+/// let (packet, _meta) = rx.recv()?;
+/// api::forward(packet, &tx)?;
+/// ```
+///
+/// [`forward`]: api::forward
+#[derive(Clone)]
+pub struct SharedUmem {
+    inner: Arc<SharedUmemInner>,
+}
+
+struct SharedUmemInner {
+    area: UmemArea,
+    handle: SharedUmemRef,
+    /// The UMEM with the fill and completion rings created with it, taken by the
+    /// first socket.
+    first: Mutex<Option<Umem>>,
+    num_frames: u32,
+    frame_size: u32,
+    frame_headroom: u32,
+    unaligned_chunks: bool,
+    /// Index of the first frame not handed to a socket yet.
+    next_frame: AtomicU32,
+}
+
+// The area and the rings are only used by the sockets, which own them.
+unsafe impl Send for SharedUmemInner {}
+unsafe impl Sync for SharedUmemInner {}
+
+impl SharedUmem {
+    /// Allocates and registers a UMEM of `num_frames` frames of `frame_size` bytes.
+    pub fn new(num_frames: u32, frame_size: u32) -> Result<Self> {
+        Self::with_config(UmemConfig {
+            num_frames,
+            frame_size,
+            ..Default::default()
+        })
+    }
+
+    /// Like [`new`](Self::new), allocating the UMEM from huge pages, which reduces the
+    /// TLB misses with many frames, if the system has enough of them (see
+    /// `/proc/sys/vm/nr_hugepages`); [`page_size`](Self::page_size) tells if it did.
+    pub fn with_hugepages(num_frames: u32, frame_size: u32) -> Result<Self> {
+        Self::with_config(UmemConfig {
+            num_frames,
+            frame_size,
+            hugepages: true,
+            ..Default::default()
+        })
+    }
+
+    /// Allocates and registers a UMEM laid out as described by `config`.
+    ///
+    /// Fails with [`ErrorKind::InvalidInput`] if the kernel would refuse the layout: the
+    /// frame size must be between 2048 bytes and the page size, and a power of two
+    /// unless the chunks are unaligned, with room for packets after the headroom.
+    pub fn with_config(config: UmemConfig) -> Result<Self> {
+        check_layout(
+            config.frame_size,
+            config.frame_headroom,
+            config.unaligned_chunks,
+        )?;
+        let size = config.num_frames as usize * config.frame_size as usize;
+        let area = UmemArea::new(size, config.hugepages)?;
+        let umem = Umem::new(
+            area.clone(),
+            config.frame_size,
+            config.frame_headroom,
+            config.unaligned_chunks,
+        )
+        .map_err(Error::Generic)?;
+        Ok(Self {
+            inner: Arc::new(SharedUmemInner {
+                area,
+                handle: umem.shared(),
+                first: Mutex::new(Some(umem)),
+                num_frames: config.num_frames,
+                frame_size: config.frame_size,
+                frame_headroom: config.frame_headroom,
+                unaligned_chunks: config.unaligned_chunks,
+                next_frame: AtomicU32::new(0),
+            }),
+        })
+    }
+
+    /// Returns the number of frames of the UMEM.
+    pub fn num_frames(&self) -> u32 {
+        self.inner.num_frames
+    }
+
+    /// Returns the size of the frames of the UMEM.
+    pub fn frame_size(&self) -> u32 {
+        self.inner.frame_size
+    }
+
+    /// Returns the headroom in front of the packets of every frame, on top of the
+    /// kernel's `XDP_PACKET_HEADROOM`.
+    pub fn frame_headroom(&self) -> u32 {
+        self.inner.frame_headroom
+    }
+
+    /// Returns whether the UMEM is registered in unaligned chunk mode.
+    pub fn unaligned_chunks(&self) -> bool {
+        self.inner.unaligned_chunks
+    }
+
+    /// Returns the size of the pages backing the UMEM.
+    pub fn page_size(&self) -> usize {
+        self.inner.area.page_size
+    }
+
+    /// Returns the number of frames not taken by a socket yet.
+    pub fn free_frames(&self) -> u32 {
+        self.inner.num_frames - self.inner.next_frame.load(Ordering::Relaxed)
+    }
+
+    /// Opens a socket on `queue` of `portspec` bound to the UMEM, with
+    /// [`num_frames`](AfXdpFlags::num_frames) frames of its own.
+    ///
+    /// Fails with [`Error::NoMemory`] if fewer frames are left, with `Unsupported` if
+    /// `flags` read metadata hints that the [`XdpProgram::Builtin`] program does not
+    /// store, and with the error of the kernel if another socket of the UMEM is bound
+    /// to the same queue.
+    pub fn create(&self, portspec: &str, queue: Option<usize>, flags: AfXdpFlags) -> Result<Sock> {
+        check_hints(&flags)?;
+        if flags.rx_timestamp {
+            // The XDP hint is only filled in once the NIC timestamps every packet.
+            api::netdev::enable_hw_timestamps(portspec)?;
+        }
+        if flags.promiscuous {
+            api::netdev::set_promiscuous(portspec, true)?;
+        }
+        if flags.allmulti {
+            api::netdev::set_allmulti(portspec, true)?;
+        }
+        let fcs = api::netdev::rx_fcs(portspec, flags.fcs);
         let xdp_flags = flags.xdp_flags;
-        let bind_flags = flags.bind_flags;
+        let bind_flags = if flags.need_wakeup {
+            flags.bind_flags | libc::XDP_USE_NEED_WAKEUP
+        } else {
+            flags.bind_flags
+        };
         let num_frames = flags.num_frames;
-        let frame_size = flags.frame_size;
-        let umem_bytes_len = (num_frames * frame_size) as usize;
-        let umem = UmemArea::new(umem_bytes_len)?;
-        let (ctx, consumer) = Ctx::new(num_frames as usize, umem.clone());
+        let frame_size = self.inner.frame_size;
 
-        for i in 0..num_frames {
+        // Only the first socket uses the rings created with the UMEM; the others get
+        // their own.
+        let mut first = wrapper::lock(&self.inner.first)?;
+        // Frames are only reserved under the lock, before binding, so that a socket
+        // that cannot get its frames never takes the rings of the UMEM.
+        let first_frame = self
+            .inner
+            .next_frame
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |next| {
+                next.checked_add(num_frames)
+                    .filter(|&end| end <= self.inner.num_frames)
+            })
+            .map_err(|_| Error::NoMemory)?;
+        let bind = |bind_flags| unsafe {
+            match &*first {
+                Some(umem) => XskSocket::create(
+                    umem,
+                    portspec,
+                    queue.unwrap_or(0) as u32,
+                    xdp_flags,
+                    &flags.xdp_program,
+                    flags.hw_offload,
+                    bind_flags,
+                    num_frames,
+                    num_frames,
+                )
+                .map(|socket| (socket, None)),
+                None => XskSocket::create_shared(
+                    &self.inner.handle,
+                    portspec,
+                    queue.unwrap_or(0) as u32,
+                    xdp_flags,
+                    &flags.xdp_program,
+                    flags.hw_offload,
+                    bind_flags,
+                    num_frames,
+                    num_frames,
+                )
+                .map(|(socket, fq, cq)| (socket, Some((fq, cq)))),
+            }
+        };
+        let create = |bind_flags: u16| match bind(bind_flags) {
+            // Kernels before 5.4 reject the flag.
+            Err(e)
+                if bind_flags & libc::XDP_USE_NEED_WAKEUP != 0
+                    && e.raw_os_error() == Some(libc::EINVAL) =>
+            {
+                bind(bind_flags & !libc::XDP_USE_NEED_WAKEUP)
+            }
+            res => res,
+        };
+        let bound = if flags.zero_copy && bind_flags & (libc::XDP_COPY | libc::XDP_ZEROCOPY) == 0 {
+            match create(bind_flags | libc::XDP_ZEROCOPY) {
+                // Drivers without zero-copy support refuse the bind; generic (SKB)
+                // mode rejects it as invalid.
+                Err(e) if matches!(e.raw_os_error(), Some(libc::EOPNOTSUPP | libc::EINVAL)) => {
+                    trace_event!(
+                        debug,
+                        backend = "af_xdp",
+                        portspec,
+                        error = %e,
+                        "zero-copy bind refused, falling back to copy mode"
+                    );
+                    create(bind_flags | libc::XDP_COPY)
+                }
+                res => res,
+            }
+        } else {
+            create(bind_flags)
+        };
+        let (socket, queues) = match bound {
+            Ok(bound) => bound,
+            Err(e) => {
+                // Still the last frames reserved: the lock is held. The rings of the
+                // UMEM are only taken below, once bound.
+                self.inner
+                    .next_frame
+                    .fetch_sub(num_frames, Ordering::Relaxed);
+                return Err(e.into());
+            }
+        };
+        let (fill_queue, completion_queue) = match queues {
+            Some(queues) => queues,
+            None => first
+                .take()
+                .expect("the first socket binds with the rings of the UMEM")
+                .into_queues(),
+        };
+        drop(first);
+
+        let (ctx, consumer) = Ctx::new(num_frames as usize, self.inner.area.clone());
+        let frame = |i: u32| ((first_frame + i) as usize) * frame_size as usize;
+
+        // The first `tx_frames` frames are reserved for the TX half, the rest feed the fill ring.
+        let tx_frames = flags
+            .tx_frames
+            .unwrap_or(flags.tx_size.min(num_frames / 2))
+            .min(num_frames);
+        let free_frames = (0..tx_frames).map(|i| frame(i) as u64).collect::<Vec<_>>();
+        for i in tx_frames..num_frames {
             let prod = &mut *ctx.producer.borrow_mut();
-            prod.push(api::BufferDesc::from((i as usize) * frame_size as usize));
+            prod.push(api::BufferDesc::from(frame(i)));
         }
         {
             let prod = &mut *ctx.producer.borrow_mut();
             prod.flush();
         }
 
-        let mut umem_manager = UmemManager::create_with_buffer(umem.clone(), consumer)?;
-
-        let socket = unsafe {
-            XskSocket::create(
-                &mut umem_manager.umem,
-                portspec,
-                queue.unwrap_or(0) as u32,
-                xdp_flags,
-                bind_flags,
-                num_frames,
-                num_frames,
-            )?
-        };
-
+        let mut umem_manager = UmemManager::new(fill_queue, consumer, frame_size);
         umem_manager.refill_fill_ring()?;
-        Ok(Self {
-            ctx,
-            xsk: RefCell::new(socket),
-            outstanding_tx: 0,
-            umem_manager: RefCell::new(umem_manager),
-            stats: Cell::new(StatsRecord::default()),
+        let (xsk_rx, xsk_tx) = socket.split();
+        // Without a mode in the bind flags, the kernel picks one itself.
+        let zero_copy = xdp_zero_copy(xsk_rx.fd())?;
+        if let Some(busy_poll) = flags.busy_poll {
+            set_busy_poll(xsk_rx.fd(), busy_poll)?;
+        }
+        let metrics = api::SocketMetrics::new(api::Backend::AfXdp, portspec, queue);
+        trace_event!(debug, backend = "af_xdp", portspec, queue = ?queue, "socket created");
+        Ok(Sock {
+            rx: RxSock {
+                ctx: ctx.clone(),
+                xsk: RefCell::new(xsk_rx),
+                umem_manager: RefCell::new(umem_manager),
+                stats: Cell::new(StatsRecord::default()),
+                queue: queue.unwrap_or(0) as u32,
+                rx_metadata: flags.rx_metadata,
+                rx_timestamp: flags.rx_timestamp,
+                rx_vlan: flags.rx_vlan,
+                vlan_strip: flags.vlan_strip,
+                fcs,
+                snaplen: flags.snaplen,
+                clock: api::SoftwareClock::new(flags.timestamp_source),
+                filter: api::UserspaceFilter::default(),
+                segments: api::SegmentTracker::default(),
+                metrics: metrics.clone(),
+                busy_poll: flags.busy_poll.is_some(),
+                refill_policy: flags.refill_policy,
+            },
+            tx: TxSock {
+                ctx,
+                xsk: RefCell::new(xsk_tx),
+                completion_queue: RefCell::new(completion_queue),
+                free_frames: RefCell::new(free_frames),
+                tx_frames: tx_frames as usize,
+                frame_size,
+                headroom: self.inner.frame_headroom,
+                stats: Cell::new(StatsRecord::default()),
+                flush_state: api::FlushState::new(flags.flush_policy),
+                pacer: api::Pacer::new(flags.tx_rate),
+                vlan_insert: flags.vlan_insert,
+                completions: api::TxCompletions::new(),
+                metrics,
+                busy_poll: flags.busy_poll.is_some(),
+                completion_batch: flags.completion_batch,
+            },
             prev_stats: Cell::new(StatsRecord::default()),
+            ifname: portspec.to_owned(),
+            zero_copy,
         })
     }
+}
 
-    fn context(&self) -> &Self::Context {
-        &self.ctx
+/// Returns whether an XSK socket is bound in zero-copy mode (`XDP_OPTIONS`).
+fn xdp_zero_copy(fd: RawFd) -> io::Result<bool> {
+    let mut opts: libc::xdp_options = unsafe { std::mem::zeroed() };
+    let mut optlen = size_of::<libc::xdp_options>() as libc::socklen_t;
+    let rc = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_XDP,
+            libc::XDP_OPTIONS,
+            &mut opts as *mut _ as *mut libc::c_void,
+            &mut optlen,
+        )
+    };
+    if rc != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(opts.flags & libc::XDP_OPTIONS_ZEROCOPY != 0)
+}
+
+/// Enables preferred busy polling on an XSK socket.
+fn set_busy_poll(fd: RawFd, busy_poll: BusyPoll) -> io::Result<()> {
+    let options = [
+        (libc::SO_PREFER_BUSY_POLL, 1),
+        (libc::SO_BUSY_POLL, busy_poll.timeout_us as libc::c_int),
+        (libc::SO_BUSY_POLL_BUDGET, busy_poll.budget as libc::c_int),
+    ];
+    for (name, value) in options {
+        let rc = unsafe {
+            libc::setsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                name,
+                &value as *const libc::c_int as *const libc::c_void,
+                size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        if rc != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Reads the kernel's `XDP_STATISTICS` counters of an XSK socket.
+///
+/// Kernels before 5.9 fill in the first three counters only.
+fn xdp_statistics(fd: RawFd) -> io::Result<XdpStats> {
+    let mut stats: libc::xdp_statistics = unsafe { std::mem::zeroed() };
+    let mut optlen = size_of::<libc::xdp_statistics>() as libc::socklen_t;
+    let rc = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_XDP,
+            libc::XDP_STATISTICS,
+            &mut stats as *mut _ as *mut libc::c_void,
+            &mut optlen,
+        )
+    };
+    if rc != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(XdpStats {
+        rx_dropped: stats.rx_dropped,
+        rx_invalid_descs: stats.rx_invalid_descs,
+        tx_invalid_descs: stats.tx_invalid_descs,
+        rx_ring_full: stats.rx_ring_full,
+        fill_ring_empty: stats.rx_fill_ring_empty_descs,
+        tx_ring_empty: stats.tx_ring_empty_descs,
+    })
+}
+
+impl AsRawFd for RxSock {
+    fn as_raw_fd(&self) -> RawFd {
+        self.xsk.borrow().fd()
+    }
+}
+
+impl AsRawFd for Sock {
+    fn as_raw_fd(&self) -> RawFd {
+        self.rx.as_raw_fd()
+    }
+}
+
+impl api::Pollable for Sock {}
+
+/// XDP program redirecting the packets of the interface to its AF_XDP sockets.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum XdpProgram {
+    /// The built-in program, redirecting every packet of a queue to its socket; see
+    /// [`XdpSteering`].
+    #[default]
+    Builtin,
+    /// A compiled eBPF object, loaded and attached to the interface in place of the
+    /// built-in program, e.g. a filter or a sampler passing only some packets to the
+    /// sockets with `bpf_redirect_map`.
+    ///
+    /// Each socket is inserted into the XSKMAP named `xsks_map` at the index of its
+    /// queue.
+    Object {
+        /// Path of the object file.
+        path: PathBuf,
+        /// Name of the XDP program in the object.
+        program: String,
+        /// Name of the XSKMAP in the object.
+        xsks_map: String,
+    },
+    /// A program the application attached itself, whose XSKMAP is pinned at
+    /// `xsks_map` (e.g. `/sys/fs/bpf/xsks_map`): each socket is only inserted into the
+    /// map, at the index of its queue.
+    Pinned {
+        /// Path of the pinned XSKMAP.
+        xsks_map: PathBuf,
+    },
+}
+
+/// When an AF_XDP socket hands the frames released by the application back to the
+/// kernel through the fill ring.
+///
+/// The kernel drops packets once the fill ring runs dry ([`XdpStats::rx_ring_full`]
+/// and [`XdpStats::fill_ring_empty`] grow), while each refill may cost a wakeup
+/// syscall: refill early for bursty traffic, late for fewer syscalls.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum RefillPolicy {
+    /// When a receive finds the RX ring empty.
+    #[default]
+    OnEmpty,
+    /// On every receive call, before reading the RX ring.
+    Eager,
+    /// On every receive call finding fewer than this many frames in the fill ring, and
+    /// when a receive finds the RX ring empty.
+    Below(u32),
+    /// Only when the application calls [`RxSock::refill`] (or [`Sock::refill`]).
+    Manual,
+}
+
+/// The kernel's counters of an XSK socket (`XDP_STATISTICS`).
+///
+/// [`Socket::stats`](api::Socket::stats) folds them into the normalized counters; these
+/// tell the causes apart, e.g. a growing [`fill_ring_empty`](Self::fill_ring_empty)
+/// means that the application does not release the packets fast enough.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct XdpStats {
+    /// Packets dropped for other reasons than the ones below.
+    pub rx_dropped: u64,
+    /// Packets dropped because of an invalid fill ring descriptor.
+    pub rx_invalid_descs: u64,
+    /// Packets not sent because of an invalid TX descriptor.
+    pub tx_invalid_descs: u64,
+    /// Packets dropped because the RX ring was full (Linux 5.9).
+    pub rx_ring_full: u64,
+    /// Times the kernel found the fill ring empty (Linux 5.9).
+    pub fill_ring_empty: u64,
+    /// Times the kernel found the TX ring empty on a wakeup (Linux 5.9).
+    pub tx_ring_empty: u64,
+}
+
+/// What the built-in XDP program does with the packets of an RX queue.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum QueueAction {
+    /// Redirect them to the socket bound to the queue, or pass them to the kernel stack
+    /// if there is none.
+    #[default]
+    Redirect,
+    /// Pass them to the kernel stack.
+    Pass,
+    /// Drop them.
+    Drop,
+}
+
+impl QueueAction {
+    /// Returns the value of the action in the `queue_map` of the program.
+    fn value(self) -> u32 {
+        match self {
+            QueueAction::Redirect => 0,
+            QueueAction::Pass => 1,
+            QueueAction::Drop => 2,
+        }
+    }
+}
+
+/// Handle on the built-in XDP program (`prog.c`) of an interface, shared by every
+/// socket on it, to change how the program steers packets without an eBPF toolchain.
+///
+/// By default, the packets of each RX queue go to the socket bound to the queue, and
+/// those of the queues without a socket to the kernel stack. The kernel only delivers
+/// a packet to a socket bound to the queue it was received on: to gather the traffic
+/// of every queue on one socket, steer it to that queue instead, e.g. with
+/// `ethtool -X <if> equal 1` or [`Socket::steer`](api::Socket::steer).
+///
+/// ```ignore
+/// let steering = socket.xdp_steering().expect("built-in program");
+/// steering.set_drop_non_ip(true)?;
+/// // Leave queue 3 to the kernel stack.
+/// steering.set_queue_action(3, af_xdp::QueueAction::Pass)?;
+/// ```
+#[derive(Clone)]
+pub struct XdpSteering {
+    program: Arc<XdpAttachment>,
+}
+
+impl XdpSteering {
+    /// Sets what the program does with the packets received on `queue`.
+    pub fn set_queue_action(&self, queue: u32, action: QueueAction) -> Result<()> {
+        Ok(self.program.set("queue_map", queue, action.value())?)
+    }
+
+    /// Drops the frames carrying neither IPv4 nor IPv6 (behind at most one VLAN tag)
+    /// in the kernel, on every queue.
+    pub fn set_drop_non_ip(&self, enable: bool) -> Result<()> {
+        let flags = if enable { STEER_DROP_NON_IP } else { 0 };
+        Ok(self.program.set("steer_config", 0, flags)?)
+    }
+
+    /// Drops the IPv4 packets from `source` in the kernel, on every queue; up to 65536
+    /// sources can be blocked.
+    pub fn block_source(&self, source: Ipv4Addr) -> Result<()> {
+        Ok(self
+            .program
+            .update("blocklist", blocklist_key(source), 1u32)?)
+    }
+
+    /// Stops dropping the IPv4 packets from `source`, returning whether it was blocked.
+    pub fn unblock_source(&self, source: Ipv4Addr) -> Result<bool> {
+        Ok(self
+            .program
+            .remove::<_, u32>("blocklist", blocklist_key(source))?)
+    }
+}
+
+/// Returns the key of `source` in the `blocklist` of the built-in program: the address
+/// in network byte order.
+fn blocklist_key(source: Ipv4Addr) -> u32 {
+    u32::from_ne_bytes(source.octets())
+}
+
+/// Handle on the maps of the XDP program attached to an interface by the sockets on
+/// it, built-in or loaded from [`XdpProgram::Object`], e.g. to maintain allow or deny
+/// lists the program enforces before packets reach any socket.
+///
+/// Keys and values are plain old data ([`Pod`]) of the sizes declared by the program;
+/// [`ErrorKind::InvalidInput`] reports a mismatch, and [`ErrorKind::NotFound`] a map
+/// the program does not have. The key of an array map is its `u32` index.
+///
+/// ```ignore
+/// #[derive(Clone, Copy)]
+/// #[repr(C)]
+/// struct Flow {
+///     addr: [u8; 4],
+///     port: u16,
+///     _pad: u16,
+/// }
+/// unsafe impl af_xdp::Pod for Flow {}
+///
+/// let maps = socket.xdp_maps().expect("program attached by the socket");
+/// maps.update("denied", Flow { addr: [10, 0, 0, 1], port: 53u16.to_be(), _pad: 0 }, 1u8)?;
+/// ```
+#[derive(Clone)]
+pub struct XdpMaps {
+    program: Arc<XdpAttachment>,
+}
+
+impl XdpMaps {
+    /// Sets the entry `key` of the hash, LRU hash or array map `map` to `value`.
+    pub fn update<K: Pod, V: Pod>(&self, map: &str, key: K, value: V) -> Result<()> {
+        Ok(self.program.update(map, key, value)?)
+    }
+
+    /// Returns the entry `key` of the hash, LRU hash or array map `map`, or `None` if
+    /// there is none.
+    pub fn get<K: Pod, V: Pod>(&self, map: &str, key: K) -> Result<Option<V>> {
+        Ok(self.program.get(map, key)?)
+    }
+
+    /// Removes the entry `key` of the hash or LRU hash map `map`, whose values are `V`,
+    /// returning whether there was one.
+    pub fn remove<K: Pod, V: Pod>(&self, map: &str, key: K) -> Result<bool> {
+        Ok(self.program.remove::<K, V>(map, key)?)
+    }
+}
+
+/// Preferred busy polling settings of an XSK socket (`SO_PREFER_BUSY_POLL`), so that
+/// the application drives the driver's NAPI loop from its own thread, through the
+/// syscalls that wake the rings up, instead of relying on softirq scheduling.
+///
+/// Interrupts must be deferred for the driver to keep away, e.g. with
+/// `echo 2 > /sys/class/net/<if>/napi_defer_hard_irqs` and
+/// `echo 200000 > /sys/class/net/<if>/gro_flush_timeout`. Setting a timeout above the
+/// `net.core.busy_poll` sysctl requires `CAP_NET_ADMIN`. Requires Linux 5.11.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BusyPoll {
+    /// How long each syscall busy polls for packets, in microseconds (`SO_BUSY_POLL`).
+    pub timeout_us: u32,
+    /// Maximum number of packets processed per poll (`SO_BUSY_POLL_BUDGET`).
+    pub budget: u16,
+}
+
+impl Default for BusyPoll {
+    fn default() -> Self {
+        Self {
+            timeout_us: 20,
+            budget: 64,
+        }
     }
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct AfXdpFlags {
     pub bind_flags: u16,
+    /// Bind in zero-copy mode (`XDP_ZEROCOPY`), falling back to copy mode (`XDP_COPY`)
+    /// if the driver refuses; [`Capabilities::zero_copy_driver`] tells which mode the
+    /// socket ended up in. Ignored if [`bind_flags`](Self::bind_flags) already picks a
+    /// mode.
+    ///
+    /// [`Capabilities::zero_copy_driver`]: api::Capabilities::zero_copy_driver
+    pub zero_copy: bool,
+    /// Bind with `XDP_USE_NEED_WAKEUP` (the default), so that the socket only makes
+    /// the `sendto` and `recvfrom` syscalls that drive the rings when the kernel flags
+    /// them as needed, instead of on every flush; ignored by kernels before 5.4.
+    pub need_wakeup: bool,
+    /// Enable preferred busy polling on the socket; see [`BusyPoll`].
+    pub busy_poll: Option<BusyPoll>,
     pub xdp_flags: u32,
+    /// XDP program attached to the interface; see [`XdpProgram`].
+    pub xdp_program: XdpProgram,
+    /// Try to attach the XDP program in hardware-offload mode (`XDP_FLAGS_HW_MODE`),
+    /// so that it runs on the NIC, falling back to the mode of
+    /// [`xdp_flags`](Self::xdp_flags) if the NIC or the program does not allow it;
+    /// [`Sock::xdp_offloaded`] tells which one was used.
+    ///
+    /// Only offload-capable NICs (e.g. Netronome) accept the mode, and only for
+    /// programs whose maps can live on the NIC: an XSKMAP cannot, so the built-in
+    /// program always runs on the host.
+    pub hw_offload: bool,
     pub num_frames: u32,
     pub frame_size: u32,
+    /// Allocate the UMEM from huge pages (`MAP_HUGETLB`), falling back to regular
+    /// pages if the system does not have enough of them;
+    /// [`Sock::umem_page_size`] tells which were used. Ignored for sockets created from
+    /// a [`SharedUmem`].
+    pub hugepages: bool,
+    /// Bytes left free in front of the packets of every frame, on top of the kernel's
+    /// 256 bytes of `XDP_PACKET_HEADROOM`, so that encapsulation headers (VXLAN, MPLS)
+    /// can be prepended in place, with [`TxSlot::prepend`] on transmit. Must leave room
+    /// for packets in the frame.
+    pub frame_headroom: u32,
+    /// Register the UMEM in unaligned chunk mode (`XDP_UMEM_UNALIGNED_CHUNK_FLAG`):
+    /// descriptors may point anywhere in the UMEM instead of inside a chunk, so packets
+    /// may cross frame boundaries and the frame size need not be a power of two. Ignored
+    /// for sockets created from a [`SharedUmem`].
+    pub unaligned_chunks: bool,
     pub tx_size: u32,
+    /// Number of the socket's [`num_frames`](Self::num_frames) frames reserved for
+    /// transmit, the others feeding the fill ring; `None` reserves `tx_size` of them, up
+    /// to half. Receive-only sockets set `Some(0)` to keep every frame for receive,
+    /// after which sends (and [`forward`](api::forward), which swaps in a TX frame) fail
+    /// with [`Error::NoMemory`].
+    pub tx_frames: Option<u32>,
     pub rx_size: u32,
+    /// Read the RX hash hint stored by the XDP program in the metadata area.
+    ///
+    /// The program must call `bpf_xdp_metadata_rx_hash` and store the hash as a
+    /// native-endian `u32` immediately before the packet data (`bpf_xdp_adjust_meta`).
+    /// The [`XdpProgram::Builtin`] program stores no hints, so sockets using it fail
+    /// to open with this flag, as with [`rx_timestamp`](Self::rx_timestamp) and
+    /// [`rx_vlan`](Self::rx_vlan).
+    pub rx_metadata: bool,
+    /// Read the RX timestamp hint stored by the XDP program in the metadata area, and
+    /// enable hardware timestamping on the interface.
+    ///
+    /// The program must call `bpf_xdp_metadata_rx_timestamp` and store the timestamp,
+    /// in nanoseconds, as a native-endian `u64` immediately before the hash hint: the
+    /// metadata area ends with `struct { __u64 timestamp; __u32 hash; }`, whether or not
+    /// [`rx_metadata`](Self::rx_metadata) is set.
+    pub rx_timestamp: bool,
+    /// Read the tag of the frames the NIC stripped (`ethtool -K <if> rxvlan on`) from
+    /// the RX VLAN hint stored by the XDP program in the metadata area.
+    ///
+    /// The program must call `bpf_xdp_metadata_rx_vlan_tag` and store the TPID, in
+    /// network order, and the TCI, in native order, as two `u16` immediately before
+    /// the timestamp hint, or zeros if the frame kept its tag.
+    pub rx_vlan: bool,
+    /// Set `IFF_PROMISC` on the interface, so frames addressed to other hosts are
+    /// received.
+    pub promiscuous: bool,
+    /// Set `IFF_ALLMULTI` on the interface, so every multicast frame is received.
+    pub allmulti: bool,
+    /// When queued TX packets are pushed to the NIC.
+    pub flush_policy: api::FlushPolicy,
+    /// When the frames released by the application go back to the fill ring.
+    pub refill_policy: RefillPolicy,
+    /// Most entries of the completion ring reaped by each TX flush; 0 leaves the
+    /// completions to [`TxSock::reap_completions`], and to sends running out of frames.
+    pub completion_batch: u32,
+    /// Rate at which packets are transmitted.
+    pub tx_rate: api::TxRate,
+    /// Remove the outermost VLAN tag from received frames, in software unless the NIC
+    /// already did (see [`rx_vlan`](Self::rx_vlan)).
+    pub vlan_strip: bool,
+    /// VLAN tag inserted into every transmitted frame (in software).
+    pub vlan_insert: Option<api::VlanTag>,
+    /// Maximum number of bytes of each received frame exposed to the application; the
+    /// NIC still writes the whole frame into the UMEM.
+    pub snaplen: Option<u32>,
+    /// Clock read on receive to timestamp the packets without a hardware timestamp.
+    pub timestamp_source: api::TimestampSource,
+    /// Keep (`rx-fcs` feature) or strip (in software) the FCS of received frames;
+    /// `None` leaves the interface as it is.
+    pub fcs: Option<bool>,
+}
+
+impl Default for AfXdpFlags {
+    fn default() -> Self {
+        Self {
+            bind_flags: 0,
+            zero_copy: false,
+            need_wakeup: true,
+            busy_poll: None,
+            xdp_flags: 0,
+            xdp_program: XdpProgram::Builtin,
+            hw_offload: false,
+            num_frames: 4096,
+            frame_size: 2048,
+            hugepages: false,
+            frame_headroom: 0,
+            unaligned_chunks: false,
+            tx_size: 2048,
+            tx_frames: None,
+            rx_size: 2048,
+            rx_metadata: false,
+            rx_timestamp: false,
+            rx_vlan: false,
+            promiscuous: false,
+            allmulti: false,
+            flush_policy: api::FlushPolicy::Manual,
+            refill_policy: RefillPolicy::OnEmpty,
+            completion_batch: 64,
+            tx_rate: api::TxRate::Unlimited,
+            vlan_strip: false,
+            vlan_insert: None,
+            snaplen: None,
+            timestamp_source: api::TimestampSource::None,
+            fcs: None,
+        }
+    }
 }
 
-impl api::Flags for AfXdpFlags {}
+impl api::Flags for AfXdpFlags {
+    fn apply(&mut self, options: &api::SocketOptions) {
+        if let Some(size) = options.rx_ring {
+            self.rx_size = size;
+        }
+        if let Some(size) = options.tx_ring {
+            self.tx_size = size;
+        }
+        if let Some(size) = options.buffer_size {
+            self.frame_size = size;
+        }
+        if let Some(count) = options.num_buffers {
+            self.num_frames = count;
+        }
+        if let Some(enable) = options.promiscuous {
+            self.promiscuous = enable;
+        }
+        if let Some(enable) = options.allmulti {
+            self.allmulti = enable;
+        }
+        if let Some(policy) = options.flush_policy {
+            self.flush_policy = policy;
+        }
+        if let Some(rate) = options.tx_rate {
+            self.tx_rate = rate;
+        }
+        if let Some(enable) = options.vlan_strip {
+            self.vlan_strip = enable;
+        }
+        if let Some(tag) = options.vlan_insert {
+            self.vlan_insert = Some(tag);
+        }
+        if let Some(snaplen) = options.snaplen {
+            self.snaplen = Some(snaplen);
+        }
+        if let Some(enable) = options.hw_timestamps {
+            self.rx_timestamp = enable;
+        }
+        if let Some(source) = options.timestamp_source {
+            self.timestamp_source = source;
+        }
+        if let Some(keep) = options.fcs {
+            self.fcs = Some(keep);
+        }
+    }
+}
+
+impl api::SocketBuilder<Sock> {
+    /// Sets the `XDP_FLAGS_*` used to attach the XDP program.
+    pub fn xdp_flags(self, xdp_flags: u32) -> Self {
+        self.configure(|f| f.xdp_flags = xdp_flags)
+    }
+
+    /// Attaches `program` to the interface instead of the built-in one.
+    pub fn xdp_program(self, program: XdpProgram) -> Self {
+        self.configure(|f| f.xdp_program = program)
+    }
+
+    /// Leaves `headroom` bytes free in front of the packets of every frame.
+    pub fn frame_headroom(self, headroom: u32) -> Self {
+        self.configure(|f| f.frame_headroom = headroom)
+    }
+
+    /// Registers the UMEM in unaligned chunk mode.
+    pub fn unaligned_chunks(self, enable: bool) -> Self {
+        self.configure(|f| f.unaligned_chunks = enable)
+    }
+
+    /// Reserves `frames` of the socket's frames for transmit.
+    pub fn tx_frames(self, frames: u32) -> Self {
+        self.configure(|f| f.tx_frames = Some(frames))
+    }
+
+    /// Allocates the UMEM from huge pages if the system has enough of them.
+    pub fn hugepages(self, enable: bool) -> Self {
+        self.configure(|f| f.hugepages = enable)
+    }
+
+    /// Tries to attach the XDP program in hardware-offload mode first.
+    pub fn hw_offload(self, enable: bool) -> Self {
+        self.configure(|f| f.hw_offload = enable)
+    }
+
+    /// Sets the `XDP_*` bind flags (e.g. `XDP_ZEROCOPY`, `XDP_COPY`).
+    pub fn bind_flags(self, bind_flags: u16) -> Self {
+        self.configure(|f| f.bind_flags = bind_flags)
+    }
+
+    /// Binds in zero-copy mode if the driver supports it, and in copy mode otherwise.
+    pub fn zero_copy(self, zero_copy: bool) -> Self {
+        self.configure(|f| f.zero_copy = zero_copy)
+    }
+
+    /// Only drives the rings with syscalls when the kernel asks for them
+    /// (`XDP_USE_NEED_WAKEUP`).
+    pub fn need_wakeup(self, need_wakeup: bool) -> Self {
+        self.configure(|f| f.need_wakeup = need_wakeup)
+    }
+
+    /// Sets when the released frames go back to the fill ring.
+    pub fn refill_policy(self, policy: RefillPolicy) -> Self {
+        self.configure(|f| f.refill_policy = policy)
+    }
+
+    /// Sets the most completions reaped by each TX flush: larger batches recycle TX
+    /// frames sooner, smaller ones keep flushes short.
+    pub fn completion_batch(self, completion_batch: u32) -> Self {
+        self.configure(|f| f.completion_batch = completion_batch)
+    }
+
+    /// Enables preferred busy polling, driving the driver from the application thread.
+    pub fn busy_poll(self, busy_poll: BusyPoll) -> Self {
+        self.configure(|f| f.busy_poll = Some(busy_poll))
+    }
+
+    /// Reads the RX hash hint from the XDP metadata area.
+    pub fn rx_metadata(self, enable: bool) -> Self {
+        self.configure(|f| f.rx_metadata = enable)
+    }
+
+    /// Reads the RX timestamp hint from the XDP metadata area.
+    pub fn rx_timestamp(self, enable: bool) -> Self {
+        self.configure(|f| f.rx_timestamp = enable)
+    }
+
+    /// Reads the stripped VLAN tag hint from the XDP metadata area.
+    pub fn rx_vlan(self, enable: bool) -> Self {
+        self.configure(|f| f.rx_vlan = enable)
+    }
+}
+
+/// Checks that the metadata hints `flags` read are stored by the XDP program: the
+/// built-in one leaves the metadata area untouched, and reading it would return
+/// whatever the headroom of the frame held before.
+fn check_hints(flags: &AfXdpFlags) -> io::Result<()> {
+    let hints = [
+        (flags.rx_metadata, "rx_metadata"),
+        (flags.rx_timestamp, "rx_timestamp"),
+        (flags.rx_vlan, "rx_vlan"),
+    ];
+    match hints.into_iter().find(|&(enabled, _)| enabled) {
+        Some((_, hint)) if matches!(flags.xdp_program, XdpProgram::Builtin) => Err(io::Error::new(
+            ErrorKind::Unsupported,
+            format!("the built-in XDP program does not store the {hint} hint"),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Checks a UMEM layout against the kernel's rules for aligned or unaligned chunks.
+fn check_layout(frame_size: u32, frame_headroom: u32, unaligned: bool) -> io::Result<()> {
+    let page_size = unsafe { sysconf(_SC_PAGESIZE) } as u32;
+    if !(2048..=page_size).contains(&frame_size) {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("frame size {frame_size} is not in 2048..={page_size}"),
+        ));
+    }
+    if !unaligned && !frame_size.is_power_of_two() {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("frame size {frame_size} is not a power of two, as aligned chunks need"),
+        ));
+    }
+    if frame_headroom as usize + XDP_PACKET_HEADROOM >= frame_size as usize {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("a headroom of {frame_headroom} leaves no room in {frame_size}-byte frames"),
+        ));
+    }
+    Ok(())
+}
+
+/// Maps `size` bytes, rounded up to whole huge pages, from the default huge page pool
+/// (`MAP_HUGETLB`), returning the memory and the huge page size.
+pub fn alloc_huge_pages(size: usize) -> io::Result<(NonNull<u8>, usize)> {
+    if size == 0 {
+        return Err(io::Error::new(ErrorKind::InvalidInput, "Invalid size"));
+    }
+    let page_size = huge_page_size();
+    let len = size.next_multiple_of(page_size);
+    // Populating the mapping fails it right away when the pool is too small, instead
+    // of on first touch.
+    let ptr = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_HUGETLB | libc::MAP_POPULATE,
+            -1,
+            0,
+        )
+    };
+    if ptr == libc::MAP_FAILED {
+        return Err(io::Error::last_os_error());
+    }
+    let ptr = NonNull::new(ptr.cast()).ok_or_else(|| io::Error::other("mmap returned null"))?;
+    Ok((ptr, page_size))
+}
+
+/// Returns the size of the default huge pages (`Hugepagesize` in `/proc/meminfo`).
+fn huge_page_size() -> usize {
+    const DEFAULT: usize = 2 << 20;
+    let Ok(meminfo) = std::fs::read_to_string("/proc/meminfo") else {
+        return DEFAULT;
+    };
+    meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("Hugepagesize:"))
+        .and_then(|size| size.trim().strip_suffix("kB"))
+        .and_then(|kb| kb.trim().parse::<usize>().ok())
+        .map_or(DEFAULT, |kb| kb * 1024)
+}
 
 pub fn alloc_page_aligned(size: usize) -> io::Result<NonNull<u8>> {
     if size == 0 {
@@ -381,12 +2208,76 @@ pub fn alloc_page_aligned(size: usize) -> io::Result<NonNull<u8>> {
     NonNull::new(ptr).ok_or_else(|| io::Error::new(ErrorKind::OutOfMemory, "Allocation failed"))
 }
 
-pub struct Meta {}
+/// Per-packet metadata from the XDP RX descriptor.
+///
+/// The whole frame is captured unless [`AfXdpFlags::snaplen`] is set. AF_XDP
+/// descriptors carry no timestamp: the
+/// RSS hash and the hardware timestamp are only available when
+/// [`AfXdpFlags::rx_metadata`] and [`AfXdpFlags::rx_timestamp`] are set, otherwise the
+/// packet is timestamped in software according to [`AfXdpFlags::timestamp_source`].
+///
+/// With the `XDP_USE_SG` bind flag, packets larger than a frame span several
+/// descriptors, received one at a time with `more` set on all but the last; the hints,
+/// the filter and the snaplen only apply to the first.
+/// [`recv_packet`](api::Socket::recv_packet) gathers them.
+pub struct Meta {
+    pub len: u32,
+    /// Length of the frame before the snaplen truncation.
+    pub wirelen: u32,
+    pub queue: u32,
+    pub rss_hash: Option<u32>,
+    pub timestamp: Option<api::Timestamp>,
+    /// The timestamp was taken by the NIC.
+    pub hw_timestamp: bool,
+    pub vlan: Option<api::VlanTag>,
+    /// The frame ends with its FCS.
+    pub fcs: bool,
+    /// More frames of the same packet follow (`XDP_PKT_CONTD`).
+    pub more: bool,
+}
 
 impl api::Metadata for Meta {
     fn into_enum(self) -> api::MetadataType {
         api::MetadataType::AfXdp(self)
     }
+
+    fn timestamp(&self) -> Option<api::Timestamp> {
+        self.timestamp
+    }
+
+    fn timestamp_kind(&self) -> Option<api::TimestampKind> {
+        self.timestamp.map(|_| {
+            if self.hw_timestamp {
+                api::TimestampKind::Hardware
+            } else {
+                api::TimestampKind::Software
+            }
+        })
+    }
+
+    fn caplen(&self) -> u32 {
+        self.len
+    }
+
+    fn wirelen(&self) -> u32 {
+        self.wirelen
+    }
+
+    fn vlan(&self) -> Option<api::VlanTag> {
+        self.vlan
+    }
+
+    fn rss_hash(&self) -> Option<u32> {
+        self.rss_hash
+    }
+
+    fn queue(&self) -> Option<u32> {
+        Some(self.queue)
+    }
+
+    fn has_fcs(&self) -> bool {
+        self.fcs
+    }
 }
 
 #[cfg(test)]
@@ -409,6 +2300,15 @@ mod tests {
                 num_frames: 4096 * 8,
                 tx_size: 2048,
                 rx_size: 2048,
+                rx_metadata: false,
+                rx_timestamp: false,
+                rx_vlan: false,
+                flush_policy: api::FlushPolicy::Manual,
+                tx_rate: api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                timestamp_source: api::TimestampSource::None,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -422,6 +2322,15 @@ mod tests {
                 num_frames: 4096,
                 tx_size: 2048,
                 rx_size: 2048,
+                rx_metadata: false,
+                rx_timestamp: false,
+                rx_vlan: false,
+                flush_policy: api::FlushPolicy::Manual,
+                tx_rate: api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                timestamp_source: api::TimestampSource::None,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -1,35 +1,91 @@
-use crate::af_xdp::{RX_BATCH_SIZE, UmemArea, resultify};
+use crate::af_xdp::{RX_BATCH_SIZE, UmemArea, XdpProgram, resultify};
 use arrayvec::ArrayVec;
-use aya::maps::Map;
+use aya::maps::{Array, HashMap, Map, MapData, MapError};
 use aya::programs::xdp::XdpLinkId;
 use aya::programs::{Xdp, XdpFlags};
-use aya::{Ebpf, include_bytes_aligned};
+use aya::{Ebpf, Pod, include_bytes_aligned};
 use libxdp_sys::{
-    XSK_LIBBPF_FLAGS__INHIBIT_PROG_LOAD, xdp_desc, xsk_prod_nb_free, xsk_ring_cons,
+    XSK_LIBBPF_FLAGS__INHIBIT_PROG_LOAD, XSK_RING_CONS__DEFAULT_NUM_DESCS,
+    XSK_RING_PROD__DEFAULT_NUM_DESCS, xdp_desc, xsk_prod_nb_free, xsk_ring_cons,
     xsk_ring_cons__comp_addr, xsk_ring_cons__peek, xsk_ring_cons__release, xsk_ring_cons__rx_desc,
     xsk_ring_prod, xsk_ring_prod__fill_addr, xsk_ring_prod__reserve, xsk_ring_prod__submit,
-    xsk_ring_prod__tx_desc, xsk_socket, xsk_socket__create, xsk_socket__delete, xsk_socket__fd,
-    xsk_socket__update_xskmap, xsk_socket_config, xsk_umem, xsk_umem__create, xsk_umem__delete,
+    xsk_ring_prod__tx_desc, xsk_socket, xsk_socket__create, xsk_socket__create_shared,
+    xsk_socket__delete, xsk_socket__fd, xsk_socket__update_xskmap, xsk_socket_config, xsk_umem,
+    xsk_umem__create, xsk_umem__delete, xsk_umem_config,
 };
 use std::io;
-use std::os::fd::{AsFd, AsRawFd};
+use std::marker::PhantomData;
+use std::os::fd::{AsFd, AsRawFd, OwnedFd};
 use std::ptr::NonNull;
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
 use std::{collections::VecDeque, ffi::CString, mem::zeroed, ptr};
 
-pub struct Umem {
+struct UmemHandle {
     inner: NonNull<xsk_umem>,
-    fq: xsk_ring_prod,
-    cq: xsk_ring_cons,
 }
 
-unsafe impl Send for Umem {}
+unsafe impl Send for UmemHandle {}
+unsafe impl Sync for UmemHandle {}
+
+impl Drop for UmemHandle {
+    fn drop(&mut self) {
+        unsafe {
+            xsk_umem__delete(self.inner.as_ptr());
+        }
+    }
+}
+
+pub struct Umem {
+    handle: Arc<UmemHandle>,
+    fq: FillQueue,
+    cq: CompletionQueue,
+}
 
-pub struct FqMut<'umem> {
+/// Producer side of the UMEM fill ring, owned by the receive path.
+pub struct FillQueue {
+    ring: xsk_ring_prod,
+    _umem: Arc<UmemHandle>,
+}
+
+unsafe impl Send for FillQueue {}
+
+impl FillQueue {
+    pub fn ring_mut(&mut self) -> FqMut<'_> {
+        FqMut {
+            inner: &mut self.ring,
+            _fq: PhantomData,
+        }
+    }
+}
+
+/// Consumer side of the UMEM completion ring, owned by the transmit path.
+pub struct CompletionQueue {
+    ring: xsk_ring_cons,
+    _umem: Arc<UmemHandle>,
+}
+
+unsafe impl Send for CompletionQueue {}
+
+impl CompletionQueue {
+    pub fn ring_mut(&mut self) -> CqMut<'_> {
+        CqMut {
+            inner: &mut self.ring,
+            _cq: PhantomData,
+        }
+    }
+}
+
+pub struct FqMut<'fq> {
     inner: *mut xsk_ring_prod,
-    _umem: &'umem mut Umem,
+    _fq: PhantomData<&'fq mut FillQueue>,
 }
 
-impl<'umem> FqMut<'umem> {
+impl<'fq> FqMut<'fq> {
+    /// Number of entries of the ring.
+    pub fn size(&self) -> u32 {
+        unsafe { (*self.inner).size }
+    }
+
     pub fn nb_free(&mut self, nb: u32) -> u32 {
         unsafe { xsk_prod_nb_free(self.inner, nb) }
     }
@@ -49,14 +105,30 @@ impl<'umem> FqMut<'umem> {
             xsk_ring_prod__submit(self.inner, nb);
         }
     }
+
+    /// Returns whether the kernel asks for a syscall to pick up the filled frames
+    /// (`XDP_USE_NEED_WAKEUP`).
+    pub fn needs_wakeup(&self) -> bool {
+        unsafe { needs_wakeup(&*self.inner) }
+    }
 }
 
-pub struct CqMut<'umem> {
+/// Reads the `XDP_RING_NEED_WAKEUP` flag of a producer ring.
+///
+/// # Safety
+/// The ring must be mapped, from a socket bound with `XDP_USE_NEED_WAKEUP`.
+unsafe fn needs_wakeup(ring: &xsk_ring_prod) -> bool {
+    // The kernel updates the flags concurrently.
+    let flags = unsafe { std::ptr::read_volatile(ring.flags) };
+    flags & libc::XDP_RING_NEED_WAKEUP != 0
+}
+
+pub struct CqMut<'cq> {
     inner: *mut xsk_ring_cons,
-    _umem: &'umem mut Umem,
+    _cq: PhantomData<&'cq mut CompletionQueue>,
 }
 
-impl<'umem> CqMut<'umem> {
+impl<'cq> CqMut<'cq> {
     pub fn peek(&mut self, nb: u32) -> (u32, u32) {
         let mut idx = 0;
         let peeked = unsafe { xsk_ring_cons__peek(self.inner, nb, &mut idx) };
@@ -75,11 +147,30 @@ impl<'umem> CqMut<'umem> {
 }
 
 impl Umem {
-    pub fn new(umem: UmemArea) -> io::Result<Umem> {
+    /// Registers `umem` as a UMEM of `frame_size`-byte chunks, the kernel leaving
+    /// `frame_headroom` bytes (on top of `XDP_PACKET_HEADROOM`) in front of every
+    /// received packet; `unaligned` lets descriptors point anywhere in the area.
+    pub fn new(
+        umem: UmemArea,
+        frame_size: u32,
+        frame_headroom: u32,
+        unaligned: bool,
+    ) -> io::Result<Umem> {
         let mut xsk_umem = ptr::null_mut();
         let mut fq = unsafe { zeroed() };
         let mut cq = unsafe { zeroed() };
         let (buffer, size) = umem.raw_parts();
+        let config = xsk_umem_config {
+            fill_size: XSK_RING_PROD__DEFAULT_NUM_DESCS,
+            comp_size: XSK_RING_CONS__DEFAULT_NUM_DESCS,
+            frame_size,
+            frame_headroom,
+            flags: if unaligned {
+                libc::XDP_UMEM_UNALIGNED_CHUNK_FLAG
+            } else {
+                0
+            },
+        };
         resultify(unsafe {
             xsk_umem__create(
                 &mut xsk_umem,
@@ -87,38 +178,51 @@ impl Umem {
                 size as u64,
                 &mut fq,
                 &mut cq,
-                ptr::null_mut(),
+                &config,
             )
         })?;
         let xsk_umem = NonNull::new(xsk_umem).expect("Failed to create xsk_umem");
+        let handle = Arc::new(UmemHandle { inner: xsk_umem });
         Ok(Umem {
-            inner: xsk_umem,
-            fq,
-            cq,
+            fq: FillQueue {
+                ring: fq,
+                _umem: handle.clone(),
+            },
+            cq: CompletionQueue {
+                ring: cq,
+                _umem: handle.clone(),
+            },
+            handle,
         })
     }
 
     pub fn ring_prod_mut(&mut self) -> FqMut<'_> {
-        FqMut {
-            inner: &mut self.fq,
-            _umem: self,
-        }
+        self.fq.ring_mut()
     }
 
     pub fn ring_cons_mut(&mut self) -> CqMut<'_> {
-        CqMut {
-            inner: &mut self.cq,
-            _umem: self,
-        }
+        self.cq.ring_mut()
     }
-}
 
-impl Drop for Umem {
-    fn drop(&mut self) {
-        unsafe {
-            xsk_umem__delete(self.inner.as_ptr());
+    /// Returns a handle for binding further sockets to the UMEM.
+    pub fn shared(&self) -> SharedUmemRef {
+        SharedUmemRef {
+            handle: self.handle.clone(),
         }
     }
+
+    /// Splits the UMEM into its fill and completion rings.
+    ///
+    /// The UMEM itself is deleted once both rings and every socket bound to it are dropped.
+    pub fn into_queues(self) -> (FillQueue, CompletionQueue) {
+        (self.fq, self.cq)
+    }
+}
+
+/// Handle of a UMEM that sockets bind to with `XDP_SHARED_UMEM`.
+#[derive(Clone)]
+pub struct SharedUmemRef {
+    handle: Arc<UmemHandle>,
 }
 
 pub struct XdpDescData {
@@ -127,53 +231,416 @@ pub struct XdpDescData {
     pub options: u32,
 }
 
-pub struct XskSocket {
+struct XskHandle {
     inner: NonNull<xsk_socket>,
+    /// Bound with `XDP_USE_NEED_WAKEUP`.
+    need_wakeup: bool,
+    /// The program we attached, if the application did not attach its own.
+    program: Option<Arc<XdpAttachment>>,
+    // Keeps the UMEM alive until the socket bound to it has been deleted.
+    _umem: Arc<UmemHandle>,
+}
+
+unsafe impl Send for XskHandle {}
+unsafe impl Sync for XskHandle {}
+
+impl XskHandle {
+    fn fd(&self) -> i32 {
+        unsafe { xsk_socket__fd(self.inner.as_ptr()) }
+    }
+}
+
+impl Drop for XskHandle {
+    fn drop(&mut self) {
+        unsafe {
+            xsk_socket__delete(self.inner.as_ptr());
+        }
+    }
+}
+
+/// Where and how an [`XskSocket`] is bound.
+#[derive(Clone, Copy)]
+struct XskConfig<'a> {
+    ifname: &'a str,
+    queue_id: u32,
+    xdp_flags: u32,
+    program: &'a XdpProgram,
+    hw_offload: bool,
+    bind_flags: u16,
+    rx_size: u32,
+    tx_size: u32,
+}
+
+pub struct XskSocket {
+    handle: Arc<XskHandle>,
     rx: RxRing,
     tx: TxRing,
-    _link: XdpLinkId,
-    _bpf: Ebpf,
 }
 
 unsafe impl Send for XskSocket {}
 
-static DEFAULT_PROG: &[u8] = include_bytes_aligned!("../../prog.o");
+/// The built-in program, empty if build.rs could not compile it.
+static DEFAULT_PROG: &[u8] = include_bytes_aligned!(concat!(env!("OUT_DIR"), "/prog.o"));
+
+/// Programs attached by the sockets, by interface: the sockets on an interface share
+/// one, since attaching another would replace it.
+static ATTACHED: Mutex<Vec<(String, XdpProgram, Weak<XdpAttachment>)>> = Mutex::new(Vec::new());
+
+/// An XDP program attached to an interface, shared by the sockets on it and detached
+/// once the last of them is dropped.
+pub struct XdpAttachment {
+    bpf: Mutex<Ebpf>,
+    _link: XdpLinkId,
+    xsks_map: OwnedFd,
+    builtin: bool,
+    /// Attached in hardware-offload mode (`XDP_FLAGS_HW_MODE`).
+    offloaded: bool,
+}
+
+unsafe impl Send for XdpAttachment {}
+unsafe impl Sync for XdpAttachment {}
+
+impl XdpAttachment {
+    /// Returns the program another socket attached to `ifname`, or attaches `program`.
+    fn get_or_attach(
+        ifname: &str,
+        program: &XdpProgram,
+        xdp_flags: u32,
+        hw_offload: bool,
+    ) -> io::Result<Arc<Self>> {
+        let mut attached = lock(&ATTACHED)?;
+        attached.retain(|(_, _, attachment)| attachment.strong_count() > 0);
+        if let Some((_, other, attachment)) = attached.iter().find(|(name, ..)| name == ifname) {
+            if other != program {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("another XDP program is attached to {ifname}"),
+                ));
+            }
+            if let Some(attachment) = attachment.upgrade() {
+                return Ok(attachment);
+            }
+        }
+        let (bpf, link, xsks_map, offloaded) = match program {
+            XdpProgram::Object {
+                path,
+                program,
+                xsks_map,
+            } => {
+                let bpf = Ebpf::load_file(path)
+                    .map_err(|e| io::Error::other(format!("Failed to load BPF object: {e}")))?;
+                attach_program(bpf, program, xsks_map, ifname, xdp_flags, hw_offload)?
+            }
+            _ => {
+                if DEFAULT_PROG.is_empty() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "the built-in XDP program was not compiled: build with clang \
+                         installed, or use XdpProgram::Object",
+                    ));
+                }
+                let bpf = Ebpf::load(DEFAULT_PROG)
+                    .map_err(|e| io::Error::other(format!("Failed to load BPF object: {e}")))?;
+                attach_program(
+                    bpf,
+                    "xdp_sock_prog",
+                    "xsks_map",
+                    ifname,
+                    xdp_flags,
+                    hw_offload,
+                )?
+            }
+        };
+        let attachment = Arc::new(Self {
+            bpf: Mutex::new(bpf),
+            _link: link,
+            xsks_map,
+            builtin: *program == XdpProgram::Builtin,
+            offloaded,
+        });
+        attached.push((
+            ifname.to_owned(),
+            program.clone(),
+            Arc::downgrade(&attachment),
+        ));
+        Ok(attachment)
+    }
+
+    /// Returns whether the program runs on the NIC.
+    pub fn is_offloaded(&self) -> bool {
+        self.offloaded
+    }
+
+    /// Returns whether this is the built-in program.
+    pub fn is_builtin(&self) -> bool {
+        self.builtin
+    }
+
+    /// Sets the entry `index` of the array map `map` of the program to `value`.
+    pub fn set(&self, map: &str, index: u32, value: u32) -> io::Result<()> {
+        self.update(map, index, value)
+    }
+
+    /// Sets the entry `key` of the hash, LRU hash or array map `map` of the program to
+    /// `value`; the key of an array is its `u32` index.
+    pub fn update<K: Pod, V: Pod>(&self, map: &str, key: K, value: V) -> io::Result<()> {
+        let mut bpf = lock(&self.bpf)?;
+        let map = map_mut(&mut bpf, map)?;
+        match map {
+            Map::Array(_) => Array::<_, V>::try_from(map)
+                .and_then(|mut array| array.set(array_index(&key)?, value, 0)),
+            _ => HashMap::<_, K, V>::try_from(map).and_then(|mut hash| hash.insert(key, value, 0)),
+        }
+        .map_err(map_error)
+    }
+
+    /// Returns the entry `key` of the hash, LRU hash or array map `map` of the program,
+    /// or `None` if there is none.
+    pub fn get<K: Pod, V: Pod>(&self, map: &str, key: K) -> io::Result<Option<V>> {
+        let mut bpf = lock(&self.bpf)?;
+        let map = map_mut(&mut bpf, map)?;
+        let value = match map {
+            Map::Array(_) => {
+                Array::<_, V>::try_from(map).and_then(|array| array.get(&array_index(&key)?, 0))
+            }
+            _ => HashMap::<_, K, V>::try_from(map).and_then(|hash| hash.get(&key, 0)),
+        };
+        match value {
+            Ok(value) => Ok(Some(value)),
+            Err(MapError::KeyNotFound) => Ok(None),
+            Err(e) => Err(map_error(e)),
+        }
+    }
+
+    /// Removes the entry `key` of the hash or LRU hash map `map` of the program, whose
+    /// values are `V`, returning whether there was one.
+    pub fn remove<K: Pod, V: Pod>(&self, map: &str, key: K) -> io::Result<bool> {
+        let mut bpf = lock(&self.bpf)?;
+        let map = map_mut(&mut bpf, map)?;
+        match HashMap::<_, K, V>::try_from(map).and_then(|mut hash| hash.remove(&key)) {
+            Ok(()) => Ok(true),
+            Err(MapError::SyscallError(e)) if e.io_error.kind() == io::ErrorKind::NotFound => {
+                Ok(false)
+            }
+            Err(e) => Err(map_error(e)),
+        }
+    }
+}
+
+/// Returns the map `name` of `bpf`.
+/// Locks `mutex`, failing instead of panicking if a thread panicked while holding it.
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> io::Result<MutexGuard<'_, T>> {
+    mutex
+        .lock()
+        .map_err(|_| io::Error::other("AF_XDP state poisoned by a panicked thread"))
+}
+
+fn map_mut<'a>(bpf: &'a mut Ebpf, name: &str) -> io::Result<&'a mut Map> {
+    bpf.map_mut(name).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{name} not found in the XDP program"),
+        )
+    })
+}
+
+/// Returns the index of an array map held in `key`.
+fn array_index<K: Pod>(key: &K) -> Result<u32, MapError> {
+    if size_of::<K>() != size_of::<u32>() {
+        return Err(MapError::InvalidKeySize {
+            size: size_of::<K>(),
+            expected: size_of::<u32>(),
+        });
+    }
+    // SAFETY: `key` is plain old data of the size of a `u32`.
+    Ok(unsafe { std::mem::transmute_copy(key) })
+}
+
+fn map_error(e: MapError) -> io::Error {
+    match e {
+        MapError::InvalidKeySize { .. }
+        | MapError::InvalidValueSize { .. }
+        | MapError::InvalidMapType { .. }
+        | MapError::OutOfBounds { .. } => io::Error::new(io::ErrorKind::InvalidInput, e),
+        e => io::Error::other(e),
+    }
+}
+
+/// Loads the XDP program `name` of `bpf` and attaches it to `ifname`, returning the
+/// object, the link and the XSKMAP `map` the sockets are inserted into.
+fn attach_program(
+    mut bpf: Ebpf,
+    name: &str,
+    map: &str,
+    ifname: &str,
+    xdp_flags: u32,
+    hw_offload: bool,
+) -> io::Result<(Ebpf, XdpLinkId, OwnedFd, bool)> {
+    let prog: &mut Xdp = bpf
+        .program_mut(name)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{name} not found in BPF object"),
+            )
+        })?
+        .try_into()
+        .map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{name} is not an Xdp program"),
+            )
+        })?;
+
+    prog.load()
+        .map_err(|e| io::Error::other(format!("Failed to load XDP program: {e}")))?;
+
+    let offloaded = if hw_offload {
+        prog.attach(
+            ifname,
+            XdpFlags::from_bits_truncate(xdp_flags) | XdpFlags::HW_MODE,
+        )
+        .inspect_err(|e| {
+            trace_event!(
+                debug,
+                backend = "af_xdp",
+                ifname,
+                error = %e,
+                "hardware offload refused, falling back to the host"
+            );
+        })
+        .ok()
+    } else {
+        None
+    };
+    let (link_id, offloaded) = match offloaded {
+        Some(link_id) => (link_id, true),
+        None => {
+            let link_id = prog
+                .attach(ifname, XdpFlags::from_bits_truncate(xdp_flags))
+                .map_err(|e| io::Error::other(format!("Failed to attach XDP program: {e}")))?;
+            (link_id, false)
+        }
+    };
+
+    let xsks_map = bpf.map(map).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{map} not found in BPF object"),
+        )
+    })?;
+    let Map::XskMap(xsks_map) = xsks_map else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{map} is not an XskMap"),
+        ));
+    };
+    let fd = xsks_map.fd().as_fd().try_clone_to_owned()?;
+    Ok((bpf, link_id, fd, offloaded))
+}
 
 impl XskSocket {
+    /// Creates a socket bound to `umem`, using the fill and completion rings created
+    /// with it; only the first socket bound to a UMEM may use them.
     #[allow(clippy::too_many_arguments)]
     pub unsafe fn create(
-        umem: &mut Umem,
+        umem: &Umem,
         ifname: &str,
         queue_id: u32,
         xdp_flags: u32,
+        program: &XdpProgram,
+        hw_offload: bool,
         bind_flags: u16,
         rx_size: u32,
         tx_size: u32,
     ) -> io::Result<Self> {
-        let ifn = CString::new(ifname)
-            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid interface name"))?;
-
-        let mut bpf = Ebpf::load(DEFAULT_PROG)
-            .map_err(|e| io::Error::other(format!("Failed to load BPF object: {e}")))?;
-
-        let prog: &mut Xdp = bpf
-            .program_mut("xdp_sock_prog")
-            .expect("xdp_sock_prog not found in BPF object")
-            .try_into()
-            .expect("xdp_sock_prog is not an Xdp program");
+        let config = XskConfig {
+            ifname,
+            queue_id,
+            xdp_flags,
+            program,
+            hw_offload,
+            bind_flags,
+            rx_size,
+            tx_size,
+        };
+        unsafe { Self::bind(&umem.handle, &config, None) }
+    }
 
-        prog.load()
-            .map_err(|e| io::Error::other(format!("Failed to load XDP program: {e}")))?;
+    /// Creates a socket sharing a UMEM already bound to another socket
+    /// (`XDP_SHARED_UMEM`), with fill and completion rings of its own, which are
+    /// returned with it.
+    ///
+    /// The queue must differ from those of the other sockets of the UMEM, which own
+    /// the rings of their queues.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn create_shared(
+        umem: &SharedUmemRef,
+        ifname: &str,
+        queue_id: u32,
+        xdp_flags: u32,
+        program: &XdpProgram,
+        hw_offload: bool,
+        bind_flags: u16,
+        rx_size: u32,
+        tx_size: u32,
+    ) -> io::Result<(Self, FillQueue, CompletionQueue)> {
+        let config = XskConfig {
+            ifname,
+            queue_id,
+            xdp_flags,
+            program,
+            hw_offload,
+            bind_flags,
+            rx_size,
+            tx_size,
+        };
+        let mut fq = unsafe { zeroed() };
+        let mut cq = unsafe { zeroed() };
+        let socket = unsafe { Self::bind(&umem.handle, &config, Some((&mut fq, &mut cq)))? };
+        Ok((
+            socket,
+            FillQueue {
+                ring: fq,
+                _umem: umem.handle.clone(),
+            },
+            CompletionQueue {
+                ring: cq,
+                _umem: umem.handle.clone(),
+            },
+        ))
+    }
 
-        let link_id = prog
-            .attach(ifname, XdpFlags::from_bits_truncate(xdp_flags))
-            .map_err(|e| io::Error::other(format!("Failed to attach XDP program: {e}")))?;
+    unsafe fn bind(
+        umem: &Arc<UmemHandle>,
+        config: &XskConfig<'_>,
+        rings: Option<(&mut xsk_ring_prod, &mut xsk_ring_cons)>,
+    ) -> io::Result<Self> {
+        let XskConfig {
+            ifname,
+            queue_id,
+            xdp_flags,
+            program,
+            hw_offload,
+            bind_flags,
+            rx_size,
+            tx_size,
+        } = *config;
+        let ifn = CString::new(ifname)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid interface name"))?;
 
-        let xsks_map = bpf
-            .map_mut("xsks_map")
-            .expect("xsks_map not found in BPF object");
-        let Map::XskMap(xsks_map) = xsks_map else {
-            panic!("xsks_map is not an XskMap");
+        let (attachment, xsks_map) = match program {
+            XdpProgram::Pinned { xsks_map } => {
+                let map = MapData::from_pin(xsks_map)
+                    .map_err(|e| io::Error::other(format!("Failed to open pinned XSKMAP: {e}")))?;
+                (None, map.fd().as_fd().try_clone_to_owned()?)
+            }
+            program => {
+                let attachment =
+                    XdpAttachment::get_or_attach(ifname, program, xdp_flags, hw_offload)?;
+                let xsks_map = attachment.xsks_map.try_clone()?;
+                (Some(attachment), xsks_map)
+            }
         };
         let mut xsk_cfg: xsk_socket_config = unsafe { std::mem::zeroed() };
         xsk_cfg.rx_size = rx_size;
@@ -187,51 +654,117 @@ impl XskSocket {
         let mut tx = unsafe { core::mem::zeroed() };
 
         resultify(unsafe {
-            xsk_socket__create(
-                &mut xsk,
-                ifn.as_ptr(),
-                xsk_if_queue,
-                umem.inner.as_ptr(),
-                &mut rx,
-                &mut tx,
-                &xsk_cfg,
-            )
+            match rings {
+                None => xsk_socket__create(
+                    &mut xsk,
+                    ifn.as_ptr(),
+                    xsk_if_queue,
+                    umem.inner.as_ptr(),
+                    &mut rx,
+                    &mut tx,
+                    &xsk_cfg,
+                ),
+                Some((fq, cq)) => xsk_socket__create_shared(
+                    &mut xsk,
+                    ifn.as_ptr(),
+                    xsk_if_queue,
+                    umem.inner.as_ptr(),
+                    &mut rx,
+                    &mut tx,
+                    fq,
+                    cq,
+                    &xsk_cfg,
+                ),
+            }
         })?;
 
         let xsk = NonNull::new(xsk).expect("Failed to create xsk_socket");
-        let xsk_map_fd = xsks_map.fd().as_fd().as_raw_fd();
-        resultify(unsafe { xsk_socket__update_xskmap(xsk.as_ptr(), xsk_map_fd) })?;
+        resultify(unsafe { xsk_socket__update_xskmap(xsk.as_ptr(), xsks_map.as_raw_fd()) })?;
         Ok(XskSocket {
+            handle: Arc::new(XskHandle {
+                inner: xsk,
+                need_wakeup: bind_flags & libc::XDP_USE_NEED_WAKEUP != 0,
+                program: attachment,
+                _umem: umem.clone(),
+            }),
             rx: RxRing::new(rx),
             tx: TxRing {
                 tx,
                 cached: VecDeque::with_capacity(RX_BATCH_SIZE),
                 to_flush: 0,
             },
-            inner: xsk,
-            _link: link_id,
-            _bpf: bpf,
         })
     }
 
+    /// Splits the socket into its RX and TX rings.
+    ///
+    /// The kernel socket is deleted once both halves are dropped.
+    pub fn split(self) -> (XskRx, XskTx) {
+        (
+            XskRx {
+                handle: self.handle.clone(),
+                rx: self.rx,
+            },
+            XskTx {
+                handle: self.handle,
+                tx: self.tx,
+            },
+        )
+    }
+}
+
+/// Receive half of an [`XskSocket`].
+pub struct XskRx {
+    handle: Arc<XskHandle>,
+    rx: RxRing,
+}
+
+unsafe impl Send for XskRx {}
+
+impl XskRx {
     pub fn rx_mut(&mut self) -> &mut RxRing {
         &mut self.rx
     }
 
+    pub fn fd(&self) -> i32 {
+        self.handle.fd()
+    }
+
+    /// Returns whether the socket was bound with `XDP_USE_NEED_WAKEUP`, so that the
+    /// kernel flags the fill ring when it needs a syscall.
+    pub fn uses_need_wakeup(&self) -> bool {
+        self.handle.need_wakeup
+    }
+
+    /// Returns the XDP program the socket attached, unless the application attached its
+    /// own.
+    pub fn program(&self) -> Option<&Arc<XdpAttachment>> {
+        self.handle.program.as_ref()
+    }
+}
+
+/// Transmit half of an [`XskSocket`].
+pub struct XskTx {
+    handle: Arc<XskHandle>,
+    tx: TxRing,
+}
+
+unsafe impl Send for XskTx {}
+
+impl XskTx {
     pub fn tx_mut(&mut self) -> &mut TxRing {
         &mut self.tx
     }
 
     pub fn fd(&self) -> i32 {
-        unsafe { xsk_socket__fd(self.inner.as_ptr()) }
+        self.handle.fd()
     }
-}
 
-impl Drop for XskSocket {
-    fn drop(&mut self) {
-        unsafe {
-            xsk_socket__delete(self.inner.as_ptr());
-        }
+    /// Returns whether a `sendto` is needed for the kernel to send the submitted
+    /// descriptors: always, unless the socket was bound with `XDP_USE_NEED_WAKEUP` and
+    /// the kernel did not flag the TX ring.
+    pub fn needs_wakeup(&self) -> bool {
+        !self.handle.need_wakeup || unsafe { needs_wakeup(&self.tx.tx) }
     }
 }
 
@@ -263,7 +796,10 @@ impl RxRing {
             }
             for i in (idx_rx..(rcvd + idx_rx)).rev() {
                 let rx_desc = unsafe { xsk_ring_cons__rx_desc(&self.rx, i) };
+                // Unaligned chunks carry the offset of the packet in the upper bits.
                 let addr = unsafe { (*rx_desc).addr };
+                let addr = (addr & libc::XSK_UNALIGNED_BUF_ADDR_MASK)
+                    + (addr >> libc::XSK_UNALIGNED_BUF_OFFSET_SHIFT);
                 let len = unsafe { (*rx_desc).len };
                 let options = unsafe { (*rx_desc).options };
                 // SAFETY: rcvd <= RX_BATCH_SIZE, cached empty at the start
@@ -311,6 +847,20 @@ impl TxRing {
     pub fn iter(&mut self) -> TxRingIter {
         TxRingIter { ring: self }
     }
+
+    /// Reserves a single descriptor outside of an iterator.
+    pub fn reserve(&mut self) -> Option<*mut xdp_desc> {
+        self.iter().advance().map(|slot| slot.desc)
+    }
+
+    /// Gives back a descriptor obtained from [`reserve`](Self::reserve).
+    ///
+    /// # Safety
+    /// `desc` must be the last descriptor reserved, with no sync in between.
+    pub unsafe fn unreserve(&mut self, desc: *mut xdp_desc) {
+        self.cached.push_front(desc);
+        self.to_flush -= 1;
+    }
 }
 
 pub struct TxRingIter<'a> {
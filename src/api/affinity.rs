@@ -0,0 +1,152 @@
+//! Worker threads pinned to CPUs.
+
+use std::io;
+use std::thread::{self, JoinHandle};
+
+use super::Result;
+use super::group::SocketGroup;
+
+/// Spawns one thread per socket of `group`, each running `f(queue, socket)`.
+///
+/// Worker `i` is pinned to the `i`-th CPU the process is allowed to run on, wrapping
+/// around when there are more queues than CPUs. Use [`spawn_per_queue_on`] to choose
+/// the CPUs, e.g. those of the NIC's NUMA node:
+///
+/// ```ignore
+/// let group = af_xdp::Sock::create_group("eth0", flags)?;
+/// let workers = api::spawn_per_queue(group, |queue, socket| worker(queue, socket))?;
+/// for w in workers {
+///     w.join().unwrap()?;
+/// }
+/// ```
+pub fn spawn_per_queue<S, F, R>(group: SocketGroup<S>, f: F) -> Result<Vec<JoinHandle<Result<R>>>>
+where
+    S: Send + 'static,
+    F: FnOnce(usize, S) -> R + Send + Clone + 'static,
+    R: Send + 'static,
+{
+    let cpus = allowed_cpus()?;
+    spawn_per_queue_on(group, &cpus, f)
+}
+
+/// Like [`spawn_per_queue`], pinning worker `i` to `cpus[i % cpus.len()]`.
+///
+/// An empty `cpus` leaves the workers unpinned. The threads are named
+/// `nethuns-q<queue>`. Fails before spawning anything if a CPU is not one the process
+/// is allowed to run on.
+///
+/// A worker that the kernel refuses to pin after the CPUs were checked, e.g. because
+/// the process affinity was changed in between, returns the error through its
+/// `JoinHandle` without running `f`.
+pub fn spawn_per_queue_on<S, F, R>(
+    group: SocketGroup<S>,
+    cpus: &[usize],
+    f: F,
+) -> Result<Vec<JoinHandle<Result<R>>>>
+where
+    S: Send + 'static,
+    F: FnOnce(usize, S) -> R + Send + Clone + 'static,
+    R: Send + 'static,
+{
+    let allowed = allowed_cpus()?;
+    if let Some(cpu) = cpus.iter().find(|cpu| !allowed.contains(cpu)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("CPU {cpu} is not available to this process"),
+        )
+        .into());
+    }
+
+    let mut workers = Vec::with_capacity(group.len());
+    for (queue, socket) in group.into_iter().enumerate() {
+        let cpu = (!cpus.is_empty()).then(|| cpus[queue % cpus.len()]);
+        let f = f.clone();
+        let worker = thread::Builder::new()
+            .name(format!("nethuns-q{queue}"))
+            .spawn(move || {
+                if let Some(cpu) = cpu
+                    && let Err(e) = pin_current_thread(cpu)
+                {
+                    return Err(io::Error::new(
+                        e.kind(),
+                        format!("cannot pin worker of queue {queue} to CPU {cpu}: {e}"),
+                    )
+                    .into());
+                }
+                Ok(f(queue, socket))
+            })?;
+        workers.push(worker);
+    }
+    Ok(workers)
+}
+
+/// Returns the CPUs on the NUMA node of `ifname`'s device that the process is allowed
+/// to run on.
+///
+/// Falls back to every allowed CPU when the device has no NUMA node (virtual
+/// interfaces, single-node machines).
+pub fn numa_local_cpus(ifname: &str) -> Result<Vec<usize>> {
+    let allowed = allowed_cpus()?;
+    let node = std::fs::read_to_string(format!("/sys/class/net/{ifname}/device/numa_node"))
+        .ok()
+        .and_then(|s| s.trim().parse::<usize>().ok());
+    let Some(node) = node else {
+        return Ok(allowed);
+    };
+    let list = std::fs::read_to_string(format!("/sys/devices/system/node/node{node}/cpulist"))?;
+    let local = parse_cpu_list(&list)?;
+    Ok(allowed
+        .into_iter()
+        .filter(|cpu| local.contains(cpu))
+        .collect())
+}
+
+/// Returns the CPUs the calling thread is allowed to run on, in ascending order.
+fn allowed_cpus() -> io::Result<Vec<usize>> {
+    // SAFETY: an all-zero cpu_set_t is a valid empty set.
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    if unsafe { libc::sched_getaffinity(0, size_of::<libc::cpu_set_t>(), &mut set) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok((0..libc::CPU_SETSIZE as usize)
+        .filter(|&cpu| unsafe { libc::CPU_ISSET(cpu, &set) })
+        .collect())
+}
+
+fn pin_current_thread(cpu: usize) -> io::Result<()> {
+    // SAFETY: an all-zero cpu_set_t is a valid empty set.
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    unsafe { libc::CPU_SET(cpu, &mut set) };
+    if unsafe { libc::sched_setaffinity(0, size_of::<libc::cpu_set_t>(), &set) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Parses a kernel CPU list such as `0-3,8,10-11`.
+fn parse_cpu_list(list: &str) -> io::Result<Vec<usize>> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("bad CPU list {list:?}"));
+    let mut cpus = Vec::new();
+    for range in list.trim().split(',').filter(|r| !r.is_empty()) {
+        let (first, last) = range.split_once('-').unwrap_or((range, range));
+        let first: usize = first.parse().map_err(|_| invalid())?;
+        let last: usize = last.parse().map_err(|_| invalid())?;
+        cpus.extend(first..=last);
+    }
+    Ok(cpus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_list() {
+        assert_eq!(
+            parse_cpu_list("0-3,8,10-11\n").unwrap(),
+            [0, 1, 2, 3, 8, 10, 11]
+        );
+        assert_eq!(parse_cpu_list("\n").unwrap(), Vec::<usize>::new());
+        assert!(parse_cpu_list("0-x").is_err());
+    }
+}
@@ -0,0 +1,294 @@
+//! Runtime selection of the backend.
+
+use std::ops::{Deref, DerefMut};
+use std::time::Duration;
+
+use super::Result;
+use super::builder::SocketOptions;
+use super::capabilities::Capabilities;
+use super::checksum::TxChecksum;
+use super::device::{Backend, LinkInfo};
+use super::metadata::{Metadata, MetadataType};
+use super::socket::{Flags, Socket};
+use super::stats::{Dropped, Stats};
+use super::steer::{FlowRule, FlowSpec};
+use super::token::Payload;
+#[cfg(feature = "af-xdp")]
+use crate::af_xdp;
+#[cfg(feature = "dpdk")]
+use crate::dpdk;
+#[cfg(feature = "netmap")]
+use crate::netmap;
+#[cfg(feature = "pcap")]
+use crate::pcap;
+
+/// A socket of one of the backends compiled in, chosen at runtime.
+///
+/// Unlike [`DynSocket`](super::DynSocket), received payloads still point into the
+/// backend buffers. Backend-specific flags are left at their defaults and only the
+/// common [`SocketOptions`] are applied:
+///
+/// ```ignore
+/// let backend: Backend = args.engine.parse()?;
+/// let socket = AnySocket::create(backend, "eth0", None, &SocketOptions::default())?;
+/// let (packet, _meta) = socket.recv()?;
+/// ```
+pub enum AnySocket {
+    #[cfg(feature = "pcap")]
+    Pcap(pcap::Sock),
+    #[cfg(feature = "af-xdp")]
+    AfXdp(af_xdp::Sock),
+    #[cfg(feature = "netmap")]
+    Netmap(netmap::Sock),
+    #[cfg(feature = "dpdk")]
+    Dpdk(dpdk::Sock),
+}
+
+/// A packet received by an [`AnySocket`].
+pub enum AnyPayload<'a> {
+    #[cfg(feature = "pcap")]
+    Pcap(Payload<'a, pcap::PcapContext>),
+    #[cfg(feature = "af-xdp")]
+    AfXdp(Payload<'a, af_xdp::Ctx>),
+    #[cfg(feature = "netmap")]
+    Netmap(Payload<'a, netmap::Ctx>),
+    #[cfg(feature = "dpdk")]
+    Dpdk(Payload<'a, dpdk::Ctx>),
+}
+
+/// Runs `$body` with `$s` bound to the socket or payload inside any variant.
+macro_rules! each_backend {
+    ($enum:ident, $value:expr, $s:ident => $body:expr) => {
+        match $value {
+            #[cfg(feature = "pcap")]
+            $enum::Pcap($s) => $body,
+            #[cfg(feature = "af-xdp")]
+            $enum::AfXdp($s) => $body,
+            #[cfg(feature = "netmap")]
+            $enum::Netmap($s) => $body,
+            #[cfg(feature = "dpdk")]
+            $enum::Dpdk($s) => $body,
+        }
+    };
+}
+
+/// Wraps the payload and metadata returned by a backend.
+macro_rules! any_packet {
+    ($variant:ident, $res:expr) => {
+        $res.map(|(payload, meta)| (AnyPayload::$variant(payload), meta.into_enum()))
+    };
+}
+
+impl AnySocket {
+    /// Opens `portspec` with `backend`, configured by the common `options`.
+    ///
+    /// Fails with an `Unsupported` error if the backend was not compiled in.
+    pub fn create(
+        backend: Backend,
+        portspec: &str,
+        queue: Option<usize>,
+        options: &SocketOptions,
+    ) -> Result<Self> {
+        match backend {
+            #[cfg(feature = "pcap")]
+            Backend::Pcap => Ok(Self::Pcap(open(portspec, queue, options)?)),
+            #[cfg(feature = "af-xdp")]
+            Backend::AfXdp => Ok(Self::AfXdp(open(portspec, queue, options)?)),
+            #[cfg(feature = "netmap")]
+            Backend::Netmap => Ok(Self::Netmap(open(portspec, queue, options)?)),
+            #[cfg(feature = "dpdk")]
+            Backend::Dpdk => Ok(Self::Dpdk(open(portspec, queue, options)?)),
+            #[allow(unreachable_patterns)]
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!("backend {backend} is not compiled in"),
+            )
+            .into()),
+        }
+    }
+
+    /// Returns the backend of the socket.
+    pub fn backend(&self) -> Backend {
+        match self {
+            #[cfg(feature = "pcap")]
+            Self::Pcap(_) => Backend::Pcap,
+            #[cfg(feature = "af-xdp")]
+            Self::AfXdp(_) => Backend::AfXdp,
+            #[cfg(feature = "netmap")]
+            Self::Netmap(_) => Backend::Netmap,
+            #[cfg(feature = "dpdk")]
+            Self::Dpdk(_) => Backend::Dpdk,
+        }
+    }
+
+    /// Receives a packet. Same semantics as [`Socket::recv`].
+    pub fn recv(&self) -> Result<(AnyPayload<'_>, MetadataType)> {
+        match self {
+            #[cfg(feature = "pcap")]
+            Self::Pcap(s) => any_packet!(Pcap, s.recv()),
+            #[cfg(feature = "af-xdp")]
+            Self::AfXdp(s) => any_packet!(AfXdp, s.recv()),
+            #[cfg(feature = "netmap")]
+            Self::Netmap(s) => any_packet!(Netmap, s.recv()),
+            #[cfg(feature = "dpdk")]
+            Self::Dpdk(s) => any_packet!(Dpdk, s.recv()),
+        }
+    }
+
+    /// Receives a packet without blocking. Same semantics as [`Socket::try_recv`].
+    pub fn try_recv(&self) -> Result<(AnyPayload<'_>, MetadataType)> {
+        match self {
+            #[cfg(feature = "pcap")]
+            Self::Pcap(s) => any_packet!(Pcap, s.try_recv()),
+            #[cfg(feature = "af-xdp")]
+            Self::AfXdp(s) => any_packet!(AfXdp, s.try_recv()),
+            #[cfg(feature = "netmap")]
+            Self::Netmap(s) => any_packet!(Netmap, s.try_recv()),
+            #[cfg(feature = "dpdk")]
+            Self::Dpdk(s) => any_packet!(Dpdk, s.try_recv()),
+        }
+    }
+
+    /// Receives a packet, waiting at most `timeout`. Same semantics as
+    /// [`Socket::recv_timeout`].
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<(AnyPayload<'_>, MetadataType)> {
+        match self {
+            #[cfg(feature = "pcap")]
+            Self::Pcap(s) => any_packet!(Pcap, s.recv_timeout(timeout)),
+            #[cfg(feature = "af-xdp")]
+            Self::AfXdp(s) => any_packet!(AfXdp, s.recv_timeout(timeout)),
+            #[cfg(feature = "netmap")]
+            Self::Netmap(s) => any_packet!(Netmap, s.recv_timeout(timeout)),
+            #[cfg(feature = "dpdk")]
+            Self::Dpdk(s) => any_packet!(Dpdk, s.recv_timeout(timeout)),
+        }
+    }
+
+    /// Receives a packet by copying it into `buf`. Same semantics as
+    /// [`Socket::recv_into`].
+    pub fn recv_into(&self, buf: &mut [u8]) -> Result<(usize, MetadataType)> {
+        let (len, meta) = each_backend!(Self, self, s => {
+            let (len, meta) = s.recv_into(buf)?;
+            (len, meta.into_enum())
+        });
+        Ok((len, meta))
+    }
+
+    /// Sends a packet.
+    pub fn send(&self, packet: &[u8]) -> Result<()> {
+        each_backend!(Self, self, s => s.send(packet))
+    }
+
+    /// Sends a batch of packets and flushes once. Same semantics as
+    /// [`Socket::send_batch`].
+    pub fn send_batch<P: AsRef<[u8]>>(&self, packets: &[P]) -> Result<usize> {
+        each_backend!(Self, self, s => s.send_batch(packets))
+    }
+
+    /// Flushes any pending transmissions.
+    pub fn flush(&self) {
+        each_backend!(Self, self, s => s.flush())
+    }
+
+    /// Sends a packet tagged with `cookie`. Same semantics as
+    /// [`Socket::send_with_cookie`].
+    pub fn send_with_cookie(&self, packet: &[u8], cookie: u64) -> Result<()> {
+        each_backend!(Self, self, s => s.send_with_cookie(packet, cookie))
+    }
+
+    /// Sends a packet with the checksums in `csum` filled in. Same semantics as
+    /// [`Socket::send_with_checksum`].
+    pub fn send_with_checksum(&self, packet: &[u8], csum: TxChecksum) -> Result<()> {
+        each_backend!(Self, self, s => s.send_with_checksum(packet, csum))
+    }
+
+    /// Sends an oversized TCP or UDP packet as frames of at most `mss` payload bytes.
+    /// Same semantics as [`Socket::send_gso`].
+    pub fn send_gso(&self, packet: &[u8], mss: usize) -> Result<usize> {
+        each_backend!(Self, self, s => s.send_gso(packet, mss))
+    }
+
+    /// Collects the cookies of the tagged packets that left the NIC. Same semantics as
+    /// [`Socket::tx_completions`].
+    pub fn tx_completions(&self, out: &mut Vec<u64>) -> usize {
+        each_backend!(Self, self, s => s.tx_completions(out))
+    }
+
+    /// Drains pending transmissions and releases the socket. Same semantics as
+    /// [`Socket::close`].
+    pub fn close(self) -> Result<()> {
+        each_backend!(Self, self, s => s.close())
+    }
+
+    /// Installs a BPF filter on received packets. Same semantics as
+    /// [`Socket::set_filter`].
+    pub fn set_filter(&self, expr: &str) -> Result<()> {
+        each_backend!(Self, self, s => s.set_filter(expr))
+    }
+
+    /// Enables or disables promiscuous mode on the underlying interface.
+    pub fn set_promiscuous(&self, enable: bool) -> Result<()> {
+        each_backend!(Self, self, s => s.set_promiscuous(enable))
+    }
+
+    /// Receives the frames sent to the multicast MAC address `mac`.
+    pub fn join_multicast(&self, mac: [u8; 6]) -> Result<()> {
+        each_backend!(Self, self, s => s.join_multicast(mac))
+    }
+
+    /// Stops receiving the frames sent to the multicast MAC address `mac`.
+    pub fn leave_multicast(&self, mac: [u8; 6]) -> Result<()> {
+        each_backend!(Self, self, s => s.leave_multicast(mac))
+    }
+
+    /// Returns the link parameters of the underlying interface.
+    pub fn link_info(&self) -> Result<LinkInfo> {
+        each_backend!(Self, self, s => s.link_info())
+    }
+
+    /// Steers the flows matching `spec` to the RX queue `queue`. Same semantics as
+    /// [`Socket::steer`].
+    pub fn steer(&self, spec: FlowSpec, queue: usize) -> Result<FlowRule> {
+        each_backend!(Self, self, s => s.steer(spec, queue))
+    }
+
+    /// Removes a rule installed by [`steer`](AnySocket::steer).
+    pub fn unsteer(&self, rule: FlowRule) -> Result<()> {
+        each_backend!(Self, self, s => s.unsteer(rule))
+    }
+
+    /// Returns the socket's packet counters.
+    pub fn stats(&self) -> Result<Stats> {
+        each_backend!(Self, self, s => s.stats())
+    }
+
+    /// Returns the packets lost by nethuns itself.
+    pub fn dropped(&self) -> Dropped {
+        each_backend!(Self, self, s => s.dropped())
+    }
+
+    /// Returns the features supported by the socket.
+    pub fn capabilities(&self) -> Capabilities {
+        each_backend!(Self, self, s => s.capabilities())
+    }
+}
+
+impl Deref for AnyPayload<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        each_backend!(AnyPayload, self, p => p)
+    }
+}
+
+impl DerefMut for AnyPayload<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        each_backend!(AnyPayload, self, p => p.payload_mut())
+    }
+}
+
+fn open<S: Socket>(portspec: &str, queue: Option<usize>, options: &SocketOptions) -> Result<S> {
+    let mut flags = S::Flags::default();
+    flags.apply(options);
+    S::create(portspec, queue, flags)
+}
@@ -0,0 +1,88 @@
+//! Tokio adapter for fd-backed sockets.
+
+use tokio::io::unix::AsyncFd;
+
+use super::Result;
+use super::poll::Pollable;
+use super::token::{Payload, Token};
+use crate::errors::Error;
+
+/// Wraps a [`Pollable`] socket so it can be driven from a tokio runtime.
+///
+/// The socket's descriptor is registered with the tokio reactor; receives wait for
+/// readiness instead of blocking a thread, and sends wait for TX ring space whenever
+/// the backend reports its ring as full.
+///
+/// ```ignore
+/// let socket = AsyncSocket::new(Sock::create("eth0", Some(0), flags)?)?;
+/// let (payload, meta) = socket.recv().await?;
+/// socket.send(&payload).await?;
+/// ```
+pub struct AsyncSocket<S: Pollable> {
+    inner: AsyncFd<S>,
+}
+
+impl<S: Pollable> AsyncSocket<S> {
+    /// Registers `socket` with the current tokio reactor.
+    ///
+    /// Must be called from within a tokio runtime. Fails with `Unsupported` if the
+    /// socket cannot be polled; see [`Pollable::poll_fd`].
+    pub fn new(socket: S) -> Result<Self> {
+        socket.poll_fd()?;
+        Ok(Self {
+            inner: AsyncFd::new(socket)?,
+        })
+    }
+
+    /// Returns a reference to the wrapped socket.
+    pub fn get_ref(&self) -> &S {
+        self.inner.get_ref()
+    }
+
+    /// Deregisters the socket from the reactor and returns it.
+    pub fn into_inner(self) -> S {
+        self.inner.into_inner()
+    }
+
+    /// Waits for a packet and returns its payload and metadata.
+    pub async fn recv(&self) -> Result<(Payload<'_, S::Context>, S::Metadata)> {
+        let (token, meta) = self.recv_token().await?;
+        Ok((token.consume(self.get_ref().context()), meta))
+    }
+
+    /// Waits for a packet and returns its token and metadata.
+    pub async fn recv_token(&self) -> Result<(Token, S::Metadata)> {
+        loop {
+            let mut guard = self.inner.readable().await?;
+            match guard.get_inner().try_recv_token() {
+                Err(Error::WouldBlock) => guard.clear_ready(),
+                res => return res,
+            }
+        }
+    }
+
+    /// Sends a packet, waiting for TX ring space if the ring is full.
+    pub async fn send(&self, packet: &[u8]) -> Result<()> {
+        loop {
+            let mut guard = self.inner.writable().await?;
+            let socket = guard.get_inner();
+            match socket.send(packet) {
+                Err(Error::NoMemory) => {
+                    socket.flush();
+                    guard.clear_ready();
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Flushes any pending transmissions.
+    pub fn flush(&self) {
+        self.get_ref().flush();
+    }
+
+    /// Returns a reference to the socket's context.
+    pub fn context(&self) -> &S::Context {
+        self.get_ref().context()
+    }
+}
@@ -0,0 +1,245 @@
+//! Backend-independent socket configuration.
+
+use super::Result;
+use super::flush::FlushPolicy;
+use super::metadata::VlanTag;
+use super::pacing::TxRate;
+use super::socket::{Flags, Socket};
+use super::timestamp::TimestampSource;
+
+/// Traffic a socket captures, relative to the interface.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Direction {
+    /// Only frames received by the interface.
+    In,
+    /// Only frames sent by the interface.
+    Out,
+    /// Both received and sent frames.
+    #[default]
+    InOut,
+}
+
+/// Options shared by all backends.
+///
+/// Each backend maps the options it supports onto its own flags in
+/// [`Flags::apply`]; the others are ignored. `None` keeps the backend default.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[non_exhaustive]
+pub struct SocketOptions {
+    /// Put the interface in promiscuous mode.
+    pub promiscuous: Option<bool>,
+    /// Receive every multicast frame, without the rest of promiscuous traffic.
+    pub allmulti: Option<bool>,
+    /// Number of RX ring descriptors.
+    pub rx_ring: Option<u32>,
+    /// Number of TX ring descriptors.
+    pub tx_ring: Option<u32>,
+    /// Maximum number of bytes of each received packet exposed to the application.
+    ///
+    /// Longer packets are truncated, with [`Metadata::wirelen`](super::Metadata::wirelen)
+    /// reporting their full length. pcap also truncates what the kernel copies; the
+    /// zero-copy backends still receive the whole frame into their buffers.
+    pub snaplen: Option<u32>,
+    /// Size of each packet buffer (UMEM frame, mbuf data room, pcap buffer).
+    pub buffer_size: Option<u32>,
+    /// Number of packet buffers in the pool.
+    pub num_buffers: Option<u32>,
+    /// Captured traffic direction.
+    ///
+    /// AF_XDP, DPDK and netmap hardware rings only ever see ingress traffic.
+    pub direction: Option<Direction>,
+    /// When queued TX packets are pushed to the NIC.
+    pub flush_policy: Option<FlushPolicy>,
+    /// Rate at which packets are transmitted.
+    pub tx_rate: Option<TxRate>,
+    /// Remove the outermost VLAN tag from received frames and report it in the
+    /// metadata.
+    ///
+    /// DPDK strips in the NIC, and so does AF_XDP when `AfXdpFlags::rx_vlan` reads the
+    /// stripped tag from the XDP metadata; otherwise the frame bytes are moved in
+    /// software.
+    pub vlan_strip: Option<bool>,
+    /// Insert this VLAN tag into every transmitted frame.
+    ///
+    /// DPDK inserts the tag in the NIC; the other backends move the frame bytes.
+    pub vlan_insert: Option<VlanTag>,
+    /// Timestamp received packets with the NIC clock.
+    ///
+    /// netmap has no hardware timestamps and ignores this option. AF_XDP reads them
+    /// from the metadata hints of the XDP program, so sockets using the built-in one
+    /// fail to open with it, and pcap only reports them if the adapter accepted them.
+    pub hw_timestamps: Option<bool>,
+    /// Clock used for software timestamps.
+    pub timestamp_source: Option<TimestampSource>,
+    /// Deliver received frames with their trailing 4-byte Ethernet FCS.
+    ///
+    /// `true` asks the NIC to keep the FCS: DPDK enables the `KEEP_CRC` offload, pcap
+    /// and AF_XDP turn on the `rx-fcs` feature of the interface. Where that is not
+    /// supported the frames come without it. `false` strips the FCS in software from
+    /// frames that carry it anyway. netmap drivers never deliver it.
+    /// [`Metadata::has_fcs`](super::Metadata::has_fcs) tells whether a frame ends
+    /// with it.
+    pub fcs: Option<bool>,
+}
+
+/// Builder for sockets of any backend, obtained through [`Socket::builder`].
+///
+/// ```ignore
+/// let socket = af_xdp::Sock::builder("eth0")
+///     .queue(2)
+///     .rx_ring(4096)
+///     .xdp_flags(XDP_FLAGS_DRV_MODE)
+///     .build()?;
+/// ```
+///
+/// Backends add inherent methods on `SocketBuilder<Sock>` for their specific knobs.
+/// Common options are applied on top of the backend flags when the socket is built, so
+/// they take precedence over values set through [`configure`](SocketBuilder::configure).
+pub struct SocketBuilder<S: Socket> {
+    portspec: String,
+    queue: Option<usize>,
+    options: SocketOptions,
+    flags: S::Flags,
+}
+
+impl<S: Socket> SocketBuilder<S> {
+    /// Starts a builder for `portspec` with the backend's default flags.
+    pub fn new(portspec: &str) -> Self {
+        Self {
+            portspec: portspec.to_string(),
+            queue: None,
+            options: SocketOptions::default(),
+            flags: S::Flags::default(),
+        }
+    }
+
+    /// Binds the socket to a specific queue.
+    pub fn queue(mut self, queue: usize) -> Self {
+        self.queue = Some(queue);
+        self
+    }
+
+    /// Enables or disables promiscuous mode.
+    pub fn promiscuous(mut self, enable: bool) -> Self {
+        self.options.promiscuous = Some(enable);
+        self
+    }
+
+    /// Enables or disables the reception of all multicast frames.
+    pub fn allmulti(mut self, enable: bool) -> Self {
+        self.options.allmulti = Some(enable);
+        self
+    }
+
+    /// Sets the number of RX ring descriptors.
+    pub fn rx_ring(mut self, size: u32) -> Self {
+        self.options.rx_ring = Some(size);
+        self
+    }
+
+    /// Sets the number of TX ring descriptors.
+    pub fn tx_ring(mut self, size: u32) -> Self {
+        self.options.tx_ring = Some(size);
+        self
+    }
+
+    /// Sets the maximum number of bytes captured per packet.
+    pub fn snaplen(mut self, snaplen: u32) -> Self {
+        self.options.snaplen = Some(snaplen);
+        self
+    }
+
+    /// Sets the size of each packet buffer.
+    pub fn buffer_size(mut self, size: u32) -> Self {
+        self.options.buffer_size = Some(size);
+        self
+    }
+
+    /// Sets the number of packet buffers in the pool.
+    pub fn num_buffers(mut self, count: u32) -> Self {
+        self.options.num_buffers = Some(count);
+        self
+    }
+
+    /// Selects which traffic direction is captured.
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.options.direction = Some(direction);
+        self
+    }
+
+    /// Sets when queued TX packets are pushed to the NIC.
+    pub fn flush_policy(mut self, policy: FlushPolicy) -> Self {
+        self.options.flush_policy = Some(policy);
+        self
+    }
+
+    /// Limits the rate at which packets are transmitted.
+    pub fn tx_rate(mut self, rate: TxRate) -> Self {
+        self.options.tx_rate = Some(rate);
+        self
+    }
+
+    /// Strips the outermost VLAN tag from received frames.
+    pub fn vlan_strip(mut self, enable: bool) -> Self {
+        self.options.vlan_strip = Some(enable);
+        self
+    }
+
+    /// Inserts `tag` into every transmitted frame.
+    pub fn vlan_insert(mut self, tag: VlanTag) -> Self {
+        self.options.vlan_insert = Some(tag);
+        self
+    }
+
+    /// Requests NIC hardware timestamps on received packets.
+    pub fn hw_timestamps(mut self, enable: bool) -> Self {
+        self.options.hw_timestamps = Some(enable);
+        self
+    }
+
+    /// Selects the clock used for software timestamps.
+    pub fn timestamp_source(mut self, source: TimestampSource) -> Self {
+        self.options.timestamp_source = Some(source);
+        self
+    }
+
+    /// Keeps or strips the Ethernet FCS of received frames.
+    pub fn fcs(mut self, keep: bool) -> Self {
+        self.options.fcs = Some(keep);
+        self
+    }
+
+    /// Replaces the backend flags.
+    pub fn flags(mut self, flags: S::Flags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Edits the backend flags in place.
+    pub fn configure(mut self, f: impl FnOnce(&mut S::Flags)) -> Self {
+        f(&mut self.flags);
+        self
+    }
+
+    /// Returns the common options set so far.
+    pub fn options(&self) -> &SocketOptions {
+        &self.options
+    }
+
+    /// Creates the socket.
+    pub fn build(self) -> Result<S> {
+        let mut flags = self.flags;
+        flags.apply(&self.options);
+        S::create(&self.portspec, self.queue, flags)
+    }
+}
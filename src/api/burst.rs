@@ -0,0 +1,167 @@
+//! Budgeted receive bursts and fair polling of several sockets from one thread.
+
+use super::Result;
+use super::context::Context;
+use super::group::SocketGroup;
+use super::metadata::Metadata;
+use super::socket::Socket;
+use super::token::Payload;
+
+/// Sources of packets that can be drained a bounded burst at a time.
+///
+/// Implemented by every [`Socket`], by the receive half of every backend and by
+/// [`Mirror`](super::Mirror), so that [`RoundRobin`] can service any of them.
+pub trait RecvBurst {
+    /// The context type that manages the buffers of the received packets.
+    type Context: Context;
+    /// The metadata type returned with each received packet.
+    type Metadata: Metadata;
+
+    /// Hands at most `budget` ready packets to `f` without blocking, and returns how
+    /// many were processed.
+    ///
+    /// Same semantics as [`Socket::dispatch`]: `Ok(0)` when no packet is ready, and an
+    /// error only if it occurs before the first packet.
+    fn poll_burst<F>(&self, budget: usize, f: F) -> Result<usize>
+    where
+        F: FnMut(Payload<'_, Self::Context>, Self::Metadata);
+}
+
+impl<S: Socket> RecvBurst for S {
+    type Context = S::Context;
+    type Metadata = S::Metadata;
+
+    #[inline]
+    fn poll_burst<F>(&self, budget: usize, f: F) -> Result<usize>
+    where
+        F: FnMut(Payload<'_, Self::Context>, Self::Metadata),
+    {
+        self.dispatch(budget, f)
+    }
+}
+
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+/// Implements [`RecvBurst`] for the receive half of a backend.
+macro_rules! rx_burst {
+    ($rx:ty) => {
+        impl $crate::api::RecvBurst for $rx {
+            type Context = <$rx as $crate::api::RxSocket>::Context;
+            type Metadata = <$rx as $crate::api::RxSocket>::Metadata;
+
+            #[inline]
+            fn poll_burst<F>(&self, budget: usize, f: F) -> $crate::api::Result<usize>
+            where
+                F: FnMut($crate::api::Payload<'_, Self::Context>, Self::Metadata),
+            {
+                $crate::api::RxSocket::dispatch(self, budget, f)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "af-xdp")]
+rx_burst!(crate::af_xdp::RxSock);
+#[cfg(feature = "dpdk")]
+rx_burst!(crate::dpdk::RxSock);
+#[cfg(feature = "netmap")]
+rx_burst!(crate::netmap::RxSock);
+#[cfg(feature = "pcap")]
+rx_burst!(crate::pcap::RxSock);
+
+/// Round-robin scheduler servicing a set of sockets from a single thread.
+///
+/// Each [`poll`](RoundRobin::poll) gives every socket the same budget, so a busy socket
+/// cannot starve the others, and starts one socket further than the previous pass, so
+/// none is always served first:
+///
+/// ```ignore
+/// let mut sockets = RoundRobin::from(af_xdp::Sock::create_group("eth0", flags)?);
+/// while !term.load(Ordering::Relaxed) {
+///     sockets.poll(32, |queue, packet, _meta| process(queue, &packet))?;
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RoundRobin<S> {
+    sockets: Vec<S>,
+    next: usize,
+}
+
+impl<S: RecvBurst> RoundRobin<S> {
+    /// Schedules `sockets`, identified in [`poll`](RoundRobin::poll) by their index.
+    pub fn new(sockets: Vec<S>) -> Self {
+        Self { sockets, next: 0 }
+    }
+
+    /// Polls every socket once, handing at most `budget` packets of each to `f` along
+    /// with the socket index, and returns the total number of packets processed.
+    ///
+    /// Never blocks. If a socket fails the pass stops and the error is returned; the
+    /// next pass starts with the socket after it, so a failing socket does not keep
+    /// the others from being serviced.
+    pub fn poll<F>(&mut self, budget: usize, mut f: F) -> Result<usize>
+    where
+        F: FnMut(usize, Payload<'_, S::Context>, S::Metadata),
+    {
+        let len = self.sockets.len();
+        let start = self.next;
+        let mut total = 0;
+        for i in 0..len {
+            let index = (start + i) % len;
+            match self.sockets[index].poll_burst(budget, |packet, meta| f(index, packet, meta)) {
+                Ok(count) => total += count,
+                Err(e) => {
+                    self.next = (index + 1) % len;
+                    return Err(e);
+                }
+            }
+        }
+        if len > 0 {
+            self.next = (start + 1) % len;
+        }
+        Ok(total)
+    }
+}
+
+impl<S> RoundRobin<S> {
+    /// Returns the number of scheduled sockets.
+    pub fn len(&self) -> usize {
+        self.sockets.len()
+    }
+
+    /// Returns `true` if no socket is scheduled.
+    pub fn is_empty(&self) -> bool {
+        self.sockets.is_empty()
+    }
+
+    /// Returns the socket with the given index.
+    pub fn get(&self, index: usize) -> Option<&S> {
+        self.sockets.get(index)
+    }
+
+    /// Iterates over the sockets in index order.
+    pub fn iter(&self) -> std::slice::Iter<'_, S> {
+        self.sockets.iter()
+    }
+
+    /// Returns the sockets in index order.
+    pub fn into_vec(self) -> Vec<S> {
+        self.sockets
+    }
+}
+
+impl<S: RecvBurst> From<Vec<S>> for RoundRobin<S> {
+    fn from(sockets: Vec<S>) -> Self {
+        Self::new(sockets)
+    }
+}
+
+impl<S: RecvBurst> From<SocketGroup<S>> for RoundRobin<S> {
+    fn from(group: SocketGroup<S>) -> Self {
+        Self::new(group.into_vec())
+    }
+}
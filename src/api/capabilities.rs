@@ -0,0 +1,53 @@
+//! Feature discovery for sockets.
+
+/// What a socket supports, as reported by [`Socket::capabilities`].
+///
+/// Lets applications choose a code path at runtime instead of matching on the backend.
+///
+/// [`Socket::capabilities`]: super::Socket::capabilities
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// Received payloads point into the backend's buffers, without a copy.
+    pub zero_copy_rx: bool,
+    /// Packets built with `tx_reserve` are written in place into the backend's buffers.
+    pub zero_copy_tx: bool,
+    /// Received payloads are exclusively owned and can be edited in place with
+    /// [`Payload::payload_mut`], e.g. to rewrite addresses before forwarding them.
+    ///
+    /// [`Payload::payload_mut`]: super::Payload::payload_mut
+    pub writable_rx: bool,
+    /// Packet timestamps are taken by the NIC.
+    pub hw_timestamps: bool,
+    /// The socket can be bound to a single hardware queue.
+    pub multi_queue: bool,
+    /// [`Socket::create_group`] opens one socket per RX queue of the interface.
+    ///
+    /// DPDK sockets configure their port with a single RX queue, so DPDK has no groups.
+    ///
+    /// [`Socket::create_group`]: super::Socket::create_group
+    pub socket_groups: bool,
+    /// Received frames arrive without their outermost VLAN tag, which is reported by
+    /// [`Metadata::vlan`] instead.
+    ///
+    /// [`Metadata::vlan`]: super::Metadata::vlan
+    pub vlan_strip: bool,
+    /// Checksums requested with [`TxSlot::set_checksum`] are computed by the NIC.
+    ///
+    /// [`TxSlot::set_checksum`]: super::TxSlot::set_checksum
+    pub tx_checksum: bool,
+    /// [`Socket::send_gso`] hands TCP packets to the NIC for segmentation.
+    ///
+    /// [`Socket::send_gso`]: super::Socket::send_gso
+    pub tso: bool,
+    /// [`Socket::set_filter`] drops packets before they reach the socket (kernel or
+    /// NIC), instead of in userspace after they are received.
+    ///
+    /// [`Socket::set_filter`]: super::Socket::set_filter
+    pub kernel_filter: bool,
+    /// The driver moves frames between the NIC and the socket's buffers, without a
+    /// copy in the kernel: AF_XDP sockets bound in zero-copy mode, netmap and DPDK.
+    pub zero_copy_driver: bool,
+    /// Largest frame, in bytes, the socket can receive or send.
+    pub max_frame_size: usize,
+}
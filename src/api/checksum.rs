@@ -0,0 +1,260 @@
+//! Checksums of transmitted packets.
+
+use super::metadata::VlanTag;
+
+/// Checksums filled in when a packet is transmitted, requested per packet with
+/// [`TxSlot::set_checksum`](super::TxSlot::set_checksum) or
+/// [`Socket::send_with_checksum`](super::Socket::send_with_checksum).
+///
+/// The NIC computes them when the socket reports
+/// [`Capabilities::tx_checksum`](super::Capabilities::tx_checksum), otherwise they are
+/// computed in software on commit. Only Ethernet frames carrying IPv4 or IPv6 (behind
+/// at most two VLAN tags) and TCP or UDP are handled; other packets are sent unchanged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TxChecksum {
+    /// The IPv4 header checksum.
+    pub ip: bool,
+    /// The TCP or UDP checksum.
+    pub l4: bool,
+}
+
+impl TxChecksum {
+    /// No checksum.
+    pub const NONE: Self = Self {
+        ip: false,
+        l4: false,
+    };
+    /// Both the IPv4 header and the TCP/UDP checksum.
+    pub const ALL: Self = Self { ip: true, l4: true };
+
+    /// Returns whether no checksum is requested.
+    pub fn is_none(&self) -> bool {
+        *self == Self::NONE
+    }
+}
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+pub(crate) const IPPROTO_TCP: u8 = 6;
+pub(crate) const IPPROTO_UDP: u8 = 17;
+
+/// Layout of the headers of an IP packet in an Ethernet frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Headers {
+    /// Length of the Ethernet header, tags included.
+    pub(crate) l2_len: usize,
+    /// Length of the IP header.
+    pub(crate) l3_len: usize,
+    pub(crate) ipv6: bool,
+    /// IP protocol of the L4 header.
+    pub(crate) proto: u8,
+    /// Length of the L4 header and payload.
+    pub(crate) l4_len: usize,
+}
+
+impl Headers {
+    /// Locates the IP and L4 headers of `frame`.
+    pub(crate) fn parse(frame: &[u8]) -> Option<Self> {
+        let mut l2_len = 14;
+        let mut ethertype = read_u16(frame, 12)?;
+        for _ in 0..2 {
+            if ethertype != VlanTag::TPID_8021Q && ethertype != VlanTag::TPID_8021AD {
+                break;
+            }
+            ethertype = read_u16(frame, l2_len + 2)?;
+            l2_len += 4;
+        }
+        let ip = frame.get(l2_len..)?;
+        let (l3_len, proto, l4_len, ipv6) = match ethertype {
+            ETHERTYPE_IPV4 => {
+                let l3_len = (*ip.first()? as usize & 0x0f) * 4;
+                let total = read_u16(ip, 2)? as usize;
+                (l3_len, *ip.get(9)?, total.checked_sub(l3_len)?, false)
+            }
+            ETHERTYPE_IPV6 => (40, *ip.get(6)?, read_u16(ip, 4)? as usize, true),
+            _ => return None,
+        };
+        if ip.len() < l3_len + l4_len {
+            return None;
+        }
+        Some(Self {
+            l2_len,
+            l3_len,
+            ipv6,
+            proto,
+            l4_len,
+        })
+    }
+
+    /// Offset of the checksum field in the L4 header, if it is TCP or UDP.
+    #[cfg(any(
+        feature = "pcap",
+        feature = "af-xdp",
+        feature = "netmap",
+        feature = "dpdk"
+    ))]
+    pub(crate) fn l4_checksum_offset(&self) -> Option<usize> {
+        match self.proto {
+            IPPROTO_TCP if self.l4_len >= 20 => Some(16),
+            IPPROTO_UDP if self.l4_len >= 8 => Some(6),
+            _ => None,
+        }
+    }
+
+    /// Length of the TCP or UDP header.
+    pub(crate) fn l4_header_len(&self, frame: &[u8]) -> Option<usize> {
+        let len = match self.proto {
+            IPPROTO_TCP => (*frame.get(self.l4_offset() + 12)? as usize >> 4) * 4,
+            IPPROTO_UDP => 8,
+            _ => return None,
+        };
+        (len <= self.l4_len).then_some(len)
+    }
+
+    /// Offset of the L4 header in the frame.
+    pub(crate) fn l4_offset(&self) -> usize {
+        self.l2_len + self.l3_len
+    }
+
+    /// Folded, not complemented, sum of the pseudo-header of the L4 checksum.
+    #[cfg(any(
+        feature = "pcap",
+        feature = "af-xdp",
+        feature = "netmap",
+        feature = "dpdk"
+    ))]
+    pub(crate) fn pseudo_header_sum(&self, frame: &[u8]) -> u16 {
+        fold(self.pseudo_header_addrs_sum(frame) + self.l4_len as u32)
+    }
+
+    /// Like [`pseudo_header_sum`](Self::pseudo_header_sum), without the L4 length:
+    /// what NICs expect in the checksum field of a packet they segment.
+    #[cfg(feature = "dpdk")]
+    pub(crate) fn pseudo_header_sum_no_len(&self, frame: &[u8]) -> u16 {
+        fold(self.pseudo_header_addrs_sum(frame))
+    }
+
+    #[cfg(any(
+        feature = "pcap",
+        feature = "af-xdp",
+        feature = "netmap",
+        feature = "dpdk"
+    ))]
+    fn pseudo_header_addrs_sum(&self, frame: &[u8]) -> u32 {
+        let ip = &frame[self.l2_len..];
+        let addrs = if self.ipv6 { &ip[8..40] } else { &ip[12..20] };
+        sum(addrs) + self.proto as u32
+    }
+}
+
+/// Computes the checksums requested by `csum` in place.
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+pub(crate) fn fill(frame: &mut [u8], csum: TxChecksum) {
+    if csum.is_none() {
+        return;
+    }
+    let Some(headers) = Headers::parse(frame) else {
+        return;
+    };
+    let l3 = headers.l2_len;
+    if csum.ip && !headers.ipv6 {
+        let ip = &mut frame[l3..l3 + headers.l3_len];
+        ip[10..12].fill(0);
+        let value = !fold(sum(ip));
+        ip[10..12].copy_from_slice(&value.to_be_bytes());
+    }
+    if csum.l4
+        && let Some(offset) = headers.l4_checksum_offset()
+    {
+        let pseudo = headers.pseudo_header_sum(frame);
+        let l4 = l3 + headers.l3_len;
+        let l4 = &mut frame[l4..l4 + headers.l4_len];
+        l4[offset..offset + 2].fill(0);
+        let mut value = !fold(sum(l4) + pseudo as u32);
+        if value == 0 && headers.proto == IPPROTO_UDP {
+            // A zero UDP checksum means "no checksum".
+            value = 0xffff;
+        }
+        l4[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([
+        *buf.get(offset)?,
+        *buf.get(offset + 1)?,
+    ]))
+}
+
+/// Sums `data` as big-endian 16-bit words, padding an odd byte with zero.
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+fn sum(data: &[u8]) -> u32 {
+    let mut chunks = data.chunks_exact(2);
+    let mut sum: u32 = 0;
+    for word in &mut chunks {
+        sum += u16::from_be_bytes([word[0], word[1]]) as u32;
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    if let [last] = chunks.remainder() {
+        sum += (*last as u32) << 8;
+    }
+    sum
+}
+
+/// Folds the carries of a one's complement sum.
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+fn fold(mut sum: u32) -> u16 {
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum as u16
+}
+
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ipv4_udp_checksums() {
+        // Ethernet + IPv4 192.168.0.1 -> 192.168.0.199 + UDP 1024 -> 2048, 4 payload bytes.
+        let mut frame = vec![0u8; 14];
+        frame[12..14].copy_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        frame.extend_from_slice(&[
+            0x45, 0x00, 0x00, 0x20, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00, 192, 168, 0, 1,
+            192, 168, 0, 199,
+        ]);
+        frame.extend_from_slice(&[0x04, 0x00, 0x08, 0x00, 0x00, 0x0c, 0x00, 0x00]);
+        frame.extend_from_slice(b"ping");
+
+        fill(&mut frame, TxChecksum::ALL);
+        assert_eq!(&frame[24..26], &[0xb8, 0xb4]);
+        // A packet with valid checksums sums to zero.
+        assert_eq!(!fold(sum(&frame[14..34])), 0);
+        let headers = Headers::parse(&frame).unwrap();
+        assert_eq!(
+            !fold(sum(&frame[34..]) + headers.pseudo_header_sum(&frame) as u32),
+            0
+        );
+    }
+}
@@ -0,0 +1,70 @@
+//! Tracking of transmitted packets until they leave the NIC.
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+
+/// Per-socket bookkeeping behind `send_with_cookie` and `tx_completions`.
+///
+/// Every queued packet gets a sequence number; tagged ones are remembered with their
+/// cookie. The TX rings of all backends complete packets in the order they were queued,
+/// so the backend only has to report how many queued packets are still in flight.
+#[derive(Debug, Default)]
+pub(crate) struct TxCompletions {
+    queued: Cell<u64>,
+    tagged: RefCell<VecDeque<(u64, u64)>>,
+}
+
+impl TxCompletions {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a packet queued for transmission.
+    #[inline]
+    pub(crate) fn queued(&self) {
+        self.queued.set(self.queued.get() + 1);
+    }
+
+    /// Tags the packet queued last with `cookie`.
+    pub(crate) fn tag_last(&self, cookie: u64) {
+        let seq = self.queued.get().wrapping_sub(1);
+        self.tagged.borrow_mut().push_back((seq, cookie));
+    }
+
+    /// Moves to `out` the cookies of the tagged packets that left, given that the last
+    /// `in_flight` queued packets have not. Returns how many were moved.
+    pub(crate) fn drain(&self, in_flight: usize, out: &mut Vec<u64>) -> usize {
+        let done = self.queued.get().saturating_sub(in_flight as u64);
+        let mut tagged = self.tagged.borrow_mut();
+        let before = out.len();
+        while let Some(&(seq, cookie)) = tagged.front() {
+            if seq >= done {
+                break;
+            }
+            out.push(cookie);
+            tagged.pop_front();
+        }
+        out.len() - before
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completions_in_order() {
+        let tx = TxCompletions::new();
+        for cookie in [10, 11, 12] {
+            tx.queued();
+            tx.queued();
+            tx.tag_last(cookie);
+        }
+        let mut out = Vec::new();
+        assert_eq!(tx.drain(6, &mut out), 0);
+        assert_eq!(tx.drain(2, &mut out), 2);
+        assert_eq!(out, [10, 11]);
+        assert_eq!(tx.drain(0, &mut out), 1);
+        assert_eq!(out, [10, 11, 12]);
+    }
+}
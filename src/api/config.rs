@@ -0,0 +1,201 @@
+//! Socket configuration loaded from files.
+
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use super::Result;
+use super::any_socket::AnySocket;
+use super::builder::SocketOptions;
+use super::device::Backend;
+use super::socket::{Flags, Socket};
+#[cfg(feature = "af-xdp")]
+use crate::af_xdp;
+#[cfg(feature = "dpdk")]
+use crate::dpdk;
+use crate::errors::Error;
+#[cfg(feature = "netmap")]
+use crate::netmap;
+#[cfg(feature = "pcap")]
+use crate::pcap;
+
+/// Everything needed to open a socket, deserializable with any serde format.
+///
+/// The backend flags sections are optional and default to the backend defaults; the
+/// common `options` are applied on top of them, as with a [`SocketBuilder`]:
+///
+/// ```toml
+/// backend = "af-xdp"
+/// portspec = "eth0"
+/// queue = 0
+///
+/// [options]
+/// rx_ring = 4096
+/// flush_policy = "manual"
+///
+/// [af_xdp]
+/// xdp_flags = 2
+/// ```
+///
+/// ```ignore
+/// let config = Config::from_toml(&std::fs::read_to_string("capture.toml")?)?;
+/// let socket = config.open()?;
+/// ```
+///
+/// [`SocketBuilder`]: super::SocketBuilder
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Config {
+    /// Backend used to open the socket.
+    pub backend: Backend,
+    /// Port specification passed to the backend.
+    pub portspec: String,
+    /// Queue to bind to, if any.
+    #[serde(default)]
+    pub queue: Option<usize>,
+    /// Options shared by all backends.
+    #[serde(default)]
+    pub options: SocketOptions,
+    /// Flags of the pcap backend.
+    #[cfg(feature = "pcap")]
+    #[serde(default)]
+    pub pcap: Option<pcap::PcapFlags>,
+    /// Flags of the AF_XDP backend.
+    #[cfg(feature = "af-xdp")]
+    #[serde(default)]
+    pub af_xdp: Option<af_xdp::AfXdpFlags>,
+    /// Flags of the netmap backend.
+    #[cfg(feature = "netmap")]
+    #[serde(default)]
+    pub netmap: Option<netmap::NetmapFlags>,
+    /// Flags of the DPDK backend.
+    #[cfg(feature = "dpdk")]
+    #[serde(default)]
+    pub dpdk: Option<dpdk::DpdkFlags>,
+}
+
+impl Config {
+    /// Parses a configuration written in TOML.
+    ///
+    /// Fails with an `InvalidData` error describing the first offending key.
+    pub fn from_toml(s: &str) -> Result<Self> {
+        toml::from_str(s).map_err(invalid)
+    }
+
+    /// Parses a configuration written in YAML.
+    ///
+    /// Fails with an `InvalidData` error describing the first offending key.
+    pub fn from_yaml(s: &str) -> Result<Self> {
+        serde_yaml::from_str(s).map_err(invalid)
+    }
+
+    /// Opens the configured socket.
+    ///
+    /// Fails with an `Unsupported` error if the backend was not compiled in.
+    pub fn open(&self) -> Result<AnySocket> {
+        match self.backend {
+            #[cfg(feature = "pcap")]
+            Backend::Pcap => Ok(AnySocket::Pcap(self.create(&self.pcap)?)),
+            #[cfg(feature = "af-xdp")]
+            Backend::AfXdp => Ok(AnySocket::AfXdp(self.create(&self.af_xdp)?)),
+            #[cfg(feature = "netmap")]
+            Backend::Netmap => Ok(AnySocket::Netmap(self.create(&self.netmap)?)),
+            #[cfg(feature = "dpdk")]
+            Backend::Dpdk => Ok(AnySocket::Dpdk(self.create(&self.dpdk)?)),
+            #[allow(unreachable_patterns)]
+            _ => AnySocket::create(self.backend, &self.portspec, self.queue, &self.options),
+        }
+    }
+
+    fn create<S: Socket>(&self, flags: &Option<S::Flags>) -> Result<S> {
+        let mut flags = flags.clone().unwrap_or_default();
+        flags.apply(&self.options);
+        S::create(&self.portspec, self.queue, flags)
+    }
+}
+
+fn invalid(e: impl std::error::Error + Send + Sync + 'static) -> Error {
+    io::Error::new(io::ErrorKind::InvalidData, e).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::api::{Direction, FlushPolicy, TxRate};
+
+    fn expected_options() -> SocketOptions {
+        SocketOptions {
+            promiscuous: Some(true),
+            rx_ring: Some(4096),
+            direction: Some(Direction::In),
+            flush_policy: Some(FlushPolicy::Auto {
+                packets: 32,
+                interval: Duration::from_micros(100),
+            }),
+            tx_rate: Some(TxRate::Packets(1_000_000)),
+            ..SocketOptions::default()
+        }
+    }
+
+    #[test]
+    fn parses_toml() {
+        let config = Config::from_toml(
+            r#"
+            backend = "pcap"
+            portspec = "eth0"
+            queue = 1
+
+            [options]
+            promiscuous = true
+            rx_ring = 4096
+            direction = "in"
+            flush_policy = { auto = { packets = 32, interval = { secs = 0, nanos = 100000 } } }
+            tx_rate = { packets = 1000000 }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.backend, Backend::Pcap);
+        assert_eq!(config.portspec, "eth0");
+        assert_eq!(config.queue, Some(1));
+        assert_eq!(config.options, expected_options());
+    }
+
+    #[test]
+    fn parses_yaml() {
+        let config = Config::from_yaml(
+            "
+            backend: pcap
+            portspec: eth0
+            options:
+              promiscuous: true
+              rx_ring: 4096
+              direction: in
+              flush_policy: !auto { packets: 32, interval: { secs: 0, nanos: 100000 } }
+              tx_rate: !packets 1000000
+            ",
+        )
+        .unwrap();
+        assert_eq!(config.queue, None);
+        assert_eq!(config.options, expected_options());
+    }
+
+    #[test]
+    fn round_trips() {
+        let config = Config::from_toml("backend = \"pcap\"\nportspec = \"eth0\"").unwrap();
+        let config = Config {
+            options: expected_options(),
+            ..config
+        };
+        let text = toml::to_string(&config).unwrap();
+        assert_eq!(Config::from_toml(&text).unwrap().options, config.options);
+        let text = serde_yaml::to_string(&config).unwrap();
+        assert_eq!(Config::from_yaml(&text).unwrap().options, config.options);
+    }
+
+    #[test]
+    fn rejects_unknown_backend() {
+        let e = Config::from_toml("backend = \"pf-ring\"\nportspec = \"eth0\"").unwrap_err();
+        assert!(matches!(e, Error::Generic(e) if e.kind() == io::ErrorKind::InvalidData));
+    }
+}
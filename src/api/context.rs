@@ -1,5 +1,11 @@
 //! Context trait and related utilities.
 
+#[cfg(any(test, feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+use std::sync::Arc;
+
+#[cfg(any(test, feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+use crossbeam_queue::ArrayQueue;
+
 use super::buffer::BufferDesc;
 use super::hint::unlikely;
 use super::token::{Payload, Token};
@@ -39,5 +45,113 @@ pub trait Context: Sized + Clone + Send + 'static {
     unsafe fn unsafe_buffer(&self, buf_idx: BufferDesc, size: usize) -> *mut [u8];
 
     /// Releases a buffer back to the pool.
+    ///
+    /// Backends only hand a buffer back to the RX ring or fill queue once it has been
+    /// released, so a buffer is never reused while a [`Payload`] still references it.
     fn release(&self, buf_idx: BufferDesc);
+
+    /// Releases the buffer of an [`OwnedPacket`](super::OwnedPacket), through a clone of
+    /// the context that may live on another thread.
+    ///
+    /// Defaults to [`release`](Self::release), which is enough for contexts whose clones
+    /// share one thread-safe pool. The zero-copy backends push the buffer to a
+    /// thread-safe queue instead, which the socket drains before it refills its rings.
+    fn release_detached(&self, buf_idx: BufferDesc) {
+        self.release(buf_idx);
+    }
+}
+
+/// Buffers released by [`OwnedPacket`](super::OwnedPacket)s, waiting to go back to the
+/// socket they were received on.
+///
+/// The `mpsc` producer of a context only sends complete groups of 16 buffers, and every
+/// clone of the context gets its own, empty, group: the few buffers a clone released
+/// before being dropped would never reach the socket. Clones share this queue instead,
+/// and the socket moves its content to its own producer with
+/// [`reclaim`](ReturnQueue::reclaim).
+#[cfg(any(test, feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+#[derive(Clone)]
+pub(crate) struct ReturnQueue(Arc<ArrayQueue<BufferDesc>>);
+
+#[cfg(any(test, feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+impl ReturnQueue {
+    /// Creates a queue for a pool of `nbufs` buffers.
+    pub(crate) fn new(nbufs: usize) -> Self {
+        Self(Arc::new(ArrayQueue::new(nbufs.max(1))))
+    }
+
+    /// Queues a released buffer; callable from any thread.
+    pub(crate) fn push(&self, buf_idx: BufferDesc) {
+        // The queue holds the whole pool, so it is never full.
+        let _ = self.0.push(buf_idx);
+    }
+
+    /// Moves the queued buffers to `producer`, returning how many were moved.
+    pub(crate) fn reclaim<T>(&self, producer: &mut mpsc::Producer<T>) -> usize {
+        let mut moved = 0;
+        while let Some(buf_idx) = self.0.pop() {
+            producer.push(buf_idx);
+            moved += 1;
+        }
+        moved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::thread;
+
+    /// A pool released into an `mpsc` channel, like the zero-copy backends.
+    #[derive(Clone)]
+    struct MockCtx {
+        frames: Arc<Vec<u8>>,
+        producer: RefCell<mpsc::Producer<BufferDesc>>,
+        returns: ReturnQueue,
+    }
+
+    impl Context for MockCtx {
+        fn pool_id(&self) -> u32 {
+            0
+        }
+
+        unsafe fn unsafe_buffer(&self, buf_idx: BufferDesc, size: usize) -> *mut [u8] {
+            let start = self.frames[usize::from(buf_idx)..].as_ptr() as *mut u8;
+            std::ptr::slice_from_raw_parts_mut(start, size)
+        }
+
+        fn release(&self, buf_idx: BufferDesc) {
+            self.producer.borrow_mut().push(buf_idx);
+        }
+
+        fn release_detached(&self, buf_idx: BufferDesc) {
+            self.returns.push(buf_idx);
+        }
+    }
+
+    #[test]
+    fn owned_packets_return_their_buffers() {
+        const BUFS: usize = 32;
+        let (producer, mut consumer) = mpsc::channel(BUFS);
+        let ctx = MockCtx {
+            frames: Arc::new(vec![0; BUFS]),
+            producer: RefCell::new(producer),
+            returns: ReturnQueue::new(BUFS),
+        };
+        let owned: Vec<_> = (0..BUFS)
+            .map(|idx| {
+                let token = Token::new(BufferDesc::from(idx), 0, 1);
+                token.consume(&ctx).into_owned_packet()
+            })
+            .collect();
+        // Each packet holds its own clone of the context.
+        thread::spawn(move || drop(owned)).join().unwrap();
+
+        assert_eq!(ctx.returns.reclaim(&mut ctx.producer.borrow_mut()), BUFS);
+        ctx.producer.borrow_mut().flush();
+        let mut returned: Vec<_> = std::iter::from_fn(|| consumer.pop()).collect();
+        returned.sort_unstable();
+        assert_eq!(returned, (0..BUFS).collect::<Vec<_>>());
+    }
 }
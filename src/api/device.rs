@@ -0,0 +1,275 @@
+//! Enumeration of the network interfaces available on the host.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::Result;
+use crate::errors::Error;
+
+const SYS_CLASS_NET: &str = "/sys/class/net";
+
+/// A packet I/O backend of the library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+#[non_exhaustive]
+pub enum Backend {
+    AfXdp,
+    Netmap,
+    Dpdk,
+    Pcap,
+}
+
+impl Backend {
+    /// Name of the backend, as accepted by [`FromStr`](std::str::FromStr).
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::AfXdp => "af-xdp",
+            Backend::Netmap => "netmap",
+            Backend::Dpdk => "dpdk",
+            Backend::Pcap => "pcap",
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Backend {
+    type Err = Error;
+
+    /// Parses a backend name, ignoring case; `af_xdp` and `xdp` are accepted for
+    /// AF_XDP.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "af-xdp" | "af_xdp" | "afxdp" | "xdp" => Ok(Backend::AfXdp),
+            "netmap" => Ok(Backend::Netmap),
+            "dpdk" => Ok(Backend::Dpdk),
+            "pcap" => Ok(Backend::Pcap),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("unknown backend: {s:?}"),
+            )
+            .into()),
+        }
+    }
+}
+
+/// A network interface, as returned by [`list_devices`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Device {
+    /// Interface name, usable as a portspec.
+    pub name: String,
+    /// Kernel interface index.
+    pub index: u32,
+    /// Hardware address, if the interface has one.
+    pub mac: Option<[u8; 6]>,
+    /// Maximum transmission unit.
+    pub mtu: u32,
+    /// Whether the interface is administratively up and has carrier.
+    pub link_up: bool,
+    /// Backends of this build that can open the interface.
+    pub backends: Vec<Backend>,
+}
+
+/// Duplex mode of a link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Duplex {
+    Half,
+    Full,
+}
+
+/// Link parameters of the interface a socket is bound to, see
+/// [`Socket::link_info`](super::Socket::link_info).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LinkInfo {
+    /// Whether the interface is up and has carrier.
+    pub up: bool,
+    /// Maximum transmission unit.
+    pub mtu: u32,
+    /// Link speed in Mb/s, if known.
+    pub speed: Option<u32>,
+    /// Duplex mode, if known.
+    pub duplex: Option<Duplex>,
+    /// Hardware address, if the interface has one.
+    pub mac: Option<[u8; 6]>,
+}
+
+/// Lists the network interfaces of the host, sorted by index.
+///
+/// Only kernel interfaces are reported: ports bound to a DPDK driver are not visible to
+/// the kernel and must be addressed by their PCI address. Without `/sys/class/net`, the
+/// list comes from libpcap when the pcap backend is built, with no MAC address or MTU;
+/// `pcap::list_devices` also reports addresses.
+pub fn list_devices() -> Result<Vec<Device>> {
+    let entries = match fs::read_dir(SYS_CLASS_NET) {
+        Ok(entries) => entries,
+        #[cfg(feature = "pcap")]
+        Err(_) => return pcap_devices(),
+        #[cfg(not(feature = "pcap"))]
+        Err(e) => return Err(e.into()),
+    };
+    let mut devices = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        // The interface may disappear while we are reading it.
+        if let Some(device) = read_device(&entry.path(), name) {
+            devices.push(device);
+        }
+    }
+    devices.sort_by_key(|d| d.index);
+    Ok(devices)
+}
+
+/// Lists the devices libpcap can capture from, for hosts without sysfs.
+#[cfg(feature = "pcap")]
+fn pcap_devices() -> Result<Vec<Device>> {
+    let mut devices: Vec<_> = crate::pcap::list_devices()?
+        .into_iter()
+        .map(|device| {
+            let name = std::ffi::CString::new(device.name.as_str()).unwrap_or_default();
+            // SAFETY: `name` is a valid C string; unknown names give 0.
+            let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+            Device {
+                name: device.name,
+                index,
+                mac: None,
+                mtu: 0,
+                link_up: device.up && device.running,
+                backends: vec![Backend::Pcap],
+            }
+        })
+        .collect();
+    devices.sort_by_key(|d| d.index);
+    Ok(devices)
+}
+
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+/// Reads the link parameters of a kernel interface.
+pub(crate) fn link_info(ifname: &str) -> Result<LinkInfo> {
+    let path = Path::new(SYS_CLASS_NET).join(ifname);
+    read_link(&path).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("no such interface: {ifname}"),
+        )
+        .into()
+    })
+}
+
+fn read_device(path: &Path, name: String) -> Option<Device> {
+    let index = fs::read_to_string(path.join("ifindex"))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    let link = read_link(path)?;
+    Some(Device {
+        name,
+        index,
+        mac: link.mac,
+        mtu: link.mtu,
+        link_up: link.up,
+        backends: backends(),
+    })
+}
+
+fn read_link(path: &Path) -> Option<LinkInfo> {
+    let attr = |attr: &str| fs::read_to_string(path.join(attr)).ok();
+
+    let mtu = attr("mtu")?.trim().parse().ok()?;
+    let mac = attr("address").and_then(|s| parse_mac(s.trim()));
+    let flags = attr("flags")
+        .and_then(|s| u32::from_str_radix(s.trim().trim_start_matches("0x"), 16).ok())
+        .unwrap_or(0);
+    // `carrier`, `speed` and `duplex` can only be read while the interface is up.
+    let up = flags & libc::IFF_UP as u32 != 0 && attr("carrier").is_some_and(|s| s.trim() == "1");
+    // Virtual interfaces report a speed of -1.
+    let speed = attr("speed")
+        .and_then(|s| s.trim().parse::<i64>().ok())
+        .and_then(|s| u32::try_from(s).ok())
+        .filter(|&s| s != 0 && s != u32::MAX);
+    let duplex = attr("duplex").and_then(|s| match s.trim() {
+        "full" => Some(Duplex::Full),
+        "half" => Some(Duplex::Half),
+        _ => None,
+    });
+
+    Some(LinkInfo {
+        up,
+        mtu,
+        speed,
+        duplex,
+        mac,
+    })
+}
+
+/// Backends compiled in that can open a kernel interface.
+fn backends() -> Vec<Backend> {
+    [
+        (cfg!(feature = "af-xdp"), Backend::AfXdp),
+        (
+            cfg!(feature = "netmap") && Path::new("/dev/netmap").exists(),
+            Backend::Netmap,
+        ),
+        (cfg!(feature = "pcap"), Backend::Pcap),
+    ]
+    .into_iter()
+    .filter_map(|(available, backend)| available.then_some(backend))
+    .collect()
+}
+
+/// Parses a `xx:xx:xx:xx:xx:xx` address; all-zero addresses count as none.
+fn parse_mac(s: &str) -> Option<[u8; 6]> {
+    let mut mac = [0u8; 6];
+    let mut parts = s.split(':');
+    for byte in &mut mac {
+        *byte = u8::from_str_radix(parts.next()?, 16).ok()?;
+    }
+    if parts.next().is_some() || mac == [0; 6] {
+        return None;
+    }
+    Some(mac)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Backend, parse_mac};
+
+    #[test]
+    fn parse_mac_address() {
+        assert_eq!(
+            parse_mac("02:42:ac:11:00:02"),
+            Some([0x02, 0x42, 0xac, 0x11, 0x00, 0x02])
+        );
+        assert_eq!(parse_mac("00:00:00:00:00:00"), None);
+        assert_eq!(parse_mac("02:42:ac:11:00"), None);
+        // Infiniband addresses are longer than an Ethernet MAC.
+        assert_eq!(parse_mac("00:00:00:00:00:00:00:00:00:00"), None);
+    }
+
+    #[test]
+    fn parse_backend() {
+        for backend in [
+            Backend::AfXdp,
+            Backend::Netmap,
+            Backend::Dpdk,
+            Backend::Pcap,
+        ] {
+            assert_eq!(backend.to_string().parse::<Backend>().unwrap(), backend);
+        }
+        assert_eq!("AF_XDP".parse::<Backend>().unwrap(), Backend::AfXdp);
+        assert!("bogus".parse::<Backend>().is_err());
+    }
+}
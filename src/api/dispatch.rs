@@ -0,0 +1,443 @@
+//! Software RSS: spreading received packets across worker threads by flow.
+//!
+//! For backends and NICs that cannot spread the load in hardware, a [`Dispatcher`]
+//! receives from one or more sockets, hashes every packet on its flow and hands it to
+//! one of N [`Worker`] queues. All the packets of a flow go to the same worker, in the
+//! order they were received.
+//!
+//! ```ignore
+//! use nethuns_rs::api::dispatch::{Dispatcher, FlowHasher};
+//!
+//! let (dispatcher, workers) = Dispatcher::new(4, 1024, FlowHasher::Symmetric);
+//! for worker in workers {
+//!     thread::spawn(move || {
+//!         while let Ok((packet, _meta)) = worker.recv() {
+//!             process(&packet);
+//!         }
+//!     });
+//! }
+//! let socket = pcap::Sock::create("eth0", None, PcapFlags::default())?;
+//! while !term.load(Ordering::Relaxed) {
+//!     dispatcher.poll(&socket, 64)?;
+//! }
+//! ```
+//!
+//! Packets are handed over without copying, as [`OwnedPacket`]s: their buffers only
+//! return to the socket pool once the workers drop them, so the pool must be large
+//! enough to cover the worker queues.
+
+use std::cell::{Cell, RefCell, UnsafeCell};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+use crossbeam_queue::ArrayQueue;
+
+use super::Result;
+use super::burst::{RecvBurst, RoundRobin};
+use super::context::Context;
+use super::hash::{flow_hash, toeplitz_hash};
+use super::token::{OwnedPacket, Payload};
+use crate::errors::Error;
+
+/// Pads the batches of the worker channels, which carry slot indices in groups of 16.
+const PADDING: usize = usize::MAX;
+/// Longest a waiting worker sleeps before looking at its queue again.
+const IDLE_WAIT: Duration = Duration::from_millis(1);
+
+/// How a [`Dispatcher`] hashes packets onto workers.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum FlowHasher {
+    /// [`flow_hash`](super::flow_hash): both directions of a flow go to the same worker.
+    #[default]
+    Symmetric,
+    /// [`toeplitz_hash`](super::toeplitz_hash) with the given key, e.g.
+    /// [`TOEPLITZ_DEFAULT_KEY`](super::TOEPLITZ_DEFAULT_KEY) to match the NIC.
+    Toeplitz([u8; 40]),
+    /// A custom function of the frame.
+    Custom(fn(&[u8]) -> u32),
+}
+
+impl FlowHasher {
+    /// Hashes `frame`.
+    #[inline]
+    pub fn hash(&self, frame: &[u8]) -> u32 {
+        match self {
+            FlowHasher::Symmetric => flow_hash(frame),
+            FlowHasher::Toeplitz(key) => toeplitz_hash(frame, key),
+            FlowHasher::Custom(f) => f(frame),
+        }
+    }
+}
+
+/// The dispatching side of software RSS, created with [`Dispatcher::new`] along with
+/// its workers.
+///
+/// Each worker queue is a ring of slots handed over through the crate's `mpsc` channel,
+/// which moves them in groups of 16: [`poll`](Dispatcher::poll) and
+/// [`poll_all`](Dispatcher::poll_all) flush the queues after every burst, but callers of
+/// [`dispatch`](Dispatcher::dispatch) must call [`flush`](Dispatcher::flush) whenever
+/// they go idle, so that the last packets reach the workers too.
+///
+/// Cloning it gives another handle on the same worker queues, e.g. to dispatch from
+/// several receiving threads; per-flow ordering then holds as long as each flow arrives
+/// on a single socket. Drops are counted per handle.
+pub struct Dispatcher<C: Context, M> {
+    workers: Vec<WorkerTx<C, M>>,
+    hasher: FlowHasher,
+    dropped: Cell<u64>,
+}
+
+impl<C: Context, M: Send> Dispatcher<C, M> {
+    /// Creates a dispatcher feeding `workers` queues of `capacity` packets each.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `workers` is zero.
+    pub fn new(workers: usize, capacity: usize, hasher: FlowHasher) -> (Self, Vec<Worker<C, M>>) {
+        assert!(workers > 0, "a dispatcher needs at least one worker");
+        let capacity = capacity.max(1);
+        let (senders, receivers) = (0..workers)
+            .map(|_| {
+                let queue = Arc::new(Queue::new(capacity));
+                // Even if every group carried a single slot, the channel could hold
+                // them all, so pushing never waits.
+                let (producer, consumer) = mpsc::channel(capacity + 1);
+                let worker = Worker {
+                    queue: queue.clone(),
+                    rx: RefCell::new(WorkerRx { consumer, next: 0 }),
+                    _producer: producer.clone(),
+                };
+                (WorkerTx::new(queue, producer), worker)
+            })
+            .unzip();
+        let dispatcher = Self {
+            workers: senders,
+            hasher,
+            dropped: Cell::new(0),
+        };
+        (dispatcher, receivers)
+    }
+
+    /// Returns the number of workers.
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Returns the worker `packet` is dispatched to.
+    #[inline]
+    pub fn worker_of(&self, packet: &[u8]) -> usize {
+        self.hasher.hash(packet) as usize % self.workers.len()
+    }
+
+    /// Hands `packet` to the worker of its flow.
+    ///
+    /// Never blocks: if the worker queue is full, or the worker is gone, the packet is
+    /// dropped, counted in [`dropped`](Dispatcher::dropped) and `false` is returned.
+    pub fn dispatch(&self, packet: Payload<'_, C>, meta: M) -> bool {
+        let worker = self.worker_of(&packet);
+        let sent = self.workers[worker].send(packet, meta);
+        if !sent {
+            self.dropped.set(self.dropped.get() + 1);
+        }
+        sent
+    }
+
+    /// Hands the packets dispatched so far to the workers.
+    pub fn flush(&self) {
+        for worker in &self.workers {
+            worker.flush();
+        }
+    }
+
+    /// Dispatches at most `budget` ready packets of `socket`, and returns how many were
+    /// received.
+    ///
+    /// Never blocks; same errors as [`RecvBurst::poll_burst`].
+    pub fn poll<S>(&self, socket: &S, budget: usize) -> Result<usize>
+    where
+        S: RecvBurst<Context = C, Metadata = M>,
+    {
+        let received = socket.poll_burst(budget, |packet, meta| {
+            self.dispatch(packet, meta);
+        });
+        self.flush();
+        received
+    }
+
+    /// Dispatches at most `budget` ready packets of each socket of `sockets`, and
+    /// returns how many were received; see [`RoundRobin::poll`].
+    pub fn poll_all<S>(&self, sockets: &mut RoundRobin<S>, budget: usize) -> Result<usize>
+    where
+        S: RecvBurst<Context = C, Metadata = M>,
+    {
+        let received = sockets.poll(budget, |_, packet, meta| {
+            self.dispatch(packet, meta);
+        });
+        self.flush();
+        received
+    }
+
+    /// Returns the number of packets this handle dropped because their worker queue
+    /// was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.get()
+    }
+}
+
+impl<C: Context, M> Clone for Dispatcher<C, M> {
+    fn clone(&self) -> Self {
+        Self {
+            workers: self
+                .workers
+                .iter()
+                .map(|worker| WorkerTx::new(worker.queue.clone(), worker.producer.borrow().clone()))
+                .collect(),
+            hasher: self.hasher,
+            dropped: Cell::new(0),
+        }
+    }
+}
+
+impl<C: Context, M> Drop for Dispatcher<C, M> {
+    fn drop(&mut self) {
+        for worker in &self.workers {
+            worker.flush();
+            worker.queue.dispatchers.fetch_sub(1, Ordering::Release);
+            worker.queue.wake();
+        }
+    }
+}
+
+/// A slot of a worker queue, holding a packet between the dispatcher and the worker.
+type Slot<C, M> = UnsafeCell<Option<(OwnedPacket<C>, M)>>;
+
+/// The slots of a worker queue, shared by the worker and the dispatcher handles.
+struct Queue<C: Context, M> {
+    slots: Box<[Slot<C, M>]>,
+    /// Indices of the slots not holding a packet.
+    free: ArrayQueue<usize>,
+    /// Number of dispatcher handles alive.
+    dispatchers: AtomicUsize,
+    /// Set once the worker is dropped.
+    closed: AtomicBool,
+    /// Thread of the worker, if it waits for packets.
+    waiter: Mutex<Option<Thread>>,
+}
+
+// SAFETY: a slot is only accessed by the thread that popped it from `free` or received
+// it from the channel; the packets and metadata in the slots are `Send`.
+unsafe impl<C: Context, M: Send> Sync for Queue<C, M> {}
+
+impl<C: Context, M> Queue<C, M> {
+    fn new(capacity: usize) -> Self {
+        let free = ArrayQueue::new(capacity);
+        for idx in 0..capacity {
+            let _ = free.push(idx);
+        }
+        Self {
+            slots: (0..capacity).map(|_| UnsafeCell::new(None)).collect(),
+            free,
+            dispatchers: AtomicUsize::new(0),
+            closed: AtomicBool::new(false),
+            waiter: Mutex::new(None),
+        }
+    }
+
+    /// Takes the packet out of slot `idx`, received from the channel, and frees the
+    /// slot.
+    fn take(&self, idx: usize) -> (OwnedPacket<C>, M) {
+        // SAFETY: the dispatcher gave the slot up when pushing it.
+        let entry = unsafe { (*self.slots[idx].get()).take() };
+        let _ = self.free.push(idx);
+        entry.expect("slots are only pushed with a packet")
+    }
+
+    /// Wakes the worker up, if it waits for packets.
+    fn wake(&self) {
+        if let Ok(waiter) = self.waiter.lock()
+            && let Some(thread) = waiter.as_ref()
+        {
+            thread.unpark();
+        }
+    }
+}
+
+/// The sending side of a worker queue, owned by a dispatcher handle.
+struct WorkerTx<C: Context, M> {
+    queue: Arc<Queue<C, M>>,
+    producer: RefCell<mpsc::Producer<usize>>,
+    /// Slot indices pushed since the channel was last padded to a full group.
+    pending: Cell<usize>,
+    /// Whether packets were pushed since the worker was last woken up.
+    unwoken: Cell<bool>,
+}
+
+impl<C: Context, M> WorkerTx<C, M> {
+    fn new(queue: Arc<Queue<C, M>>, producer: mpsc::Producer<usize>) -> Self {
+        queue.dispatchers.fetch_add(1, Ordering::Relaxed);
+        Self {
+            queue,
+            producer: RefCell::new(producer),
+            pending: Cell::new(0),
+            unwoken: Cell::new(false),
+        }
+    }
+
+    fn send(&self, packet: Payload<'_, C>, meta: M) -> bool {
+        if self.queue.closed.load(Ordering::Relaxed) {
+            return false;
+        }
+        let Some(idx) = self.queue.free.pop() else {
+            return false;
+        };
+        // SAFETY: the slot was free, so this thread has it to itself until it is pushed
+        // to the worker.
+        unsafe { *self.queue.slots[idx].get() = Some((packet.into_owned_packet(), meta)) };
+        self.push(idx);
+        self.unwoken.set(true);
+        true
+    }
+
+    fn push(&self, idx: usize) {
+        self.producer.borrow_mut().push(idx);
+        self.pending.set((self.pending.get() + 1) % 16);
+    }
+
+    fn flush(&self) {
+        while self.pending.get() != 0 {
+            self.push(PADDING);
+        }
+        self.producer.borrow_mut().flush();
+        if self.unwoken.replace(false) {
+            self.queue.wake();
+        }
+    }
+}
+
+/// A worker queue of a [`Dispatcher`], to be moved to its own thread.
+pub struct Worker<C: Context, M> {
+    queue: Arc<Queue<C, M>>,
+    rx: RefCell<WorkerRx>,
+    /// Keeps the per-thread queues of the channel alive once every dispatcher handle
+    /// is dropped, until the worker has drained them.
+    _producer: mpsc::Producer<usize>,
+}
+
+/// The receiving end of the channel of a worker.
+struct WorkerRx {
+    consumer: mpsc::Consumer<usize>,
+    /// Number of entries of the consumer cache already received.
+    next: usize,
+}
+
+impl WorkerRx {
+    /// Returns the next slot index, in the order the dispatchers pushed them.
+    fn pop(&mut self) -> Option<usize> {
+        loop {
+            // Popping takes the latest index first: walk the cache in order instead,
+            // so that flows are not reordered.
+            if self.next == self.consumer.available_len() {
+                self.consumer.cached().clear();
+                self.next = 0;
+                self.consumer.sync();
+                if self.consumer.available_len() == 0 {
+                    return None;
+                }
+            }
+            let idx = self.consumer.cached()[self.next];
+            self.next += 1;
+            if idx != PADDING {
+                return Some(idx);
+            }
+        }
+    }
+}
+
+impl<C: Context, M> Worker<C, M> {
+    /// Waits for the next packet.
+    ///
+    /// Fails with `BrokenPipe` once every dispatcher handle is dropped and the queue is
+    /// empty.
+    pub fn recv(&self) -> Result<(OwnedPacket<C>, M)> {
+        loop {
+            match self.try_recv() {
+                Err(Error::WouldBlock) => self.wait(IDLE_WAIT),
+                res => return res,
+            }
+        }
+    }
+
+    /// Returns the next packet without blocking, or [`Error::WouldBlock`] if the queue
+    /// is empty.
+    pub fn try_recv(&self) -> Result<(OwnedPacket<C>, M)> {
+        let mut rx = self.rx.borrow_mut();
+        if let Some(idx) = rx.pop() {
+            return Ok(self.queue.take(idx));
+        }
+        if self.queue.dispatchers.load(Ordering::Acquire) != 0 {
+            return Err(Error::WouldBlock);
+        }
+        // Everything pushed before the last handle was dropped is visible now.
+        match rx.pop() {
+            Some(idx) => Ok(self.queue.take(idx)),
+            None => Err(disconnected()),
+        }
+    }
+
+    /// Waits at most `timeout` for the next packet, returning [`Error::WouldBlock`] if
+    /// none arrives.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<(OwnedPacket<C>, M)> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.try_recv() {
+                Err(Error::WouldBlock) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        return Err(Error::WouldBlock);
+                    }
+                    self.wait(left.min(IDLE_WAIT));
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Returns the number of packets waiting in the queue.
+    pub fn len(&self) -> usize {
+        self.queue.slots.len() - self.queue.free.len()
+    }
+
+    /// Returns `true` if no packet is waiting in the queue.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Sleeps until a dispatcher flushes packets to this worker, or at most `timeout`.
+    fn wait(&self, timeout: Duration) {
+        if let Ok(mut waiter) = self.queue.waiter.lock() {
+            *waiter = Some(thread::current());
+        }
+        thread::park_timeout(timeout);
+    }
+}
+
+impl<C: Context, M> Drop for Worker<C, M> {
+    fn drop(&mut self) {
+        self.queue.closed.store(true, Ordering::Relaxed);
+        // Hand the buffers of the packets still queued back to their sockets.
+        let rx = self.rx.get_mut();
+        while let Some(idx) = rx.pop() {
+            drop(self.queue.take(idx));
+        }
+    }
+}
+
+fn disconnected() -> Error {
+    std::io::Error::new(
+        std::io::ErrorKind::BrokenPipe,
+        "every dispatcher handle was dropped",
+    )
+    .into()
+}
@@ -0,0 +1,279 @@
+//! Type-erased sockets.
+
+use std::fmt;
+use std::time::Duration;
+
+use super::Result;
+use super::capabilities::Capabilities;
+use super::checksum::TxChecksum;
+use super::device::LinkInfo;
+use super::metadata::{Metadata, MetadataType};
+use super::socket::Socket;
+use super::stats::{Dropped, Stats};
+use super::steer::{FlowRule, FlowSpec};
+
+/// A socket of any backend behind a single type.
+///
+/// [`Socket`] has associated types and generic methods, so it cannot be used as a trait
+/// object. `DynSocket` wraps any socket and returns packets copied out of the backend
+/// buffers, with the metadata converted to [`MetadataType`], so sockets of different
+/// backends can be kept in one collection:
+///
+/// ```ignore
+/// let sockets = vec![DynSocket::new(af_xdp_socket), DynSocket::new(pcap_socket)];
+/// for socket in &sockets {
+///     if let Ok((packet, _meta)) = socket.try_recv() {
+///         println!("{} bytes", packet.len());
+///     }
+/// }
+/// ```
+///
+/// Each received packet costs a copy; use [`recv_into`](DynSocket::recv_into) to reuse a
+/// buffer, or the generic API where zero-copy matters.
+pub struct DynSocket {
+    inner: Box<dyn ErasedSocket>,
+}
+
+impl DynSocket {
+    /// Wraps `socket`.
+    pub fn new<S: Socket + 'static>(socket: S) -> Self {
+        Self {
+            inner: Box::new(socket),
+        }
+    }
+
+    /// Receives a packet. Same semantics as [`Socket::recv`].
+    pub fn recv(&self) -> Result<(Vec<u8>, MetadataType)> {
+        self.inner.recv()
+    }
+
+    /// Receives a packet without blocking. Same semantics as [`Socket::try_recv`].
+    pub fn try_recv(&self) -> Result<(Vec<u8>, MetadataType)> {
+        self.inner.try_recv()
+    }
+
+    /// Receives a packet, waiting at most `timeout`. Same semantics as
+    /// [`Socket::recv_timeout`].
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<(Vec<u8>, MetadataType)> {
+        self.inner.recv_timeout(timeout)
+    }
+
+    /// Receives a packet by copying it into `buf`. Same semantics as
+    /// [`Socket::recv_into`].
+    pub fn recv_into(&self, buf: &mut [u8]) -> Result<(usize, MetadataType)> {
+        self.inner.recv_into(buf)
+    }
+
+    /// Sends a packet.
+    pub fn send(&self, packet: &[u8]) -> Result<()> {
+        self.inner.send(packet)
+    }
+
+    /// Flushes any pending transmissions.
+    pub fn flush(&self) {
+        self.inner.flush();
+    }
+
+    /// Sends a packet tagged with `cookie`. Same semantics as
+    /// [`Socket::send_with_cookie`].
+    pub fn send_with_cookie(&self, packet: &[u8], cookie: u64) -> Result<()> {
+        self.inner.send_with_cookie(packet, cookie)
+    }
+
+    /// Sends a packet with the checksums in `csum` filled in. Same semantics as
+    /// [`Socket::send_with_checksum`].
+    pub fn send_with_checksum(&self, packet: &[u8], csum: TxChecksum) -> Result<()> {
+        self.inner.send_with_checksum(packet, csum)
+    }
+
+    /// Sends an oversized TCP or UDP packet as frames of at most `mss` payload bytes.
+    /// Same semantics as [`Socket::send_gso`].
+    pub fn send_gso(&self, packet: &[u8], mss: usize) -> Result<usize> {
+        self.inner.send_gso(packet, mss)
+    }
+
+    /// Collects the cookies of the tagged packets that left the NIC. Same semantics as
+    /// [`Socket::tx_completions`].
+    pub fn tx_completions(&self, out: &mut Vec<u64>) -> usize {
+        self.inner.tx_completions(out)
+    }
+
+    /// Drains pending transmissions and releases the socket. Same semantics as
+    /// [`Socket::close`].
+    pub fn close(self) -> Result<()> {
+        self.inner.close()
+    }
+
+    /// Installs a BPF filter on received packets. Same semantics as
+    /// [`Socket::set_filter`].
+    pub fn set_filter(&self, expr: &str) -> Result<()> {
+        self.inner.set_filter(expr)
+    }
+
+    /// Enables or disables promiscuous mode on the underlying interface.
+    pub fn set_promiscuous(&self, enable: bool) -> Result<()> {
+        self.inner.set_promiscuous(enable)
+    }
+
+    /// Receives the frames sent to the multicast MAC address `mac`.
+    pub fn join_multicast(&self, mac: [u8; 6]) -> Result<()> {
+        self.inner.join_multicast(mac)
+    }
+
+    /// Stops receiving the frames sent to the multicast MAC address `mac`.
+    pub fn leave_multicast(&self, mac: [u8; 6]) -> Result<()> {
+        self.inner.leave_multicast(mac)
+    }
+
+    /// Returns the link parameters of the underlying interface.
+    pub fn link_info(&self) -> Result<LinkInfo> {
+        self.inner.link_info()
+    }
+
+    /// Steers the flows matching `spec` to the RX queue `queue`. Same semantics as
+    /// [`Socket::steer`].
+    pub fn steer(&self, spec: FlowSpec, queue: usize) -> Result<FlowRule> {
+        self.inner.steer(spec, queue)
+    }
+
+    /// Removes a rule installed by [`steer`](DynSocket::steer).
+    pub fn unsteer(&self, rule: FlowRule) -> Result<()> {
+        self.inner.unsteer(rule)
+    }
+
+    /// Returns the socket's packet counters.
+    pub fn stats(&self) -> Result<Stats> {
+        self.inner.stats()
+    }
+
+    /// Returns the packets lost by nethuns itself.
+    pub fn dropped(&self) -> Dropped {
+        self.inner.dropped()
+    }
+
+    /// Returns the features supported by the socket.
+    pub fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+}
+
+impl fmt::Debug for DynSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynSocket").finish_non_exhaustive()
+    }
+}
+
+/// Object-safe subset of [`Socket`], implemented for every socket.
+trait ErasedSocket: Send {
+    fn recv(&self) -> Result<(Vec<u8>, MetadataType)>;
+    fn try_recv(&self) -> Result<(Vec<u8>, MetadataType)>;
+    fn recv_timeout(&self, timeout: Duration) -> Result<(Vec<u8>, MetadataType)>;
+    fn recv_into(&self, buf: &mut [u8]) -> Result<(usize, MetadataType)>;
+    fn send(&self, packet: &[u8]) -> Result<()>;
+    fn flush(&self);
+    fn send_with_cookie(&self, packet: &[u8], cookie: u64) -> Result<()>;
+    fn send_with_checksum(&self, packet: &[u8], csum: TxChecksum) -> Result<()>;
+    fn send_gso(&self, packet: &[u8], mss: usize) -> Result<usize>;
+    fn tx_completions(&self, out: &mut Vec<u64>) -> usize;
+    fn close(self: Box<Self>) -> Result<()>;
+    fn set_filter(&self, expr: &str) -> Result<()>;
+    fn set_promiscuous(&self, enable: bool) -> Result<()>;
+    fn join_multicast(&self, mac: [u8; 6]) -> Result<()>;
+    fn leave_multicast(&self, mac: [u8; 6]) -> Result<()>;
+    fn link_info(&self) -> Result<LinkInfo>;
+    fn steer(&self, spec: FlowSpec, queue: usize) -> Result<FlowRule>;
+    fn unsteer(&self, rule: FlowRule) -> Result<()>;
+    fn stats(&self) -> Result<Stats>;
+    fn dropped(&self) -> Dropped;
+    fn capabilities(&self) -> Capabilities;
+}
+
+impl<S: Socket> ErasedSocket for S {
+    fn recv(&self) -> Result<(Vec<u8>, MetadataType)> {
+        let (payload, meta) = Socket::recv(self)?;
+        Ok((payload.to_vec(), meta.into_enum()))
+    }
+
+    fn try_recv(&self) -> Result<(Vec<u8>, MetadataType)> {
+        let (payload, meta) = Socket::try_recv(self)?;
+        Ok((payload.to_vec(), meta.into_enum()))
+    }
+
+    fn recv_timeout(&self, timeout: Duration) -> Result<(Vec<u8>, MetadataType)> {
+        let (payload, meta) = Socket::recv_timeout(self, timeout)?;
+        Ok((payload.to_vec(), meta.into_enum()))
+    }
+
+    fn recv_into(&self, buf: &mut [u8]) -> Result<(usize, MetadataType)> {
+        let (len, meta) = Socket::recv_into(self, buf)?;
+        Ok((len, meta.into_enum()))
+    }
+
+    fn send(&self, packet: &[u8]) -> Result<()> {
+        Socket::send(self, packet)
+    }
+
+    fn flush(&self) {
+        Socket::flush(self);
+    }
+
+    fn send_with_cookie(&self, packet: &[u8], cookie: u64) -> Result<()> {
+        Socket::send_with_cookie(self, packet, cookie)
+    }
+
+    fn send_with_checksum(&self, packet: &[u8], csum: TxChecksum) -> Result<()> {
+        Socket::send_with_checksum(self, packet, csum)
+    }
+
+    fn send_gso(&self, packet: &[u8], mss: usize) -> Result<usize> {
+        Socket::send_gso(self, packet, mss)
+    }
+
+    fn tx_completions(&self, out: &mut Vec<u64>) -> usize {
+        Socket::tx_completions(self, out)
+    }
+
+    fn close(self: Box<Self>) -> Result<()> {
+        Socket::close(*self)
+    }
+
+    fn set_filter(&self, expr: &str) -> Result<()> {
+        Socket::set_filter(self, expr)
+    }
+
+    fn set_promiscuous(&self, enable: bool) -> Result<()> {
+        Socket::set_promiscuous(self, enable)
+    }
+
+    fn join_multicast(&self, mac: [u8; 6]) -> Result<()> {
+        Socket::join_multicast(self, mac)
+    }
+
+    fn leave_multicast(&self, mac: [u8; 6]) -> Result<()> {
+        Socket::leave_multicast(self, mac)
+    }
+
+    fn link_info(&self) -> Result<LinkInfo> {
+        Socket::link_info(self)
+    }
+
+    fn steer(&self, spec: FlowSpec, queue: usize) -> Result<FlowRule> {
+        Socket::steer(self, spec, queue)
+    }
+
+    fn unsteer(&self, rule: FlowRule) -> Result<()> {
+        Socket::unsteer(self, rule)
+    }
+
+    fn stats(&self) -> Result<Stats> {
+        Socket::stats(self)
+    }
+
+    fn dropped(&self) -> Dropped {
+        Socket::dropped(self)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Socket::capabilities(self)
+    }
+}
@@ -0,0 +1,181 @@
+//! BPF packet filters.
+//!
+//! Besides the [`Filter`] the sockets run in userspace, [`compile`] turns an expression
+//! into a raw classic BPF program, to attach with `SO_ATTACH_FILTER`, load into an XDP
+//! or NIC filter, or interpret in userspace.
+//!
+//! Expressions are compiled by libpcap, through the `bpf` feature. Every backend
+//! feature enables it, so filters work on builds without the pcap backend too.
+
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+use std::cell::RefCell;
+use std::fmt;
+
+use super::Result;
+
+/// A compiled BPF filter expression, in `pcap-filter(7)` syntax.
+///
+/// Backends that cannot filter in the kernel or in the NIC run it in userspace on every
+/// received frame, before the packet is handed to the application.
+///
+/// Compilation relies on libpcap, so it requires the `bpf` feature, which every
+/// backend feature enables.
+pub struct Filter {
+    expr: String,
+    #[cfg(feature = "bpf")]
+    program: pcap::BpfProgram,
+}
+
+impl Filter {
+    /// Compiles `expr` for Ethernet frames.
+    pub fn new(expr: &str) -> Result<Self> {
+        Self::with_linktype(expr, LINKTYPE_ETHERNET)
+    }
+
+    /// Compiles `expr` for frames of link type `linktype` (a `DLT_*` value).
+    #[cfg(feature = "bpf")]
+    pub fn with_linktype(expr: &str, linktype: i32) -> Result<Self> {
+        let dead = pcap::Capture::dead(pcap::Linktype(linktype))?;
+        let program = dead.compile(expr, true)?;
+        Ok(Self {
+            expr: expr.to_owned(),
+            program,
+        })
+    }
+
+    /// Compiles `expr` for frames of link type `linktype` (a `DLT_*` value).
+    #[cfg(not(feature = "bpf"))]
+    pub fn with_linktype(expr: &str, linktype: i32) -> Result<Self> {
+        let _ = (expr, linktype);
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "BPF filters require the `bpf` feature",
+        )
+        .into())
+    }
+
+    /// Returns the source expression.
+    pub fn expr(&self) -> &str {
+        &self.expr
+    }
+
+    /// Returns whether `frame` passes the filter.
+    pub fn matches(&self, frame: &[u8]) -> bool {
+        #[cfg(feature = "bpf")]
+        return self.program.filter(frame);
+        #[cfg(not(feature = "bpf"))]
+        {
+            let _ = frame;
+            true
+        }
+    }
+}
+
+impl fmt::Debug for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Filter").field(&self.expr).finish()
+    }
+}
+
+/// Link type of Ethernet frames (`DLT_EN10MB`).
+pub const LINKTYPE_ETHERNET: i32 = 1;
+
+/// A classic BPF instruction, laid out as libpcap's `struct bpf_insn` and the kernel's
+/// `struct sock_filter`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct BpfInsn {
+    /// Opcode.
+    pub code: u16,
+    /// Jump offset if the condition is true.
+    pub jt: u8,
+    /// Jump offset if the condition is false.
+    pub jf: u8,
+    /// Operand.
+    pub k: u32,
+}
+
+/// Compiles `expr`, in `pcap-filter(7)` syntax, into an optimized classic BPF program
+/// for frames of link type `linktype` (a `DLT_*` value such as [`LINKTYPE_ETHERNET`]).
+///
+/// No capture is opened: libpcap compiles against a dead handle with a 64 KiB snaplen.
+#[cfg(feature = "bpf")]
+pub fn compile(expr: &str, linktype: i32) -> Result<Vec<BpfInsn>> {
+    let dead = pcap::Capture::dead(pcap::Linktype(linktype))?;
+    let program = dead.compile(expr, true)?;
+    let insns = program.get_instructions();
+    // SAFETY: `BpfInstruction` is a transparent wrapper of `struct bpf_insn`, which
+    // `BpfInsn` mirrors.
+    let insns =
+        unsafe { std::slice::from_raw_parts(insns.as_ptr() as *const BpfInsn, insns.len()) };
+    Ok(insns.to_vec())
+}
+
+/// Compiles `expr` into a classic BPF program for frames of link type `linktype`.
+#[cfg(not(feature = "bpf"))]
+pub fn compile(expr: &str, linktype: i32) -> Result<Vec<BpfInsn>> {
+    let _ = (expr, linktype);
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "BPF filters require the `bpf` feature",
+    )
+    .into())
+}
+
+/// The filter a socket runs in userspace on the frames it receives, replaceable at
+/// runtime.
+///
+/// The frames have already been taken off the ring: dropped ones are released right
+/// away, but cost as much to receive as accepted ones.
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+#[derive(Debug, Default)]
+pub(crate) struct UserspaceFilter(RefCell<Option<Filter>>);
+
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+impl UserspaceFilter {
+    /// Installs `expr`, replacing the current filter; an empty expression removes it.
+    pub(crate) fn set(&self, expr: &str) -> Result<()> {
+        self.set_with_linktype(expr, LINKTYPE_ETHERNET)
+    }
+
+    /// Installs `expr`, compiled for frames of link type `linktype`.
+    pub(crate) fn set_with_linktype(&self, expr: &str, linktype: i32) -> Result<()> {
+        let filter = match expr.trim() {
+            "" => None,
+            expr => Some(Filter::with_linktype(expr, linktype)?),
+        };
+        *self.0.borrow_mut() = filter;
+        Ok(())
+    }
+
+    /// Removes the filter.
+    pub(crate) fn clear(&self) {
+        *self.0.borrow_mut() = None;
+    }
+
+    /// Returns `true` if a filter is installed.
+    pub(crate) fn is_set(&self) -> bool {
+        self.0.borrow().is_some()
+    }
+
+    /// Returns whether `frame` passes the installed filter, if any.
+    #[inline]
+    pub(crate) fn accepts(&self, frame: &[u8]) -> bool {
+        self.0.borrow().as_ref().is_none_or(|f| f.matches(frame))
+    }
+}
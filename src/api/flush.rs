@@ -0,0 +1,133 @@
+//! Automatic flushing of TX rings.
+
+#[cfg(any(test, feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+use std::cell::Cell;
+#[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+use std::io;
+use std::time::Duration;
+#[cfg(any(test, feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+use std::time::Instant;
+
+#[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+use super::Result;
+
+/// How long [`Socket::close`](super::Socket::close) waits for the TX ring to drain.
+#[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// When a socket pushes the packets queued with `send` or `tx_reserve` to the NIC.
+///
+/// The deadline of [`Auto`](FlushPolicy::Auto) is only checked when a packet is queued:
+/// the last packets of a burst stay in the ring until the next send or an explicit
+/// `flush`. The pcap backend writes every packet immediately and ignores the policy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum FlushPolicy {
+    /// Packets are only pushed by `flush`, `send_batch`, or when the ring is full.
+    #[default]
+    Manual,
+    /// Flush after `packets` queued packets, or once `interval` has elapsed since the
+    /// first packet queued after the last flush, whichever comes first.
+    Auto { packets: usize, interval: Duration },
+}
+
+/// Per-socket bookkeeping of a [`FlushPolicy`].
+#[cfg(any(test, feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+#[derive(Debug)]
+pub(crate) struct FlushState {
+    policy: FlushPolicy,
+    pending: Cell<usize>,
+    since: Cell<Option<Instant>>,
+}
+
+#[cfg(any(test, feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+impl FlushState {
+    pub(crate) fn new(policy: FlushPolicy) -> Self {
+        Self {
+            policy,
+            pending: Cell::new(0),
+            since: Cell::new(None),
+        }
+    }
+
+    /// Records a queued packet; returns whether the ring must be flushed now.
+    #[inline]
+    pub(crate) fn queued(&self) -> bool {
+        let FlushPolicy::Auto { packets, interval } = self.policy else {
+            return false;
+        };
+        let pending = self.pending.get() + 1;
+        self.pending.set(pending);
+        if pending >= packets {
+            return true;
+        }
+        match self.since.get() {
+            Some(since) => since.elapsed() >= interval,
+            None => {
+                self.since.set(Some(Instant::now()));
+                interval.is_zero()
+            }
+        }
+    }
+
+    /// Resets the counters after the ring has been flushed.
+    #[inline]
+    pub(crate) fn flushed(&self) {
+        self.pending.set(0);
+        self.since.set(None);
+    }
+}
+
+/// Calls `flush_pending` until it reports no pending transmission, for at most
+/// [`CLOSE_TIMEOUT`].
+#[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+pub(crate) fn drain_tx(mut flush_pending: impl FnMut() -> Result<bool>) -> Result<()> {
+    let deadline = Instant::now() + CLOSE_TIMEOUT;
+    while flush_pending()? {
+        if Instant::now() >= deadline {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "pending transmissions were not drained",
+            )
+            .into());
+        }
+        std::thread::sleep(Duration::from_micros(100));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flush_after_packet_count() {
+        let state = FlushState::new(FlushPolicy::Auto {
+            packets: 3,
+            interval: Duration::from_secs(3600),
+        });
+        assert!(!state.queued());
+        assert!(!state.queued());
+        assert!(state.queued());
+        state.flushed();
+        assert!(!state.queued());
+
+        let manual = FlushState::new(FlushPolicy::Manual);
+        assert!((0..100).all(|_| !manual.queued()));
+    }
+
+    #[test]
+    fn flush_after_interval() {
+        let state = FlushState::new(FlushPolicy::Auto {
+            packets: usize::MAX,
+            interval: Duration::from_millis(1),
+        });
+        assert!(!state.queued());
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(state.queued());
+    }
+}
@@ -0,0 +1,37 @@
+//! Forwarding of received packets.
+
+use super::Result;
+use super::context::Context;
+use super::socket::Socket;
+use super::token::Payload;
+
+/// Sends a received packet out of `tx`, without copying it when possible.
+///
+/// Netmap ports sharing a memory region, and the two halves of an AF_XDP socket (which
+/// share its UMEM), hand the packet buffer over to the TX ring and take a free TX
+/// buffer in exchange for the RX pool. Other combinations copy the packet like
+/// [`Socket::send`]. Either way the packet is queued as by `send` and goes out on the
+/// next flush.
+///
+/// Split TX halves offer the same through
+/// [`TxSocket::send_payload`](super::TxSocket::send_payload).
+///
+/// ```ignore
+/// let (packet, _meta) = rx.recv()?;
+/// api::forward(packet, &tx)?;
+/// tx.flush();
+/// ```
+#[inline]
+pub fn forward<C: Context, S: Socket>(packet: Payload<'_, C>, tx: &S) -> Result<()> {
+    tx.send_payload(packet)
+}
+
+/// Returns the context of `packet` if it is a `Ctx`, i.e. if the packet was received by
+/// a socket of the calling backend.
+#[cfg(any(feature = "af-xdp", feature = "netmap"))]
+#[inline]
+pub(crate) fn same_backend<'ctx, Ctx: Context, C: Context>(
+    packet: &Payload<'ctx, C>,
+) -> Option<&'ctx Ctx> {
+    (packet.context() as &dyn std::any::Any).downcast_ref::<Ctx>()
+}
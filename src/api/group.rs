@@ -0,0 +1,87 @@
+//! Sets of sockets spreading the traffic of one interface.
+
+use super::Result;
+use super::socket::Socket;
+
+/// One socket per hardware queue of an interface, obtained through
+/// [`Socket::create_group`].
+///
+/// The NIC (or the kernel, on backends without queues) spreads the traffic across the
+/// members, so each one can be moved to its own worker thread:
+///
+/// ```ignore
+/// let group = af_xdp::Sock::create_group("eth0", flags)?;
+/// for (queue, socket) in group.into_iter().enumerate() {
+///     thread::spawn(move || worker(queue, socket));
+/// }
+/// ```
+///
+/// [`spawn_per_queue`](super::spawn_per_queue) does the same and pins each worker to a
+/// CPU.
+///
+/// The DPDK backend has no groups: each of its sockets owns a port configured with one
+/// RX queue.
+#[derive(Debug)]
+pub struct SocketGroup<S> {
+    sockets: Vec<S>,
+}
+
+impl<S: Socket> SocketGroup<S> {
+    /// Opens one socket on each of the first `queues` queues of `portspec`.
+    pub fn open(portspec: &str, queues: usize, flags: S::Flags) -> Result<Self> {
+        let sockets = (0..queues)
+            .map(|queue| S::create(portspec, Some(queue), flags.clone()))
+            .collect::<Result<_>>()?;
+        Ok(Self { sockets })
+    }
+}
+
+impl<S> SocketGroup<S> {
+    #[cfg(feature = "pcap")]
+    pub(crate) fn from_sockets(sockets: Vec<S>) -> Self {
+        Self { sockets }
+    }
+
+    /// Returns the number of sockets in the group.
+    pub fn len(&self) -> usize {
+        self.sockets.len()
+    }
+
+    /// Returns `true` if the group has no sockets.
+    pub fn is_empty(&self) -> bool {
+        self.sockets.is_empty()
+    }
+
+    /// Returns the socket bound to the `index`-th queue.
+    pub fn get(&self, index: usize) -> Option<&S> {
+        self.sockets.get(index)
+    }
+
+    /// Iterates over the sockets in queue order.
+    pub fn iter(&self) -> std::slice::Iter<'_, S> {
+        self.sockets.iter()
+    }
+
+    /// Returns the sockets in queue order.
+    pub fn into_vec(self) -> Vec<S> {
+        self.sockets
+    }
+}
+
+impl<S> IntoIterator for SocketGroup<S> {
+    type Item = S;
+    type IntoIter = std::vec::IntoIter<S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.sockets.into_iter()
+    }
+}
+
+impl<'a, S> IntoIterator for &'a SocketGroup<S> {
+    type Item = &'a S;
+    type IntoIter = std::slice::Iter<'a, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.sockets.iter()
+    }
+}
@@ -0,0 +1,156 @@
+//! Software segmentation of oversized TCP and UDP packets.
+
+use std::io;
+
+use super::Result;
+use super::checksum::{Headers, IPPROTO_TCP, IPPROTO_UDP, TxChecksum};
+use super::tx_slot::TxSlot;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_PSH: u8 = 0x08;
+
+/// Splits `packet` into frames carrying at most `mss` bytes of L4 payload each, built
+/// in the slots returned by `reserve`, and returns how many were committed.
+///
+/// Every frame repeats the headers of `packet`, with the lengths, IPv4 identification,
+/// TCP sequence number and flags of its segment; checksums are requested with
+/// [`TxSlot::set_checksum`], so capable NICs compute them.
+pub(crate) fn segment<S, F>(mut reserve: F, packet: &[u8], mss: usize) -> Result<usize>
+where
+    S: TxSlot,
+    F: FnMut(usize) -> Result<S>,
+{
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidInput, msg.to_string());
+    if mss == 0 {
+        return Err(invalid("the MSS must not be zero").into());
+    }
+    let headers = Headers::parse(packet).ok_or_else(|| invalid("not an IP packet"))?;
+    let l4_header_len = headers
+        .l4_header_len(packet)
+        .ok_or_else(|| invalid("not a TCP or UDP packet"))?;
+    let l3 = headers.l2_len;
+    let l4 = headers.l4_offset();
+    let header_len = l4 + l4_header_len;
+    let payload = &packet[header_len..l4 + headers.l4_len];
+
+    let ip_id = u16::from_be_bytes([packet[l3 + 4], packet[l3 + 5]]);
+    let seq = u32::from_be_bytes(packet[l4 + 4..l4 + 8].try_into().unwrap());
+    let segments = payload.len().div_ceil(mss).max(1);
+    for i in 0..segments {
+        let chunk = &payload[(i * mss).min(payload.len())..((i + 1) * mss).min(payload.len())];
+        let len = header_len + chunk.len();
+        let mut slot = reserve(len)?;
+        let frame = &mut slot[..len];
+        frame[..header_len].copy_from_slice(&packet[..header_len]);
+        frame[header_len..].copy_from_slice(chunk);
+
+        let l4_len = (l4_header_len + chunk.len()) as u16;
+        if headers.ipv6 {
+            frame[l3 + 4..l3 + 6].copy_from_slice(&l4_len.to_be_bytes());
+        } else {
+            let total = headers.l3_len as u16 + l4_len;
+            frame[l3 + 2..l3 + 4].copy_from_slice(&total.to_be_bytes());
+            let id = ip_id.wrapping_add(i as u16);
+            frame[l3 + 4..l3 + 6].copy_from_slice(&id.to_be_bytes());
+        }
+        match headers.proto {
+            IPPROTO_TCP => {
+                let seq = seq.wrapping_add((i * mss) as u32);
+                frame[l4 + 4..l4 + 8].copy_from_slice(&seq.to_be_bytes());
+                if i + 1 < segments {
+                    // Only the last segment finishes or pushes the stream.
+                    frame[l4 + 13] &= !(TCP_FIN | TCP_PSH);
+                }
+            }
+            IPPROTO_UDP => frame[l4 + 4..l4 + 6].copy_from_slice(&l4_len.to_be_bytes()),
+            _ => unreachable!("l4_header_len only accepts TCP and UDP"),
+        }
+        slot.set_checksum(TxChecksum {
+            ip: !headers.ipv6,
+            l4: true,
+        });
+        slot.commit(len)?;
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::ops::{Deref, DerefMut};
+
+    use super::*;
+
+    /// A slot that records the committed frames.
+    struct VecSlot<'a> {
+        buf: Vec<u8>,
+        sent: &'a RefCell<Vec<Vec<u8>>>,
+    }
+
+    impl Deref for VecSlot<'_> {
+        type Target = [u8];
+
+        fn deref(&self) -> &[u8] {
+            &self.buf
+        }
+    }
+
+    impl DerefMut for VecSlot<'_> {
+        fn deref_mut(&mut self) -> &mut [u8] {
+            &mut self.buf
+        }
+    }
+
+    impl TxSlot for VecSlot<'_> {
+        fn commit(self, len: usize) -> Result<()> {
+            self.sent.borrow_mut().push(self.buf[..len].to_vec());
+            Ok(())
+        }
+
+        fn set_checksum(&mut self, csum: TxChecksum) {
+            assert_eq!(csum, TxChecksum::ALL);
+        }
+    }
+
+    #[test]
+    fn tcp_segments() {
+        // Ethernet + IPv4 (id 7) + TCP (seq 1000, FIN|PSH|ACK) + 2500 payload bytes.
+        let payload: Vec<u8> = (0..2500u32).map(|i| i as u8).collect();
+        let mut packet = vec![0u8; 12];
+        packet.extend_from_slice(&[0x08, 0x00]);
+        let total = (20 + 20 + payload.len()) as u16;
+        packet.extend_from_slice(&[0x45, 0]);
+        packet.extend_from_slice(&total.to_be_bytes());
+        packet.extend_from_slice(&[0, 7, 0x40, 0, 64, 6, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2]);
+        let mut tcp = [0u8; 20];
+        tcp[4..8].copy_from_slice(&1000u32.to_be_bytes());
+        tcp[12] = 5 << 4;
+        tcp[13] = 0x10 | TCP_PSH | TCP_FIN;
+        packet.extend_from_slice(&tcp);
+        packet.extend_from_slice(&payload);
+
+        let sent = RefCell::new(Vec::new());
+        let reserve = |len| {
+            Ok(VecSlot {
+                buf: vec![0; len],
+                sent: &sent,
+            })
+        };
+        assert_eq!(segment(reserve, &packet, 1000).unwrap(), 3);
+
+        let sent = sent.into_inner();
+        let lens: Vec<_> = sent.iter().map(Vec::len).collect();
+        assert_eq!(lens, [1054, 1054, 554]);
+        for (i, frame) in sent.iter().enumerate() {
+            let total = u16::from_be_bytes([frame[16], frame[17]]) as usize;
+            assert_eq!(total, frame.len() - 14);
+            assert_eq!(frame[19], 7 + i as u8);
+            let seq = u32::from_be_bytes(frame[38..42].try_into().unwrap());
+            assert_eq!(seq, 1000 + 1000 * i as u32);
+            let last = i == 2;
+            assert_eq!(frame[47] & (TCP_PSH | TCP_FIN) != 0, last);
+        }
+        let joined: Vec<u8> = sent.iter().flat_map(|f| f[54..].to_vec()).collect();
+        assert_eq!(joined, payload);
+    }
+}
@@ -0,0 +1,47 @@
+//! Interface configuration, for the setups AF_XDP and netmap expect before binding.
+//!
+//! These are thin wrappers around the `SIOCSIF*` ioctls, equivalent to
+//! `ip link set <ifname> up|down|mtu|address`. They all require `CAP_NET_ADMIN`.
+//!
+//! ```ignore
+//! use nethuns_rs::api::ifctl;
+//!
+//! ifctl::set_mtu("eth0", 3000)?;
+//! ifctl::set_up("eth0", true)?;
+//! ```
+
+use super::Result;
+use super::netdev::{control_socket, ifreq, ioctl, set_flag};
+
+/// Brings `ifname` up or down (`IFF_UP`).
+pub fn set_up(ifname: &str, up: bool) -> Result<()> {
+    Ok(set_flag(ifname, libc::IFF_UP, up)?)
+}
+
+/// Sets the MTU of `ifname`.
+///
+/// AF_XDP in native mode typically requires the MTU to fit in a single page, netmap
+/// in a single buffer.
+pub fn set_mtu(ifname: &str, mtu: u32) -> Result<()> {
+    let sock = control_socket()?;
+    let mut ifr = ifreq(ifname)?;
+    ifr.ifr_ifru.ifru_mtu = mtu.min(i32::MAX as u32) as libc::c_int;
+    Ok(ioctl(&sock, libc::SIOCSIFMTU, &mut ifr)?)
+}
+
+/// Sets the Ethernet address of `ifname`.
+///
+/// Most drivers only accept a new address while the interface is down.
+pub fn set_mac(ifname: &str, mac: [u8; 6]) -> Result<()> {
+    let sock = control_socket()?;
+    let mut ifr = ifreq(ifname)?;
+    // SAFETY: the hwaddr member is a plain sockaddr.
+    unsafe {
+        let addr = &mut ifr.ifr_ifru.ifru_hwaddr;
+        addr.sa_family = libc::ARPHRD_ETHER;
+        for (dst, &src) in addr.sa_data.iter_mut().zip(&mac) {
+            *dst = src as libc::c_char;
+        }
+    }
+    Ok(ioctl(&sock, libc::SIOCSIFHWADDR, &mut ifr)?)
+}
@@ -0,0 +1,86 @@
+//! Link-layer header types of captured frames.
+
+/// Link-layer header type of a capture (`LINKTYPE_*` / `DLT_*`), telling how to parse
+/// the frames.
+///
+/// Every backend captures Ethernet frames, except the pcap one, which may capture from
+/// wireless interfaces in monitor mode, the `any` pseudo-device, tunnels or capture
+/// files of any kind; see [`Metadata::linktype`](super::Metadata::linktype).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LinkType {
+    /// BSD loopback: a 4-byte address family in host byte order (`DLT_NULL`).
+    Null,
+    /// Ethernet (`DLT_EN10MB`).
+    #[default]
+    Ethernet,
+    /// PPP, starting with the protocol field (`DLT_PPP`).
+    Ppp,
+    /// Raw IPv4 or IPv6 packets (`LINKTYPE_RAW`, also `DLT_RAW`).
+    Raw,
+    /// 802.11 frames without radio information (`DLT_IEEE802_11`).
+    Ieee80211,
+    /// Linux cooked capture, as on the `any` device (`DLT_LINUX_SLL`).
+    LinuxSll,
+    /// 802.11 frames behind a radiotap header (`DLT_IEEE802_11_RADIO`).
+    Radiotap,
+    /// Linux cooked capture, version 2 (`DLT_LINUX_SLL2`).
+    LinuxSll2,
+    /// Any other link type.
+    Other(u16),
+}
+
+impl LinkType {
+    /// Returns the `LINKTYPE_*` number, as written in capture files.
+    pub fn value(self) -> u16 {
+        match self {
+            Self::Null => 0,
+            Self::Ethernet => 1,
+            Self::Ppp => 9,
+            Self::Raw => 101,
+            Self::Ieee80211 => 105,
+            Self::LinuxSll => 113,
+            Self::Radiotap => 127,
+            Self::LinuxSll2 => 276,
+            Self::Other(value) => value,
+        }
+    }
+}
+
+/// Maps a `LINKTYPE_*` or `DLT_*` number; `DLT_RAW` (12 or 14, depending on the
+/// platform) is [`Raw`](LinkType::Raw) too.
+impl From<u16> for LinkType {
+    fn from(value: u16) -> Self {
+        match value {
+            0 => Self::Null,
+            1 => Self::Ethernet,
+            9 => Self::Ppp,
+            12 | 14 | 101 => Self::Raw,
+            105 => Self::Ieee80211,
+            113 => Self::LinuxSll,
+            127 => Self::Radiotap,
+            276 => Self::LinuxSll2,
+            value => Self::Other(value),
+        }
+    }
+}
+
+impl From<LinkType> for u16 {
+    fn from(linktype: LinkType) -> Self {
+        linktype.value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        for value in [0, 1, 9, 101, 105, 113, 127, 276, 147] {
+            assert_eq!(LinkType::from(value).value(), value);
+        }
+        assert_eq!(LinkType::from(12), LinkType::Raw);
+        assert_eq!(LinkType::from(147), LinkType::Other(147));
+    }
+}
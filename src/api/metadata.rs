@@ -1,5 +1,7 @@
 //! Metadata types for different backends.
 
+use super::linktype::LinkType;
+use super::timestamp::{Timestamp, TimestampKind};
 #[cfg(feature = "af-xdp")]
 use crate::af_xdp;
 #[cfg(feature = "dpdk")]
@@ -11,6 +13,231 @@ use crate::netmap;
 pub trait Metadata: Send {
     /// Converts backend-specific metadata into the unified enum type.
     fn into_enum(self) -> MetadataType;
+
+    /// Capture timestamp, in the clock selected with
+    /// [`TimestampSource`](super::TimestampSource).
+    ///
+    /// Returns `None` when the packet was not timestamped.
+    fn timestamp(&self) -> Option<Timestamp> {
+        None
+    }
+
+    /// Whether [`timestamp`](Metadata::timestamp) was taken in software or by the NIC,
+    /// or `None` if there is no timestamp.
+    ///
+    /// Hardware timestamps come from the NIC clock, which is only comparable with the
+    /// system clock when it is synchronized to it (e.g. with `phc2sys`).
+    fn timestamp_kind(&self) -> Option<TimestampKind> {
+        self.timestamp().map(|_| TimestampKind::Software)
+    }
+
+    /// Number of bytes actually captured and available in the payload.
+    fn caplen(&self) -> u32;
+
+    /// Length of the packet on the wire, which may exceed [`caplen`](Metadata::caplen).
+    fn wirelen(&self) -> u32 {
+        self.caplen()
+    }
+
+    /// Outermost 802.1Q/802.1ad tag of the packet, if any.
+    ///
+    /// When the socket strips VLAN tags (see [`Capabilities::vlan_strip`]) this is the
+    /// tag removed from the payload, otherwise the one still in front of the EtherType.
+    ///
+    /// [`Capabilities::vlan_strip`]: super::Capabilities::vlan_strip
+    fn vlan(&self) -> Option<VlanTag> {
+        None
+    }
+
+    /// RSS hash computed by the NIC.
+    ///
+    /// Returns `None` when the backend or device does not report one; use
+    /// [`flow_hash`](super::flow_hash) on the payload as a software fallback.
+    fn rss_hash(&self) -> Option<u32> {
+        None
+    }
+
+    /// Index of the RX queue (or netmap ring) the packet arrived on, if known.
+    fn queue(&self) -> Option<u32> {
+        None
+    }
+
+    /// Whether the payload ends with the 4-byte Ethernet FCS, see
+    /// [`SocketOptions::fcs`](super::SocketOptions::fcs).
+    ///
+    /// When it does, [`wirelen`](Metadata::wirelen) counts it too.
+    fn has_fcs(&self) -> bool {
+        false
+    }
+
+    /// Link-layer header type of the payload.
+    ///
+    /// Always Ethernet, except on pcap sockets, where it depends on the device or the
+    /// capture file, see `pcap::Sock::datalink`.
+    fn linktype(&self) -> LinkType {
+        LinkType::Ethernet
+    }
+}
+
+/// An 802.1Q (or 802.1ad) VLAN tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VlanTag {
+    /// Tag protocol identifier (`0x8100` or `0x88a8`).
+    pub tpid: u16,
+    /// Tag control information: PCP, DEI and VLAN ID.
+    pub tci: u16,
+}
+
+/// Offset of the VLAN tag in an Ethernet frame, right after the MAC addresses.
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+const TAG_OFFSET: usize = 12;
+/// Size of a VLAN tag.
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+const TAG_LEN: usize = 4;
+
+impl VlanTag {
+    /// 802.1Q customer tag TPID.
+    pub const TPID_8021Q: u16 = 0x8100;
+    /// 802.1ad service tag TPID.
+    pub const TPID_8021AD: u16 = 0x88a8;
+
+    /// Reads the outermost VLAN tag from an Ethernet frame.
+    ///
+    /// Returns `None` if the frame is untagged or too short.
+    #[inline]
+    pub fn parse(frame: &[u8]) -> Option<Self> {
+        let tpid = u16::from_be_bytes([*frame.get(12)?, *frame.get(13)?]);
+        if tpid != Self::TPID_8021Q && tpid != Self::TPID_8021AD {
+            return None;
+        }
+        let tci = u16::from_be_bytes([*frame.get(14)?, *frame.get(15)?]);
+        Some(Self { tpid, tci })
+    }
+
+    /// An 802.1Q tag with the given VLAN identifier and priority 0.
+    pub fn new(vid: u16) -> Self {
+        Self {
+            tpid: Self::TPID_8021Q,
+            tci: vid & 0x0fff,
+        }
+    }
+
+    /// Removes the outermost tag from `frame`, moving the rest of the frame back over
+    /// it. Returns the tag and the new length of the frame.
+    #[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+    pub(crate) fn strip(frame: &mut [u8]) -> Option<(Self, usize)> {
+        let tag = Self::parse(frame)?;
+        frame.copy_within(TAG_OFFSET + TAG_LEN.., TAG_OFFSET);
+        Some((tag, frame.len() - TAG_LEN))
+    }
+
+    /// Inserts the tag after the MAC addresses of the `len`-byte frame at the start of
+    /// `buf`. Returns the new length, or `None` if `buf` has no room for the tag.
+    #[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+    pub(crate) fn insert(self, buf: &mut [u8], len: usize) -> Option<usize> {
+        let at = len.min(TAG_OFFSET);
+        if len + TAG_LEN > buf.len() {
+            return None;
+        }
+        buf.copy_within(at..len, at + TAG_LEN);
+        buf[at..at + 2].copy_from_slice(&self.tpid.to_be_bytes());
+        buf[at + 2..at + TAG_LEN].copy_from_slice(&self.tci.to_be_bytes());
+        Some(len + TAG_LEN)
+    }
+
+    /// Copies `frame` into `buf` with the tag inserted, like [`insert`](Self::insert).
+    #[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+    pub(crate) fn write_tagged(self, frame: &[u8], buf: &mut [u8]) -> Option<usize> {
+        buf.get_mut(..frame.len())?.copy_from_slice(frame);
+        self.insert(buf, frame.len())
+    }
+
+    /// VLAN identifier (12 bits).
+    pub fn vid(&self) -> u16 {
+        self.tci & 0x0fff
+    }
+
+    /// Priority code point (3 bits).
+    pub fn pcp(&self) -> u8 {
+        (self.tci >> 13) as u8
+    }
+
+    /// Drop eligible indicator.
+    pub fn dei(&self) -> bool {
+        self.tci & 0x1000 != 0
+    }
+}
+
+/// Size of the Ethernet frame check sequence.
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "dpdk"))]
+const FCS_LEN: u32 = 4;
+
+/// Whether received frames end with their FCS, and what to do about it.
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "dpdk"))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum RxFcs {
+    /// The frames come without FCS.
+    #[default]
+    Absent,
+    /// The frames end with the FCS, which is left in place.
+    Present,
+    /// The frames end with the FCS, which is trimmed in software.
+    Strip,
+}
+
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "dpdk"))]
+impl RxFcs {
+    /// Whether the frames delivered to the application end with the FCS.
+    #[inline]
+    pub(crate) fn present(self) -> bool {
+        self == RxFcs::Present
+    }
+
+    /// Returns the captured and wire lengths of a frame once its FCS is dealt with.
+    ///
+    /// The FCS comes last, so a frame truncated to `caplen` may have lost it already.
+    #[inline]
+    pub(crate) fn apply(self, caplen: u32, wirelen: u32) -> (u32, u32) {
+        match self {
+            RxFcs::Strip => {
+                let wirelen = wirelen.saturating_sub(FCS_LEN);
+                (caplen.min(wirelen), wirelen)
+            }
+            _ => (caplen, wirelen),
+        }
+    }
+}
+
+/// Metadata of a packet that did not come from a socket, such as the packets a
+/// [`SpoolWriter`](super::SpoolWriter) hands to its thread: a timestamp and the packet
+/// lengths.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BasicMeta {
+    /// Capture timestamp, if any.
+    pub timestamp: Option<Timestamp>,
+    /// Number of bytes captured.
+    pub caplen: u32,
+    /// Length of the packet on the wire.
+    pub wirelen: u32,
+}
+
+impl Metadata for BasicMeta {
+    fn into_enum(self) -> MetadataType {
+        MetadataType::Basic(self)
+    }
+
+    fn timestamp(&self) -> Option<Timestamp> {
+        self.timestamp
+    }
+
+    fn caplen(&self) -> u32 {
+        self.caplen
+    }
+
+    fn wirelen(&self) -> u32 {
+        self.wirelen
+    }
 }
 
 /// Unified enum containing metadata from all supported backends.
@@ -27,4 +254,112 @@ pub enum MetadataType {
     /// Metadata from pcap backend.
     #[cfg(feature = "pcap")]
     Pcap(crate::pcap::Meta),
+    /// Metadata of a packet that did not come from a socket.
+    Basic(BasicMeta),
+}
+
+/// Runs `$body` with `$m` bound to the metadata inside any variant.
+macro_rules! each_meta {
+    ($value:expr, $m:ident => $body:expr) => {
+        match $value {
+            #[cfg(feature = "netmap")]
+            MetadataType::Netmap($m) => $body,
+            #[cfg(feature = "af-xdp")]
+            MetadataType::AfXdp($m) => $body,
+            #[cfg(feature = "dpdk")]
+            MetadataType::Dpdk($m) => $body,
+            #[cfg(feature = "pcap")]
+            MetadataType::Pcap($m) => $body,
+            MetadataType::Basic($m) => $body,
+        }
+    };
+}
+
+impl Metadata for MetadataType {
+    fn into_enum(self) -> MetadataType {
+        self
+    }
+
+    fn timestamp(&self) -> Option<Timestamp> {
+        each_meta!(self, m => m.timestamp())
+    }
+
+    fn timestamp_kind(&self) -> Option<TimestampKind> {
+        each_meta!(self, m => m.timestamp_kind())
+    }
+
+    fn caplen(&self) -> u32 {
+        each_meta!(self, m => m.caplen())
+    }
+
+    fn wirelen(&self) -> u32 {
+        each_meta!(self, m => m.wirelen())
+    }
+
+    fn vlan(&self) -> Option<VlanTag> {
+        each_meta!(self, m => m.vlan())
+    }
+
+    fn rss_hash(&self) -> Option<u32> {
+        each_meta!(self, m => m.rss_hash())
+    }
+
+    fn queue(&self) -> Option<u32> {
+        each_meta!(self, m => m.queue())
+    }
+
+    fn has_fcs(&self) -> bool {
+        each_meta!(self, m => m.has_fcs())
+    }
+
+    fn linktype(&self) -> LinkType {
+        each_meta!(self, m => m.linktype())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_vlan_tag() {
+        let mut frame = [0u8; 18];
+        frame[12..16].copy_from_slice(&[0x81, 0x00, 0xa0, 0x64]);
+        let tag = VlanTag::parse(&frame).unwrap();
+        assert_eq!(tag.vid(), 100);
+        assert_eq!(tag.pcp(), 5);
+        assert!(!tag.dei());
+
+        frame[12..14].copy_from_slice(&[0x08, 0x00]);
+        assert_eq!(VlanTag::parse(&frame), None);
+        assert_eq!(VlanTag::parse(&frame[..14]), None);
+    }
+
+    #[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+    #[test]
+    fn strip_and_insert() {
+        let untagged: Vec<u8> = (0..20).collect();
+        let tag = VlanTag::new(100);
+        let mut buf = [0u8; 32];
+        let len = tag.write_tagged(&untagged, &mut buf).unwrap();
+        assert_eq!(len, 24);
+        assert_eq!(&buf[12..16], &[0x81, 0x00, 0x00, 0x64]);
+        assert_eq!(&buf[16..24], &untagged[12..]);
+
+        assert_eq!(VlanTag::strip(&mut buf[..len]), Some((tag, 20)));
+        assert_eq!(&buf[..20], &untagged[..]);
+        assert_eq!(VlanTag::strip(&mut buf[..20]), None);
+        assert_eq!(tag.insert(&mut buf[..22], 20), None);
+    }
+
+    #[cfg(any(feature = "pcap", feature = "af-xdp", feature = "dpdk"))]
+    #[test]
+    fn strip_fcs() {
+        assert_eq!(RxFcs::Present.apply(64, 64), (64, 64));
+        assert_eq!(RxFcs::Strip.apply(64, 64), (60, 60));
+        // Truncated before the FCS: only the wire length changes.
+        assert_eq!(RxFcs::Strip.apply(32, 64), (32, 60));
+        assert_eq!(RxFcs::Strip.apply(62, 64), (60, 60));
+        assert_eq!(RxFcs::Strip.apply(2, 2), (0, 0));
+    }
 }
@@ -0,0 +1,157 @@
+//! Per-socket counters exported through the [`metrics`](https://docs.rs/metrics) facade.
+//!
+//! With the `metrics` feature every socket registers, labelled with `backend`, `port`
+//! and `queue`:
+//!
+//! - `nethuns_rx_packets_total`, `nethuns_rx_bytes_total`
+//! - `nethuns_tx_packets_total`, `nethuns_tx_bytes_total`
+//! - `nethuns_dropped_total`, the losses inside nethuns counted by [`Dropped`](super::Dropped),
+//!   on AF_XDP and netmap sockets
+//! - `nethuns_rx_batch_size`, `nethuns_tx_batch_size`, histograms of the packets moved
+//!   per ring sync and per flush (pcap has no rings and does not register them)
+//!
+//! The handles are resolved once, when the socket is created, so an update on the hot
+//! path is a relaxed atomic add in the installed recorder; the batch sizes are only
+//! recorded at ring syncs and flushes. Without the feature [`SocketMetrics`] is empty
+//! and every update compiles to nothing.
+
+#[cfg(all(
+    feature = "metrics",
+    any(feature = "af-xdp", feature = "netmap", feature = "dpdk")
+))]
+use std::cell::Cell;
+
+use super::Backend;
+
+#[cfg(feature = "metrics")]
+use metrics::{Counter, counter};
+#[cfg(all(
+    feature = "metrics",
+    any(feature = "af-xdp", feature = "netmap", feature = "dpdk")
+))]
+use metrics::{Histogram, histogram};
+
+/// The counters of one socket; its receive and transmit halves each hold a clone.
+#[cfg(feature = "metrics")]
+#[derive(Clone)]
+pub(crate) struct SocketMetrics {
+    rx_packets: Counter,
+    rx_bytes: Counter,
+    tx_packets: Counter,
+    tx_bytes: Counter,
+    #[cfg(any(feature = "af-xdp", feature = "netmap"))]
+    dropped: Counter,
+    #[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+    rx_batch_size: Histogram,
+    #[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+    tx_batch_size: Histogram,
+    /// Packets received since the last RX ring sync.
+    #[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+    rx_batch: Cell<u64>,
+    /// Packets queued since the last flush.
+    #[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+    tx_batch: Cell<u64>,
+}
+
+#[cfg(feature = "metrics")]
+impl SocketMetrics {
+    pub(crate) fn new(backend: Backend, port: &str, queue: Option<usize>) -> Self {
+        let queue = queue.map_or_else(|| "any".to_string(), |q| q.to_string());
+        let labels = [
+            ("backend", backend.name().to_string()),
+            ("port", port.to_string()),
+            ("queue", queue),
+        ];
+        Self {
+            rx_packets: counter!("nethuns_rx_packets_total", &labels),
+            rx_bytes: counter!("nethuns_rx_bytes_total", &labels),
+            tx_packets: counter!("nethuns_tx_packets_total", &labels),
+            tx_bytes: counter!("nethuns_tx_bytes_total", &labels),
+            #[cfg(any(feature = "af-xdp", feature = "netmap"))]
+            dropped: counter!("nethuns_dropped_total", &labels),
+            #[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+            rx_batch_size: histogram!("nethuns_rx_batch_size", &labels),
+            #[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+            tx_batch_size: histogram!("nethuns_tx_batch_size", &labels),
+            #[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+            rx_batch: Cell::new(0),
+            #[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+            tx_batch: Cell::new(0),
+        }
+    }
+
+    /// Counts a packet of `len` bytes delivered to the application.
+    #[inline]
+    pub(crate) fn rx(&self, len: usize) {
+        self.rx_packets.increment(1);
+        self.rx_bytes.increment(len as u64);
+        #[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+        self.rx_batch.set(self.rx_batch.get() + 1);
+    }
+
+    /// Counts a packet of `len` bytes handed to the backend for transmission.
+    #[inline]
+    pub(crate) fn tx(&self, len: usize) {
+        self.tx_packets.increment(1);
+        self.tx_bytes.increment(len as u64);
+        #[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+        self.tx_batch.set(self.tx_batch.get() + 1);
+    }
+
+    /// Counts `n` packets lost inside nethuns.
+    #[cfg(any(feature = "af-xdp", feature = "netmap"))]
+    #[inline]
+    pub(crate) fn dropped(&self, n: u64) {
+        self.dropped.increment(n);
+    }
+
+    /// Records the packets received since the previous RX ring sync.
+    #[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+    #[inline]
+    pub(crate) fn rx_sync(&self) {
+        let n = self.rx_batch.replace(0);
+        if n > 0 {
+            self.rx_batch_size.record(n as f64);
+        }
+    }
+
+    /// Records the packets queued since the previous flush.
+    #[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+    #[inline]
+    pub(crate) fn tx_flush(&self) {
+        let n = self.tx_batch.replace(0);
+        if n > 0 {
+            self.tx_batch_size.record(n as f64);
+        }
+    }
+}
+
+/// The counters of one socket; empty without the `metrics` feature.
+#[cfg(not(feature = "metrics"))]
+#[derive(Clone)]
+pub(crate) struct SocketMetrics;
+
+#[cfg(not(feature = "metrics"))]
+impl SocketMetrics {
+    pub(crate) fn new(_backend: Backend, _port: &str, _queue: Option<usize>) -> Self {
+        Self
+    }
+
+    #[inline(always)]
+    pub(crate) fn rx(&self, _len: usize) {}
+
+    #[inline(always)]
+    pub(crate) fn tx(&self, _len: usize) {}
+
+    #[cfg(any(feature = "af-xdp", feature = "netmap"))]
+    #[inline(always)]
+    pub(crate) fn dropped(&self, _n: u64) {}
+
+    #[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+    #[inline(always)]
+    pub(crate) fn rx_sync(&self) {}
+
+    #[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+    #[inline(always)]
+    pub(crate) fn tx_flush(&self) {}
+}
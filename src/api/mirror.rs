@@ -0,0 +1,245 @@
+//! Mirroring of received packets to a secondary socket.
+
+use std::cell::{Cell, RefCell};
+use std::ops::Deref;
+use std::time::{Duration, Instant};
+
+use super::Result;
+use super::burst::RecvBurst;
+use super::metadata::Metadata;
+use super::packets::Batch;
+use super::socket::Socket;
+use super::token::{Payload, Token};
+
+/// Destination of the packets copied by a [`Mirror`].
+///
+/// Implemented by every [`Socket`], which sends the copies, and with the `pcap`
+/// feature by [`pcap::Dumper`](crate::pcap::Dumper) and `pcap::Savefile`, which write
+/// them to a capture file.
+pub trait MirrorSink {
+    /// Copies out a received packet.
+    ///
+    /// Must not wait for room: an error counts the copy as dropped and the receive
+    /// goes on.
+    fn mirror<M: Metadata>(&mut self, packet: &[u8], meta: &M) -> Result<()>;
+
+    /// Pushes out the copies queued so far.
+    fn flush_mirror(&mut self) {}
+}
+
+impl<S: Socket> MirrorSink for S {
+    #[inline]
+    fn mirror<M: Metadata>(&mut self, packet: &[u8], _meta: &M) -> Result<()> {
+        self.send(packet)
+    }
+
+    fn flush_mirror(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(feature = "pcap")]
+impl MirrorSink for pcap::Savefile {
+    fn mirror<M: Metadata>(&mut self, packet: &[u8], meta: &M) -> Result<()> {
+        let header = crate::pcap::packet_header(packet, meta, false);
+        self.write(&pcap::Packet::new(&header, packet));
+        Ok(())
+    }
+
+    fn flush_mirror(&mut self) {
+        // Write errors surface again on the next flush or when the file is closed.
+        let _ = self.flush();
+    }
+}
+
+#[cfg(feature = "pcap")]
+impl MirrorSink for crate::pcap::Dumper {
+    fn mirror<M: Metadata>(&mut self, packet: &[u8], meta: &M) -> Result<()> {
+        self.try_dump(packet, meta)
+    }
+
+    fn flush_mirror(&mut self) {
+        // Write errors surface again on the next flush or when the file is closed.
+        let _ = self.flush();
+    }
+}
+
+/// A socket whose received packets are also copied to a tap, obtained with
+/// [`Mirror::new`].
+///
+/// The receive methods mirror [`Socket`]'s: each hands the packet to the application
+/// after copying it to the tap. Everything else, sending included, goes to the primary
+/// socket through `Deref`. The tap never stalls the primary: a copy it cannot take
+/// right away, e.g. because its TX ring is full, is counted in
+/// [`mirror_dropped`](Mirror::mirror_dropped) and the packet is delivered anyway.
+///
+/// Copies are flushed at the end of [`recv_batch`](Mirror::recv_batch) and
+/// [`dispatch`](Mirror::dispatch); with single receives open the tap with
+/// [`FlushPolicy::Auto`](super::FlushPolicy::Auto) or call
+/// [`flush_mirror`](Mirror::flush_mirror).
+///
+/// ```ignore
+/// let tap = pcap::Sock::create("tap0", None, PcapFlags::default())?;
+/// let socket = Mirror::new(af_xdp::Sock::create("eth0", Some(0), flags)?, tap);
+/// let (packet, _meta) = socket.recv()?; // also sent out of tap0
+/// ```
+pub struct Mirror<S, M> {
+    primary: S,
+    tap: RefCell<M>,
+    dropped: Cell<u64>,
+}
+
+impl<S: Socket, M: MirrorSink> Mirror<S, M> {
+    /// Copies every packet received by `primary` to `tap`.
+    pub fn new(primary: S, tap: M) -> Self {
+        Self {
+            primary,
+            tap: RefCell::new(tap),
+            dropped: Cell::new(0),
+        }
+    }
+
+    /// Returns the primary socket.
+    pub fn primary(&self) -> &S {
+        &self.primary
+    }
+
+    /// Returns the tap mutably.
+    pub fn tap_mut(&mut self) -> &mut M {
+        self.tap.get_mut()
+    }
+
+    /// Separates the primary socket from the tap.
+    pub fn into_parts(self) -> (S, M) {
+        (self.primary, self.tap.into_inner())
+    }
+
+    /// Returns the number of packets the tap could not take.
+    pub fn mirror_dropped(&self) -> u64 {
+        self.dropped.get()
+    }
+
+    /// Pushes out the copies queued on the tap.
+    pub fn flush_mirror(&self) {
+        self.tap.borrow_mut().flush_mirror();
+    }
+
+    #[inline]
+    fn copy(&self, packet: &[u8], meta: &S::Metadata) {
+        if self.tap.borrow_mut().mirror(packet, meta).is_err() {
+            self.dropped.set(self.dropped.get() + 1);
+        }
+    }
+
+    #[inline]
+    fn copy_token(&self, (token, meta): (Token, S::Metadata)) -> (Token, S::Metadata) {
+        let payload = token.consume(self.primary.context());
+        self.copy(&payload, &meta);
+        (payload.into_token(), meta)
+    }
+
+    /// Receives a packet and copies it to the tap, see [`Socket::recv`].
+    pub fn recv(&self) -> Result<(Payload<'_, S::Context>, S::Metadata)> {
+        let (payload, meta) = self.primary.recv()?;
+        self.copy(&payload, &meta);
+        Ok((payload, meta))
+    }
+
+    /// Token-returning counterpart of [`recv`](Mirror::recv), see
+    /// [`Socket::recv_token`].
+    pub fn recv_token(&self) -> Result<(Token, S::Metadata)> {
+        Ok(self.copy_token(self.primary.recv_token()?))
+    }
+
+    /// Receives a packet by copying it into `buf`, see [`Socket::recv_into`].
+    pub fn recv_into(&self, buf: &mut [u8]) -> Result<(usize, S::Metadata)> {
+        let (payload, meta) = self.recv()?;
+        let len = payload.len().min(buf.len());
+        buf[..len].copy_from_slice(&payload[..len]);
+        Ok((len, meta))
+    }
+
+    /// Receives a packet without blocking, see [`Socket::try_recv`].
+    pub fn try_recv(&self) -> Result<(Payload<'_, S::Context>, S::Metadata)> {
+        let (payload, meta) = self.primary.try_recv()?;
+        self.copy(&payload, &meta);
+        Ok((payload, meta))
+    }
+
+    /// Non-blocking counterpart of [`recv_token`](Mirror::recv_token).
+    pub fn try_recv_token(&self) -> Result<(Token, S::Metadata)> {
+        Ok(self.copy_token(self.primary.try_recv_token()?))
+    }
+
+    /// Receives a packet, waiting at most `timeout` for one, see
+    /// [`Socket::recv_timeout`].
+    pub fn recv_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<(Payload<'_, S::Context>, S::Metadata)> {
+        let (payload, meta) = self.primary.recv_timeout(timeout)?;
+        self.copy(&payload, &meta);
+        Ok((payload, meta))
+    }
+
+    /// Token-returning counterpart of [`recv_timeout`](Mirror::recv_timeout).
+    pub fn recv_token_timeout(&self, timeout: Duration) -> Result<(Token, S::Metadata)> {
+        Ok(self.copy_token(self.primary.recv_token_timeout(timeout)?))
+    }
+
+    /// Receives up to `budget` packets, see [`Socket::recv_batch`], and flushes their
+    /// copies.
+    pub fn recv_batch(&self, budget: usize) -> Result<Vec<(Token, S::Metadata)>> {
+        let batch = self.primary.recv_batch(budget)?;
+        let batch = batch.into_iter().map(|pkt| self.copy_token(pkt)).collect();
+        self.flush_mirror();
+        Ok(batch)
+    }
+
+    /// Collects packets until `max` have arrived or `deadline` passes, see
+    /// [`Socket::recv_until`], and flushes their copies.
+    pub fn recv_until(&self, deadline: Instant, max: usize) -> Result<Batch<S::Metadata>> {
+        let batch = self.primary.recv_until(deadline, max)?;
+        let batch: Batch<_> = batch.into_iter().map(|pkt| self.copy_token(pkt)).collect();
+        if !batch.is_empty() {
+            self.flush_mirror();
+        }
+        Ok(batch)
+    }
+
+    /// Hands up to `budget` ready packets to `f`, see [`Socket::dispatch`], and flushes
+    /// their copies.
+    pub fn dispatch<F>(&self, budget: usize, mut f: F) -> Result<usize>
+    where
+        F: FnMut(Payload<'_, S::Context>, S::Metadata),
+    {
+        let count = self.primary.dispatch(budget, |payload, meta| {
+            self.copy(&payload, &meta);
+            f(payload, meta)
+        })?;
+        if count > 0 {
+            self.flush_mirror();
+        }
+        Ok(count)
+    }
+}
+
+impl<S: Socket, M: MirrorSink> RecvBurst for Mirror<S, M> {
+    type Context = S::Context;
+    type Metadata = S::Metadata;
+
+    fn poll_burst<F>(&self, budget: usize, f: F) -> Result<usize>
+    where
+        F: FnMut(Payload<'_, Self::Context>, Self::Metadata),
+    {
+        self.dispatch(budget, f)
+    }
+}
+
+impl<S, M> Deref for Mirror<S, M> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.primary
+    }
+}
@@ -20,20 +20,199 @@
 //! println!("Received {} bytes", payload.len());
 //! ```
 
+mod affinity;
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+mod any_socket;
+#[cfg(feature = "tokio")]
+mod async_socket;
 mod buffer;
+mod builder;
+mod burst;
+mod capabilities;
+pub(crate) mod checksum;
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+mod completion;
+#[cfg(all(
+    feature = "serde",
+    any(
+        feature = "pcap",
+        feature = "af-xdp",
+        feature = "netmap",
+        feature = "dpdk"
+    )
+))]
+mod config;
 mod context;
+mod device;
+pub mod dispatch;
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+mod dyn_socket;
+pub mod filter;
+mod flush;
+mod forward;
+mod group;
+pub(crate) mod gso;
+mod hash;
 mod hint;
+pub mod ifctl;
+mod linktype;
 mod metadata;
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+mod metrics;
+mod mirror;
+pub(crate) mod netdev;
+mod pacing;
+mod packet;
+mod packets;
+pub mod pcapng;
+pub mod pipeline;
+pub(crate) mod poll;
+mod privileges;
+#[cfg(feature = "pcap")]
+mod replay;
+mod rx_loop;
 mod socket;
+mod socket_with;
+mod split;
+mod spool;
+mod stats;
+mod steer;
+mod timestamp;
 mod token;
+mod tx_slot;
 
 // Re-export all public types
+pub use affinity::{numa_local_cpus, spawn_per_queue, spawn_per_queue_on};
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+pub use any_socket::{AnyPayload, AnySocket};
+#[cfg(feature = "tokio")]
+pub use async_socket::AsyncSocket;
 pub use buffer::{BufferDesc, BufferRef};
+pub use builder::{Direction, SocketBuilder, SocketOptions};
+pub use burst::{RecvBurst, RoundRobin};
+pub use capabilities::Capabilities;
+pub use checksum::TxChecksum;
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+pub(crate) use completion::TxCompletions;
+#[cfg(all(
+    feature = "serde",
+    any(
+        feature = "pcap",
+        feature = "af-xdp",
+        feature = "netmap",
+        feature = "dpdk"
+    )
+))]
+pub use config::Config;
 pub use context::Context;
+#[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+pub(crate) use context::ReturnQueue;
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+pub(crate) use device::link_info;
+pub use device::{Backend, Device, Duplex, LinkInfo, list_devices};
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+pub use dyn_socket::DynSocket;
+pub use filter::Filter;
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+pub(crate) use filter::UserspaceFilter;
+pub use flush::FlushPolicy;
+#[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+pub(crate) use flush::{FlushState, drain_tx};
+pub use forward::forward;
+#[cfg(any(feature = "af-xdp", feature = "netmap"))]
+pub(crate) use forward::same_backend;
+pub use group::SocketGroup;
+pub use hash::{TOEPLITZ_DEFAULT_KEY, TOEPLITZ_SYMMETRIC_KEY, flow_hash, toeplitz_hash};
 pub use hint::{likely, unlikely};
-pub use metadata::{Metadata, MetadataType};
+pub use linktype::LinkType;
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "dpdk"))]
+pub(crate) use metadata::RxFcs;
+pub use metadata::{BasicMeta, Metadata, MetadataType, VlanTag};
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+pub(crate) use metrics::SocketMetrics;
+pub use mirror::{Mirror, MirrorSink};
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+pub(crate) use pacing::Pacer;
+pub use pacing::TxRate;
+pub use packet::Packet;
+#[cfg(any(feature = "af-xdp", feature = "netmap"))]
+pub(crate) use packet::{Segment, SegmentTracker};
+pub use packets::{Batch, Packets};
+pub use poll::Pollable;
+pub use privileges::drop_privileges;
+#[cfg(feature = "pcap")]
+pub use replay::{Replay, ReplayStats};
+pub use rx_loop::{ErrorPolicy, RxLoopStats, run_rx_loop};
+#[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+pub(crate) use socket::fill_batch;
 pub use socket::{Flags, Socket};
-pub use token::{Payload, Token};
+pub use socket_with::SocketWith;
+pub use split::{RxSocket, TxSocket};
+pub use spool::SpoolWriter;
+pub use stats::{Dropped, Stats};
+pub use steer::{FlowProtocol, FlowRule, FlowSpec};
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+pub(crate) use steer::{ntuple_delete, ntuple_insert};
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+pub(crate) use timestamp::SoftwareClock;
+pub use timestamp::{Timestamp, TimestampKind, TimestampSource};
+pub use token::{OwnedPacket, Payload, SharedPayload, Token};
+pub use tx_slot::TxSlot;
 
 /// Result type for API operations.
 pub type Result<T> = std::result::Result<T, crate::errors::Error>;
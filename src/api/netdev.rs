@@ -0,0 +1,423 @@
+//! Interface configuration helpers shared by the backends.
+
+use std::io;
+#[cfg(feature = "pcap")]
+use std::os::fd::RawFd;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+/// Sets or clears `IFF_PROMISC` on `ifname`.
+///
+/// The kernel counts promiscuous users, so clearing the flag only leaves promiscuous
+/// mode if nobody else asked for it.
+#[cfg(any(feature = "af-xdp", feature = "netmap"))]
+pub(crate) fn set_promiscuous(ifname: &str, enable: bool) -> io::Result<()> {
+    set_flag(ifname, libc::IFF_PROMISC, enable)
+}
+
+/// Sets or clears `IFF_ALLMULTI` on `ifname`, so that every multicast frame is received.
+#[cfg(any(feature = "af-xdp", feature = "netmap"))]
+pub(crate) fn set_allmulti(ifname: &str, enable: bool) -> io::Result<()> {
+    set_flag(ifname, libc::IFF_ALLMULTI, enable)
+}
+
+/// Adds (`SIOCADDMULTI`) or removes (`SIOCDELMULTI`) the multicast address `mac` to or
+/// from the filter of `ifname`.
+///
+/// The address belongs to the interface, not to a socket: it stays until removed.
+#[cfg(any(feature = "af-xdp", feature = "netmap"))]
+pub(crate) fn set_multicast(ifname: &str, mac: [u8; 6], join: bool) -> io::Result<()> {
+    check_multicast(mac)?;
+    let sock = control_socket()?;
+    let mut ifr = ifreq(ifname)?;
+    // SAFETY: the hwaddr member is a plain sockaddr.
+    unsafe {
+        let addr = &mut ifr.ifr_ifru.ifru_hwaddr;
+        addr.sa_family = libc::AF_UNSPEC as libc::sa_family_t;
+        for (dst, &src) in addr.sa_data.iter_mut().zip(&mac) {
+            *dst = src as libc::c_char;
+        }
+    }
+    let request = if join {
+        libc::SIOCADDMULTI
+    } else {
+        libc::SIOCDELMULTI
+    };
+    ioctl(&sock, request, &mut ifr)
+}
+
+pub(super) fn set_flag(ifname: &str, flag: libc::c_int, enable: bool) -> io::Result<()> {
+    let sock = control_socket()?;
+    let mut ifr = ifreq(ifname)?;
+    ioctl(&sock, libc::SIOCGIFFLAGS, &mut ifr)?;
+    // SAFETY: SIOCGIFFLAGS filled in the flags member of the union.
+    unsafe {
+        let flags = &mut ifr.ifr_ifru.ifru_flags;
+        if enable {
+            *flags |= flag as libc::c_short;
+        } else {
+            *flags &= !(flag as libc::c_short);
+        }
+    }
+    ioctl(&sock, libc::SIOCSIFFLAGS, &mut ifr)
+}
+
+/// Turns on hardware timestamping of every received packet on `ifname`.
+///
+/// TX timestamping is left off. Requires `CAP_NET_ADMIN` and a driver supporting
+/// `SIOCSHWTSTAMP`.
+#[cfg(feature = "af-xdp")]
+pub(crate) fn enable_hw_timestamps(ifname: &str) -> io::Result<()> {
+    let sock = control_socket()?;
+    let mut ifr = ifreq(ifname)?;
+    let mut config = libc::hwtstamp_config {
+        flags: 0,
+        tx_type: libc::HWTSTAMP_TX_OFF as libc::c_int,
+        rx_filter: libc::HWTSTAMP_FILTER_ALL as libc::c_int,
+    };
+    ifr.ifr_ifru.ifru_data = &mut config as *mut _ as *mut libc::c_char;
+    ioctl(&sock, libc::SIOCSHWTSTAMP, &mut ifr)
+}
+
+#[cfg(any(feature = "pcap", feature = "af-xdp"))]
+const ETHTOOL_GSTRINGS: u32 = 0x1b;
+#[cfg(any(feature = "pcap", feature = "af-xdp"))]
+const ETHTOOL_GSSET_INFO: u32 = 0x37;
+#[cfg(any(feature = "pcap", feature = "af-xdp"))]
+const ETHTOOL_GFEATURES: u32 = 0x3a;
+#[cfg(any(feature = "pcap", feature = "af-xdp"))]
+const ETHTOOL_SFEATURES: u32 = 0x3b;
+#[cfg(feature = "af-xdp")]
+const ETHTOOL_GCHANNELS: u32 = 0x3c;
+#[cfg(any(feature = "pcap", feature = "af-xdp"))]
+const ETH_SS_FEATURES: u32 = 4;
+#[cfg(any(feature = "pcap", feature = "af-xdp"))]
+const ETH_GSTRING_LEN: usize = 32;
+
+/// Applies the [`fcs`](super::SocketOptions::fcs) option to `ifname` and returns how
+/// its received frames end.
+///
+/// Asking for the FCS turns on the `rx-fcs` feature (`ethtool -K <if> rx-fcs on`),
+/// which not every driver offers; refusing it leaves the feature alone and strips the
+/// FCS in software if the interface delivers it anyway.
+#[cfg(any(feature = "pcap", feature = "af-xdp"))]
+pub(crate) fn rx_fcs(ifname: &str, keep: Option<bool>) -> super::RxFcs {
+    if keep == Some(true) {
+        // Without driver support the frames simply keep coming without FCS.
+        let _ = set_feature(ifname, "rx-fcs", true);
+    }
+    match (feature(ifname, "rx-fcs").unwrap_or(false), keep) {
+        (false, _) => super::RxFcs::Absent,
+        (true, Some(false)) => super::RxFcs::Strip,
+        (true, _) => super::RxFcs::Present,
+    }
+}
+
+/// Returns whether the ethtool feature `name` (e.g. `rx-fcs`) of `ifname` is active.
+#[cfg(any(feature = "pcap", feature = "af-xdp"))]
+pub(crate) fn feature(ifname: &str, name: &str) -> io::Result<bool> {
+    let sock = control_socket()?;
+    let (index, count) = feature_index(&sock, ifname, name)?;
+    // struct ethtool_gfeatures, followed by {available, requested, active,
+    // never_changed} blocks of 32 features each.
+    let blocks = count.div_ceil(32);
+    let mut buf = vec![0u32; 2 + 4 * blocks];
+    buf[0] = ETHTOOL_GFEATURES;
+    buf[1] = blocks as u32;
+    ethtool(&sock, ifname, buf.as_mut_ptr().cast())?;
+    let active = buf[2 + 4 * (index / 32) + 2];
+    Ok(active & (1 << (index % 32)) != 0)
+}
+
+/// Turns the ethtool feature `name` of `ifname` on or off. Requires `CAP_NET_ADMIN`.
+#[cfg(any(feature = "pcap", feature = "af-xdp"))]
+pub(crate) fn set_feature(ifname: &str, name: &str, enable: bool) -> io::Result<()> {
+    let sock = control_socket()?;
+    let (index, count) = feature_index(&sock, ifname, name)?;
+    // struct ethtool_sfeatures, followed by {valid, requested} blocks.
+    let blocks = count.div_ceil(32);
+    let mut buf = vec![0u32; 2 + 2 * blocks];
+    buf[0] = ETHTOOL_SFEATURES;
+    buf[1] = blocks as u32;
+    let bit = 1 << (index % 32);
+    buf[2 + 2 * (index / 32)] = bit;
+    if enable {
+        buf[2 + 2 * (index / 32) + 1] = bit;
+    }
+    ethtool(&sock, ifname, buf.as_mut_ptr().cast())
+}
+
+/// Returns the index of the feature `name` of `ifname`, and the number of features.
+#[cfg(any(feature = "pcap", feature = "af-xdp"))]
+fn feature_index(sock: &OwnedFd, ifname: &str, name: &str) -> io::Result<(usize, usize)> {
+    let mut info = SsetInfo {
+        cmd: ETHTOOL_GSSET_INFO,
+        reserved: 0,
+        sset_mask: 1 << ETH_SS_FEATURES,
+        data: [0],
+    };
+    ethtool(sock, ifname, (&mut info as *mut SsetInfo).cast())?;
+    if info.sset_mask == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{ifname} does not report its features"),
+        ));
+    }
+    let count = info.data[0] as usize;
+
+    // struct ethtool_gstrings, followed by the names.
+    let mut buf = vec![0u32; 3 + count * ETH_GSTRING_LEN / 4];
+    buf[0] = ETHTOOL_GSTRINGS;
+    buf[1] = ETH_SS_FEATURES;
+    buf[2] = count as u32;
+    ethtool(sock, ifname, buf.as_mut_ptr().cast())?;
+    // SAFETY: reinterpreting initialized u32s as bytes.
+    let names = unsafe {
+        std::slice::from_raw_parts(buf[3..].as_ptr().cast::<u8>(), count * ETH_GSTRING_LEN)
+    };
+    names
+        .chunks(ETH_GSTRING_LEN)
+        .position(|s| s.split(|&b| b == 0).next() == Some(name.as_bytes()))
+        .map(|index| (index, count))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{ifname} has no {name} feature"),
+            )
+        })
+}
+
+/// `struct ethtool_sset_info` with room for a single count.
+#[cfg(any(feature = "pcap", feature = "af-xdp"))]
+#[repr(C)]
+struct SsetInfo {
+    cmd: u32,
+    reserved: u32,
+    sset_mask: u64,
+    data: [u32; 1],
+}
+
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+pub(super) fn ethtool(sock: &OwnedFd, ifname: &str, data: *mut libc::c_char) -> io::Result<()> {
+    let mut ifr = ifreq(ifname)?;
+    ifr.ifr_ifru.ifru_data = data;
+    ioctl(sock, libc::SIOCETHTOOL, &mut ifr)
+}
+
+/// Adds or drops the promiscuous membership of a packet socket on `ifindex`.
+///
+/// The membership belongs to the socket and goes away when it is closed.
+#[cfg(feature = "pcap")]
+pub(crate) fn set_packet_promiscuous(fd: RawFd, ifindex: u32, enable: bool) -> io::Result<()> {
+    packet_membership(fd, ifindex, libc::PACKET_MR_PROMISC, None, enable)
+}
+
+/// Adds or drops the all-multicast membership of a packet socket on `ifindex`.
+#[cfg(feature = "pcap")]
+pub(crate) fn set_packet_allmulti(fd: RawFd, ifindex: u32, enable: bool) -> io::Result<()> {
+    packet_membership(fd, ifindex, libc::PACKET_MR_ALLMULTI, None, enable)
+}
+
+/// Adds or drops the membership of a packet socket to the multicast address `mac` on
+/// `ifindex`.
+#[cfg(feature = "pcap")]
+pub(crate) fn set_packet_multicast(
+    fd: RawFd,
+    ifindex: u32,
+    mac: [u8; 6],
+    join: bool,
+) -> io::Result<()> {
+    check_multicast(mac)?;
+    packet_membership(fd, ifindex, libc::PACKET_MR_MULTICAST, Some(mac), join)
+}
+
+#[cfg(feature = "pcap")]
+fn packet_membership(
+    fd: RawFd,
+    ifindex: u32,
+    kind: libc::c_int,
+    mac: Option<[u8; 6]>,
+    enable: bool,
+) -> io::Result<()> {
+    let mut mreq = libc::packet_mreq {
+        mr_ifindex: ifindex as libc::c_int,
+        mr_type: kind as libc::c_ushort,
+        mr_alen: 0,
+        mr_address: [0; 8],
+    };
+    if let Some(mac) = mac {
+        mreq.mr_alen = mac.len() as libc::c_ushort;
+        mreq.mr_address[..6].copy_from_slice(&mac);
+    }
+    let opt = if enable {
+        libc::PACKET_ADD_MEMBERSHIP
+    } else {
+        libc::PACKET_DROP_MEMBERSHIP
+    };
+    let rc = unsafe {
+        libc::setsockopt(
+            fd,
+            libc::SOL_PACKET,
+            opt,
+            &mreq as *const _ as *const libc::c_void,
+            size_of::<libc::packet_mreq>() as libc::socklen_t,
+        )
+    };
+    if rc != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Joins a packet socket to the hash fanout group `group`.
+///
+/// The kernel spreads the flows received on the interface across the group members.
+#[cfg(feature = "pcap")]
+pub(crate) fn join_fanout(fd: RawFd, group: u16) -> io::Result<()> {
+    let arg = group as u32 | (libc::PACKET_FANOUT_HASH | libc::PACKET_FANOUT_FLAG_DEFRAG) << 16;
+    let rc = unsafe {
+        libc::setsockopt(
+            fd,
+            libc::SOL_PACKET,
+            libc::PACKET_FANOUT,
+            &arg as *const _ as *const libc::c_void,
+            size_of::<u32>() as libc::socklen_t,
+        )
+    };
+    if rc != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// `SO_GET_FILTER`, which `libc` lacks on Linux.
+#[cfg(feature = "pcap")]
+const SO_GET_FILTER: libc::c_int = 26;
+
+/// Returns the number of instructions of the classic BPF filter attached to the socket
+/// `fd`, zero if it has none.
+#[cfg(feature = "pcap")]
+pub(crate) fn attached_filter_len(fd: RawFd) -> io::Result<u32> {
+    // With a zero length, the kernel only reports the length of the program.
+    let mut len: libc::socklen_t = 0;
+    let rc = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            SO_GET_FILTER,
+            std::ptr::null_mut(),
+            &mut len,
+        )
+    };
+    if rc != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(len)
+}
+
+/// Returns the number of RX queues of `ifname`.
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+pub(crate) fn rx_queues(ifname: &str) -> io::Result<usize> {
+    let dir = std::fs::read_dir(format!("/sys/class/net/{ifname}/queues"))?;
+    let mut count = 0;
+    for entry in dir {
+        if entry?.file_name().to_string_lossy().starts_with("rx-") {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Returns the number of channels of `ifname` that receive packets
+/// (`ethtool -l <if>`), or its number of RX queues if the driver does not report
+/// channels.
+#[cfg(feature = "af-xdp")]
+pub(crate) fn rx_channels(ifname: &str) -> io::Result<usize> {
+    let sock = control_socket()?;
+    let mut channels = Channels {
+        cmd: ETHTOOL_GCHANNELS,
+        ..Channels::default()
+    };
+    match ethtool(&sock, ifname, (&mut channels as *mut Channels).cast()) {
+        Ok(()) => Ok((channels.combined_count + channels.rx_count) as usize),
+        Err(e) if e.raw_os_error() == Some(libc::EOPNOTSUPP) => rx_queues(ifname),
+        Err(e) => Err(e),
+    }
+}
+
+/// `struct ethtool_channels`.
+#[cfg(feature = "af-xdp")]
+#[repr(C)]
+#[derive(Default)]
+struct Channels {
+    cmd: u32,
+    max_rx: u32,
+    max_tx: u32,
+    max_other: u32,
+    max_combined: u32,
+    rx_count: u32,
+    tx_count: u32,
+    other_count: u32,
+    combined_count: u32,
+}
+
+/// Returns the index of `ifname`.
+#[cfg(feature = "pcap")]
+pub(crate) fn ifindex(ifname: &str) -> io::Result<u32> {
+    let name = std::ffi::CString::new(ifname)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid interface name"))?;
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => Err(io::Error::last_os_error()),
+        index => Ok(index),
+    }
+}
+
+/// Rejects addresses without the group bit set.
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+pub(crate) fn check_multicast(mac: [u8; 6]) -> io::Result<()> {
+    if mac[0] & 1 == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a multicast MAC address",
+        ));
+    }
+    Ok(())
+}
+
+/// Opens a datagram socket to issue interface ioctls on.
+pub(super) fn control_socket() -> io::Result<OwnedFd> {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+pub(super) fn ifreq(ifname: &str) -> io::Result<libc::ifreq> {
+    if ifname.is_empty() || ifname.len() >= libc::IFNAMSIZ {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid interface name: {ifname:?}"),
+        ));
+    }
+    let mut ifr: libc::ifreq = unsafe { std::mem::zeroed() };
+    for (dst, &src) in ifr.ifr_name.iter_mut().zip(ifname.as_bytes()) {
+        *dst = src as libc::c_char;
+    }
+    Ok(ifr)
+}
+
+pub(super) fn ioctl(
+    sock: &OwnedFd,
+    request: libc::c_ulong,
+    ifr: &mut libc::ifreq,
+) -> io::Result<()> {
+    if unsafe { libc::ioctl(sock.as_raw_fd(), request as _, ifr as *mut libc::ifreq) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,162 @@
+//! Rate limiting of transmitted packets.
+
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+use std::cell::Cell;
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+use std::time::{Duration, Instant};
+
+/// Rate at which a socket transmits, enforced by the send path.
+///
+/// Every queued packet waits for its departure time and is then pushed to the NIC
+/// immediately, regardless of the [`FlushPolicy`](super::FlushPolicy). Short waits
+/// busy-spin, longer ones sleep first, so the rate stays accurate at millions of
+/// packets per second. A sender that falls behind may catch up with a burst of at most
+/// 1 ms worth of traffic.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum TxRate {
+    /// Packets are sent as fast as the ring accepts them.
+    #[default]
+    Unlimited,
+    /// Packets per second.
+    Packets(u64),
+    /// Bits per second, counting the bytes of each frame as in
+    /// [`Stats::tx_bytes`](super::Stats::tx_bytes).
+    Bits(u64),
+}
+
+/// Most time a late sender can catch up on, i.e. the depth of the token bucket.
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+const MAX_BURST: Duration = Duration::from_millis(1);
+/// Waits shorter than this busy-spin instead of sleeping.
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+const SPIN_THRESHOLD: Duration = Duration::from_micros(50);
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+const PS_PER_SEC: u128 = 1_000_000_000_000;
+
+/// Per-socket state of a [`TxRate`]: a token bucket kept as the departure time of the
+/// next packet, in picoseconds since `origin` so that rounding errors do not add up.
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+#[derive(Debug)]
+pub(crate) struct Pacer {
+    rate: TxRate,
+    origin: Instant,
+    next: Cell<u128>,
+}
+
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+impl Pacer {
+    pub(crate) fn new(rate: TxRate) -> Self {
+        Self {
+            rate,
+            origin: Instant::now(),
+            next: Cell::new(0),
+        }
+    }
+
+    /// Returns whether a rate is enforced.
+    #[cfg(feature = "dpdk")]
+    pub(crate) fn is_limited(&self) -> bool {
+        self.rate != TxRate::Unlimited
+    }
+
+    /// Waits until a packet of `len` bytes may leave; returns `false` without waiting
+    /// if the rate is unlimited.
+    #[inline]
+    pub(crate) fn pace(&self, len: usize) -> bool {
+        let cost = match self.rate {
+            TxRate::Unlimited => return false,
+            TxRate::Packets(pps) => PS_PER_SEC / pps.max(1) as u128,
+            TxRate::Bits(bps) => len as u128 * 8 * PS_PER_SEC / bps.max(1) as u128,
+        };
+        let now = self.origin.elapsed().as_nanos() * 1000;
+        let next = self.next.get();
+        if next > now {
+            wait(Duration::from_nanos(((next - now) / 1000) as u64));
+        }
+        let floor = now.saturating_sub(MAX_BURST.as_nanos() * 1000);
+        self.next.set(next.max(floor) + cost);
+        true
+    }
+}
+
+/// Sleeps for the bulk of `wait`, then spins for the rest.
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+pub(crate) fn wait(wait: Duration) {
+    let deadline = Instant::now() + wait;
+    if wait > SPIN_THRESHOLD {
+        std::thread::sleep(wait - SPIN_THRESHOLD);
+    }
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packet_rate() {
+        let pacer = Pacer::new(TxRate::Packets(10_000));
+        let start = Instant::now();
+        for _ in 0..101 {
+            assert!(pacer.pace(64));
+        }
+        // The first packet leaves immediately, the other 100 are 100 µs apart.
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(10), "{elapsed:?}");
+
+        assert!(!Pacer::new(TxRate::Unlimited).pace(64));
+    }
+}
@@ -0,0 +1,234 @@
+//! Packets spanning several buffers.
+
+use std::borrow::Cow;
+#[cfg(any(test, feature = "af-xdp", feature = "netmap"))]
+use std::cell::Cell;
+use std::ptr::NonNull;
+
+use super::context::Context;
+use super::token::Payload;
+
+/// A received frame, possibly split over several buffers, obtained with
+/// [`Socket::recv_packet`](super::Socket::recv_packet).
+///
+/// Jumbo frames larger than a buffer arrive in several segments: netmap slots flagged
+/// `NS_MOREFRAG`, AF_XDP multi-buffer descriptors (`XDP_USE_SG`) or chained DPDK
+/// mbufs. The single-buffer receive methods hand them out one segment at a time (or,
+/// for DPDK, the first segment only); a `Packet` keeps them together.
+///
+/// ```ignore
+/// let (packet, _meta) = socket.recv_packet()?;
+/// for segment in packet.segments() {
+///     hasher.update(segment);
+/// }
+/// let frame = packet.contiguous(); // borrowed unless the packet is segmented
+/// ```
+pub struct Packet<'ctx, C: Context> {
+    head: Payload<'ctx, C>,
+    /// Segments chained to the head buffer and freed along with it (DPDK mbufs).
+    chained: Vec<NonNull<[u8]>>,
+    /// Segments held in buffers of their own (netmap slots, AF_XDP frames).
+    tail: Vec<Payload<'ctx, C>>,
+}
+
+impl<'ctx, C: Context> Packet<'ctx, C> {
+    /// Appends a segment held in a buffer of its own.
+    #[cfg(any(test, feature = "af-xdp", feature = "netmap"))]
+    pub(crate) fn push(&mut self, segment: Payload<'ctx, C>) {
+        self.tail.push(segment);
+    }
+
+    /// Appends a segment chained to the head buffer.
+    ///
+    /// # Safety
+    ///
+    /// `segment` must stay valid until the head buffer is released.
+    #[cfg(any(test, feature = "dpdk"))]
+    pub(crate) unsafe fn push_chained(&mut self, segment: NonNull<[u8]>) {
+        self.chained.push(segment);
+    }
+
+    /// Returns the first segment, which holds the frame headers.
+    pub fn head(&self) -> &Payload<'ctx, C> {
+        &self.head
+    }
+
+    /// Iterates over the segments, in frame order.
+    pub fn segments(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        let chained = self.chained.iter().map(|segment| {
+            // SAFETY: chained segments live as long as the head buffer.
+            unsafe { segment.as_ref() }
+        });
+        std::iter::once(&*self.head)
+            .chain(chained)
+            .chain(self.tail.iter().map(|segment| &**segment))
+    }
+
+    /// Returns the number of segments.
+    pub fn num_segments(&self) -> usize {
+        1 + self.chained.len() + self.tail.len()
+    }
+
+    /// Returns `true` if the frame fits in a single buffer.
+    pub fn is_contiguous(&self) -> bool {
+        self.num_segments() == 1
+    }
+
+    /// Returns the length of the whole frame.
+    pub fn len(&self) -> usize {
+        self.segments().map(<[u8]>::len).sum()
+    }
+
+    /// Returns `true` if the frame is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copies the whole frame into a new buffer.
+    pub fn copy_contiguous(&self) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(self.len());
+        for segment in self.segments() {
+            frame.extend_from_slice(segment);
+        }
+        frame
+    }
+
+    /// Copies the frame into `buf`, truncating it if `buf` is too short, and returns
+    /// the number of bytes copied.
+    pub fn copy_to(&self, buf: &mut [u8]) -> usize {
+        let mut copied = 0;
+        for segment in self.segments() {
+            let len = segment.len().min(buf.len() - copied);
+            buf[copied..copied + len].copy_from_slice(&segment[..len]);
+            copied += len;
+            if copied == buf.len() {
+                break;
+            }
+        }
+        copied
+    }
+
+    /// Returns the whole frame, borrowed from the buffer if it is contiguous and copied
+    /// otherwise.
+    pub fn contiguous(&self) -> Cow<'_, [u8]> {
+        if self.is_contiguous() {
+            Cow::Borrowed(&self.head)
+        } else {
+            Cow::Owned(self.copy_contiguous())
+        }
+    }
+}
+
+impl<'ctx, C: Context> From<Payload<'ctx, C>> for Packet<'ctx, C> {
+    fn from(head: Payload<'ctx, C>) -> Self {
+        Self {
+            head,
+            chained: Vec::new(),
+            tail: Vec::new(),
+        }
+    }
+}
+
+/// Where a received buffer falls in its frame.
+#[cfg(any(test, feature = "af-xdp", feature = "netmap"))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Segment {
+    /// The first, or only, buffer of a frame.
+    #[default]
+    First,
+    /// A continuation of a frame whose first buffer was delivered.
+    Next,
+    /// A continuation of a frame whose first buffer was dropped.
+    Dropped,
+}
+
+/// Follows the frames of backends that split them over several buffers, so that only
+/// first buffers are filtered and continuations of dropped frames are dropped too.
+#[cfg(any(test, feature = "af-xdp", feature = "netmap"))]
+#[derive(Debug, Default)]
+pub(crate) struct SegmentTracker(Cell<Segment>);
+
+#[cfg(any(test, feature = "af-xdp", feature = "netmap"))]
+impl SegmentTracker {
+    /// Returns where the buffer being received falls in its frame.
+    #[inline]
+    pub(crate) fn current(&self) -> Segment {
+        self.0.get()
+    }
+
+    /// Moves past the buffer being received, which was `delivered` to the application
+    /// or dropped, and is followed by `more` buffers of the same frame or not.
+    #[inline]
+    pub(crate) fn advance(&self, delivered: bool, more: bool) {
+        self.0.set(match (more, delivered) {
+            (false, _) => Segment::First,
+            (true, true) => Segment::Next,
+            (true, false) => Segment::Dropped,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{BufferDesc, Token};
+
+    /// Buffers are leaked boxes, identified by their address.
+    #[derive(Clone)]
+    struct TestCtx;
+
+    impl Context for TestCtx {
+        fn pool_id(&self) -> u32 {
+            0
+        }
+
+        unsafe fn unsafe_buffer(&self, buf_idx: BufferDesc, size: usize) -> *mut [u8] {
+            std::ptr::slice_from_raw_parts_mut(usize::from(buf_idx) as *mut u8, size)
+        }
+
+        fn release(&self, _buf_idx: BufferDesc) {}
+    }
+
+    fn payload<'a>(ctx: &'a TestCtx, data: &[u8]) -> Payload<'a, TestCtx> {
+        let buf = Box::leak(data.to_vec().into_boxed_slice());
+        let token = Token::new(BufferDesc::from(buf.as_ptr() as usize), 0, buf.len() as u32);
+        token.consume(ctx)
+    }
+
+    #[test]
+    fn segments_in_order() {
+        let ctx = TestCtx;
+        let mut packet = Packet::from(payload(&ctx, b"head"));
+        assert!(packet.is_contiguous());
+        assert!(matches!(packet.contiguous(), Cow::Borrowed(b"head")));
+
+        let chained: &'static mut [u8] = Box::leak(b"-chained".to_vec().into_boxed_slice());
+        unsafe { packet.push_chained(NonNull::from(chained)) };
+        packet.push(payload(&ctx, b"-tail"));
+        assert_eq!(packet.num_segments(), 3);
+        assert_eq!(packet.len(), 17);
+        assert_eq!(packet.copy_contiguous(), b"head-chained-tail");
+        assert_eq!(&*packet.contiguous(), b"head-chained-tail");
+
+        let mut buf = [0u8; 10];
+        assert_eq!(packet.copy_to(&mut buf), 10);
+        assert_eq!(&buf, b"head-chain");
+    }
+
+    #[test]
+    fn tracks_segments() {
+        let tracker = SegmentTracker::default();
+        assert_eq!(tracker.current(), Segment::First);
+        tracker.advance(true, true);
+        assert_eq!(tracker.current(), Segment::Next);
+        tracker.advance(true, false);
+        assert_eq!(tracker.current(), Segment::First);
+        // The first buffer is filtered out: so is the rest of the frame.
+        tracker.advance(false, true);
+        assert_eq!(tracker.current(), Segment::Dropped);
+        tracker.advance(false, true);
+        assert_eq!(tracker.current(), Segment::Dropped);
+        tracker.advance(false, false);
+        assert_eq!(tracker.current(), Segment::First);
+    }
+}
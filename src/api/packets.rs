@@ -0,0 +1,103 @@
+//! Iteration over received packets.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use super::socket::Socket;
+use super::token::{Payload, Token};
+use crate::errors::Error;
+
+/// Packets returned by [`recv_until`](Socket::recv_until), in arrival order.
+pub type Batch<M> = Vec<(Token, M)>;
+
+/// Packets fetched from the socket at once.
+const BATCH_SIZE: usize = 32;
+/// How long an idle iterator waits for a packet before checking the stop flag again.
+const IDLE_WAIT: Duration = Duration::from_millis(100);
+
+/// Iterator over the packets received by a socket, returned by [`Socket::iter`] and
+/// [`Socket::iter_until`].
+///
+/// Packets are fetched in batches with [`recv_batch`](Socket::recv_batch); when the
+/// queue is empty the iterator waits for the next packet instead of ending. It ends
+/// when the stop flag is set, which is noticed within 100 ms on an idle socket, or on
+/// the first receive error, e.g. at the end of a capture file:
+///
+/// ```ignore
+/// for (packet, meta) in socket.iter_until(&term) {
+///     println!("{} bytes", packet.len());
+/// }
+/// ```
+pub struct Packets<'a, S: Socket> {
+    socket: &'a S,
+    stop: Option<&'a AtomicBool>,
+    batch: VecDeque<(Token, S::Metadata)>,
+    error: Option<Error>,
+}
+
+impl<'a, S: Socket> Packets<'a, S> {
+    pub(crate) fn new(socket: &'a S, stop: Option<&'a AtomicBool>) -> Self {
+        Self {
+            socket,
+            stop,
+            batch: VecDeque::with_capacity(BATCH_SIZE),
+            error: None,
+        }
+    }
+
+    /// Returns the error that ended the iteration, if any.
+    ///
+    /// Iterate with [`by_ref`](Iterator::by_ref) to inspect it after the loop.
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    fn stopped(&self) -> bool {
+        self.stop.is_some_and(|stop| stop.load(Ordering::Relaxed))
+    }
+
+    /// Refills the batch, waiting for a packet if the queue is empty.
+    fn refill(&mut self) -> Result<(), Error> {
+        match self.socket.recv_batch(BATCH_SIZE) {
+            Ok(batch) if !batch.is_empty() => {
+                self.batch.extend(batch);
+                return Ok(());
+            }
+            Ok(_) | Err(Error::NoPacket | Error::WouldBlock) => {}
+            Err(e) => return Err(e),
+        }
+        match self.socket.recv_token_timeout(IDLE_WAIT) {
+            Ok(pkt) => self.batch.push_back(pkt),
+            Err(Error::NoPacket | Error::WouldBlock) => {}
+            Err(e) => return Err(e),
+        }
+        Ok(())
+    }
+}
+
+impl<'a, S: Socket> Iterator for Packets<'a, S> {
+    type Item = (Payload<'a, S::Context>, S::Metadata);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.error.is_some() || self.stopped() {
+                return None;
+            }
+            if let Some((token, meta)) = self.batch.pop_front() {
+                return Some((token.consume(self.socket.context()), meta));
+            }
+            if let Err(e) = self.refill() {
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+impl<S: Socket> Drop for Packets<'_, S> {
+    fn drop(&mut self) {
+        for (token, _) in self.batch.drain(..) {
+            token.release(self.socket.context());
+        }
+    }
+}
@@ -0,0 +1,503 @@
+//! Writing pcapng capture files.
+//!
+//! Unlike plain pcap, a pcapng file keeps the packets of several interfaces apart and
+//! records their drop counters. A [`Writer`] describes each capture interface with an
+//! interface description block, writes the packets as enhanced packet blocks with
+//! nanosecond timestamps, and appends interface statistics blocks built from
+//! [`Socket::stats`]:
+//!
+//! ```ignore
+//! use nethuns_rs::api::pcapng::Writer;
+//!
+//! let mut writer = Writer::create("capture.pcapng")?.stats_interval(Duration::from_secs(1));
+//! let eth0 = writer.add_interface("eth0", 0)?;
+//! while !term.load(Ordering::Relaxed) {
+//!     if let Ok((packet, meta)) = socket.recv_timeout(Duration::from_millis(100)) {
+//!         writer.write_packet(eth0, &packet, &meta)?;
+//!     }
+//!     writer.poll_stats(eth0, &socket)?;
+//! }
+//! writer.write_stats(eth0, &socket)?;
+//! ```
+//!
+//! The timestamps are written as they are: sockets timestamping with the realtime
+//! clock, the default, give wall-clock times.
+//!
+//! Packets can carry annotations, such as a comment or the verdict of an analysis, as
+//! [`PacketOption`]s, which Wireshark shows along with the packet; custom blocks hold
+//! data of any other kind.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+use super::Result;
+use super::metadata::Metadata;
+use super::socket::Socket;
+use super::stats::Stats;
+use super::timestamp::Timestamp;
+
+const SECTION_HEADER_BLOCK: u32 = 0x0a0d_0d0a;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 1;
+const INTERFACE_STATISTICS_BLOCK: u32 = 5;
+const ENHANCED_PACKET_BLOCK: u32 = 6;
+const CUSTOM_BLOCK: u32 = 0x0000_0bad;
+const BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const LINKTYPE_ETHERNET: u16 = 1;
+
+const OPT_ENDOFOPT: u16 = 0;
+const OPT_COMMENT: u16 = 1;
+const OPT_CUSTOM_STR: u16 = 2988;
+const OPT_CUSTOM_BIN: u16 = 2989;
+const SHB_USERAPPL: u16 = 4;
+const IF_NAME: u16 = 2;
+const IF_TSRESOL: u16 = 9;
+const ISB_STARTTIME: u16 = 2;
+const ISB_ENDTIME: u16 = 3;
+const ISB_IFRECV: u16 = 4;
+const ISB_IFDROP: u16 = 5;
+const ISB_OSDROP: u16 = 7;
+const ISB_USRDELIV: u16 = 8;
+const EPB_FLAGS: u16 = 2;
+
+/// Capture interface of a [`Writer`], as returned by
+/// [`add_interface`](Writer::add_interface).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InterfaceId(u32);
+
+/// An option of an enhanced packet block, annotating a packet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PacketOption {
+    /// A comment, shown by Wireshark as `frame.comment` (`opt_comment`).
+    Comment(String),
+    /// The `epb_flags` word: direction, reception type, FCS length and link-layer
+    /// errors.
+    Flags(u32),
+    /// A custom string, e.g. a flow id or a verdict, under the private enterprise number
+    /// `pen` of its author (`opt_custom`, safe to copy).
+    CustomString {
+        /// Private enterprise number.
+        pen: u32,
+        /// UTF-8 value.
+        value: String,
+    },
+    /// Custom binary data under the private enterprise number `pen` (`opt_custom`,
+    /// safe to copy).
+    CustomBinary {
+        /// Private enterprise number.
+        pen: u32,
+        /// Value.
+        data: Vec<u8>,
+    },
+}
+
+impl PacketOption {
+    fn push(&self, body: &mut Vec<u8>) {
+        match self {
+            Self::Comment(comment) => option(body, OPT_COMMENT, comment.as_bytes()),
+            Self::Flags(flags) => option(body, EPB_FLAGS, &flags.to_le_bytes()),
+            Self::CustomString { pen, value } => {
+                custom_option(body, OPT_CUSTOM_STR, *pen, value.as_bytes())
+            }
+            Self::CustomBinary { pen, data } => custom_option(body, OPT_CUSTOM_BIN, *pen, data),
+        }
+    }
+}
+
+/// Callback of [`Writer::annotate`].
+type Annotator = Box<dyn FnMut(InterfaceId, &[u8]) -> Vec<PacketOption> + Send>;
+
+struct Interface {
+    started: Timestamp,
+    last_stats: Option<Instant>,
+}
+
+/// Writes a pcapng capture file holding Ethernet frames from one or more interfaces.
+///
+/// Every write goes through to the underlying writer, buffered by
+/// [`create`](Writer::create); call [`flush`](Writer::flush) to push it out.
+pub struct Writer<W: Write> {
+    out: W,
+    interfaces: Vec<Interface>,
+    stats_interval: Option<Duration>,
+    annotator: Option<Annotator>,
+}
+
+impl Writer<BufWriter<File>> {
+    /// Creates the capture file `path`, truncating it if it exists.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Writer<zstd::stream::AutoFinishEncoder<'static, File>> {
+    /// Creates the capture file `path`, conventionally named `*.pcapng.zst`, compressed
+    /// with zstd at `level` (1 to 22, or 0 for the default of 3) as it is written.
+    ///
+    /// The compressed stream is finished when the writer is dropped. Each
+    /// [`flush`](Writer::flush) ends a zstd block, so that what was written so far can
+    /// be decompressed, at some cost in compression ratio.
+    pub fn create_zstd(path: impl AsRef<Path>, level: i32) -> Result<Self> {
+        Self::new(zstd::Encoder::new(File::create(path)?, level)?.auto_finish())
+    }
+}
+
+impl<W: Write> Writer<W> {
+    /// Starts a capture file on `out` by writing its section header.
+    pub fn new(mut out: W) -> Result<Self> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        body.extend_from_slice(&1u16.to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes());
+        // Unknown section length.
+        body.extend_from_slice(&(-1i64).to_le_bytes());
+        option(&mut body, SHB_USERAPPL, b"nethuns-rs");
+        option(&mut body, OPT_ENDOFOPT, &[]);
+        write_block(&mut out, SECTION_HEADER_BLOCK, &body)?;
+        Ok(Self {
+            out,
+            interfaces: Vec::new(),
+            stats_interval: None,
+            annotator: None,
+        })
+    }
+
+    /// Sets how often [`poll_stats`](Writer::poll_stats) writes statistics.
+    pub fn stats_interval(mut self, interval: Duration) -> Self {
+        self.stats_interval = Some(interval);
+        self
+    }
+
+    /// Annotates each packet written with [`write_packet`](Writer::write_packet) with
+    /// the options `f` returns for it, e.g. a comment on the packets an analysis
+    /// flagged.
+    ///
+    /// ```ignore
+    /// let writer = Writer::create("alerts.pcapng")?.annotate(|_, packet| {
+    ///     match ids.check(packet) {
+    ///         Some(rule) => vec![PacketOption::Comment(format!("matched {rule}"))],
+    ///         None => Vec::new(),
+    ///     }
+    /// });
+    /// ```
+    pub fn annotate(
+        mut self,
+        f: impl FnMut(InterfaceId, &[u8]) -> Vec<PacketOption> + Send + 'static,
+    ) -> Self {
+        self.annotator = Some(Box::new(f));
+        self
+    }
+
+    /// Describes a capture interface named `name`, whose frames are captured up to
+    /// `snaplen` bytes (0 for no limit), and returns its identifier.
+    pub fn add_interface(&mut self, name: &str, snaplen: u32) -> Result<InterfaceId> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes());
+        body.extend_from_slice(&snaplen.to_le_bytes());
+        option(&mut body, IF_NAME, name.as_bytes());
+        // Nanosecond timestamps.
+        option(&mut body, IF_TSRESOL, &[9]);
+        option(&mut body, OPT_ENDOFOPT, &[]);
+        write_block(&mut self.out, INTERFACE_DESCRIPTION_BLOCK, &body)?;
+        self.interfaces.push(Interface {
+            started: now(),
+            last_stats: None,
+        });
+        Ok(InterfaceId(self.interfaces.len() as u32 - 1))
+    }
+
+    /// Writes a packet received on `interface` with `meta`, annotated by the
+    /// [`annotate`](Writer::annotate) callback if any.
+    ///
+    /// Packets without a timestamp are written at 0.
+    pub fn write_packet<M: Metadata>(
+        &mut self,
+        interface: InterfaceId,
+        packet: &[u8],
+        meta: &M,
+    ) -> Result<()> {
+        self.check(interface)?;
+        let options = match &mut self.annotator {
+            Some(f) => f(interface, packet),
+            None => Vec::new(),
+        };
+        self.write_packet_with(interface, packet, meta, &options)
+    }
+
+    /// Writes a packet received on `interface` with `meta`, annotated with `options`.
+    pub fn write_packet_with<M: Metadata>(
+        &mut self,
+        interface: InterfaceId,
+        packet: &[u8],
+        meta: &M,
+        options: &[PacketOption],
+    ) -> Result<()> {
+        self.check(interface)?;
+        let ts = meta.timestamp().unwrap_or_default();
+        let mut body = Vec::with_capacity(20 + packet.len() + 3);
+        body.extend_from_slice(&interface.0.to_le_bytes());
+        push_timestamp(&mut body, ts);
+        body.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        body.extend_from_slice(&meta.wirelen().max(packet.len() as u32).to_le_bytes());
+        body.extend_from_slice(packet);
+        pad(&mut body);
+        if !options.is_empty() {
+            for opt in options {
+                opt.push(&mut body);
+            }
+            option(&mut body, OPT_ENDOFOPT, &[]);
+        }
+        Ok(write_block(&mut self.out, ENHANCED_PACKET_BLOCK, &body)?)
+    }
+
+    /// Writes a custom block holding `data` under the private enterprise number `pen`
+    /// of its author, which readers that do not know it skip (and may copy along).
+    pub fn write_custom_block(&mut self, pen: u32, data: &[u8]) -> Result<()> {
+        let mut body = Vec::with_capacity(4 + data.len() + 3);
+        body.extend_from_slice(&pen.to_le_bytes());
+        body.extend_from_slice(data);
+        pad(&mut body);
+        Ok(write_block(&mut self.out, CUSTOM_BLOCK, &body)?)
+    }
+
+    /// Writes the counters of `socket`, capturing on `interface`, as they are now.
+    pub fn write_stats<S: Socket>(&mut self, interface: InterfaceId, socket: &S) -> Result<()> {
+        let stats = socket.stats()?;
+        let dropped = socket.dropped();
+        self.write_counters(interface, &stats, dropped.no_buffers)
+    }
+
+    /// Writes the counters of `socket` like [`write_stats`](Writer::write_stats) if the
+    /// [`stats_interval`](Writer::stats_interval) has elapsed since they were last
+    /// written for `interface`, and returns whether it did.
+    pub fn poll_stats<S: Socket>(&mut self, interface: InterfaceId, socket: &S) -> Result<bool> {
+        self.check(interface)?;
+        let Some(interval) = self.stats_interval else {
+            return Ok(false);
+        };
+        let last = self.interfaces[interface.0 as usize].last_stats;
+        if last.is_some_and(|last| last.elapsed() < interval) {
+            return Ok(false);
+        }
+        self.write_stats(interface, socket)?;
+        Ok(true)
+    }
+
+    /// Writes an interface statistics block: `stats.rx_dropped` is reported as dropped
+    /// by the interface and `os_dropped` as dropped by the capture software.
+    pub fn write_counters(
+        &mut self,
+        interface: InterfaceId,
+        stats: &Stats,
+        os_dropped: u64,
+    ) -> Result<()> {
+        self.check(interface)?;
+        let now = now();
+        let iface = &mut self.interfaces[interface.0 as usize];
+        let mut body = Vec::new();
+        body.extend_from_slice(&interface.0.to_le_bytes());
+        push_timestamp(&mut body, now);
+        option(&mut body, ISB_STARTTIME, &timestamp_bytes(iface.started));
+        option(&mut body, ISB_ENDTIME, &timestamp_bytes(now));
+        let received = stats.rx_packets + stats.rx_dropped + os_dropped;
+        option(&mut body, ISB_IFRECV, &received.to_le_bytes());
+        option(&mut body, ISB_IFDROP, &stats.rx_dropped.to_le_bytes());
+        option(&mut body, ISB_OSDROP, &os_dropped.to_le_bytes());
+        option(&mut body, ISB_USRDELIV, &stats.rx_packets.to_le_bytes());
+        option(&mut body, OPT_ENDOFOPT, &[]);
+        write_block(&mut self.out, INTERFACE_STATISTICS_BLOCK, &body)?;
+        iface.last_stats = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.out.flush()?)
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn check(&self, interface: InterfaceId) -> io::Result<()> {
+        if interface.0 as usize >= self.interfaces.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("no interface {} in the capture file", interface.0),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn write_block(out: &mut impl Write, kind: u32, body: &[u8]) -> io::Result<()> {
+    let len = (12 + body.len()) as u32;
+    out.write_all(&kind.to_le_bytes())?;
+    out.write_all(&len.to_le_bytes())?;
+    out.write_all(body)?;
+    out.write_all(&len.to_le_bytes())
+}
+
+/// Appends an option, padded to 32 bits.
+fn option(body: &mut Vec<u8>, code: u16, value: &[u8]) {
+    body.extend_from_slice(&code.to_le_bytes());
+    body.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    body.extend_from_slice(value);
+    pad(body);
+}
+
+/// Appends a custom option of the private enterprise number `pen`, padded to 32 bits.
+fn custom_option(body: &mut Vec<u8>, code: u16, pen: u32, value: &[u8]) {
+    body.extend_from_slice(&code.to_le_bytes());
+    body.extend_from_slice(&(4 + value.len() as u16).to_le_bytes());
+    body.extend_from_slice(&pen.to_le_bytes());
+    body.extend_from_slice(value);
+    pad(body);
+}
+
+fn pad(body: &mut Vec<u8>) {
+    body.resize(body.len().next_multiple_of(4), 0);
+}
+
+/// Appends a timestamp as its high and low 32 bits.
+fn push_timestamp(body: &mut Vec<u8>, ts: Timestamp) {
+    body.extend_from_slice(&timestamp_bytes(ts));
+}
+
+fn timestamp_bytes(ts: Timestamp) -> [u8; 8] {
+    let nanos = ts.as_nanos();
+    let mut bytes = [0; 8];
+    bytes[..4].copy_from_slice(&((nanos >> 32) as u32).to_le_bytes());
+    bytes[4..].copy_from_slice(&(nanos as u32).to_le_bytes());
+    bytes
+}
+
+fn now() -> Timestamp {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .into()
+}
+
+#[cfg(all(test, feature = "pcap"))]
+mod tests {
+    use super::*;
+    use crate::api::BasicMeta;
+    use pcap_parser::pcapng::Block;
+    use pcap_parser::{PcapBlockOwned, PcapError, create_reader};
+
+    fn meta(len: u32) -> BasicMeta {
+        BasicMeta {
+            timestamp: Some(Timestamp::from_nanos(1_700_000_000_123_456_789)),
+            caplen: len,
+            wirelen: len,
+        }
+    }
+
+    #[test]
+    fn writes_readable_file() {
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        let eth0 = writer.add_interface("eth0", 0).unwrap();
+        let eth1 = writer.add_interface("eth1", 128).unwrap();
+        writer.write_packet(eth1, &[1; 61], &meta(1500)).unwrap();
+        let stats = Stats {
+            rx_packets: 10,
+            rx_dropped: 3,
+            ..Stats::default()
+        };
+        writer.write_counters(eth0, &stats, 2).unwrap();
+        assert!(writer.write_packet(InterfaceId(2), &[], &meta(0)).is_err());
+        let file = writer.into_inner();
+
+        let mut reader = create_reader(65536, file.as_slice()).unwrap();
+        let mut blocks = Vec::new();
+        loop {
+            match reader.next() {
+                Ok((offset, PcapBlockOwned::NG(block))) => {
+                    blocks.push(match block {
+                        Block::SectionHeader(_) => "shb".to_string(),
+                        Block::InterfaceDescription(idb) => format!("idb {}", idb.snaplen),
+                        Block::EnhancedPacket(epb) => {
+                            assert_eq!(epb.data[..epb.caplen as usize], [1; 61]);
+                            assert_eq!(epb.origlen, 1500);
+                            let ts = (epb.ts_high as u64) << 32 | epb.ts_low as u64;
+                            assert_eq!(ts, 1_700_000_000_123_456_789);
+                            format!("epb {}", epb.if_id)
+                        }
+                        Block::InterfaceStatistics(isb) => {
+                            assert_eq!(isb.isb_ifdrop().unwrap().unwrap(), 3);
+                            assert_eq!(isb.isb_osdrop().unwrap().unwrap(), 2);
+                            assert_eq!(isb.isb_ifrecv().unwrap().unwrap(), 15);
+                            format!("isb {}", isb.if_id)
+                        }
+                        _ => "other".to_string(),
+                    });
+                    reader.consume(offset);
+                }
+                Ok(_) => panic!("not a pcapng file"),
+                Err(PcapError::Eof) => break,
+                Err(PcapError::Incomplete(_)) => reader.refill().unwrap(),
+                Err(e) => panic!("{e:?}"),
+            }
+        }
+        assert_eq!(blocks, ["shb", "idb 0", "idb 128", "epb 1", "isb 0"]);
+    }
+
+    #[test]
+    fn writes_annotations() {
+        let mut writer = Writer::new(Vec::new())
+            .unwrap()
+            .annotate(|_, packet| vec![PacketOption::Comment(format!("{} bytes", packet.len()))]);
+        let eth0 = writer.add_interface("eth0", 0).unwrap();
+        writer.write_packet(eth0, &[1; 60], &meta(60)).unwrap();
+        let verdict = PacketOption::CustomString {
+            pen: 32473,
+            value: "drop".to_string(),
+        };
+        writer
+            .write_packet_with(eth0, &[2; 60], &meta(60), &[verdict])
+            .unwrap();
+        writer.write_custom_block(32473, b"summary").unwrap();
+        let file = writer.into_inner();
+
+        let mut reader = create_reader(65536, file.as_slice()).unwrap();
+        let mut seen = Vec::new();
+        loop {
+            match reader.next() {
+                Ok((offset, PcapBlockOwned::NG(block))) => {
+                    match block {
+                        Block::EnhancedPacket(epb) => {
+                            let opt = &epb.options[0];
+                            seen.push((opt.code.0, opt.as_bytes().unwrap().to_vec()));
+                        }
+                        Block::Custom(cb) => {
+                            assert_eq!(cb.pen, 32473);
+                            assert!(cb.data.starts_with(b"summary"));
+                            seen.push((0, Vec::new()));
+                        }
+                        _ => {}
+                    }
+                    reader.consume(offset);
+                }
+                Ok(_) => panic!("not a pcapng file"),
+                Err(PcapError::Eof) => break,
+                Err(PcapError::Incomplete(_)) => reader.refill().unwrap(),
+                Err(e) => panic!("{e:?}"),
+            }
+        }
+        let mut custom = 32473u32.to_le_bytes().to_vec();
+        custom.extend_from_slice(b"drop");
+        assert_eq!(
+            seen,
+            [
+                (OPT_COMMENT, b"60 bytes".to_vec()),
+                (OPT_CUSTOM_STR, custom),
+                (0, Vec::new())
+            ]
+        );
+    }
+}
@@ -0,0 +1,294 @@
+//! Packet processing pipelines assembled from reusable stages.
+//!
+//! A [`Pipeline`] receives a burst of packets from a socket and hands the whole batch
+//! to each of its [`Stage`]s in turn. A stage may drop packets from the batch, rewrite
+//! them, or send them out; whatever is left at the end of the chain is released.
+//! Being built per socket, a pipeline runs on the thread of its RX queue:
+//!
+//! ```ignore
+//! use nethuns_rs::api::pipeline::{Count, Forward, Pipeline, Tee};
+//!
+//! let group = af_xdp::Sock::create_group("eth0", flags)?;
+//! let counted = Count::new(); // a clone kept here reads the total of every queue
+//! api::spawn_per_queue(group, move |queue, socket| {
+//!     let mut pipeline = Pipeline::new()
+//!         .stage(Filter::new("tcp port 443")?)
+//!         .stage(counted.clone())
+//!         .stage(Tee::new(pcap::Savefile::create(format!("q{queue}.pcap"))?))
+//!         .stage(Forward::new(af_xdp::Sock::create("eth1", Some(queue), flags)?));
+//!     pipeline.run(&socket, 64, &term)
+//! })?;
+//! ```
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use super::Result;
+use super::context::Context;
+use super::filter::Filter;
+use super::mirror::MirrorSink;
+use super::rx_loop::IDLE_WAIT;
+use super::socket::Socket;
+use super::token::{Payload, Token};
+use crate::errors::Error;
+
+/// A batch of received packets, as handed from one [`Stage`] to the next.
+pub type PacketBatch<'ctx, C, M> = Vec<(Payload<'ctx, C>, M)>;
+
+/// A step of a [`Pipeline`].
+pub trait Stage<C: Context, M> {
+    /// Processes a batch of packets.
+    ///
+    /// Packets removed from `batch` are released, or handed over if the stage sent
+    /// them; the remaining ones go on to the next stage. An error stops the pipeline
+    /// for this batch and releases its packets.
+    fn process<'ctx>(&mut self, batch: &mut PacketBatch<'ctx, C, M>) -> Result<()>;
+}
+
+/// Keeps the packets matching a BPF filter.
+impl<C: Context, M> Stage<C, M> for Filter {
+    fn process<'ctx>(&mut self, batch: &mut PacketBatch<'ctx, C, M>) -> Result<()> {
+        batch.retain(|(packet, _)| self.matches(packet));
+        Ok(())
+    }
+}
+
+/// Keeps the packets for which a predicate returns `true`.
+pub struct Select<F>(pub F);
+
+impl<C: Context, M, F: FnMut(&[u8], &M) -> bool> Stage<C, M> for Select<F> {
+    fn process<'ctx>(&mut self, batch: &mut PacketBatch<'ctx, C, M>) -> Result<()> {
+        batch.retain(|(packet, meta)| (self.0)(packet, meta));
+        Ok(())
+    }
+}
+
+/// Modifies every packet in place, e.g. to rewrite its addresses before forwarding.
+pub struct Rewrite<F>(pub F);
+
+impl<C: Context, M, F: FnMut(&mut [u8], &mut M)> Stage<C, M> for Rewrite<F> {
+    fn process<'ctx>(&mut self, batch: &mut PacketBatch<'ctx, C, M>) -> Result<()> {
+        for (packet, meta) in batch.iter_mut() {
+            (self.0)(packet, meta);
+        }
+        Ok(())
+    }
+}
+
+/// Counts the packets and bytes going through.
+///
+/// Clones share the same totals, so a clone kept aside reads the counts of a stage
+/// owned by a pipeline, from any thread.
+#[derive(Clone, Debug, Default)]
+pub struct Count {
+    packets: Arc<AtomicU64>,
+    bytes: Arc<AtomicU64>,
+}
+
+impl Count {
+    /// Creates a counter starting at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of packets counted.
+    pub fn packets(&self) -> u64 {
+        self.packets.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of bytes counted.
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+}
+
+impl<C: Context, M> Stage<C, M> for Count {
+    fn process<'ctx>(&mut self, batch: &mut PacketBatch<'ctx, C, M>) -> Result<()> {
+        let packets = batch.len() as u64;
+        let bytes: usize = batch.iter().map(|(packet, _)| packet.len()).sum();
+        self.packets.fetch_add(packets, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Copies every packet to a [`MirrorSink`], i.e. a socket or a capture file, and lets
+/// the batch through.
+///
+/// Like [`Mirror`](super::Mirror), it never stalls the pipeline: copies the sink
+/// cannot take are counted in [`dropped`](Tee::dropped).
+pub struct Tee<T> {
+    sink: T,
+    dropped: u64,
+}
+
+impl<T: MirrorSink> Tee<T> {
+    /// Copies the packets to `sink`.
+    pub fn new(sink: T) -> Self {
+        Self { sink, dropped: 0 }
+    }
+
+    /// Returns the number of copies the sink could not take.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+impl<C: Context, M: super::Metadata, T: MirrorSink> Stage<C, M> for Tee<T> {
+    fn process<'ctx>(&mut self, batch: &mut PacketBatch<'ctx, C, M>) -> Result<()> {
+        for (packet, meta) in batch.iter() {
+            if self.sink.mirror(packet, meta).is_err() {
+                self.dropped += 1;
+            }
+        }
+        self.sink.flush_mirror();
+        Ok(())
+    }
+}
+
+/// Sends the whole batch out of a socket, without copying when the backend can (see
+/// [`forward`](super::forward)), and flushes it.
+///
+/// Ends the batch: later stages see no packet. Packets the socket cannot take are
+/// counted in [`dropped`](Forward::dropped).
+pub struct Forward<S> {
+    socket: S,
+    dropped: u64,
+}
+
+impl<S: Socket> Forward<S> {
+    /// Sends the packets out of `socket`.
+    pub fn new(socket: S) -> Self {
+        Self { socket, dropped: 0 }
+    }
+
+    /// Returns the output socket.
+    pub fn socket(&self) -> &S {
+        &self.socket
+    }
+
+    /// Returns the number of packets the socket could not take.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+impl<C: Context, M, S: Socket> Stage<C, M> for Forward<S> {
+    fn process<'ctx>(&mut self, batch: &mut PacketBatch<'ctx, C, M>) -> Result<()> {
+        for (packet, _) in batch.drain(..) {
+            if self.socket.send_payload(packet).is_err() {
+                self.dropped += 1;
+            }
+        }
+        self.socket.flush();
+        Ok(())
+    }
+}
+
+/// A chain of [`Stage`]s run over every batch received from a socket.
+pub struct Pipeline<C: Context, M> {
+    stages: Vec<Box<dyn Stage<C, M>>>,
+    tokens: Vec<(Token, M)>,
+}
+
+impl<C: Context, M> Pipeline<C, M> {
+    /// Creates an empty pipeline, which releases every packet.
+    pub fn new() -> Self {
+        Self {
+            stages: Vec::new(),
+            tokens: Vec::new(),
+        }
+    }
+
+    /// Appends `stage` to the chain.
+    pub fn stage(mut self, stage: impl Stage<C, M> + 'static) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Returns the number of stages.
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    /// Returns `true` if the pipeline has no stage.
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Runs `batch` through the stages, stopping early once it is empty.
+    pub fn process<'ctx>(&mut self, batch: &mut PacketBatch<'ctx, C, M>) -> Result<()> {
+        for stage in &mut self.stages {
+            if batch.is_empty() {
+                break;
+            }
+            stage.process(batch)?;
+        }
+        Ok(())
+    }
+
+    /// Receives at most `budget` ready packets from `socket`, runs them through the
+    /// stages and returns how many were received.
+    ///
+    /// Never blocks; same errors as [`Socket::dispatch`], plus those of the stages.
+    pub fn poll<S>(&mut self, socket: &S, budget: usize) -> Result<usize>
+    where
+        S: Socket<Context = C, Metadata = M>,
+    {
+        let tokens = &mut self.tokens;
+        let count = socket.dispatch(budget, |packet, meta| {
+            tokens.push((packet.into_token(), meta))
+        })?;
+        self.run_tokens(socket.context())?;
+        Ok(count)
+    }
+
+    /// Polls `socket` with the given `budget` until `stop` is set, and returns the
+    /// number of packets received.
+    ///
+    /// When no packet is ready the loop waits for one like
+    /// [`run_rx_loop`](super::run_rx_loop), noticing `stop` within 100 ms, and it ends
+    /// cleanly at the end of a capture file. The first receive or stage error is
+    /// returned.
+    pub fn run<S>(&mut self, socket: &S, budget: usize, stop: &AtomicBool) -> Result<u64>
+    where
+        S: Socket<Context = C, Metadata = M>,
+    {
+        let mut packets = 0;
+        while !stop.load(Ordering::Relaxed) {
+            let res = match self.poll(socket, budget) {
+                Ok(0) => socket.recv_token_timeout(IDLE_WAIT).and_then(|packet| {
+                    self.tokens.push(packet);
+                    self.run_tokens(socket.context()).map(|()| 1)
+                }),
+                res => res,
+            };
+            match res {
+                Ok(n) => packets += n as u64,
+                Err(Error::WouldBlock | Error::NoPacket) => {}
+                #[cfg(feature = "pcap")]
+                Err(Error::Pcap(pcap::Error::NoMorePackets)) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(packets)
+    }
+
+    fn run_tokens(&mut self, ctx: &C) -> Result<()> {
+        if self.tokens.is_empty() {
+            return Ok(());
+        }
+        let mut batch: PacketBatch<'_, C, M> = self
+            .tokens
+            .drain(..)
+            .map(|(token, meta)| (token.consume(ctx), meta))
+            .collect();
+        self.process(&mut batch)
+    }
+}
+
+impl<C: Context, M> Default for Pipeline<C, M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
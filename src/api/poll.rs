@@ -0,0 +1,155 @@
+//! Readiness helpers for file-descriptor backed sockets.
+
+use std::io;
+use std::os::fd::{AsRawFd, RawFd};
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+use std::time::{Duration, Instant};
+
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+use super::Result;
+use super::socket::Socket;
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+use super::token::Token;
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+use crate::errors::Error;
+
+/// A socket backed by a file descriptor that can be registered with `poll`/`epoll`.
+///
+/// The descriptor signals RX readiness with `POLLIN`/`EPOLLIN`; what that means depends
+/// on the backend:
+///
+/// - **pcap**: the capture handle is readable (packets may still be buffered inside
+///   libpcap, so drain with [`try_recv`](Socket::try_recv) until
+///   [`Error::WouldBlock`]). Offline and remote captures have no descriptor that
+///   signals readiness: [`poll_fd`](Pollable::poll_fd) fails on them.
+/// - **AF_XDP**: the RX ring holds descriptors. Writability (`POLLOUT`) reports free
+///   TX ring entries.
+/// - **netmap**: polling the port performs an RX sync, and the descriptor is readable
+///   once any RX ring has slots available.
+///
+/// DPDK sockets are purely poll-mode and do not implement this trait.
+///
+/// With the `mio` feature, every `Pollable` backend socket also implements
+/// `mio::event::Source` and can be registered directly with a `mio::Poll`.
+pub trait Pollable: Socket + AsRawFd {
+    /// Returns the descriptor to register with an event loop.
+    ///
+    /// Fails with `Unsupported` if the socket has none that signals readiness;
+    /// registering [`as_raw_fd`](AsRawFd::as_raw_fd) would then fail, or never wake up.
+    fn poll_fd(&self) -> io::Result<RawFd> {
+        Ok(self.as_raw_fd())
+    }
+}
+
+#[cfg(all(
+    feature = "mio",
+    any(feature = "pcap", feature = "af-xdp", feature = "netmap")
+))]
+/// Implements `mio::event::Source` for a [`Pollable`] socket, on its
+/// [`poll_fd`](Pollable::poll_fd).
+macro_rules! mio_source {
+    ($sock:ty) => {
+        impl mio::event::Source for $sock {
+            fn register(
+                &mut self,
+                registry: &mio::Registry,
+                token: mio::Token,
+                interests: mio::Interest,
+            ) -> io::Result<()> {
+                let fd = Pollable::poll_fd(self)?;
+                mio::unix::SourceFd(&fd).register(registry, token, interests)
+            }
+
+            fn reregister(
+                &mut self,
+                registry: &mio::Registry,
+                token: mio::Token,
+                interests: mio::Interest,
+            ) -> io::Result<()> {
+                let fd = Pollable::poll_fd(self)?;
+                mio::unix::SourceFd(&fd).reregister(registry, token, interests)
+            }
+
+            fn deregister(&mut self, registry: &mio::Registry) -> io::Result<()> {
+                mio::unix::SourceFd(&Pollable::poll_fd(self)?).deregister(registry)
+            }
+        }
+    };
+}
+
+#[cfg(all(feature = "mio", feature = "af-xdp"))]
+mio_source!(crate::af_xdp::Sock);
+#[cfg(all(feature = "mio", feature = "netmap"))]
+mio_source!(crate::netmap::Sock);
+#[cfg(all(feature = "mio", feature = "pcap"))]
+mio_source!(crate::pcap::Sock);
+
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+/// Waits until `fd` is readable or `timeout` elapses.
+///
+/// Returns `Ok(false)` on timeout. An interrupted wait is reported as ready so that
+/// the caller simply retries the receive.
+pub(crate) fn wait_readable(fd: RawFd, timeout: Duration) -> io::Result<bool> {
+    let mut pfd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    // Round up so that sub-millisecond timeouts still wait instead of spinning.
+    let ms = timeout.as_nanos().div_ceil(1_000_000).min(i32::MAX as u128) as libc::c_int;
+    match unsafe { libc::poll(&mut pfd, 1, ms) } {
+        0 => Ok(false),
+        n if n > 0 => Ok(true),
+        _ => {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                Ok(true)
+            } else {
+                Err(err)
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+/// Shared `recv_token_timeout` for backends whose RX readiness is signalled on `fd`.
+pub(crate) fn recv_token_polling<S: Socket>(
+    sock: &S,
+    fd: RawFd,
+    timeout: Duration,
+) -> Result<(Token, S::Metadata)> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match sock.try_recv_token() {
+            Err(Error::WouldBlock) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() || !wait_readable(fd, remaining)? {
+                    return Err(Error::WouldBlock);
+                }
+            }
+            res => return res,
+        }
+    }
+}
+
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wait_readable_pipe() {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let [rd, wr] = fds;
+
+        assert!(!wait_readable(rd, Duration::from_millis(5)).unwrap());
+        assert_eq!(unsafe { libc::write(wr, b"x".as_ptr().cast(), 1) }, 1);
+        assert!(wait_readable(rd, Duration::from_millis(5)).unwrap());
+
+        unsafe {
+            libc::close(rd);
+            libc::close(wr);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Dropping root privileges once the sockets are open.
+
+use std::ffi::CString;
+use std::io;
+
+use super::Result;
+
+const CAP_NET_ADMIN: u32 = 12;
+const CAP_NET_RAW: u32 = 13;
+const LINUX_CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+
+#[repr(C)]
+struct CapHeader {
+    version: u32,
+    pid: libc::c_int,
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct CapData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32,
+}
+
+/// Switches the process to `user` and `group`, or to the primary group of `user` if
+/// `group` is `None`.
+///
+/// Meant to be called by capture tools started as root, once their sockets are open.
+/// The supplementary groups are reduced to `group`. With `keep_caps`, `CAP_NET_RAW`
+/// and `CAP_NET_ADMIN` stay effective and every other capability is dropped; without,
+/// the process keeps none. Fails with `NotFound` if the user or group does not exist.
+///
+/// Sockets created before the drop keep working on every backend; what changes is what
+/// can still be done afterwards:
+///
+/// - **pcap**: receiving, sending and `set_filter` keep working. Opening new devices
+///   needs `keep_caps`; capture files only need the new user to have access.
+/// - **AF_XDP**: the bound sockets, their UMEM and the XDP program attached at creation
+///   keep working, as does `ifctl` with `keep_caps`. Creating new sockets, which loads
+///   programs and updates BPF maps, also needs capabilities that are not kept, such as
+///   `CAP_BPF` or `CAP_SYS_ADMIN`, so create every queue beforehand.
+/// - **netmap**: the open ports and their mapped rings keep working. New ports need
+///   the new user to have access to `/dev/netmap`.
+/// - **DPDK**: the ports started by the EAL keep working, the hugepages and devices
+///   being mapped already. Starting or reconfiguring ports needs access to the VFIO
+///   or UIO devices.
+///
+/// Setting the promiscuous or all-multicast mode, joining multicast groups and the
+/// [`ifctl`](super::ifctl) calls need `keep_caps`.
+///
+/// Capabilities are per thread: call it before spawning the workers, so that they
+/// inherit the reduced set.
+pub fn drop_privileges(user: &str, group: Option<&str>, keep_caps: bool) -> Result<()> {
+    let (uid, primary_gid) = lookup_user(user)?;
+    let gid = match group {
+        Some(group) => lookup_group(group)?,
+        None => primary_gid,
+    };
+
+    // SAFETY: plain system calls on integers and a valid one-element array.
+    unsafe {
+        if keep_caps && libc::prctl(libc::PR_SET_KEEPCAPS, 1, 0, 0, 0) != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        if libc::setgroups(1, &gid) != 0 || libc::setgid(gid) != 0 || libc::setuid(uid) != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+    }
+
+    if keep_caps {
+        let kept = (1 << CAP_NET_ADMIN) | (1 << CAP_NET_RAW);
+        set_capabilities(kept)?;
+        // SAFETY: plain system call.
+        unsafe { libc::prctl(libc::PR_SET_KEEPCAPS, 0, 0, 0, 0) };
+    }
+
+    // Make sure the drop cannot be undone.
+    // SAFETY: plain system call.
+    if uid != 0 && unsafe { libc::setuid(0) } == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "root privileges could be regained after dropping them",
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Sets the effective and permitted capabilities of the calling thread to `caps`
+/// (the low 32 capabilities), clearing the inheritable set.
+fn set_capabilities(caps: u32) -> io::Result<()> {
+    let mut header = CapHeader {
+        version: LINUX_CAPABILITY_VERSION_3,
+        pid: 0,
+    };
+    let mut data = [CapData::default(); 2];
+    data[0].effective = caps;
+    data[0].permitted = caps;
+    // SAFETY: the header and the two data structs match the version 3 ABI.
+    let ret = unsafe { libc::syscall(libc::SYS_capset, &mut header, data.as_mut_ptr()) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Returns the uid and primary gid of `name`.
+fn lookup_user(name: &str) -> io::Result<(libc::uid_t, libc::gid_t)> {
+    let cname = c_name(name)?;
+    let mut buf = vec![0u8; 1024];
+    loop {
+        // SAFETY: zeroed passwd is valid; `buf` outlives the call and is only read
+        // through `pwd` before being reused.
+        let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        let ret = unsafe {
+            libc::getpwnam_r(
+                cname.as_ptr(),
+                &mut pwd,
+                buf.as_mut_ptr().cast(),
+                buf.len(),
+                &mut result,
+            )
+        };
+        match ret {
+            libc::ERANGE => buf.resize(buf.len() * 2, 0),
+            0 if result.is_null() => return Err(not_found("user", name)),
+            0 => return Ok((pwd.pw_uid, pwd.pw_gid)),
+            err => return Err(io::Error::from_raw_os_error(err)),
+        }
+    }
+}
+
+/// Returns the gid of `name`.
+fn lookup_group(name: &str) -> io::Result<libc::gid_t> {
+    let cname = c_name(name)?;
+    let mut buf = vec![0u8; 1024];
+    loop {
+        // SAFETY: as in `lookup_user`.
+        let mut grp: libc::group = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        let ret = unsafe {
+            libc::getgrnam_r(
+                cname.as_ptr(),
+                &mut grp,
+                buf.as_mut_ptr().cast(),
+                buf.len(),
+                &mut result,
+            )
+        };
+        match ret {
+            libc::ERANGE => buf.resize(buf.len() * 2, 0),
+            0 if result.is_null() => return Err(not_found("group", name)),
+            0 => return Ok(grp.gr_gid),
+            err => return Err(io::Error::from_raw_os_error(err)),
+        }
+    }
+}
+
+fn c_name(name: &str) -> io::Result<CString> {
+    CString::new(name).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid name {name:?}"),
+        )
+    })
+}
+
+fn not_found(kind: &str, name: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("no such {kind}: {name}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looks_up_root() {
+        assert_eq!(lookup_user("root").unwrap(), (0, 0));
+        assert_eq!(lookup_group("root").unwrap(), 0);
+    }
+
+    #[test]
+    fn unknown_user_is_not_found() {
+        let err = drop_privileges("nethuns-no-such-user", None, false).unwrap_err();
+        let crate::errors::Error::Generic(err) = err else {
+            panic!("unexpected error {err:?}");
+        };
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        let err = lookup_group("nethuns-no-such-group").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    /// Runs `f` in a forked child, so that the test process keeps its privileges.
+    ///
+    /// A panic in `f` fails the check instead of unwinding into the copy of the test
+    /// harness in the child.
+    #[cfg(any(feature = "pcap", feature = "af-xdp"))]
+    fn in_child(f: impl FnOnce() -> bool) -> bool {
+        use std::panic::{AssertUnwindSafe, catch_unwind};
+
+        // SAFETY: the child only runs `f`, catching its panics, and exits with `_exit`.
+        unsafe {
+            match libc::fork() {
+                -1 => panic!("fork: {}", io::Error::last_os_error()),
+                0 => {
+                    let passed = catch_unwind(AssertUnwindSafe(f)).unwrap_or(false);
+                    libc::_exit(if passed { 0 } else { 1 })
+                }
+                pid => {
+                    let mut status = 0;
+                    libc::waitpid(pid, &mut status, 0);
+                    libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "pcap")]
+    #[test]
+    #[ignore = "needs root"]
+    fn pcap_socket_works_after_drop() {
+        use crate::api::Socket;
+        use crate::pcap::{PcapFlags, Sock};
+        use std::time::Duration;
+
+        assert!(in_child(|| {
+            let socket = Sock::create("lo", None, PcapFlags::default()).unwrap();
+            drop_privileges("nobody", None, false).unwrap();
+            // New devices cannot be opened any more, the open one keeps working.
+            let reopened = Sock::create("lo", None, PcapFlags::default());
+            let frame = [0xffu8; 60];
+            socket.send(&frame).unwrap();
+            socket.flush();
+            reopened.is_err() && socket.recv_timeout(Duration::from_secs(1)).is_ok()
+        }));
+    }
+
+    #[cfg(feature = "af-xdp")]
+    #[test]
+    #[ignore = "needs root"]
+    fn af_xdp_socket_works_after_drop() {
+        use crate::af_xdp::{AfXdpFlags, Sock};
+        use crate::api::Socket;
+
+        assert!(in_child(|| {
+            let socket = Sock::create("lo", Some(0), AfXdpFlags::default()).unwrap();
+            drop_privileges("nobody", None, false).unwrap();
+            let rx_ok = matches!(
+                socket.try_recv(),
+                Ok(_) | Err(crate::errors::Error::WouldBlock)
+            );
+            rx_ok && socket.send(&[0xffu8; 60]).is_ok()
+        }));
+    }
+}
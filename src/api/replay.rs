@@ -0,0 +1,179 @@
+//! Timed replay of capture files.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use super::Result;
+use super::metadata::Metadata;
+use super::pacing::wait;
+use super::socket::Socket;
+use super::timestamp::Timestamp;
+use crate::errors::Error;
+use crate::pcap::{PcapFlags, Sock};
+
+/// Largest frame read from a capture file, libpcap's maximum snaplen.
+const MAX_FRAME: usize = 262_144;
+
+/// Transmits the packets of a pcap or pcapng file on a socket of any backend,
+/// reproducing the gaps between their timestamps.
+///
+/// The file is read with the pcap backend, so replay requires the `pcap` feature, but
+/// the packets can go out of an AF_XDP, netmap or DPDK socket, whose gaps are far more
+/// accurate than those of libpcap injection. Waits sleep, then busy-spin for the last
+/// microseconds, like a [`TxRate`](super::TxRate); the socket is flushed before each
+/// wait, so every packet leaves when it is due. A sender that falls behind sends the
+/// late packets back to back instead of dropping them.
+///
+/// ```ignore
+/// use nethuns_rs::api::Replay;
+///
+/// let stats = Replay::new("trace.pcapng").speed(2.0).loops(3).run(&tx)?;
+/// println!("{} packets, {} bytes", stats.packets, stats.bytes);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Replay {
+    path: PathBuf,
+    speed: f64,
+    loops: usize,
+}
+
+/// Counters of a [`Replay`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReplayStats {
+    /// Packets sent.
+    pub packets: u64,
+    /// Bytes sent.
+    pub bytes: u64,
+    /// Passes over the whole file.
+    pub loops: usize,
+}
+
+impl Replay {
+    /// Replays the capture file at `path` once, at the original speed.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+            speed: 1.0,
+            loops: 1,
+        }
+    }
+
+    /// Divides the gaps between packets by `factor`: 2.0 replays twice as fast, 0.5
+    /// twice as slow, and `f64::INFINITY` sends the packets back to back.
+    ///
+    /// # Panics
+    ///
+    /// If `factor` is not positive.
+    pub fn speed(mut self, factor: f64) -> Self {
+        assert!(factor > 0.0, "replay speed must be positive");
+        self.speed = factor;
+        self
+    }
+
+    /// Replays the file `count` times, or forever if `count` is 0. Each pass starts
+    /// right after the last packet of the previous one.
+    pub fn loops(mut self, count: usize) -> Self {
+        self.loops = count;
+        self
+    }
+
+    /// Replays the file on `tx`, returning once every pass is done.
+    pub fn run<S: Socket>(&self, tx: &S) -> Result<ReplayStats> {
+        self.run_until(tx, &AtomicBool::new(false))
+    }
+
+    /// Replays the file on `tx` until every pass is done or `stop` is set.
+    pub fn run_until<S: Socket>(&self, tx: &S, stop: &AtomicBool) -> Result<ReplayStats> {
+        let mut stats = ReplayStats::default();
+        let mut buf = vec![0u8; MAX_FRAME];
+        while self.loops == 0 || stats.loops < self.loops {
+            let file = Sock::open_file(&self.path, PcapFlags::default())?;
+            let mut schedule = Schedule::new(self.speed);
+            loop {
+                if stop.load(Ordering::Relaxed) {
+                    tx.flush();
+                    return Ok(stats);
+                }
+                let (len, meta) = match file.recv_into(&mut buf) {
+                    Ok(res) => res,
+                    Err(Error::Pcap(pcap::Error::NoMorePackets)) => break,
+                    Err(e) => return Err(e),
+                };
+                let now = Instant::now();
+                if let Some(due) = schedule.due(meta.timestamp(), now).filter(|&d| d > now) {
+                    tx.flush();
+                    wait(due - now);
+                }
+                send(tx, &buf[..len])?;
+                stats.packets += 1;
+                stats.bytes += len as u64;
+            }
+            tx.flush();
+            stats.loops += 1;
+        }
+        Ok(stats)
+    }
+}
+
+/// Departure times of the packets of one pass, relative to the first one.
+struct Schedule {
+    speed: f64,
+    origin: Option<(Timestamp, Instant)>,
+}
+
+impl Schedule {
+    fn new(speed: f64) -> Self {
+        Self {
+            speed,
+            origin: None,
+        }
+    }
+
+    /// Returns when the packet captured at `ts` is due, the first one being due `now`,
+    /// or `None` for packets without a timestamp, which are sent at once.
+    fn due(&mut self, ts: Option<Timestamp>, now: Instant) -> Option<Instant> {
+        let ts = ts?;
+        let (first, start) = *self.origin.get_or_insert((ts, now));
+        let gap = ts.as_nanos().saturating_sub(first.as_nanos());
+        Some(start + Duration::from_nanos((gap as f64 / self.speed) as u64))
+    }
+}
+
+/// Sends `packet`, flushing the socket while its TX ring is full.
+fn send<S: Socket>(tx: &S, packet: &[u8]) -> Result<()> {
+    loop {
+        match tx.send(packet) {
+            Err(Error::NoMemory) => {
+                tx.flush();
+                std::hint::spin_loop();
+            }
+            res => return res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scales_gaps() {
+        let start = Instant::now();
+        let mut schedule = Schedule::new(2.0);
+        let ts = |secs: u64| Some(Timestamp::from_nanos(secs * 1_000_000_000));
+        assert_eq!(schedule.due(ts(10), start), Some(start));
+        let later = start + Duration::from_secs(5);
+        assert_eq!(
+            schedule.due(ts(11), later),
+            Some(start + Duration::from_millis(500))
+        );
+        // Out-of-order timestamps are due at once, as are missing ones.
+        assert_eq!(schedule.due(ts(9), later), Some(start));
+        assert_eq!(schedule.due(None, later), None);
+
+        let mut fastest = Schedule::new(f64::INFINITY);
+        fastest.due(ts(10), start);
+        assert_eq!(fastest.due(ts(20), start), Some(start));
+    }
+}
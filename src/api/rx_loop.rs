@@ -0,0 +1,91 @@
+//! Ready-made receive loop.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use super::Result;
+use super::socket::Socket;
+use super::token::Payload;
+use crate::errors::Error;
+
+/// Packets handed to the handler per pass over the RX ring.
+const BUDGET: usize = 64;
+/// How long an idle loop waits for a packet before checking the stop flag again.
+pub(super) const IDLE_WAIT: Duration = Duration::from_millis(100);
+
+/// What [`run_rx_loop`] does when a receive fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum ErrorPolicy {
+    /// Return the first error.
+    #[default]
+    Abort,
+    /// Count the error and keep receiving.
+    Ignore,
+    /// Keep receiving until this many errors have been counted, then return the last.
+    Limit(u64),
+}
+
+/// Counters of a finished [`run_rx_loop`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RxLoopStats {
+    /// Packets handed to the handler.
+    pub packets: u64,
+    /// Receive errors tolerated by the [`ErrorPolicy`].
+    pub errors: u64,
+}
+
+/// Receives packets from `socket` and hands them to `handler` until `stop` is set.
+///
+/// Ready packets are drained with [`dispatch`](Socket::dispatch). When the queue is
+/// empty the loop waits with [`recv_token_timeout`](Socket::recv_token_timeout), which
+/// sleeps in `poll()` on fd-backed sockets (pcap, AF_XDP, netmap) and busy-spins on
+/// DPDK; either way `stop` is noticed within 100 ms. The loop also ends cleanly at the
+/// end of a capture file. Receive errors are handled according to `policy`.
+///
+/// ```ignore
+/// let stats = api::run_rx_loop(&socket, &term, ErrorPolicy::Ignore, |packet, _meta| {
+///     api::forward(packet, &out).ok();
+/// })?;
+/// println!("{} packets, {} errors", stats.packets, stats.errors);
+/// ```
+pub fn run_rx_loop<S, F>(
+    socket: &S,
+    stop: &AtomicBool,
+    policy: ErrorPolicy,
+    mut handler: F,
+) -> Result<RxLoopStats>
+where
+    S: Socket,
+    F: FnMut(Payload<'_, S::Context>, S::Metadata),
+{
+    let mut stats = RxLoopStats::default();
+    while !stop.load(Ordering::Relaxed) {
+        let res = match socket.dispatch(BUDGET, &mut handler) {
+            Ok(0) => socket
+                .recv_token_timeout(IDLE_WAIT)
+                .map(|(token, meta)| handler(token.consume(socket.context()), meta))
+                .map(|()| 1),
+            res => res,
+        };
+        match res {
+            Ok(n) => stats.packets += n as u64,
+            Err(Error::WouldBlock | Error::NoPacket) => {}
+            #[cfg(feature = "pcap")]
+            Err(Error::Pcap(pcap::Error::NoMorePackets)) => break,
+            Err(e) => {
+                stats.errors += 1;
+                match policy {
+                    ErrorPolicy::Ignore => {}
+                    ErrorPolicy::Limit(max) if stats.errors < max => {}
+                    _ => return Err(e),
+                }
+            }
+        }
+    }
+    Ok(stats)
+}
@@ -1,14 +1,40 @@
 //! Socket trait and related types.
 
 use std::fmt::Debug;
+use std::io::IoSlice;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
 
 use super::Result;
+use super::builder::{SocketBuilder, SocketOptions};
+use super::capabilities::Capabilities;
+use super::checksum::TxChecksum;
 use super::context::Context;
+use super::device::LinkInfo;
+use super::group::SocketGroup;
+use super::gso;
 use super::metadata::Metadata;
+#[cfg(feature = "pcap")]
+use super::mirror::Mirror;
+use super::packet::Packet;
+use super::packets::{Batch, Packets};
+use super::socket_with::SocketWith;
+use super::split::{RxSocket, TxSocket};
+use super::stats::{Dropped, Stats};
+use super::steer::{FlowRule, FlowSpec};
 use super::token::{Payload, Token};
+use super::tx_slot::{self, TxSlot};
+use crate::errors::Error;
 
 /// Trait for backend-specific socket configuration flags.
-pub trait Flags: Clone + Debug {}
+pub trait Flags: Clone + Debug + Default {
+    /// Maps the backend-independent options of a [`SocketBuilder`] onto these flags.
+    ///
+    /// Options the backend has no equivalent for are ignored.
+    fn apply(&mut self, options: &SocketOptions) {
+        let _ = options;
+    }
+}
 
 /// A network socket that can send and receive packets.
 ///
@@ -36,6 +62,14 @@ pub trait Socket: Send + Sized {
     type Metadata: Metadata;
     /// The flags type for configuring this socket.
     type Flags: Flags;
+    /// The receive half returned by [`split`](Socket::split).
+    type Rx: RxSocket<Context = Self::Context, Metadata = Self::Metadata>;
+    /// The transmit half returned by [`split`](Socket::split).
+    type Tx: TxSocket;
+    /// The zero-copy buffer returned by [`tx_reserve`](Socket::tx_reserve).
+    type Slot<'a>: TxSlot
+    where
+        Self: 'a;
 
     /// Receives a packet, returning the payload and metadata.
     ///
@@ -45,6 +79,15 @@ pub trait Socket: Send + Sized {
         Ok((token.consume(self.context()), meta))
     }
 
+    /// Receives a whole frame, even one spanning several buffers, see [`Packet`].
+    ///
+    /// The metadata lengths cover every segment. Backends without multi-buffer
+    /// frames return the same as [`recv`](Socket::recv).
+    fn recv_packet(&self) -> Result<(Packet<'_, Self::Context>, Self::Metadata)> {
+        let (payload, meta) = self.recv()?;
+        Ok((Packet::from(payload), meta))
+    }
+
     /// Receives a packet, returning a token and metadata.
     ///
     /// The token represents ownership of a packet buffer. It must be either:
@@ -54,15 +97,376 @@ pub trait Socket: Send + Sized {
     /// The buffer is automatically released when the resulting [`Payload`] is dropped.
     fn recv_token(&self) -> Result<(Token, Self::Metadata)>;
 
+    /// Receives a packet by copying it into `buf`, returning the copied length and the
+    /// metadata.
+    ///
+    /// Packets longer than `buf` are truncated; compare the result with
+    /// [`Metadata::caplen`]. The backend buffer is released before returning, so the
+    /// packet is copied exactly once.
+    fn recv_into(&self, buf: &mut [u8]) -> Result<(usize, Self::Metadata)> {
+        let (payload, meta) = self.recv()?;
+        Ok((copy_truncated(&payload, buf), meta))
+    }
+
+    /// Receives a packet without blocking.
+    ///
+    /// Returns [`Error::WouldBlock`] when no packet is ready, so poll-driven applications
+    /// can tell an empty queue apart from a failure.
+    fn try_recv(&self) -> Result<(Payload<'_, Self::Context>, Self::Metadata)> {
+        let (token, meta) = self.try_recv_token()?;
+        Ok((token.consume(self.context()), meta))
+    }
+
+    /// Non-blocking counterpart of [`recv_token`](Socket::recv_token).
+    ///
+    /// Guaranteed not to block on every backend; returns [`Error::WouldBlock`] when no
+    /// packet is ready.
+    fn try_recv_token(&self) -> Result<(Token, Self::Metadata)>;
+
+    /// Receives a packet, waiting at most `timeout` for one to arrive.
+    ///
+    /// Returns [`Error::WouldBlock`] if the timeout elapses with no packet ready.
+    fn recv_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<(Payload<'_, Self::Context>, Self::Metadata)> {
+        let (token, meta) = self.recv_token_timeout(timeout)?;
+        Ok((token.consume(self.context()), meta))
+    }
+
+    /// Token-returning counterpart of [`recv_timeout`](Socket::recv_timeout).
+    ///
+    /// The default implementation spins on [`try_recv_token`](Socket::try_recv_token) until
+    /// the deadline; fd-backed backends override it to sleep in `poll()` instead.
+    fn recv_token_timeout(&self, timeout: Duration) -> Result<(Token, Self::Metadata)> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.try_recv_token() {
+                Err(Error::WouldBlock) if Instant::now() < deadline => std::hint::spin_loop(),
+                res => return res,
+            }
+        }
+    }
+
+    /// Receives up to `budget` packets in a single call, returning their tokens and metadata.
+    ///
+    /// Backends override this to drain their RX rings in one pass instead of performing
+    /// repeated single receives. An error is returned only if no packet at all could be
+    /// received; a failure after the first packet simply ends the batch.
+    fn recv_batch(&self, budget: usize) -> Result<Vec<(Token, Self::Metadata)>> {
+        recv_batch(budget, || self.recv_token())
+    }
+
+    /// Collects packets until `max` have arrived or `deadline` passes, whichever comes
+    /// first.
+    ///
+    /// Unlike [`recv_batch`](Socket::recv_batch), which returns what is in the ring right
+    /// now, this waits for the batch to fill up, bounding the latency added by batching
+    /// to the deadline. The batch is empty if no packet arrived in time. An error is
+    /// returned only if it occurs before the first packet; afterwards it simply ends the
+    /// batch.
+    fn recv_until(&self, deadline: Instant, max: usize) -> Result<Batch<Self::Metadata>> {
+        let mut batch = Vec::with_capacity(max);
+        while batch.len() < max {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.recv_token_timeout(remaining) {
+                Ok(pkt) => batch.push(pkt),
+                Err(Error::WouldBlock | Error::NoPacket) => break,
+                Err(e) if batch.is_empty() => return Err(e),
+                Err(_) => break,
+            }
+        }
+        Ok(batch)
+    }
+
+    /// Hands up to `budget` ready packets to `f` without blocking, returning how many
+    /// were processed.
+    ///
+    /// Returns `Ok(0)` when no packet is ready. An error is returned only if it occurs
+    /// before the first packet; afterwards it simply ends the loop. Each payload is
+    /// released when `f` drops it.
+    fn dispatch<F>(&self, budget: usize, f: F) -> Result<usize>
+    where
+        F: FnMut(Payload<'_, Self::Context>, Self::Metadata),
+    {
+        dispatch(self.context(), budget, || self.try_recv_token(), f)
+    }
+
+    /// Iterates over the received packets until a receive error occurs, see
+    /// [`Packets`].
+    fn iter(&self) -> Packets<'_, Self> {
+        Packets::new(self, None)
+    }
+
+    /// Iterates over the received packets until `stop` is set or a receive error
+    /// occurs, see [`Packets`].
+    fn iter_until<'a>(&'a self, stop: &'a AtomicBool) -> Packets<'a, Self> {
+        Packets::new(self, Some(stop))
+    }
+
     /// Sends a packet.
     fn send(&self, packet: &[u8]) -> Result<()>;
 
+    /// Sends a packet made of several buffers, gathered into a single TX slot.
+    ///
+    /// Avoids concatenating header and payload in a temporary buffer; errors are the
+    /// same as [`tx_reserve`](Socket::tx_reserve).
+    fn send_vectored(&self, bufs: &[IoSlice<'_>]) -> Result<()> {
+        let len = bufs.iter().map(|buf| buf.len()).sum();
+        tx_slot::gather(self.tx_reserve(len)?, bufs)
+    }
+
+    /// Sends a received packet, handing its buffer over to the TX ring when the backend
+    /// can; see [`forward`](super::forward).
+    fn send_payload<C: Context>(&self, packet: Payload<'_, C>) -> Result<()> {
+        self.send(&packet)
+    }
+
+    /// Sends a batch of packets and flushes once for the whole batch.
+    ///
+    /// Returns the number of packets enqueued. Sending stops at the first packet that
+    /// cannot be enqueued; an error is returned only if none of them could be.
+    fn send_batch<P: AsRef<[u8]>>(&self, packets: &[P]) -> Result<usize> {
+        let sent = send_each(packets, |packet| self.send(packet))?;
+        self.flush();
+        Ok(sent)
+    }
+
     /// Flushes any pending transmissions.
     fn flush(&self);
 
+    /// Sends a packet after filling in the checksums requested by `csum`, by the NIC
+    /// if the socket reports [`Capabilities::tx_checksum`], in software otherwise.
+    ///
+    /// Lets packet generators leave the checksums to capable hardware; see
+    /// [`TxChecksum`] for the packets handled.
+    fn send_with_checksum(&self, packet: &[u8], csum: TxChecksum) -> Result<()> {
+        tx_slot::checksummed(self.tx_reserve(packet.len())?, packet, csum)
+    }
+
+    /// Sends a TCP or UDP packet larger than the MTU as frames carrying at most `mss`
+    /// bytes of L4 payload each, and returns how many frames were queued.
+    ///
+    /// `packet` is a complete frame whose headers are repeated in every segment, with
+    /// the lengths, IPv4 identification and TCP sequence number adjusted and the
+    /// checksums recomputed. The NIC segments TCP packets when the socket reports
+    /// [`Capabilities::tso`]; otherwise the frames are built in software. If a frame
+    /// cannot be queued the error is returned, and the frames before it stay queued.
+    fn send_gso(&self, packet: &[u8], mss: usize) -> Result<usize> {
+        gso::segment(|len| self.tx_reserve(len), packet, mss)
+    }
+
+    /// Sends a packet tagged with `cookie`, which [`tx_completions`](Socket::tx_completions)
+    /// reports once the packet has left the NIC.
+    ///
+    /// Lets applications recycle their own buffers only after the hardware is done with
+    /// them. Untagged packets are tracked too, but not reported.
+    fn send_with_cookie(&self, packet: &[u8], cookie: u64) -> Result<()>;
+
+    /// Appends to `out` the cookies of the tagged packets that left the NIC since the
+    /// last call, in the order they were sent, and returns how many were appended.
+    ///
+    /// Completions are read from the AF_XDP completion ring, from the netmap TX rings
+    /// as of the last sync (i.e. the last flush), and from the number of descriptors
+    /// still in use in the DPDK TX queue. pcap hands packets to the kernel
+    /// synchronously, so they complete as soon as they are sent.
+    fn tx_completions(&self, out: &mut Vec<u64>) -> usize;
+
+    /// Reserves a TX buffer of at least `len` bytes to build a packet in place.
+    ///
+    /// Only one slot can be outstanding at a time. Returns [`Error::NoMemory`] when the
+    /// TX ring is full (flush and retry) and [`Error::TooBigPacket`] if `len` exceeds the
+    /// backend's buffer size.
+    fn tx_reserve(&self, len: usize) -> Result<Self::Slot<'_>>;
+
+    /// Installs a BPF filter, in `pcap-filter(7)` syntax, on received packets.
+    ///
+    /// Replaces any filter set before; an empty expression removes it. Packets that do
+    /// not match are dropped before they are returned by the receive methods and are not
+    /// counted in [`stats`](Socket::stats).
+    ///
+    /// Live pcap captures install it in the kernel with `pcap_setfilter`. Capture
+    /// files, AF_XDP, netmap and DPDK run it in userspace on every received frame;
+    /// [`Capabilities::kernel_filter`](super::Capabilities::kernel_filter) tells which
+    /// one applies.
+    fn set_filter(&self, expr: &str) -> Result<()>;
+
+    /// Enables or disables promiscuous mode on the underlying interface.
+    fn set_promiscuous(&self, enable: bool) -> Result<()>;
+
+    /// Receives the frames sent to the multicast MAC address `mac` (e.g.
+    /// `01:00:5e:00:00:12` for VRRP), without making the interface promiscuous.
+    ///
+    /// Fails with `InvalidInput` if `mac` is not a multicast address. With pcap the
+    /// membership ends when the socket is closed; the other backends program the
+    /// interface or port, which keeps the address until
+    /// [`leave_multicast`](Socket::leave_multicast).
+    fn join_multicast(&self, mac: [u8; 6]) -> Result<()>;
+
+    /// Stops receiving the frames sent to `mac`, joined with
+    /// [`join_multicast`](Socket::join_multicast).
+    fn leave_multicast(&self, mac: [u8; 6]) -> Result<()>;
+
+    /// Returns carrier status, MTU, speed, duplex and MAC address of the underlying
+    /// interface.
+    fn link_info(&self) -> Result<LinkInfo>;
+
+    /// Steers the flows matching `spec` to the RX queue `queue` of the NIC, typically
+    /// the one this socket is bound to, and returns the installed rule.
+    ///
+    /// AF_XDP, netmap and pcap install an ntuple rule with ethtool, which needs
+    /// `CAP_NET_ADMIN` and the `ntuple` feature of the NIC; DPDK creates an `rte_flow`
+    /// rule on the port. Fails with the driver's error if the NIC cannot match `spec`.
+    fn steer(&self, spec: FlowSpec, queue: usize) -> Result<FlowRule>;
+
+    /// Removes a rule installed by [`steer`](Socket::steer).
+    fn unsteer(&self, rule: FlowRule) -> Result<()>;
+
     /// Creates a new socket bound to the given port specification.
     fn create(portspec: &str, queue: Option<usize>, flags: Self::Flags) -> Result<Self>;
 
+    /// Opens one socket per RX queue of the interface, with traffic spread across them.
+    ///
+    /// Supported by AF_XDP, netmap and live pcap captures. DPDK fails with
+    /// `Unsupported`; see [`Capabilities::socket_groups`].
+    fn create_group(portspec: &str, flags: Self::Flags) -> Result<SocketGroup<Self>>;
+
+    /// Returns a [`SocketBuilder`] for the given port specification.
+    fn builder(portspec: &str) -> SocketBuilder<Self> {
+        SocketBuilder::new(portspec)
+    }
+
+    /// Wraps the socket so that every packet it receives is also written to the capture
+    /// file `path`, see [`pcap::Dumper`](crate::pcap::Dumper).
+    ///
+    /// The file is flushed after each batch; with single receives call
+    /// [`flush_mirror`](Mirror::flush_mirror) from time to time.
+    #[cfg(feature = "pcap")]
+    fn dump_to(
+        self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<Mirror<Self, crate::pcap::Dumper>> {
+        Ok(Mirror::new(self, crate::pcap::Dumper::create(path)?))
+    }
+
+    /// Bundles the socket with application state, see [`SocketWith`].
+    fn with_state<T>(self, state: T) -> SocketWith<T, Self> {
+        SocketWith::new(self, state)
+    }
+
     /// Returns a reference to this socket's context.
     fn context(&self) -> &Self::Context;
+
+    /// Returns the socket's packet counters.
+    fn stats(&self) -> Result<Stats>;
+
+    /// Returns the packets lost by nethuns itself rather than by the kernel or the NIC,
+    /// see [`Dropped`].
+    ///
+    /// Unlike [`stats`](Socket::stats) this never issues a system call.
+    fn dropped(&self) -> Dropped;
+
+    /// Returns the features supported by this socket.
+    fn capabilities(&self) -> Capabilities;
+
+    /// Closes the socket, reporting the teardown errors that dropping it would swallow.
+    ///
+    /// Pending transmissions are flushed and given up to one second to leave the TX
+    /// ring; a `TimedOut` error is returned if some are still queued. Buffers released
+    /// by the application are handed back to the backend before the rings are torn
+    /// down. The socket is closed even when an error is returned.
+    fn close(self) -> Result<()> {
+        self.flush();
+        Ok(())
+    }
+
+    /// Splits the socket into independently owned receive and transmit halves.
+    ///
+    /// Each half owns the backend rings for its direction and is `Send`, so one thread can
+    /// receive while another transmits without sharing the whole socket.
+    fn split(self) -> (Self::Rx, Self::Tx);
+}
+
+// The default bodies shared by `Socket` and the halves of `split.rs`.
+
+/// Copies `payload` into `buf`, truncating it to fit, and returns the copied length.
+pub(crate) fn copy_truncated(payload: &[u8], buf: &mut [u8]) -> usize {
+    let len = payload.len().min(buf.len());
+    buf[..len].copy_from_slice(&payload[..len]);
+    len
+}
+
+/// Receives up to `budget` packets with `recv`, failing only if the first receive does.
+pub(crate) fn recv_batch<M>(
+    budget: usize,
+    mut recv: impl FnMut() -> Result<(Token, M)>,
+) -> Result<Vec<(Token, M)>> {
+    let mut batch = Vec::with_capacity(budget);
+    while batch.len() < budget {
+        match recv() {
+            Ok(pkt) => batch.push(pkt),
+            Err(e) if batch.is_empty() => return Err(e),
+            Err(_) => break,
+        }
+    }
+    Ok(batch)
+}
+
+/// Receives the ring entries of `bufs` into `batch` with `recv`, which returns `None` for
+/// those it drops; like [`recv_batch`], a failure only ends the batch once it holds a
+/// packet, whose token would otherwise leak its buffer.
+#[cfg(any(feature = "af-xdp", feature = "netmap", feature = "dpdk"))]
+pub(crate) fn fill_batch<B, M>(
+    batch: &mut Vec<(Token, M)>,
+    bufs: impl Iterator<Item = B>,
+    mut recv: impl FnMut(B) -> Result<Option<(Token, M)>>,
+) -> Result<()> {
+    for buf in bufs {
+        match recv(buf) {
+            Ok(pkt) => batch.extend(pkt),
+            Err(e) if batch.is_empty() => return Err(e),
+            Err(_) => break,
+        }
+    }
+    Ok(())
+}
+
+/// Hands up to `budget` packets received with `try_recv` to `f`, until none is ready.
+pub(crate) fn dispatch<'a, C: Context, M, F>(
+    ctx: &'a C,
+    budget: usize,
+    mut try_recv: impl FnMut() -> Result<(Token, M)>,
+    mut f: F,
+) -> Result<usize>
+where
+    F: FnMut(Payload<'a, C>, M),
+{
+    let mut count = 0;
+    while count < budget {
+        match try_recv() {
+            Ok((token, meta)) => f(token.consume(ctx), meta),
+            Err(Error::WouldBlock) => break,
+            Err(e) if count == 0 => return Err(e),
+            Err(_) => break,
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Sends `packets` with `send` until one cannot be enqueued, failing only if the first
+/// one cannot, and returns how many were.
+pub(crate) fn send_each<P: AsRef<[u8]>>(
+    packets: &[P],
+    mut send: impl FnMut(&[u8]) -> Result<()>,
+) -> Result<usize> {
+    let mut sent = 0;
+    for packet in packets {
+        match send(packet.as_ref()) {
+            Ok(()) => sent += 1,
+            Err(e) if sent == 0 => return Err(e),
+            Err(_) => break,
+        }
+    }
+    Ok(sent)
 }
@@ -0,0 +1,70 @@
+//! Per-socket user state.
+
+use std::ops::{Deref, DerefMut};
+
+/// A socket bundled with application state, obtained through
+/// [`Socket::with_state`](super::Socket::with_state).
+///
+/// Dereferences to the socket, so it is used like the socket itself, while counters,
+/// flow tables and the like travel with it through generic code and across threads:
+///
+/// ```ignore
+/// fn worker<S: Socket>(socket: &mut SocketWith<FlowTable, S>) -> Result<()> {
+///     let (payload, _) = socket.recv()?;
+///     let key = flow_hash(&payload);
+///     drop(payload);
+///     socket.state_mut().update(key);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SocketWith<T, S> {
+    socket: S,
+    state: T,
+}
+
+impl<T, S> SocketWith<T, S> {
+    /// Bundles `socket` with `state`.
+    pub fn new(socket: S, state: T) -> Self {
+        Self { socket, state }
+    }
+
+    /// Returns the socket.
+    pub fn socket(&self) -> &S {
+        &self.socket
+    }
+
+    /// Returns the state.
+    pub fn state(&self) -> &T {
+        &self.state
+    }
+
+    /// Returns the state mutably.
+    pub fn state_mut(&mut self) -> &mut T {
+        &mut self.state
+    }
+
+    /// Replaces the state, returning the previous one.
+    pub fn set_state(&mut self, state: T) -> T {
+        std::mem::replace(&mut self.state, state)
+    }
+
+    /// Separates the socket from its state.
+    pub fn into_parts(self) -> (S, T) {
+        (self.socket, self.state)
+    }
+}
+
+impl<T, S> Deref for SocketWith<T, S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.socket
+    }
+}
+
+impl<T, S> DerefMut for SocketWith<T, S> {
+    fn deref_mut(&mut self) -> &mut S {
+        &mut self.socket
+    }
+}
@@ -0,0 +1,183 @@
+//! Independently owned RX and TX halves of a socket.
+
+use std::io::IoSlice;
+use std::time::Instant;
+
+use super::Result;
+use super::checksum::TxChecksum;
+use super::context::Context;
+use super::gso;
+use super::metadata::Metadata;
+use super::packet::Packet;
+use super::packets::Batch;
+use super::socket;
+use super::stats::Dropped;
+use super::token::{Payload, Token};
+use super::tx_slot::{self, TxSlot};
+use crate::errors::Error;
+
+/// The receive half of a socket obtained through [`Socket::split`](super::Socket::split).
+///
+/// It owns the backend's RX rings and buffer pool, so it can live on a different
+/// thread than the matching [`TxSocket`].
+pub trait RxSocket: Send + Sized {
+    /// The context type that manages buffer pools for this half.
+    type Context: Context;
+    /// The metadata type returned with each received packet.
+    type Metadata: Metadata;
+
+    /// Receives a packet, returning the payload and metadata.
+    fn recv(&self) -> Result<(Payload<'_, Self::Context>, Self::Metadata)> {
+        let (token, meta) = self.recv_token()?;
+        Ok((token.consume(self.context()), meta))
+    }
+
+    /// Receives a whole frame, even one spanning several buffers, see
+    /// [`Socket::recv_packet`](super::Socket::recv_packet).
+    fn recv_packet(&self) -> Result<(Packet<'_, Self::Context>, Self::Metadata)> {
+        let (payload, meta) = self.recv()?;
+        Ok((Packet::from(payload), meta))
+    }
+
+    /// Receives a packet, returning a token and metadata.
+    fn recv_token(&self) -> Result<(Token, Self::Metadata)>;
+
+    /// Receives a packet by copying it into `buf`.
+    ///
+    /// Same semantics as [`Socket::recv_into`](super::Socket::recv_into).
+    fn recv_into(&self, buf: &mut [u8]) -> Result<(usize, Self::Metadata)> {
+        let (payload, meta) = self.recv()?;
+        Ok((socket::copy_truncated(&payload, buf), meta))
+    }
+
+    /// Receives a packet without blocking.
+    fn try_recv(&self) -> Result<(Payload<'_, Self::Context>, Self::Metadata)> {
+        let (token, meta) = self.try_recv_token()?;
+        Ok((token.consume(self.context()), meta))
+    }
+
+    /// Non-blocking counterpart of [`recv_token`](RxSocket::recv_token).
+    fn try_recv_token(&self) -> Result<(Token, Self::Metadata)>;
+
+    /// Receives up to `budget` packets in a single call.
+    ///
+    /// Same semantics as [`Socket::recv_batch`](super::Socket::recv_batch).
+    fn recv_batch(&self, budget: usize) -> Result<Vec<(Token, Self::Metadata)>> {
+        socket::recv_batch(budget, || self.recv_token())
+    }
+
+    /// Collects packets until `max` have arrived or `deadline` passes.
+    ///
+    /// Same semantics as [`Socket::recv_until`](super::Socket::recv_until); the halves
+    /// have no way to sleep, so the wait spins on
+    /// [`try_recv_token`](RxSocket::try_recv_token).
+    fn recv_until(&self, deadline: Instant, max: usize) -> Result<Batch<Self::Metadata>> {
+        let mut batch = Vec::with_capacity(max);
+        while batch.len() < max {
+            match self.try_recv_token() {
+                Ok(pkt) => batch.push(pkt),
+                Err(Error::WouldBlock | Error::NoPacket) if Instant::now() < deadline => {
+                    std::hint::spin_loop()
+                }
+                Err(Error::WouldBlock | Error::NoPacket) => break,
+                Err(e) if batch.is_empty() => return Err(e),
+                Err(_) => break,
+            }
+        }
+        Ok(batch)
+    }
+
+    /// Hands up to `budget` ready packets to `f` without blocking.
+    ///
+    /// Same semantics as [`Socket::dispatch`](super::Socket::dispatch).
+    fn dispatch<F>(&self, budget: usize, f: F) -> Result<usize>
+    where
+        F: FnMut(Payload<'_, Self::Context>, Self::Metadata),
+    {
+        socket::dispatch(self.context(), budget, || self.try_recv_token(), f)
+    }
+
+    /// Installs a BPF filter on received packets.
+    ///
+    /// Same semantics as [`Socket::set_filter`](super::Socket::set_filter).
+    fn set_filter(&self, expr: &str) -> Result<()>;
+
+    /// Returns the packets this half lost inside nethuns.
+    ///
+    /// Same counters as [`Socket::dropped`](super::Socket::dropped).
+    fn dropped(&self) -> Dropped;
+
+    /// Returns a reference to this half's context.
+    fn context(&self) -> &Self::Context;
+}
+
+/// The transmit half of a socket obtained through [`Socket::split`](super::Socket::split).
+pub trait TxSocket: Send + Sized {
+    /// The zero-copy buffer returned by [`tx_reserve`](TxSocket::tx_reserve).
+    type Slot<'a>: TxSlot
+    where
+        Self: 'a;
+
+    /// Sends a packet.
+    fn send(&self, packet: &[u8]) -> Result<()>;
+
+    /// Sends a packet made of several buffers, gathered into a single TX slot.
+    ///
+    /// Avoids concatenating header and payload in a temporary buffer; errors are the
+    /// same as [`tx_reserve`](TxSocket::tx_reserve).
+    fn send_vectored(&self, bufs: &[IoSlice<'_>]) -> Result<()> {
+        let len = bufs.iter().map(|buf| buf.len()).sum();
+        tx_slot::gather(self.tx_reserve(len)?, bufs)
+    }
+
+    /// Sends a received packet, handing its buffer over to the TX ring when the backend
+    /// can.
+    ///
+    /// Same semantics as [`Socket::send_payload`](super::Socket::send_payload).
+    fn send_payload<C: Context>(&self, packet: Payload<'_, C>) -> Result<()> {
+        self.send(&packet)
+    }
+
+    /// Sends a batch of packets and flushes once for the whole batch.
+    ///
+    /// Same semantics as [`Socket::send_batch`](super::Socket::send_batch).
+    fn send_batch<P: AsRef<[u8]>>(&self, packets: &[P]) -> Result<usize> {
+        let sent = socket::send_each(packets, |packet| self.send(packet))?;
+        self.flush();
+        Ok(sent)
+    }
+
+    /// Flushes any pending transmissions.
+    fn flush(&self);
+
+    /// Sends a packet after filling in the checksums requested by `csum`.
+    ///
+    /// Same semantics as [`Socket::send_with_checksum`](super::Socket::send_with_checksum).
+    fn send_with_checksum(&self, packet: &[u8], csum: TxChecksum) -> Result<()> {
+        tx_slot::checksummed(self.tx_reserve(packet.len())?, packet, csum)
+    }
+
+    /// Sends an oversized TCP or UDP packet as frames of at most `mss` payload bytes.
+    ///
+    /// Same semantics as [`Socket::send_gso`](super::Socket::send_gso).
+    fn send_gso(&self, packet: &[u8], mss: usize) -> Result<usize> {
+        gso::segment(|len| self.tx_reserve(len), packet, mss)
+    }
+
+    /// Sends a packet tagged with `cookie`.
+    ///
+    /// Same semantics as [`Socket::send_with_cookie`](super::Socket::send_with_cookie).
+    fn send_with_cookie(&self, packet: &[u8], cookie: u64) -> Result<()>;
+
+    /// Collects the cookies of the tagged packets that left the NIC.
+    ///
+    /// Same semantics as [`Socket::tx_completions`](super::Socket::tx_completions).
+    fn tx_completions(&self, out: &mut Vec<u64>) -> usize;
+
+    /// Reserves a TX buffer of at least `len` bytes to build a packet in place.
+    ///
+    /// Only one slot can be outstanding at a time. Returns [`Error::NoMemory`](crate::errors::Error::NoMemory) when the
+    /// TX ring is full (flush and retry) and [`Error::TooBigPacket`](crate::errors::Error::TooBigPacket) if `len` exceeds the
+    /// backend's buffer size.
+    fn tx_reserve(&self, len: usize) -> Result<Self::Slot<'_>>;
+}
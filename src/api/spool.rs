@@ -0,0 +1,268 @@
+//! Writing captured packets on a dedicated thread.
+
+use std::cell::UnsafeCell;
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crossbeam_queue::ArrayQueue;
+
+use super::Result;
+use super::metadata::{BasicMeta, Metadata};
+use super::mirror::MirrorSink;
+
+/// Pads the batches of the channel, which carries slot indices in groups of 16.
+const PADDING: usize = usize::MAX;
+/// Longest the writer thread sleeps when the spool is empty.
+const IDLE_WAIT: Duration = Duration::from_millis(1);
+
+/// Writes captured packets to a [`MirrorSink`], such as a `pcap::Dumper`, on a
+/// dedicated thread, so that slow storage does not stall the receive loop.
+///
+/// [`write`](SpoolWriter::write) copies the packet into a free slot of the spool and
+/// hands the slot to the writer thread through the crate's `mpsc` channel. When the
+/// disk cannot keep up and every slot is in use, the packet is dropped and counted in
+/// [`dropped`](SpoolWriter::dropped) instead of blocking the capture.
+///
+/// The channel moves slots in groups of 16: call [`flush`](SpoolWriter::flush)
+/// whenever the receive loop goes idle, so that the last packets are written too.
+/// Dropping the writer, or [`finish`](SpoolWriter::finish), writes everything still
+/// spooled and stops the thread.
+///
+/// ```ignore
+/// let dumper = pcap::Dumper::create("capture.pcap")?;
+/// let mut spool = SpoolWriter::new(dumper, 65536, 2048)?;
+/// loop {
+///     match socket.recv_timeout(Duration::from_millis(10)) {
+///         Ok((packet, meta)) => {
+///             spool.write(&packet, &meta);
+///         }
+///         Err(_) => spool.flush(),
+///     }
+/// }
+/// ```
+pub struct SpoolWriter<S: MirrorSink + Send + 'static> {
+    producer: mpsc::Producer<usize>,
+    /// Slot indices pushed since the channel was last padded to a full group.
+    pending: usize,
+    spool: Arc<Spool>,
+    dropped: u64,
+    thread: Option<JoinHandle<S>>,
+}
+
+impl<S: MirrorSink + Send + 'static> SpoolWriter<S> {
+    /// Starts the writer thread, with a spool of `slots` buffers of `slot_size` bytes;
+    /// longer packets are truncated.
+    pub fn new(sink: S, slots: usize, slot_size: usize) -> Result<Self> {
+        let slots = slots.max(1);
+        let spool = Arc::new(Spool::new(slots, slot_size));
+        // Even if every group carried a single slot, the channel could hold them all,
+        // so pushing never waits.
+        let (producer, consumer) = mpsc::channel(slots + 1);
+        let thread = thread::Builder::new().name("nethuns-spool".into()).spawn({
+            let spool = spool.clone();
+            move || spool.drain(sink, consumer)
+        })?;
+        Ok(Self {
+            producer,
+            pending: 0,
+            spool,
+            dropped: 0,
+            thread: Some(thread),
+        })
+    }
+
+    /// Spools a packet received with `meta`, returning `false` if it was dropped
+    /// because the spool is full.
+    pub fn write<M: Metadata>(&mut self, packet: &[u8], meta: &M) -> bool {
+        let Some(idx) = self.spool.free.pop() else {
+            self.dropped += 1;
+            return false;
+        };
+        // SAFETY: the slot was free, so this thread has it to itself until it is
+        // pushed to the writer thread.
+        let slot = unsafe { &mut *self.spool.slots[idx].get() };
+        let len = packet.len().min(slot.data.len());
+        slot.data[..len].copy_from_slice(&packet[..len]);
+        slot.meta = BasicMeta {
+            timestamp: meta.timestamp(),
+            caplen: len as u32,
+            wirelen: meta.wirelen().max(packet.len() as u32),
+        };
+        self.push(idx);
+        true
+    }
+
+    /// Hands the packets spooled so far to the writer thread.
+    pub fn flush(&mut self) {
+        while self.pending != 0 {
+            self.push(PADDING);
+        }
+        self.producer.flush();
+        if let Some(thread) = &self.thread {
+            thread.thread().unpark();
+        }
+    }
+
+    /// Returns the number of packets dropped because the spool was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Returns the number of packets the writer thread handed to the sink.
+    pub fn written(&self) -> u64 {
+        self.spool.written.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of packets the sink failed to write.
+    pub fn errors(&self) -> u64 {
+        self.spool.errors.load(Ordering::Relaxed)
+    }
+
+    /// Writes the spooled packets, stops the writer thread and returns the sink.
+    pub fn finish(mut self) -> Result<S> {
+        self.stop()
+    }
+
+    fn push(&mut self, idx: usize) {
+        self.producer.push(idx);
+        self.pending = (self.pending + 1) % 16;
+    }
+
+    fn stop(&mut self) -> Result<S> {
+        self.flush();
+        self.spool.stop.store(true, Ordering::Release);
+        let thread = self
+            .thread
+            .take()
+            .expect("the writer thread is only stopped once");
+        thread.thread().unpark();
+        thread
+            .join()
+            .map_err(|_| io::Error::other("the spool writer thread panicked").into())
+    }
+}
+
+impl<S: MirrorSink + Send + 'static> Drop for SpoolWriter<S> {
+    fn drop(&mut self) {
+        if self.thread.is_some() {
+            let _ = self.stop();
+        }
+    }
+}
+
+/// Buffers shared by a [`SpoolWriter`] and its thread.
+struct Spool {
+    slots: Box<[UnsafeCell<Slot>]>,
+    /// Indices of the slots not holding a packet.
+    free: ArrayQueue<usize>,
+    stop: AtomicBool,
+    written: AtomicU64,
+    errors: AtomicU64,
+}
+
+// SAFETY: a slot is only accessed by the thread that popped it from `free` or received
+// it from the channel.
+unsafe impl Sync for Spool {}
+
+struct Slot {
+    data: Box<[u8]>,
+    meta: BasicMeta,
+}
+
+impl Spool {
+    fn new(slots: usize, slot_size: usize) -> Self {
+        let free = ArrayQueue::new(slots);
+        for idx in 0..slots {
+            let _ = free.push(idx);
+        }
+        Self {
+            slots: (0..slots)
+                .map(|_| {
+                    UnsafeCell::new(Slot {
+                        data: vec![0; slot_size].into_boxed_slice(),
+                        meta: BasicMeta::default(),
+                    })
+                })
+                .collect(),
+            free,
+            stop: AtomicBool::new(false),
+            written: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+        }
+    }
+
+    /// Body of the writer thread: writes the slots it receives until stopped.
+    fn drain<S: MirrorSink>(&self, mut sink: S, mut consumer: mpsc::Consumer<usize>) -> S {
+        loop {
+            // Popping takes the latest index first: drain the batch in order instead,
+            // so that packets are written as they were captured.
+            consumer.sync();
+            if consumer.available_len() != 0 {
+                for idx in consumer.cached().drain(..) {
+                    self.write_slot(&mut sink, idx);
+                }
+                continue;
+            }
+            sink.flush_mirror();
+            if self.stop.load(Ordering::Acquire) {
+                // Everything pushed before the stop is visible now.
+                consumer.sync();
+                if consumer.available_len() == 0 {
+                    break;
+                }
+            } else {
+                thread::park_timeout(IDLE_WAIT);
+            }
+        }
+        sink
+    }
+
+    fn write_slot<S: MirrorSink>(&self, sink: &mut S, idx: usize) {
+        if idx == PADDING {
+            return;
+        }
+        // SAFETY: the capture thread gave the slot up when pushing it.
+        let slot = unsafe { &*self.slots[idx].get() };
+        let packet = &slot.data[..slot.meta.caplen as usize];
+        if sink.mirror(packet, &slot.meta).is_err() {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        self.written.fetch_add(1, Ordering::Relaxed);
+        let _ = self.free.push(idx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Collects the packets it is handed.
+    #[derive(Clone, Default)]
+    struct Collect(Arc<Mutex<Vec<Vec<u8>>>>);
+
+    impl MirrorSink for Collect {
+        fn mirror<M: Metadata>(&mut self, packet: &[u8], _meta: &M) -> Result<()> {
+            self.0.lock().unwrap().push(packet.to_vec());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn drops_on_overflow() {
+        let sink = Collect::default();
+        let mut spool = SpoolWriter::new(sink.clone(), 4, 2).unwrap();
+        let meta = BasicMeta::default();
+        // Nothing reaches the writer thread before a flush: the spool fills up.
+        for i in 0..6u8 {
+            assert_eq!(spool.write(&[i, i, i], &meta), i < 4);
+        }
+        assert_eq!(spool.dropped(), 2);
+        let sink = spool.finish().unwrap();
+        let packets = sink.0.lock().unwrap();
+        assert_eq!(*packets, [[0, 0], [1, 1], [2, 2], [3, 3]]);
+    }
+}
@@ -0,0 +1,43 @@
+//! Normalized socket statistics.
+
+/// Packet counters of a socket, normalized across backends.
+///
+/// Packet and byte counters are kept by the socket itself unless the backend
+/// documents otherwise; drop and error counters come from the kernel or the NIC.
+/// Counters a backend cannot provide stay at zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Stats {
+    /// Packets delivered to the application.
+    pub rx_packets: u64,
+    /// Bytes delivered to the application.
+    pub rx_bytes: u64,
+    /// Packets dropped before reaching the socket (ring full, no free buffers).
+    pub rx_dropped: u64,
+    /// Packets discarded because they were invalid.
+    pub rx_errors: u64,
+    /// Packets handed to the backend for transmission.
+    pub tx_packets: u64,
+    /// Bytes handed to the backend for transmission.
+    pub tx_bytes: u64,
+    /// Packets the backend failed to transmit.
+    pub tx_errors: u64,
+}
+
+/// Packets lost inside nethuns itself, as opposed to the kernel and NIC drops counted
+/// in [`Stats::rx_dropped`].
+///
+/// The counters are plain increments on the receive path, cheap enough to poll from a
+/// monitoring loop and alarm on. Counters a backend cannot hit stay at zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Dropped {
+    /// Times the AF_XDP fill ring was found empty with no free frame to refill it,
+    /// because the application holds them all: the kernel drops packets until one is
+    /// released.
+    pub fill_underruns: u64,
+    /// Received packets discarded because no free buffer could take their place in the
+    /// RX ring (netmap extra buffers exhausted, or a pcap pool that overwrites the
+    /// oldest packets).
+    pub no_buffers: u64,
+}
@@ -0,0 +1,365 @@
+//! Hardware flow steering: pinning flows to an RX queue of the NIC.
+
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+use std::io;
+use std::net::IpAddr;
+
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+use super::netdev::{control_socket, ethtool};
+
+/// Transport protocol matched by a [`FlowSpec`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FlowProtocol {
+    Tcp,
+    Udp,
+}
+
+impl FlowProtocol {
+    /// Returns the IP protocol number.
+    pub fn number(self) -> u8 {
+        match self {
+            FlowProtocol::Tcp => libc::IPPROTO_TCP as u8,
+            FlowProtocol::Udp => libc::IPPROTO_UDP as u8,
+        }
+    }
+}
+
+/// The flows steered by [`Socket::steer`](super::Socket::steer); fields left to `None`
+/// match any value.
+///
+/// The address family follows the addresses, IPv4 if there is none. Ports need a
+/// protocol.
+///
+/// ```ignore
+/// // HTTPS towards 10.0.0.1 goes to the queue this socket is bound to.
+/// let spec = FlowSpec::tcp().dst(Ipv4Addr::new(10, 0, 0, 1).into()).dst_port(443);
+/// let rule = socket.steer(spec, queue)?;
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FlowSpec {
+    pub protocol: Option<FlowProtocol>,
+    pub src: Option<IpAddr>,
+    pub dst: Option<IpAddr>,
+    pub src_port: Option<u16>,
+    pub dst_port: Option<u16>,
+}
+
+impl FlowSpec {
+    /// Matches every TCP flow.
+    pub fn tcp() -> Self {
+        Self {
+            protocol: Some(FlowProtocol::Tcp),
+            ..Self::default()
+        }
+    }
+
+    /// Matches every UDP flow.
+    pub fn udp() -> Self {
+        Self {
+            protocol: Some(FlowProtocol::Udp),
+            ..Self::default()
+        }
+    }
+
+    /// Only matches packets from `addr`.
+    pub fn src(mut self, addr: IpAddr) -> Self {
+        self.src = Some(addr);
+        self
+    }
+
+    /// Only matches packets to `addr`.
+    pub fn dst(mut self, addr: IpAddr) -> Self {
+        self.dst = Some(addr);
+        self
+    }
+
+    /// Only matches packets from `port`.
+    pub fn src_port(mut self, port: u16) -> Self {
+        self.src_port = Some(port);
+        self
+    }
+
+    /// Only matches packets to `port`.
+    pub fn dst_port(mut self, port: u16) -> Self {
+        self.dst_port = Some(port);
+        self
+    }
+
+    /// Returns `true` for an IPv6 flow, or an `InvalidInput` error if the fields
+    /// contradict each other.
+    #[cfg(any(
+        feature = "pcap",
+        feature = "af-xdp",
+        feature = "netmap",
+        feature = "dpdk"
+    ))]
+    pub(crate) fn is_ipv6(&self) -> io::Result<bool> {
+        if self.protocol.is_none() && (self.src_port.is_some() || self.dst_port.is_some()) {
+            return Err(invalid("ports need a protocol"));
+        }
+        match (self.src, self.dst) {
+            (Some(src), Some(dst)) if src.is_ipv6() != dst.is_ipv6() => {
+                Err(invalid("addresses of different families"))
+            }
+            (Some(addr), _) | (_, Some(addr)) => Ok(addr.is_ipv6()),
+            (None, None) => Ok(false),
+        }
+    }
+}
+
+/// A steering rule installed by [`Socket::steer`](super::Socket::steer).
+///
+/// Rules belong to the NIC, not to the socket: they stay until removed with
+/// [`Socket::unsteer`](super::Socket::unsteer), on a socket of the same interface.
+#[derive(Debug, PartialEq, Eq)]
+pub struct FlowRule {
+    id: u64,
+}
+
+impl FlowRule {
+    #[cfg(any(
+        feature = "pcap",
+        feature = "af-xdp",
+        feature = "netmap",
+        feature = "dpdk"
+    ))]
+    pub(crate) fn new(id: u64) -> Self {
+        Self { id }
+    }
+
+    /// Returns the backend identifier of the rule: the ntuple rule location (as in
+    /// `ethtool -N <if> delete <id>`) or the DPDK `rte_flow` handle.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+const ETHTOOL_GRXCLSRLCNT: u32 = 0x2e;
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+const ETHTOOL_GRXCLSRLALL: u32 = 0x30;
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+const ETHTOOL_SRXCLSRLDEL: u32 = 0x31;
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+const ETHTOOL_SRXCLSRLINS: u32 = 0x32;
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+const TCP_V4_FLOW: u32 = 0x01;
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+const UDP_V4_FLOW: u32 = 0x02;
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+const TCP_V6_FLOW: u32 = 0x05;
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+const UDP_V6_FLOW: u32 = 0x06;
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+const IPV4_USER_FLOW: u32 = 0x0d;
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+const IPV6_USER_FLOW: u32 = 0x0e;
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+const ETH_RX_NFC_IP4: u8 = 1;
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+const RX_CLS_LOC_SPECIAL: u64 = 0x8000_0000;
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+const RX_CLS_LOC_ANY: u32 = 0xffff_ffff;
+
+/// `struct ethtool_rx_flow_spec`, with the header and mask unions as raw bytes.
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+#[repr(C)]
+struct RxFlowSpec {
+    flow_type: u32,
+    h_u: [u8; 52],
+    h_ext: [u8; 20],
+    m_u: [u8; 52],
+    m_ext: [u8; 20],
+    ring_cookie: u64,
+    location: u32,
+}
+
+/// `struct ethtool_rxnfc`, without the trailing rule locations.
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+#[repr(C)]
+struct RxNfc {
+    cmd: u32,
+    flow_type: u32,
+    data: u64,
+    fs: RxFlowSpec,
+    rule_cnt: u32,
+}
+
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+impl RxNfc {
+    fn new(cmd: u32) -> Self {
+        // SAFETY: plain integers and byte arrays.
+        let mut nfc: Self = unsafe { std::mem::zeroed() };
+        nfc.cmd = cmd;
+        nfc
+    }
+}
+
+/// Installs an ntuple rule (`ethtool -N <if> flow-type ...`) steering `spec` to
+/// `queue` of `ifname`, and returns it.
+///
+/// Requires `CAP_NET_ADMIN` and the `ntuple` feature of the NIC (`ethtool -K <if> ntuple
+/// on`).
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+pub(crate) fn ntuple_insert(ifname: &str, spec: &FlowSpec, queue: usize) -> io::Result<FlowRule> {
+    let sock = control_socket()?;
+    let mut nfc = RxNfc::new(ETHTOOL_SRXCLSRLINS);
+    encode(spec, &mut nfc.fs)?;
+    nfc.fs.ring_cookie = queue as u64;
+    nfc.fs.location = free_location(&sock, ifname)?;
+    ethtool(&sock, ifname, (&mut nfc as *mut RxNfc).cast())?;
+    // Drivers choosing the location report it back.
+    Ok(FlowRule::new(nfc.fs.location as u64))
+}
+
+/// Removes an ntuple rule installed by [`ntuple_insert`].
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+pub(crate) fn ntuple_delete(ifname: &str, rule: FlowRule) -> io::Result<()> {
+    let sock = control_socket()?;
+    let mut nfc = RxNfc::new(ETHTOOL_SRXCLSRLDEL);
+    nfc.fs.location = rule.id as u32;
+    ethtool(&sock, ifname, (&mut nfc as *mut RxNfc).cast())
+}
+
+/// Returns where to insert a rule: anywhere if the driver picks the location itself,
+/// otherwise the first free entry of its rule table, as ethtool does.
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+fn free_location(sock: &std::os::fd::OwnedFd, ifname: &str) -> io::Result<u32> {
+    let mut nfc = RxNfc::new(ETHTOOL_GRXCLSRLCNT);
+    ethtool(sock, ifname, (&mut nfc as *mut RxNfc).cast())?;
+    if nfc.data & RX_CLS_LOC_SPECIAL != 0 {
+        return Ok(RX_CLS_LOC_ANY);
+    }
+    let size = nfc.data as u32;
+    let count = nfc.rule_cnt as usize;
+
+    // struct ethtool_rxnfc, followed by the locations of the rules.
+    let header = size_of::<RxNfc>();
+    let mut buf = vec![0u64; (header + 4 * count).div_ceil(8)];
+    let mut all = RxNfc::new(ETHTOOL_GRXCLSRLALL);
+    all.rule_cnt = count as u32;
+    // SAFETY: `buf` is 8-byte aligned and holds the header and the locations.
+    let used = unsafe {
+        buf.as_mut_ptr().cast::<RxNfc>().write(all);
+        ethtool(sock, ifname, buf.as_mut_ptr().cast())?;
+        std::slice::from_raw_parts(buf.as_ptr().cast::<u8>().add(header).cast::<u32>(), count)
+    };
+    (0..size).find(|loc| !used.contains(loc)).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::StorageFull,
+            format!("the ntuple rule table of {ifname} is full"),
+        )
+    })
+}
+
+/// Encodes `spec` as the flow type, header and mask of an ethtool flow spec.
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+fn encode(spec: &FlowSpec, fs: &mut RxFlowSpec) -> io::Result<()> {
+    let ipv6 = spec.is_ipv6()?;
+    fs.flow_type = match (spec.protocol, ipv6) {
+        (Some(FlowProtocol::Tcp), false) => TCP_V4_FLOW,
+        (Some(FlowProtocol::Udp), false) => UDP_V4_FLOW,
+        (Some(FlowProtocol::Tcp), true) => TCP_V6_FLOW,
+        (Some(FlowProtocol::Udp), true) => UDP_V6_FLOW,
+        (None, false) => {
+            // usrip4_spec.ip_ver, which is not masked.
+            fs.h_u[13] = ETH_RX_NFC_IP4;
+            IPV4_USER_FLOW
+        }
+        (None, true) => IPV6_USER_FLOW,
+    };
+
+    // The address and port fields are at the same offsets in the TCP/UDP and user
+    // specs of each family.
+    let addr_len = if ipv6 { 16 } else { 4 };
+    let mut set = |offset: usize, value: &[u8]| {
+        fs.h_u[offset..offset + value.len()].copy_from_slice(value);
+        fs.m_u[offset..offset + value.len()].fill(0xff);
+    };
+    for (offset, addr) in [(0, spec.src), (addr_len, spec.dst)] {
+        match addr {
+            Some(IpAddr::V4(addr)) => set(offset, &addr.octets()),
+            Some(IpAddr::V6(addr)) => set(offset, &addr.octets()),
+            None => {}
+        }
+    }
+    for (offset, port) in [
+        (2 * addr_len, spec.src_port),
+        (2 * addr_len + 2, spec.dst_port),
+    ] {
+        if let Some(port) = port {
+            set(offset, &port.to_be_bytes());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("invalid flow spec: {msg}"),
+    )
+}
+
+#[cfg(any(feature = "pcap", feature = "af-xdp", feature = "netmap"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn matches_kernel_layout() {
+        assert_eq!(size_of::<RxFlowSpec>(), 168);
+        assert_eq!(size_of::<RxNfc>(), 192);
+    }
+
+    #[test]
+    fn encodes_spec() {
+        let mut nfc = RxNfc::new(ETHTOOL_SRXCLSRLINS);
+        let spec = FlowSpec::tcp()
+            .dst(Ipv4Addr::new(10, 0, 0, 1).into())
+            .dst_port(443);
+        encode(&spec, &mut nfc.fs).unwrap();
+        assert_eq!(nfc.fs.flow_type, TCP_V4_FLOW);
+        assert_eq!(nfc.fs.h_u[..12], [0, 0, 0, 0, 10, 0, 0, 1, 0, 0, 1, 187]);
+        assert_eq!(
+            nfc.fs.m_u[..12],
+            [0, 0, 0, 0, 255, 255, 255, 255, 0, 0, 255, 255]
+        );
+
+        let mut nfc = RxNfc::new(ETHTOOL_SRXCLSRLINS);
+        encode(&FlowSpec::udp().src_port(53), &mut nfc.fs).unwrap();
+        assert_eq!(nfc.fs.flow_type, UDP_V4_FLOW);
+        assert_eq!(nfc.fs.h_u[8..10], [0, 53]);
+
+        let mut nfc = RxNfc::new(ETHTOOL_SRXCLSRLINS);
+        let spec = FlowSpec::default().src(Ipv6Addr::LOCALHOST.into());
+        encode(&spec, &mut nfc.fs).unwrap();
+        assert_eq!(nfc.fs.flow_type, IPV6_USER_FLOW);
+        assert_eq!(nfc.fs.h_u[15], 1);
+        assert_eq!(nfc.fs.m_u[..16], [255; 16]);
+    }
+
+    #[test]
+    fn rejects_inconsistent_spec() {
+        let spec = FlowSpec::default().dst_port(80);
+        assert!(spec.is_ipv6().is_err());
+        let spec = FlowSpec::udp()
+            .src(Ipv4Addr::LOCALHOST.into())
+            .dst(Ipv6Addr::LOCALHOST.into());
+        assert!(spec.is_ipv6().is_err());
+    }
+}
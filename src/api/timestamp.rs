@@ -0,0 +1,198 @@
+//! Packet timestamps.
+
+use std::fmt;
+use std::time::Duration;
+
+/// A packet timestamp with nanosecond resolution.
+///
+/// The epoch depends on the clock that took it: the Unix epoch for
+/// [`TimestampSource::Realtime`], boot for [`TimestampSource::Monotonic`], and
+/// whatever the NIC clock counts from for hardware timestamps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(u64);
+
+impl Timestamp {
+    /// Creates a timestamp from nanoseconds since the clock epoch.
+    pub const fn from_nanos(nanos: u64) -> Self {
+        Self(nanos)
+    }
+
+    /// Creates a timestamp from microseconds since the clock epoch.
+    pub const fn from_micros(micros: u64) -> Self {
+        Self(micros.saturating_mul(1_000))
+    }
+
+    /// Nanoseconds since the clock epoch.
+    pub const fn as_nanos(self) -> u64 {
+        self.0
+    }
+
+    /// Whole seconds since the clock epoch.
+    pub const fn as_secs(self) -> u64 {
+        self.0 / 1_000_000_000
+    }
+
+    /// Fractional part of the timestamp, in nanoseconds.
+    pub const fn subsec_nanos(self) -> u32 {
+        (self.0 % 1_000_000_000) as u32
+    }
+
+    /// Time elapsed since the clock epoch.
+    pub const fn as_duration(self) -> Duration {
+        Duration::from_nanos(self.0)
+    }
+}
+
+impl From<Timestamp> for Duration {
+    fn from(ts: Timestamp) -> Self {
+        ts.as_duration()
+    }
+}
+
+impl From<Duration> for Timestamp {
+    /// Saturates at `u64::MAX` nanoseconds, i.e. about 584 years.
+    fn from(d: Duration) -> Self {
+        Self(d.as_nanos().min(u64::MAX as u128) as u64)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:09}", self.as_secs(), self.subsec_nanos())
+    }
+}
+
+/// Clock used for software packet timestamps.
+///
+/// Every backend reports its software timestamps in the selected clock: kernel
+/// timestamps (pcap, netmap) are converted, backends without them (AF_XDP, DPDK) read
+/// the clock when the packet is received. By default pcap and netmap use
+/// [`Realtime`](TimestampSource::Realtime), AF_XDP and DPDK do not timestamp.
+///
+/// Hardware timestamps are always in the NIC clock, and timestamps read from capture
+/// files are reported as stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum TimestampSource {
+    /// `CLOCK_REALTIME`: time since the Unix epoch, subject to clock adjustments.
+    Realtime,
+    /// `CLOCK_MONOTONIC`: time since boot, never stepped.
+    Monotonic,
+    /// Packets are not timestamped in software.
+    None,
+}
+
+/// Whether a timestamp was taken in software or by the NIC, see
+/// [`Metadata::timestamp_kind`](super::Metadata::timestamp_kind).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampKind {
+    /// Taken by the kernel or the library when the packet was received.
+    Software,
+    /// Taken by the NIC.
+    Hardware,
+}
+
+/// Software timestamping of a socket in its [`TimestampSource`].
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SoftwareClock {
+    source: TimestampSource,
+    /// `CLOCK_REALTIME - CLOCK_MONOTONIC` when the socket was created.
+    offset: u64,
+}
+
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+impl SoftwareClock {
+    pub(crate) fn new(source: TimestampSource) -> Self {
+        let offset = match source {
+            TimestampSource::Monotonic => {
+                read_clock(libc::CLOCK_REALTIME).saturating_sub(read_clock(libc::CLOCK_MONOTONIC))
+            }
+            _ => 0,
+        };
+        Self { source, offset }
+    }
+
+    /// Timestamps a packet received now.
+    #[cfg(any(feature = "af-xdp", feature = "dpdk"))]
+    #[inline]
+    pub(crate) fn now(&self) -> Option<Timestamp> {
+        match self.source {
+            TimestampSource::Realtime => Some(Timestamp(read_clock(libc::CLOCK_REALTIME))),
+            TimestampSource::Monotonic => Some(Timestamp(read_clock(libc::CLOCK_MONOTONIC))),
+            TimestampSource::None => None,
+        }
+    }
+
+    /// Converts a software timestamp taken by the kernel with `CLOCK_REALTIME`.
+    ///
+    /// The offset between the clocks is sampled once, so a step of the realtime clock
+    /// after the socket was created shifts the converted timestamps.
+    #[cfg(any(test, feature = "pcap", feature = "netmap"))]
+    #[inline]
+    pub(crate) fn convert_realtime(&self, ts: Timestamp) -> Option<Timestamp> {
+        match self.source {
+            TimestampSource::Realtime => Some(ts),
+            TimestampSource::Monotonic => Some(Timestamp(ts.0.saturating_sub(self.offset))),
+            TimestampSource::None => None,
+        }
+    }
+}
+
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+fn read_clock(clock: libc::clockid_t) -> u64 {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `ts` is a valid timespec and both clocks exist on every Linux kernel.
+    unsafe { libc::clock_gettime(clock, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monotonic_conversion() {
+        let clock = SoftwareClock::new(TimestampSource::Monotonic);
+        let now = Timestamp(read_clock(libc::CLOCK_REALTIME));
+        let converted = clock.convert_realtime(now).unwrap();
+        let mono = read_clock(libc::CLOCK_MONOTONIC);
+        // Allow for a clock adjustment between the two readings.
+        assert!(converted.as_nanos().abs_diff(mono) < 1_000_000_000);
+
+        let none = SoftwareClock::new(TimestampSource::None);
+        assert_eq!(none.convert_realtime(now), None);
+        assert_eq!(
+            Timestamp::from_nanos(1_500_000_001).to_string(),
+            "1.500000001"
+        );
+    }
+}
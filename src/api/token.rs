@@ -2,6 +2,9 @@
 
 use std::mem::ManuallyDrop;
 use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+use std::rc::Rc;
+use std::sync::Arc;
 
 use super::buffer::BufferDesc;
 use super::context::Context;
@@ -50,6 +53,15 @@ impl Token {
     pub fn consume<'ctx, Ctx: Context>(self, ctx: &'ctx Ctx) -> Payload<'ctx, Ctx> {
         ctx.packet(self)
     }
+
+    /// Returns the buffer to the pool of `ctx` without looking at the packet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the token does not belong to `ctx`.
+    pub fn release<Ctx: Context>(self, ctx: &Ctx) {
+        ctx.packet(self).release();
+    }
 }
 
 /// A smart pointer to packet data that automatically releases the buffer on drop.
@@ -71,6 +83,53 @@ impl<'ctx, Ctx: Context> Payload<'ctx, Ctx> {
         }
     }
 
+    /// Returns the context the buffer belongs to.
+    pub fn context(&self) -> &'ctx Ctx {
+        self.ctx
+    }
+
+    /// Returns the packet bytes for in-place editing, e.g. to rewrite the MAC or IP
+    /// addresses before sending the packet out with [`forward`](super::forward).
+    ///
+    /// The buffer is exclusively owned by the payload on every backend that reports
+    /// [`Capabilities::writable_rx`](super::Capabilities::writable_rx): in the
+    /// zero-copy backends the edit lands directly in the AF_XDP frame, netmap slot or
+    /// mbuf. The length cannot change; trailing bytes beyond it are not part of the
+    /// packet.
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        self
+    }
+
+    /// Returns the buffer to its pool.
+    ///
+    /// Equivalent to dropping the payload, but makes the point where the backend may
+    /// hand the slot or frame back to the RX ring explicit.
+    pub fn release(self) {
+        drop(self);
+    }
+
+    /// Converts this payload into a reference-counted [`SharedPayload`].
+    pub fn into_shared(self) -> SharedPayload<'ctx, Ctx> {
+        SharedPayload(Rc::new(self))
+    }
+
+    /// Detaches the packet from the socket into an [`OwnedPacket`] that can be sent to
+    /// other threads.
+    ///
+    /// The buffer is not copied: it stays out of the pool until the last clone of the
+    /// `OwnedPacket` is dropped.
+    pub fn into_owned_packet(self) -> OwnedPacket<Ctx> {
+        let data = NonNull::from(&*self);
+        let ctx = self.ctx.clone();
+        let token = self.into_token();
+        OwnedPacket(Arc::new(OwnedBuffer {
+            data,
+            buf: token.buffer_desc(),
+            pool_id: token.pool_id(),
+            ctx,
+        }))
+    }
+
     /// Converts this payload back into a token without releasing the buffer.
     ///
     /// This is useful when you need to transfer ownership to another context.
@@ -113,3 +172,91 @@ impl<'ctx, Ctx: Context> Drop for Payload<'ctx, Ctx> {
         self.ctx.release(self.token.buffer_desc());
     }
 }
+
+/// A reference-counted [`Payload`].
+///
+/// Clones share the same packet buffer, which is released to the pool only when the
+/// last clone is dropped, so the backend never recycles a buffer that is still
+/// referenced.
+pub struct SharedPayload<'ctx, Ctx: Context>(Rc<Payload<'ctx, Ctx>>);
+
+impl<'ctx, Ctx: Context> SharedPayload<'ctx, Ctx> {
+    /// Returns the number of clones currently referencing the buffer.
+    pub fn ref_count(&self) -> usize {
+        Rc::strong_count(&self.0)
+    }
+
+    /// Returns the unique [`Payload`] if this is the last reference.
+    pub fn try_unwrap(self) -> std::result::Result<Payload<'ctx, Ctx>, Self> {
+        Rc::try_unwrap(self.0).map_err(Self)
+    }
+}
+
+impl<'ctx, Ctx: Context> Clone for SharedPayload<'ctx, Ctx> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<'ctx, Ctx: Context> Deref for SharedPayload<'ctx, Ctx> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// A received packet that is `Send` and `'static`, obtained through
+/// [`Payload::into_owned_packet`].
+///
+/// Holds a clone of the socket context, so it can outlive the borrow of the socket and
+/// be queued to worker threads. Clones share the buffer, which is released to the pool
+/// of the receiving socket when the last clone is dropped, possibly on another thread;
+/// the zero-copy backends take it back the next time they refill their rings.
+pub struct OwnedPacket<Ctx: Context>(Arc<OwnedBuffer<Ctx>>);
+
+struct OwnedBuffer<Ctx: Context> {
+    data: NonNull<[u8]>,
+    buf: BufferDesc,
+    pool_id: u32,
+    ctx: Ctx,
+}
+
+// SAFETY: the packet bytes are only read through shared references, and the context is
+// only used in `drop`, which has exclusive access. Contexts are `Send`, and
+// `release_detached` can be called on a clone from any thread.
+unsafe impl<Ctx: Context> Send for OwnedBuffer<Ctx> {}
+unsafe impl<Ctx: Context> Sync for OwnedBuffer<Ctx> {}
+
+impl<Ctx: Context> OwnedPacket<Ctx> {
+    /// Returns the number of clones currently referencing the buffer.
+    pub fn ref_count(&self) -> usize {
+        Arc::strong_count(&self.0)
+    }
+
+    /// Returns the ID of the pool the buffer belongs to.
+    pub fn pool_id(&self) -> u32 {
+        self.0.pool_id
+    }
+}
+
+impl<Ctx: Context> Clone for OwnedPacket<Ctx> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Ctx: Context> Deref for OwnedPacket<Ctx> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: the buffer is not released to the pool while `self` is alive.
+        unsafe { self.0.data.as_ref() }
+    }
+}
+
+impl<Ctx: Context> Drop for OwnedBuffer<Ctx> {
+    fn drop(&mut self) {
+        self.ctx.release_detached(self.buf);
+    }
+}
@@ -0,0 +1,47 @@
+//! Zero-copy transmit slots.
+
+use std::io::IoSlice;
+use std::ops::DerefMut;
+
+use super::Result;
+use super::checksum::TxChecksum;
+
+/// A transmit buffer reserved with [`Socket::tx_reserve`](super::Socket::tx_reserve).
+///
+/// The slot dereferences to the backend's TX buffer (a UMEM frame, a netmap slot or an
+/// mbuf), so the packet can be built in place. At least the requested number of bytes
+/// is available; the buffer may be larger.
+///
+/// [`commit`](TxSlot::commit) enqueues the packet for transmission, to be sent on the
+/// next flush like any other packet. Dropping the slot without committing it gives the
+/// buffer back to the backend and nothing is sent.
+pub trait TxSlot: DerefMut<Target = [u8]> {
+    /// Enqueues the first `len` bytes of the buffer for transmission.
+    ///
+    /// Returns [`Error::TooBigPacket`](crate::errors::Error::TooBigPacket) if `len`
+    /// exceeds the buffer.
+    fn commit(self, len: usize) -> Result<()>;
+
+    /// Has the checksums in `csum` filled in when the packet is committed: by the NIC
+    /// if the socket reports [`Capabilities::tx_checksum`], in software otherwise.
+    ///
+    /// [`Capabilities::tx_checksum`]: super::Capabilities::tx_checksum
+    fn set_checksum(&mut self, csum: TxChecksum);
+}
+
+/// Copies `bufs` back to back into `slot` and commits the result.
+pub(crate) fn gather<S: TxSlot>(mut slot: S, bufs: &[IoSlice<'_>]) -> Result<()> {
+    let mut len = 0;
+    for buf in bufs {
+        slot[len..len + buf.len()].copy_from_slice(buf);
+        len += buf.len();
+    }
+    slot.commit(len)
+}
+
+/// Copies `packet` into `slot` and commits it with the checksums in `csum`.
+pub(crate) fn checksummed<S: TxSlot>(mut slot: S, packet: &[u8], csum: TxChecksum) -> Result<()> {
+    slot[..packet.len()].copy_from_slice(packet);
+    slot.set_checksum(csum);
+    slot.commit(packet.len())
+}
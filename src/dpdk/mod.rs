@@ -2,23 +2,27 @@ mod wrapper;
 use crate::api;
 use crate::api::Result;
 use crate::api::Token;
+use crate::api::{RxSocket, TxSocket};
 use crate::errors::Error;
 use dpdk_sys::*;
 use std::mem::ManuallyDrop;
+use std::ptr::NonNull;
 use std::slice;
 use std::sync::atomic::AtomicU32;
 use std::sync::atomic::Ordering;
 use wrapper::Context;
 use wrapper::Receiver;
 use wrapper::RteMBuf;
-use wrapper::RteMBufRef;
 use wrapper::Transmitter;
 
 type RefCell<T> = crate::unsafe_refcell::UnsafeRefCell<T>;
+type UnsafeRefMut<'a, T> = crate::unsafe_refcell::UnsafeRefMut<'a, T>;
 
 #[derive(Clone)]
 pub struct Ctx {
     producer: RefCell<mpsc::Producer<api::BufferDesc>>,
+    /// Mbufs released by `OwnedPacket`s, shared by every clone.
+    returns: api::ReturnQueue,
     index: u32,
 }
 
@@ -39,10 +43,16 @@ impl Ctx {
         let (producer, cons) = mpsc::channel(nbufs);
         let res = Self {
             producer: RefCell::new(producer),
+            returns: api::ReturnQueue::new(nbufs),
             index: COUNTER.fetch_add(1, Ordering::SeqCst),
         };
         (res, cons)
     }
+
+    /// Moves the mbufs released by `OwnedPacket`s to the mbufs the socket frees.
+    fn reclaim(&self) {
+        self.returns.reclaim(&mut unsafe { self.producer.borrow_mut() });
+    }
 }
 
 impl api::Context for Ctx {
@@ -58,55 +68,317 @@ impl api::Context for Ctx {
         let mut producer_mut = unsafe { self.producer.borrow_mut() };
         producer_mut.push(buf_idx);
     }
+
+    fn release_detached(&self, buf_idx: api::BufferDesc) {
+        self.returns.push(buf_idx);
+    }
 }
 
 pub struct Sock {
-    tx: RefCell<Transmitter>,
+    rx: RxSock,
+    tx: TxSock,
+    max_frame_size: usize,
+}
+
+/// Receive half of a DPDK socket: the port's RX queue and the mbuf release queue.
+pub struct RxSock {
     rx: RefCell<Receiver>,
     ctx: Ctx,
     consumer: RefCell<mpsc::Consumer<api::BufferDesc>>,
+    queue: u16,
+    hw_timestamps: bool,
+    vlan_strip: bool,
+    fcs: api::RxFcs,
+    snaplen: Option<u32>,
+    clock: api::SoftwareClock,
+    filter: api::UserspaceFilter,
+    metrics: api::SocketMetrics,
+}
+
+/// Transmit half of a DPDK socket: the port's TX queue.
+pub struct TxSock {
+    tx: RefCell<Transmitter>,
+    flush_state: api::FlushState,
+    pacer: api::Pacer,
+    vlan_insert: Option<api::VlanTag>,
+    tx_checksum: bool,
+    tso: bool,
+    completions: api::TxCompletions,
+    metrics: api::SocketMetrics,
 }
 
-pub struct Meta {}
+/// `RTE_ETH_RX_OFFLOAD_VLAN_STRIP`: the NIC removes the outermost VLAN tag.
+const RX_OFFLOAD_VLAN_STRIP: u64 = 1 << 0;
+/// `RTE_ETH_RX_OFFLOAD_KEEP_CRC`: the NIC leaves the FCS at the end of the frame.
+const RX_OFFLOAD_KEEP_CRC: u64 = 1 << 16;
+/// `RTE_ETH_TX_OFFLOAD_VLAN_INSERT`: the NIC inserts the tag of mbufs flagged `TX_VLAN`.
+const TX_OFFLOAD_VLAN_INSERT: u64 = 1 << 0;
+/// `RTE_ETH_TX_OFFLOAD_{IPV4,UDP,TCP}_CKSUM`: the NIC computes the checksums of mbufs
+/// flagged `TX_*_CKSUM`.
+const TX_OFFLOAD_CHECKSUM: u64 = (1 << 1) | (1 << 2) | (1 << 3);
+/// `RTE_ETH_TX_OFFLOAD_TCP_TSO`: the NIC segments mbufs flagged `TX_TCP_SEG`.
+const TX_OFFLOAD_TCP_TSO: u64 = 1 << 5;
+/// `RTE_MBUF_F_TX_TCP_SEG`: segment the TCP packet into `tso_segsz`-byte segments.
+const TX_TCP_SEG: u64 = 1 << 50;
+/// `RTE_MBUF_F_TX_VLAN`: insert `vlan_tci` as an 802.1Q tag on transmit.
+const TX_VLAN: u64 = 1 << 57;
+/// `RTE_MBUF_F_TX_TCP_CKSUM`: compute the TCP checksum.
+const TX_TCP_CKSUM: u64 = 1 << 52;
+/// `RTE_MBUF_F_TX_UDP_CKSUM`: compute the UDP checksum.
+const TX_UDP_CKSUM: u64 = 3 << 52;
+/// `RTE_MBUF_F_TX_IP_CKSUM`: compute the IPv4 header checksum.
+const TX_IP_CKSUM: u64 = 1 << 54;
+/// `RTE_MBUF_F_TX_IPV4`: the packet is IPv4.
+const TX_IPV4: u64 = 1 << 55;
+/// `RTE_MBUF_F_TX_IPV6`: the packet is IPv6.
+const TX_IPV6: u64 = 1 << 56;
+/// `RTE_MBUF_F_RX_RSS_HASH`: `hash.rss` holds the NIC-computed RSS hash.
+const RX_RSS_HASH: u64 = 1 << 1;
+/// `RTE_MBUF_F_RX_VLAN_STRIPPED`: the NIC removed the tag and stored it in `vlan_tci`.
+const RX_VLAN_STRIPPED: u64 = 1 << 6;
+/// `RTE_MBUF_F_RX_IEEE1588_TMST`: the NIC latched an RX timestamp for this packet.
+const RX_IEEE1588_TMST: u64 = 1 << 10;
+/// `RTE_PKTMBUF_HEADROOM` of the default DPDK build configuration.
+const PKTMBUF_HEADROOM: usize = 128;
+
+/// Per-packet metadata from the mbuf header.
+///
+/// `caplen` is the length of the first segment, or of the whole chain with `recv_packet`,
+/// and `wirelen` the length of the whole chain.
+/// With [`DpdkFlags::hw_timestamps`], the packets the NIC timestamped (usually PTP
+/// frames only) carry the hardware timestamp; the others are timestamped in software
+/// according to [`DpdkFlags::timestamp_source`].
+pub struct Meta {
+    pub caplen: u32,
+    pub wirelen: u32,
+    pub queue: u16,
+    pub rss_hash: Option<u32>,
+    pub timestamp: Option<api::Timestamp>,
+    /// The timestamp was taken by the NIC.
+    pub hw_timestamp: bool,
+    pub vlan: Option<api::VlanTag>,
+    /// The frame ends with its FCS.
+    pub fcs: bool,
+}
 
 impl api::Metadata for Meta {
     fn into_enum(self) -> api::MetadataType {
         api::MetadataType::Dpdk(self)
     }
+
+    fn caplen(&self) -> u32 {
+        self.caplen
+    }
+
+    fn wirelen(&self) -> u32 {
+        self.wirelen
+    }
+
+    fn vlan(&self) -> Option<api::VlanTag> {
+        self.vlan
+    }
+
+    fn rss_hash(&self) -> Option<u32> {
+        self.rss_hash
+    }
+
+    fn queue(&self) -> Option<u32> {
+        Some(self.queue as u32)
+    }
+
+    fn has_fcs(&self) -> bool {
+        self.fcs
+    }
+
+    fn timestamp(&self) -> Option<api::Timestamp> {
+        self.timestamp
+    }
+
+    fn timestamp_kind(&self) -> Option<api::TimestampKind> {
+        self.timestamp.map(|_| {
+            if self.hw_timestamp {
+                api::TimestampKind::Hardware
+            } else {
+                api::TimestampKind::Software
+            }
+        })
+    }
 }
 
-impl Sock {
+impl RxSock {
     //#[inline(never)]
     //#[cold]
     fn flush_to_memory_pool(&self) {
+        self.ctx.reclaim();
         let mut consumer = unsafe { self.consumer.borrow_mut() };
         consumer.sync();
         let buf = &mut consumer.cached;
         unsafe { rust_rte_pktmbuf_free_bulk(buf.as_mut_ptr() as *mut _, buf.len() as u32) };
+        trace_event!(
+            trace,
+            backend = "dpdk",
+            mbufs = buf.len(),
+            "released mbufs to the pool"
+        );
         buf.clear();
     }
 
+    /// Returns the next packet that passes the filter, or `None` if the queue is empty.
+    fn next_packet(&self) -> Result<Option<(Token, Meta)>> {
+        let mut flushed = false;
+        loop {
+            match unsafe { self.rx.borrow_mut().iter_mut().next() } {
+                Some(buf) => {
+                    if let Some(pkt) = self.recv_inner(buf)? {
+                        return Ok(Some(pkt));
+                    }
+                }
+                None if !flushed => {
+                    self.metrics.rx_sync();
+                    self.flush_to_memory_pool();
+                    flushed = true;
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Turns an mbuf into a packet; mbufs rejected by the filter are released.
     #[inline(always)]
-    fn recv_inner(&self, buf: RteMBuf) -> Result<(Token, Meta)> {
+    fn recv_inner(&self, buf: RteMBuf) -> Result<Option<(Token, Meta)>> {
         let buf = ManuallyDrop::new(buf);
         let token = buf.as_ptr() as usize;
         let token = api::BufferDesc::from(token);
 
-        let size = {
-            let m = buf.as_ptr();
-            unsafe { (*m).__bindgen_anon_2.__bindgen_anon_1.data_len as u32 }
+        let m = buf.as_ptr();
+        let (frame, wirelen) = unsafe {
+            let fields = &(*m).__bindgen_anon_2.__bindgen_anon_1;
+            let data = rust_rte_pktmbuf_mtod(m) as *const u8;
+            (
+                slice::from_raw_parts(data, fields.data_len as usize),
+                fields.pkt_len,
+            )
+        };
+        if !self.filter.accepts(frame) {
+            api::Context::release(&self.ctx, token);
+            return Ok(None);
+        }
+
+        self.metrics.rx(frame.len());
+        let size = frame.len() as u32;
+        let size = self.snaplen.map_or(size, |snaplen| size.min(snaplen));
+        let (vlan, rss_hash) = unsafe {
+            let fields = &(*m).__bindgen_anon_2.__bindgen_anon_1;
+            let rss_hash = ((*m).ol_flags & RX_RSS_HASH != 0).then(|| fields.hash.rss);
+            let vlan = if (*m).ol_flags & RX_VLAN_STRIPPED != 0 {
+                Some(api::VlanTag {
+                    tpid: api::VlanTag::TPID_8021Q,
+                    tci: fields.vlan_tci,
+                })
+            } else {
+                api::VlanTag::parse(frame)
+            };
+            (vlan, rss_hash)
+        };
+        let hw_timestamp = if self.hw_timestamps && unsafe { (*m).ol_flags } & RX_IEEE1588_TMST != 0
+        {
+            unsafe { self.rx.borrow() }.read_rx_timestamp()
+        } else {
+            None
         };
         let token = ManuallyDrop::new(Token {
             idx: token,
             len: size,
             buffer_pool: api::Context::pool_id(&self.ctx),
         });
-        let meta = Meta {};
-        Ok((ManuallyDrop::into_inner(token), meta))
+        let meta = Meta {
+            caplen: size,
+            wirelen,
+            queue: self.queue,
+            rss_hash,
+            timestamp: hw_timestamp.or_else(|| self.clock.now()),
+            hw_timestamp: hw_timestamp.is_some(),
+            vlan,
+            fcs: self.fcs.present(),
+        };
+        Ok(Some((ManuallyDrop::into_inner(token), meta)))
+    }
+}
+
+impl api::RxSocket for RxSock {
+    type Context = Ctx;
+    type Metadata = Meta;
+
+    fn recv_token(&self) -> Result<(Token, Self::Metadata)> {
+        self.next_packet()?.ok_or(Error::NoPacket)
+    }
+
+    fn try_recv_token(&self) -> Result<(Token, Self::Metadata)> {
+        // Ring scans never block: an empty ring is the only way to get `NoPacket`.
+        match self.recv_token() {
+            Err(Error::NoPacket) => Err(Error::WouldBlock),
+            res => res,
+        }
+    }
+
+    fn recv_packet(&self) -> Result<(api::Packet<'_, Ctx>, Meta)> {
+        let (token, mut meta) = self.recv_token()?;
+        let mut next = unsafe { rust_rte_pktmbuf_next(usize::from(token.buffer_desc()) as _) };
+        let mut packet = api::Packet::from(token.consume(&self.ctx));
+        // The rest of the chain goes back to the pool along with the first mbuf.
+        while let Some(m) = NonNull::new(next) {
+            let m = m.as_ptr();
+            unsafe {
+                let len = (*m).__bindgen_anon_2.__bindgen_anon_1.data_len as usize;
+                let data = rust_rte_pktmbuf_mtod(m) as *mut u8;
+                packet.push_chained(NonNull::from(slice::from_raw_parts_mut(data, len)));
+                next = rust_rte_pktmbuf_next(m);
+            }
+        }
+        meta.caplen = packet.len() as u32;
+        Ok((packet, meta))
+    }
+
+    fn recv_batch(&self, budget: usize) -> Result<Vec<(Token, Self::Metadata)>> {
+        let mut batch = Vec::with_capacity(budget);
+        {
+            let mut rx = unsafe { self.rx.borrow_mut() };
+            api::fill_batch(&mut batch, rx.iter_mut().take(budget), |buf| {
+                self.recv_inner(buf)
+            })?;
+        }
+        if batch.is_empty() && budget > 0 {
+            self.metrics.rx_sync();
+            self.flush_to_memory_pool();
+            let mut rx = unsafe { self.rx.borrow_mut() };
+            api::fill_batch(&mut batch, rx.iter_mut().take(budget), |buf| {
+                self.recv_inner(buf)
+            })?;
+            if batch.is_empty() {
+                return Err(Error::NoPacket);
+            }
+        }
+        Ok(batch)
+    }
+
+    fn set_filter(&self, expr: &str) -> Result<()> {
+        // Filtering runs in userspace on the received mbufs.
+        self.filter.set(expr)
+    }
+
+    fn dropped(&self) -> api::Dropped {
+        // The PMD allocates the RX mbufs and counts its failures in `rx_nombuf`.
+        api::Dropped::default()
+    }
+
+    fn context(&self) -> &Self::Context {
+        &self.ctx
     }
+}
 
-    fn send_inner(&self, scan: RteMBufRef, packet: &[u8]) -> Result<()> {
-        let m = scan.as_ptr().as_ptr();
+impl TxSock {
+    fn send_inner(&self, mbuf: NonNull<rte_mbuf>, packet: &[u8]) -> Result<()> {
+        let m = mbuf.as_ptr();
         let len = packet.len() as u16;
         let buf = unsafe {
             if len > (*m).__bindgen_anon_2.__bindgen_anon_1.buf_len {
@@ -119,47 +391,412 @@ impl Sock {
         };
         let slice_mut = unsafe { slice::from_raw_parts_mut(buf, len as usize) };
         slice_mut.copy_from_slice(packet);
+        self.tag(m);
         Ok(())
     }
+
+    /// Fills in the checksums in `csum` of `frame`, the data of `m`: by the NIC if the
+    /// port was started with checksum offloads, in software otherwise.
+    fn checksum(&self, m: *mut rte_mbuf, frame: &mut [u8], csum: api::TxChecksum) {
+        if !self.tx_checksum {
+            api::checksum::fill(frame, csum);
+            return;
+        }
+        let Some(headers) = api::checksum::Headers::parse(frame) else {
+            return;
+        };
+        let mut flags = if headers.ipv6 { TX_IPV6 } else { TX_IPV4 };
+        if csum.ip && !headers.ipv6 {
+            flags |= TX_IP_CKSUM;
+            let l3 = headers.l2_len;
+            frame[l3 + 10..l3 + 12].fill(0);
+        }
+        if csum.l4
+            && let Some(offset) = headers.l4_checksum_offset()
+        {
+            flags |= match headers.proto {
+                api::checksum::IPPROTO_TCP => TX_TCP_CKSUM,
+                _ => TX_UDP_CKSUM,
+            };
+            // The NIC expects the sum of the pseudo-header in the checksum field.
+            let at = headers.l2_len + headers.l3_len + offset;
+            let pseudo = headers.pseudo_header_sum(frame);
+            frame[at..at + 2].copy_from_slice(&pseudo.to_be_bytes());
+        }
+        unsafe {
+            (*m).ol_flags |= flags;
+            (*m).__bindgen_anon_3.tx_offload = headers.l2_len as u64 | (headers.l3_len as u64) << 7;
+        }
+    }
+
+    /// Hands a TCP `packet` to the NIC to be segmented into `mss`-byte segments, and
+    /// returns the number of segments; `None` if the NIC cannot take it in one mbuf.
+    fn send_tso(&self, packet: &[u8], mss: usize) -> Result<Option<usize>> {
+        let headers = api::checksum::Headers::parse(packet)
+            .filter(|h| self.tso && h.proto == api::checksum::IPPROTO_TCP);
+        let Some((headers, l4_header_len)) =
+            headers.and_then(|h| Some((h, h.l4_header_len(packet)?)))
+        else {
+            return Ok(None);
+        };
+        if mss == 0 || mss > u16::MAX as usize {
+            return Ok(None);
+        }
+        let mut tx = unsafe { self.tx.borrow_mut() };
+        let mbuf = tx.reserve().ok_or(Error::NoMemory)?;
+        let m = mbuf.as_ptr();
+        let data = unsafe {
+            let buf_len = (*m).__bindgen_anon_2.__bindgen_anon_1.buf_len as usize;
+            slice::from_raw_parts_mut((*m).buf_addr as *mut u8, buf_len)
+        };
+        if packet.len() > data.len() {
+            tx.unreserve(mbuf);
+            return Ok(None);
+        }
+        let frame = &mut data[..packet.len()];
+        frame.copy_from_slice(packet);
+        let l4 = headers.l4_offset();
+        let mut flags = TX_TCP_SEG | TX_TCP_CKSUM;
+        if headers.ipv6 {
+            flags |= TX_IPV6;
+        } else {
+            flags |= TX_IPV4 | TX_IP_CKSUM;
+            frame[headers.l2_len + 10..headers.l2_len + 12].fill(0);
+        }
+        let pseudo = headers.pseudo_header_sum_no_len(frame);
+        frame[l4 + 16..l4 + 18].copy_from_slice(&pseudo.to_be_bytes());
+        unsafe {
+            (*m).__bindgen_anon_1.__bindgen_anon_1.data_off = 0;
+            (*m).__bindgen_anon_2.__bindgen_anon_1.data_len = packet.len() as u16;
+            (*m).__bindgen_anon_2.__bindgen_anon_1.pkt_len = packet.len() as u32;
+            (*m).ol_flags |= flags;
+            (*m).__bindgen_anon_3.tx_offload = headers.l2_len as u64
+                | (headers.l3_len as u64) << 7
+                | (l4_header_len as u64) << 16
+                | (mss as u64) << 24;
+        }
+        self.tag(m);
+        tx.enqueue(mbuf);
+        drop(tx);
+        self.queued(packet.len());
+        let payload = headers.l4_len - l4_header_len;
+        Ok(Some(payload.div_ceil(mss).max(1)))
+    }
+
+    /// Has the NIC insert the configured VLAN tag, if any, into `m`.
+    fn tag(&self, m: *mut rte_mbuf) {
+        if let Some(tag) = self.vlan_insert {
+            unsafe {
+                (*m).ol_flags |= TX_VLAN;
+                (*m).__bindgen_anon_2.__bindgen_anon_1.vlan_tci = tag.tci;
+            }
+        }
+    }
+
+    /// Records a queued packet of `len` bytes and bursts the queued mbufs if the pacing
+    /// or the flush policy asks for it.
+    fn queued(&self, len: usize) {
+        self.completions.queued();
+        self.metrics.tx(len);
+        if self.pacer.pace(len) | self.flush_state.queued() {
+            api::TxSocket::flush(self);
+        }
+    }
+}
+
+impl api::TxSocket for TxSock {
+    type Slot<'a> = TxSlot<'a>;
+
+    fn send(&self, packet: &[u8]) -> Result<()> {
+        let mut tx = unsafe { self.tx.borrow_mut() };
+        let mbuf = tx.reserve().ok_or(Error::NoPacket)?;
+        if let Err(e) = self.send_inner(mbuf, packet) {
+            tx.unreserve(mbuf);
+            return Err(e);
+        }
+        tx.enqueue(mbuf);
+        drop(tx);
+        self.queued(packet.len());
+        Ok(())
+    }
+
+    fn send_batch<P: AsRef<[u8]>>(&self, packets: &[P]) -> Result<usize> {
+        if self.pacer.is_limited() {
+            // Paced packets leave one at a time.
+            let mut sent = 0;
+            for packet in packets {
+                match self.send(packet.as_ref()) {
+                    Ok(()) => sent += 1,
+                    Err(e) if sent == 0 => return Err(e),
+                    Err(_) => break,
+                }
+            }
+            return Ok(sent);
+        }
+        let mut tx = unsafe { self.tx.borrow_mut() };
+        // A single iterator for the whole batch: dropping it issues one tx burst.
+        let mut iter = tx.iter_mut();
+        let mut sent = 0;
+        for packet in packets {
+            let Some(scan) = iter.next() else {
+                break;
+            };
+            match self.send_inner(scan.as_ptr(), packet.as_ref()) {
+                Ok(()) => {
+                    self.completions.queued();
+                    self.metrics.tx(packet.as_ref().len());
+                    sent += 1;
+                }
+                Err(e) if sent == 0 => return Err(e),
+                Err(_) => break,
+            }
+        }
+        drop(iter);
+        self.flush_state.flushed();
+        self.metrics.tx_flush();
+        if sent == 0 && !packets.is_empty() {
+            return Err(Error::NoPacket);
+        }
+        Ok(sent)
+    }
+
+    fn flush(&self) {
+        self.flush_state.flushed();
+        self.metrics.tx_flush();
+        let mut tx = unsafe { self.tx.borrow_mut() };
+        tx.flush();
+        // Mbufs the NIC did not accept stay queued for the next burst.
+        trace_event!(trace, backend = "dpdk", pending = tx.pending(), "TX burst");
+    }
+
+    fn send_with_cookie(&self, packet: &[u8], cookie: u64) -> Result<()> {
+        self.send(packet)?;
+        self.completions.tag_last(cookie);
+        Ok(())
+    }
+
+    fn tx_completions(&self, out: &mut Vec<u64>) -> usize {
+        let in_flight = unsafe { self.tx.borrow() }.in_flight();
+        self.completions.drain(in_flight, out)
+    }
+
+    fn send_gso(&self, packet: &[u8], mss: usize) -> Result<usize> {
+        match self.send_tso(packet, mss)? {
+            Some(segments) => Ok(segments),
+            None => api::gso::segment(|len| self.tx_reserve(len), packet, mss),
+        }
+    }
+
+    fn tx_reserve(&self, len: usize) -> Result<TxSlot<'_>> {
+        let mut tx = unsafe { self.tx.borrow_mut() };
+        let mbuf = tx.reserve().ok_or(Error::NoMemory)?;
+        let m = mbuf.as_ptr();
+        let data = unsafe {
+            let buf_len = (*m).__bindgen_anon_2.__bindgen_anon_1.buf_len as usize;
+            slice::from_raw_parts_mut((*m).buf_addr as *mut u8, buf_len)
+        };
+        if len > data.len() {
+            tx.unreserve(mbuf);
+            return Err(Error::TooBigPacket(len));
+        }
+        Ok(TxSlot {
+            tx,
+            mbuf: Some(mbuf),
+            data,
+            sock: self,
+            checksum: api::TxChecksum::NONE,
+        })
+    }
+}
+
+/// Zero-copy TX slot of the DPDK backend: an mbuf taken from the TX pool.
+pub struct TxSlot<'a> {
+    tx: UnsafeRefMut<'a, Transmitter>,
+    mbuf: Option<NonNull<rte_mbuf>>,
+    data: &'a mut [u8],
+    sock: &'a TxSock,
+    checksum: api::TxChecksum,
+}
+
+impl std::ops::Deref for TxSlot<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.data
+    }
+}
+
+impl std::ops::DerefMut for TxSlot<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.data
+    }
+}
+
+impl api::TxSlot for TxSlot<'_> {
+    fn commit(mut self, len: usize) -> Result<()> {
+        if len > self.data.len() {
+            return Err(Error::TooBigPacket(len));
+        }
+        let mbuf = self.mbuf.take().expect("slot committed twice");
+        let m = mbuf.as_ptr();
+        unsafe {
+            (*m).__bindgen_anon_1.__bindgen_anon_1.data_off = 0;
+            (*m).__bindgen_anon_2.__bindgen_anon_1.data_len = len as u16;
+            (*m).__bindgen_anon_2.__bindgen_anon_1.pkt_len = len as u32;
+        }
+        if !self.checksum.is_none() {
+            self.sock.checksum(m, &mut self.data[..len], self.checksum);
+        }
+        self.sock.tag(m);
+        self.tx.enqueue(mbuf);
+        // Release the transmitter before a possible burst.
+        let sock = self.sock;
+        drop(self);
+        sock.queued(len);
+        Ok(())
+    }
+
+    fn set_checksum(&mut self, csum: api::TxChecksum) {
+        self.checksum = csum;
+    }
+}
+
+impl Drop for TxSlot<'_> {
+    fn drop(&mut self) {
+        if let Some(mbuf) = self.mbuf.take() {
+            self.tx.unreserve(mbuf);
+        }
+    }
 }
 
 impl api::Socket for Sock {
     type Context = Ctx;
     type Metadata = Meta;
     type Flags = DpdkFlags;
+    type Rx = RxSock;
+    type Tx = TxSock;
+    type Slot<'a> = TxSlot<'a>;
 
     fn recv_token(&self) -> Result<(Token, Self::Metadata)> {
-        if let Some(tmp) = unsafe { self.rx.borrow_mut().iter_mut().next() } {
-            self.recv_inner(tmp)
-        } else {
-            self.flush_to_memory_pool();
-            let mut rx = unsafe { self.rx.borrow_mut() };
-            //let mut consumer = self.consumer.borrow_mut();
-            let tmp = rx.iter_mut().next().ok_or(Error::NoPacket)?;
-            self.recv_inner(tmp)
-        }
+        self.rx.recv_token()
+    }
+
+    fn try_recv_token(&self) -> Result<(Token, Self::Metadata)> {
+        self.rx.try_recv_token()
+    }
+
+    fn recv_packet(&self) -> Result<(api::Packet<'_, Ctx>, Meta)> {
+        self.rx.recv_packet()
+    }
+
+    fn recv_batch(&self, budget: usize) -> Result<Vec<(Token, Self::Metadata)>> {
+        self.rx.recv_batch(budget)
     }
 
     fn send(&self, packet: &[u8]) -> Result<()> {
-        let mut tx = unsafe { self.tx.borrow_mut() };
-        let scan = tx.iter_mut().next().ok_or(Error::NoPacket)?;
-        self.send_inner(scan, packet)
+        self.tx.send(packet)
+    }
+
+    fn send_batch<P: AsRef<[u8]>>(&self, packets: &[P]) -> Result<usize> {
+        self.tx.send_batch(packets)
     }
 
     fn flush(&self) {
-        unsafe { self.tx.borrow_mut().flush() };
+        self.tx.flush();
+    }
+
+    fn send_with_cookie(&self, packet: &[u8], cookie: u64) -> Result<()> {
+        self.tx.send_with_cookie(packet, cookie)
+    }
+
+    fn tx_completions(&self, out: &mut Vec<u64>) -> usize {
+        self.tx.tx_completions(out)
+    }
+
+    fn send_gso(&self, packet: &[u8], mss: usize) -> Result<usize> {
+        self.tx.send_gso(packet, mss)
+    }
+
+    fn tx_reserve(&self, len: usize) -> Result<TxSlot<'_>> {
+        self.tx.tx_reserve(len)
+    }
+
+    fn set_filter(&self, expr: &str) -> Result<()> {
+        self.rx.set_filter(expr)
+    }
+
+    fn set_promiscuous(&self, enable: bool) -> Result<()> {
+        Ok(unsafe { self.rx.rx.borrow() }.set_promiscuous(enable)?)
+    }
+
+    fn join_multicast(&self, mac: [u8; 6]) -> Result<()> {
+        api::netdev::check_multicast(mac)?;
+        Ok(unsafe { self.rx.rx.borrow_mut() }.join_multicast(mac)?)
+    }
+
+    fn leave_multicast(&self, mac: [u8; 6]) -> Result<()> {
+        Ok(unsafe { self.rx.rx.borrow_mut() }.leave_multicast(mac)?)
+    }
+
+    fn link_info(&self) -> Result<api::LinkInfo> {
+        Ok(unsafe { self.rx.rx.borrow() }.link_info()?)
+    }
+
+    fn steer(&self, spec: api::FlowSpec, queue: usize) -> Result<api::FlowRule> {
+        Ok(unsafe { self.rx.rx.borrow() }.steer(&spec, queue)?)
+    }
+
+    fn unsteer(&self, rule: api::FlowRule) -> Result<()> {
+        Ok(unsafe { self.rx.rx.borrow() }.unsteer(rule)?)
     }
 
     fn create(portspec: &str, queue: Option<usize>, flags: Self::Flags) -> Result<Self> {
+        let mut rx_offloads = if flags.vlan_strip {
+            RX_OFFLOAD_VLAN_STRIP
+        } else {
+            0
+        };
+        if flags.keep_crc {
+            rx_offloads |= RX_OFFLOAD_KEEP_CRC;
+        }
+        let mut tx_offloads = if flags.vlan_insert.is_some() {
+            TX_OFFLOAD_VLAN_INSERT
+        } else {
+            0
+        };
+        if flags.tx_checksum || flags.tso {
+            tx_offloads |= TX_OFFLOAD_CHECKSUM;
+        }
+        if flags.tso {
+            tx_offloads |= TX_OFFLOAD_TCP_TSO;
+        }
         let (mut buffer_pool, rx, tx) = Context::create(
             portspec,
             flags.num_mbufs,
             flags.mbuf_cache_size,
             flags.mbuf_default_buf_size,
             queue.unwrap_or(0) as u16,
+            rx_offloads,
+            tx_offloads,
         )?;
+        if flags.hw_timestamps {
+            rx.enable_timesync()?;
+        }
+        if flags.promiscuous {
+            rx.set_promiscuous(true)?;
+        }
+        if flags.allmulti {
+            rx.set_allmulti(true)?;
+        }
+        let fcs = if rx.rx_offloads()? & RX_OFFLOAD_KEEP_CRC != 0 {
+            api::RxFcs::Present
+        } else {
+            api::RxFcs::Absent
+        };
 
+        trace_event!(debug, backend = "dpdk", portspec, queue = ?queue, "socket created");
         let (ctx, consumer) = Ctx::new(flags.num_mbufs as usize);
+        let metrics = api::SocketMetrics::new(api::Backend::Dpdk, portspec, queue);
         loop {
             let tmp = buffer_pool.allocate();
             if tmp.is_null() {
@@ -171,26 +808,230 @@ impl api::Socket for Sock {
             a.push(tmp);
         }
         Ok(Self {
-            tx: RefCell::new(tx),
-            rx: RefCell::new(rx),
-            ctx,
-            consumer: RefCell::new(consumer),
+            rx: RxSock {
+                rx: RefCell::new(rx),
+                ctx,
+                consumer: RefCell::new(consumer),
+                queue: queue.unwrap_or(0) as u16,
+                hw_timestamps: flags.hw_timestamps,
+                vlan_strip: flags.vlan_strip,
+                fcs,
+                snaplen: flags.snaplen,
+                clock: api::SoftwareClock::new(flags.timestamp_source),
+                filter: api::UserspaceFilter::default(),
+                metrics: metrics.clone(),
+            },
+            tx: TxSock {
+                tx: RefCell::new(tx),
+                flush_state: api::FlushState::new(flags.flush_policy),
+                pacer: api::Pacer::new(flags.tx_rate),
+                vlan_insert: flags.vlan_insert,
+                tx_checksum: flags.tx_checksum || flags.tso,
+                tso: flags.tso,
+                completions: api::TxCompletions::new(),
+                metrics,
+            },
+            max_frame_size: (flags.mbuf_default_buf_size as usize).saturating_sub(PKTMBUF_HEADROOM),
         })
     }
 
+    fn create_group(_portspec: &str, _flags: Self::Flags) -> Result<api::SocketGroup<Self>> {
+        // The EAL is initialized once per process and the port is configured with a
+        // single RX queue, so a second socket on the same port cannot be opened.
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "socket groups are not supported by the DPDK backend",
+        )
+        .into())
+    }
+
     fn context(&self) -> &Self::Context {
-        &self.ctx
+        &self.rx.ctx
+    }
+
+    fn stats(&self) -> Result<api::Stats> {
+        // Port-wide device counters: they include the traffic of every queue.
+        let stats = unsafe { self.rx.rx.borrow() }.port_stats()?;
+        Ok(api::Stats {
+            rx_packets: stats.ipackets,
+            rx_bytes: stats.ibytes,
+            rx_dropped: stats.imissed + stats.rx_nombuf,
+            rx_errors: stats.ierrors,
+            tx_packets: stats.opackets,
+            tx_bytes: stats.obytes,
+            tx_errors: stats.oerrors,
+        })
+    }
+
+    fn dropped(&self) -> api::Dropped {
+        api::RxSocket::dropped(&self.rx)
+    }
+
+    fn capabilities(&self) -> api::Capabilities {
+        api::Capabilities {
+            zero_copy_rx: true,
+            zero_copy_tx: true,
+            writable_rx: true,
+            hw_timestamps: self.rx.hw_timestamps,
+            multi_queue: true,
+            socket_groups: false,
+            vlan_strip: self.rx.vlan_strip,
+            tx_checksum: self.tx.tx_checksum,
+            tso: self.tx.tso,
+            kernel_filter: false,
+            zero_copy_driver: true,
+            max_frame_size: self.max_frame_size,
+        }
+    }
+
+    fn close(self) -> Result<()> {
+        let drained = api::drain_tx(|| {
+            let mut tx = unsafe { self.tx.tx.borrow_mut() };
+            tx.flush();
+            Ok(tx.pending() > 0)
+        });
+        unsafe { self.rx.rx.borrow_mut() }.release_pending();
+        self.rx.ctx.reclaim();
+        unsafe { self.rx.ctx.producer.borrow_mut() }.flush();
+        self.rx.flush_to_memory_pool();
+        // Dropping the socket stops the port again and ignores the result.
+        let stopped = unsafe { self.rx.rx.borrow() }.stop_port();
+        drained.and(stopped.map_err(Error::from))
+    }
+
+    fn split(self) -> (RxSock, TxSock) {
+        (self.rx, self.tx)
     }
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct DpdkFlags {
     pub num_mbufs: u32,
     pub mbuf_cache_size: u32,
     pub mbuf_default_buf_size: u16,
+    /// Enable IEEE 1588 timesync on the port and report the RX timestamps it latches.
+    pub hw_timestamps: bool,
+    /// Receive frames addressed to other hosts (`rte_eth_promiscuous_enable`).
+    pub promiscuous: bool,
+    /// Receive every multicast frame (`rte_eth_allmulticast_enable`).
+    pub allmulti: bool,
+    /// When queued TX packets are burst out; by default every packet is sent as soon as
+    /// it is queued.
+    pub flush_policy: api::FlushPolicy,
+    /// Rate at which packets are transmitted.
+    pub tx_rate: api::TxRate,
+    /// Have the NIC remove the outermost VLAN tag of received frames
+    /// (`RTE_ETH_RX_OFFLOAD_VLAN_STRIP`); the port fails to start if it cannot.
+    pub vlan_strip: bool,
+    /// VLAN tag the NIC inserts into every transmitted frame
+    /// (`RTE_ETH_TX_OFFLOAD_VLAN_INSERT`). The NIC always inserts an 802.1Q tag, so
+    /// only the TCI is used.
+    pub vlan_insert: Option<api::VlanTag>,
+    /// Have the NIC compute the checksums requested per packet
+    /// (`RTE_ETH_TX_OFFLOAD_{IPV4,UDP,TCP}_CKSUM`); otherwise they are computed in
+    /// software.
+    pub tx_checksum: bool,
+    /// Have the NIC segment the TCP packets sent with `send_gso`
+    /// (`RTE_ETH_TX_OFFLOAD_TCP_TSO`), as long as they fit in an mbuf; implies
+    /// [`tx_checksum`](Self::tx_checksum).
+    pub tso: bool,
+    /// Have the NIC leave the FCS at the end of received frames
+    /// (`RTE_ETH_RX_OFFLOAD_KEEP_CRC`); NICs that cannot strip it as usual.
+    pub keep_crc: bool,
+    /// Maximum number of bytes of each received frame exposed to the application; the
+    /// NIC still writes the whole frame into the mbuf.
+    pub snaplen: Option<u32>,
+    /// Clock read on receive to timestamp the packets without a hardware timestamp.
+    pub timestamp_source: api::TimestampSource,
+}
+
+impl Default for DpdkFlags {
+    fn default() -> Self {
+        Self {
+            num_mbufs: 8192,
+            mbuf_cache_size: 250,
+            mbuf_default_buf_size: 2176,
+            hw_timestamps: false,
+            promiscuous: false,
+            allmulti: false,
+            flush_policy: api::FlushPolicy::Auto {
+                packets: 1,
+                interval: std::time::Duration::ZERO,
+            },
+            tx_rate: api::TxRate::Unlimited,
+            vlan_strip: false,
+            vlan_insert: None,
+            tx_checksum: false,
+            tso: false,
+            keep_crc: false,
+            snaplen: None,
+            timestamp_source: api::TimestampSource::None,
+        }
+    }
+}
+
+impl api::Flags for DpdkFlags {
+    fn apply(&mut self, options: &api::SocketOptions) {
+        if let Some(size) = options.buffer_size {
+            self.mbuf_default_buf_size = size.min(u16::MAX as u32) as u16;
+        }
+        if let Some(count) = options.num_buffers {
+            self.num_mbufs = count;
+        }
+        if let Some(enable) = options.promiscuous {
+            self.promiscuous = enable;
+        }
+        if let Some(enable) = options.allmulti {
+            self.allmulti = enable;
+        }
+        if let Some(policy) = options.flush_policy {
+            self.flush_policy = policy;
+        }
+        if let Some(rate) = options.tx_rate {
+            self.tx_rate = rate;
+        }
+        if let Some(enable) = options.vlan_strip {
+            self.vlan_strip = enable;
+        }
+        if let Some(tag) = options.vlan_insert {
+            self.vlan_insert = Some(tag);
+        }
+        if let Some(snaplen) = options.snaplen {
+            self.snaplen = Some(snaplen);
+        }
+        if let Some(enable) = options.hw_timestamps {
+            self.hw_timestamps = enable;
+        }
+        if let Some(source) = options.timestamp_source {
+            self.timestamp_source = source;
+        }
+        if let Some(keep) = options.fcs {
+            self.keep_crc = keep;
+        }
+    }
 }
 
-impl api::Flags for DpdkFlags {}
+impl api::SocketBuilder<Sock> {
+    /// Sets the per-lcore mbuf cache size of the mempool.
+    pub fn mbuf_cache_size(self, size: u32) -> Self {
+        self.configure(|f| f.mbuf_cache_size = size)
+    }
+
+    /// Has the NIC compute the checksums requested per packet.
+    pub fn tx_checksum(self, enable: bool) -> Self {
+        self.configure(|f| f.tx_checksum = enable)
+    }
+
+    /// Has the NIC segment the TCP packets sent with `send_gso`.
+    pub fn tso(self, enable: bool) -> Self {
+        self.configure(|f| f.tso = enable)
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -206,6 +1047,15 @@ mod tests {
                 num_mbufs: 8192,
                 mbuf_cache_size: 250,
                 mbuf_default_buf_size: 2176,
+                hw_timestamps: false,
+                flush_policy: api::FlushPolicy::Manual,
+                tx_rate: api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                tx_checksum: false,
+                tso: false,
+                timestamp_source: api::TimestampSource::None,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -216,6 +1066,15 @@ mod tests {
                 num_mbufs: 8192,
                 mbuf_cache_size: 250,
                 mbuf_default_buf_size: 2176,
+                hw_timestamps: false,
+                flush_policy: api::FlushPolicy::Manual,
+                tx_rate: api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                tx_checksum: false,
+                tso: false,
+                timestamp_source: api::TimestampSource::None,
+                ..Default::default()
             },
         )
         .unwrap();
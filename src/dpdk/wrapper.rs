@@ -1,6 +1,7 @@
 const RX_RING_SIZE: u16 = 1024;
 const BURST_SIZE: u16 = 32;
 
+use crate::api;
 use arrayvec::ArrayVec;
 use dpdk_sys::*;
 use rand::RngCore;
@@ -19,10 +20,21 @@ pub(crate) fn resultify(x: i32) -> io::Result<u32> {
     }
 }
 
-/// Initializes a port with the given mempool.
-pub(crate) unsafe fn init_port(port: u16, pool: *mut rte_mempool) -> io::Result<()> {
+/// Initializes a port with the given mempool and `RTE_ETH_{RX,TX}_OFFLOAD_*` flags.
+pub(crate) unsafe fn init_port(
+    port: u16,
+    pool: *mut rte_mempool,
+    rx_offloads: u64,
+    tx_offloads: u64,
+) -> io::Result<()> {
     // Zero-initialize the port configuration.
-    let port_conf: rte_eth_conf = unsafe { mem::zeroed() };
+    let mut port_conf: rte_eth_conf = unsafe { mem::zeroed() };
+    let mut dev_info: rte_eth_dev_info = unsafe { mem::zeroed() };
+    unsafe { resultify(rte_eth_dev_info_get(port, &mut dev_info))? };
+    // Keeping the CRC is only a request: NICs that cannot simply strip it.
+    port_conf.rxmode.offloads =
+        rx_offloads & (dev_info.rx_offload_capa | !super::RX_OFFLOAD_KEEP_CRC);
+    port_conf.txmode.offloads = tx_offloads;
     unsafe { resultify(rte_eth_dev_configure(port, 1, 1, &port_conf))? };
 
     unsafe {
@@ -131,6 +143,8 @@ impl Context {
         mbuf_cache_size: u32,
         mbuf_default_buf_size: u16,
         queue_id: u16,
+        rx_offloads: u64,
+        tx_offloads: u64,
     ) -> io::Result<Self> {
         // let file_prefix = rand::rng().next_u64();
         let file_prefix_str = format!("--file-prefix={}", "server");
@@ -167,7 +181,7 @@ impl Context {
         }
         let port_id = 0;
         unsafe {
-            init_port(port_id, mbuf_pool)?;
+            init_port(port_id, mbuf_pool, rx_offloads, tx_offloads)?;
         }
         Ok(Context {
             // file_prefix,
@@ -183,6 +197,8 @@ impl Context {
         mbuf_cache_size: u32,
         mbuf_default_buf_size: u16,
         queue_id: u16,
+        rx_offloads: u64,
+        tx_offloads: u64,
     ) -> io::Result<(BufferPool, Receiver, Transmitter)> {
         let ctx = Self::inner_new(
             iface,
@@ -190,6 +206,8 @@ impl Context {
             mbuf_cache_size,
             mbuf_default_buf_size,
             queue_id,
+            rx_offloads,
+            tx_offloads,
         )?;
         Ok(Self::split(ctx))
     }
@@ -210,6 +228,7 @@ impl Context {
             index: 0,
             port_id,
             queue_id,
+            mc_addrs: Vec::new(),
         };
 
         let trasmitter = Transmitter::new(ctx, mempool);
@@ -252,6 +271,8 @@ pub(crate) struct Receiver {
     index: usize,
     port_id: u16,
     queue_id: u16,
+    /// Multicast addresses joined on the port, in the flat layout DPDK takes.
+    mc_addrs: Vec<[u8; 6]>,
 }
 
 unsafe impl Send for Receiver {}
@@ -260,6 +281,164 @@ impl Receiver {
     pub(crate) fn iter_mut<'a>(&'a mut self) -> ReceiverIterMut<'a> {
         ReceiverIterMut { rx: self }
     }
+
+    /// Returns the `RTE_ETH_RX_OFFLOAD_*` flags the port was configured with.
+    pub(crate) fn rx_offloads(&self) -> io::Result<u64> {
+        let mut conf: rte_eth_conf = unsafe { mem::zeroed() };
+        resultify(unsafe { rte_eth_dev_conf_get(self.port_id, &mut conf) })?;
+        Ok(conf.rxmode.offloads)
+    }
+
+    /// Enables or disables promiscuous mode on the port this queue belongs to.
+    pub(crate) fn set_promiscuous(&self, enable: bool) -> io::Result<()> {
+        let rc = unsafe {
+            if enable {
+                rte_eth_promiscuous_enable(self.port_id)
+            } else {
+                rte_eth_promiscuous_disable(self.port_id)
+            }
+        };
+        resultify(rc).map(drop)
+    }
+
+    /// Enables or disables the reception of all multicast frames on the port this queue
+    /// belongs to.
+    pub(crate) fn set_allmulti(&self, enable: bool) -> io::Result<()> {
+        let rc = unsafe {
+            if enable {
+                rte_eth_allmulticast_enable(self.port_id)
+            } else {
+                rte_eth_allmulticast_disable(self.port_id)
+            }
+        };
+        resultify(rc).map(drop)
+    }
+
+    /// Adds `mac` to the multicast filter of the port, if it is not there yet.
+    pub(crate) fn join_multicast(&mut self, mac: [u8; 6]) -> io::Result<()> {
+        if self.mc_addrs.contains(&mac) {
+            return Ok(());
+        }
+        self.mc_addrs.push(mac);
+        self.update_mc_addrs().inspect_err(|_| {
+            self.mc_addrs.pop();
+        })
+    }
+
+    /// Removes `mac` from the multicast filter of the port.
+    pub(crate) fn leave_multicast(&mut self, mac: [u8; 6]) -> io::Result<()> {
+        let Some(pos) = self.mc_addrs.iter().position(|&m| m == mac) else {
+            return Err(io::Error::from_raw_os_error(libc::EADDRNOTAVAIL));
+        };
+        self.mc_addrs.remove(pos);
+        self.update_mc_addrs().inspect_err(|_| {
+            self.mc_addrs.insert(pos, mac);
+        })
+    }
+
+    /// Installs `mc_addrs` as the multicast filter of the port; DPDK replaces the whole
+    /// list on every call.
+    fn update_mc_addrs(&self) -> io::Result<()> {
+        let rc = unsafe {
+            rust_rte_eth_dev_set_mc_addr_list(
+                self.port_id,
+                self.mc_addrs.as_ptr().cast(),
+                self.mc_addrs.len() as u32,
+            )
+        };
+        resultify(rc).map(drop)
+    }
+
+    /// Reads the link parameters of the port this queue belongs to, without waiting for
+    /// link negotiation to complete.
+    pub(crate) fn link_info(&self) -> io::Result<api::LinkInfo> {
+        let (mut speed, mut full_duplex, mut up) = (0u32, 0, 0);
+        resultify(unsafe {
+            rust_rte_eth_link_info(self.port_id, &mut speed, &mut full_duplex, &mut up)
+        })?;
+        let mut mtu = 0u16;
+        resultify(unsafe { rte_eth_dev_get_mtu(self.port_id, &mut mtu) })?;
+        let mut mac = [0u8; 6];
+        resultify(unsafe { rust_rte_eth_macaddr_get(self.port_id, mac.as_mut_ptr()) })?;
+        let known = up != 0 && speed != 0 && speed != u32::MAX;
+        Ok(api::LinkInfo {
+            up: up != 0,
+            mtu: mtu as u32,
+            speed: known.then_some(speed),
+            duplex: known.then_some(if full_duplex != 0 {
+                api::Duplex::Full
+            } else {
+                api::Duplex::Half
+            }),
+            mac: Some(mac),
+        })
+    }
+
+    /// Creates an `rte_flow` rule sending the packets matching `spec` to `queue` of the
+    /// port this queue belongs to.
+    pub(crate) fn steer(&self, spec: &api::FlowSpec, queue: usize) -> io::Result<api::FlowRule> {
+        let ipv6 = spec.is_ipv6()?;
+        let octets = |addr: Option<std::net::IpAddr>| match addr {
+            Some(std::net::IpAddr::V4(addr)) => Some(addr.octets().to_vec()),
+            Some(std::net::IpAddr::V6(addr)) => Some(addr.octets().to_vec()),
+            None => None,
+        };
+        let (src, dst) = (octets(spec.src), octets(spec.dst));
+        let mut flow = ptr::null_mut();
+        resultify(unsafe {
+            rust_rte_flow_steer(
+                self.port_id,
+                queue as u16,
+                ipv6 as c_int,
+                spec.protocol.map_or(0, |proto| proto.number()),
+                src.as_ref().map_or(ptr::null(), |addr| addr.as_ptr()),
+                dst.as_ref().map_or(ptr::null(), |addr| addr.as_ptr()),
+                spec.src_port.unwrap_or(0),
+                spec.dst_port.unwrap_or(0),
+                &mut flow,
+            )
+        })?;
+        Ok(api::FlowRule::new(flow as u64))
+    }
+
+    /// Destroys a rule created by [`steer`](Self::steer) on the same port.
+    pub(crate) fn unsteer(&self, rule: api::FlowRule) -> io::Result<()> {
+        let flow = rule.id() as *mut rte_flow;
+        resultify(unsafe { rust_rte_flow_destroy(self.port_id, flow) }).map(drop)
+    }
+
+    /// Frees the mbufs of the last burst that were not handed out yet.
+    pub(crate) fn release_pending(&mut self) {
+        let pending = &mut self.bufs[self.index..self.nb_rx];
+        unsafe { rust_rte_pktmbuf_free_bulk(pending.as_mut_ptr(), pending.len() as u32) };
+        self.index = self.nb_rx;
+    }
+
+    /// Stops the port this queue belongs to.
+    pub(crate) fn stop_port(&self) -> io::Result<()> {
+        resultify(unsafe { rte_eth_dev_stop(self.port_id) }).map(drop)
+    }
+
+    /// Enables IEEE 1588 timestamping on the port this queue belongs to.
+    pub(crate) fn enable_timesync(&self) -> io::Result<()> {
+        resultify(unsafe { rte_eth_timesync_enable(self.port_id) }).map(drop)
+    }
+
+    /// Reads the timestamp latched by the NIC for the last timestamped packet.
+    pub(crate) fn read_rx_timestamp(&self) -> Option<api::Timestamp> {
+        let mut ts: timespec = unsafe { mem::zeroed() };
+        let rc = unsafe { rte_eth_timesync_read_rx_timestamp(self.port_id, &mut ts, 0) };
+        (rc == 0).then(|| {
+            api::Timestamp::from_nanos(ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64)
+        })
+    }
+
+    /// Reads the device counters of the port this queue belongs to.
+    pub(crate) fn port_stats(&self) -> io::Result<rte_eth_stats> {
+        let mut stats: rte_eth_stats = unsafe { mem::zeroed() };
+        resultify(unsafe { rte_eth_stats_get(self.port_id, &mut stats) })?;
+        Ok(stats)
+    }
 }
 
 pub(crate) struct ReceiverIterMut<'a> {
@@ -335,6 +514,52 @@ impl Transmitter {
         self.ready_bufs.drain(..sent);
     }
 
+    /// Number of queued mbufs not accepted by the NIC yet.
+    pub(crate) fn pending(&self) -> usize {
+        self.ready_bufs.len()
+    }
+
+    /// Number of queued mbufs not sent yet: those waiting for a burst plus the
+    /// descriptors still in use in the NIC's TX queue.
+    pub(crate) fn in_flight(&self) -> usize {
+        let used = unsafe { rust_rte_eth_tx_queue_count(self.port_id, self.queue_id) };
+        self.ready_bufs.len() + used.max(0) as usize
+    }
+
+    /// Takes a free mbuf, bursting the ready ones out first if the queue is full.
+    pub(crate) fn reserve(&mut self) -> Option<NonNull<rte_mbuf>> {
+        if self.ready_bufs.is_full() {
+            self.flush();
+            let old_len = self.ready_bufs.len();
+            let can_ask = self.bufs.capacity() - old_len;
+            while !self.bufs.is_full() {
+                self.bufs.push(ptr::null_mut());
+            }
+
+            let slice = &mut self.bufs[old_len..];
+            let res = unsafe {
+                rust_rte_pktmbuf_alloc_bulk(self.mempool, slice.as_mut_ptr(), can_ask as u32)
+            };
+            if res != 0 {
+                for _ in 0..can_ask {
+                    self.bufs.pop().unwrap();
+                }
+                return None;
+            }
+        }
+        self.bufs.pop().map(|buf| NonNull::new(buf).unwrap())
+    }
+
+    /// Queues an mbuf obtained from [`reserve`](Self::reserve) for the next burst.
+    pub(crate) fn enqueue(&mut self, buf: NonNull<rte_mbuf>) {
+        self.ready_bufs.push(buf);
+    }
+
+    /// Gives back an mbuf obtained from [`reserve`](Self::reserve) without sending it.
+    pub(crate) fn unreserve(&mut self, buf: NonNull<rte_mbuf>) {
+        self.bufs.push(buf.as_ptr());
+    }
+
     fn new(ctx: Arc<UnsafeCell<Context>>, mempool: *mut rte_mempool) -> Self {
         let mut bufs = ArrayVec::new();
         while !bufs.is_full() {
@@ -371,26 +596,7 @@ pub(crate) struct TransmitterIterMut<'a> {
 
 impl<'a> TransmitterIterMut<'a> {
     fn advance(&mut self) -> Option<NonNull<rte_mbuf>> {
-        if self.tx.ready_bufs.is_full() {
-            self.tx.flush();
-            let old_len = self.tx.ready_bufs.len();
-            let can_ask = self.tx.bufs.capacity() - old_len;
-            while !self.tx.bufs.is_full() {
-                self.tx.bufs.push(ptr::null_mut());
-            }
-
-            let slice = &mut self.tx.bufs[old_len..];
-            let res = unsafe {
-                rust_rte_pktmbuf_alloc_bulk(self.tx.mempool, slice.as_mut_ptr(), can_ask as u32)
-            };
-            if res != 0 {
-                for _ in 0..can_ask {
-                    self.tx.bufs.pop().unwrap();
-                }
-                return None;
-            }
-        }
-        self.tx.bufs.pop().map(|buf| NonNull::new(buf).unwrap())
+        self.tx.reserve()
     }
 }
 
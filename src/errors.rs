@@ -6,6 +6,8 @@ use thiserror::Error;
 pub enum Error {
     #[error("Can't receive packet")]
     NoPacket,
+    #[error("Operation would block")]
+    WouldBlock,
     #[error("Can't allocate memory")]
     NoMemory,
     #[error("{0}")]
@@ -17,7 +19,7 @@ pub enum Error {
     Generic(#[from] io::Error),
     //#[error("{0}")]
     #[error("{0}")]
-    #[cfg(feature = "pcap")]
+    #[cfg(feature = "bpf")]
     Pcap(#[from] pcap::Error),
     //Temporary(#[from] anyhow::Error),
     #[error("unknown error")]
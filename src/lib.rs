@@ -10,6 +10,23 @@
 //! - **DPDK** - Data Plane Development Kit (feature: `dpdk`)
 //! - **pcap** - libpcap-based capture/injection (feature: `pcap`, enabled by default)
 //!
+//! ## Optional Features
+//!
+//! - **tokio** - [`api::AsyncSocket`] adapter for fd-backed sockets
+//! - **mio** - `mio::event::Source` implementations for fd-backed sockets
+//! - **serde** - `Serialize`/`Deserialize` for flags and options, and `api::Config`
+//! - **tracing** - `tracing` events for socket creation, ring syncs, flushes and error
+//!   paths; **tracing-verbose** adds per-packet events
+//! - **metrics** - per-socket RX/TX, drop and batch size counters registered with the
+//!   `metrics` facade; see the `metrics` example for a Prometheus exporter
+//! - **bpf** - compiling `pcap-filter(7)` expressions with libpcap, for
+//!   `Socket::set_filter` and `api::filter`; enabled by every backend feature
+//! - **gzip**, **zstd** - reading gzip- and zstd-compressed capture files with the pcap
+//!   backend; **zstd** also adds `api::pcapng::Writer::create_zstd`
+//!
+//! C applications can use the library through the `nethuns-ffi` crate, which builds
+//! `libnethuns` with the same backend features.
+//!
 //! ## Quick Start
 //!
 //! ```ignore
@@ -21,6 +38,22 @@
 //! let (packet, meta) = socket.recv()?;
 //! println!("Received {} bytes", packet.len());
 //! ```
+//!
+//! Sockets can also be configured generically through [`api::SocketBuilder`]:
+//!
+//! ```ignore
+//! let socket = Sock::builder("eth0").promiscuous(true).snaplen(128).build()?;
+//! ```
+
+// Internal macros, declared first so that every module can use them
+#[cfg(any(
+    feature = "pcap",
+    feature = "af-xdp",
+    feature = "netmap",
+    feature = "dpdk"
+))]
+#[macro_use]
+mod trace;
 
 // Backend modules (conditionally compiled)
 #[cfg(feature = "af-xdp")]
@@ -37,4 +70,5 @@ pub mod api;
 
 // Internal utilities
 pub mod errors;
+#[cfg(any(feature = "netmap", feature = "dpdk"))]
 pub(crate) mod unsafe_refcell;
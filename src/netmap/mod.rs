@@ -1,18 +1,27 @@
-use crate::api::{self, Context};
+use crate::api::{self, Context, RxSocket, TxSocket};
 use crate::api::{Result, Token};
 use crate::errors::Error;
-use netmap_rs::context::{BufferPool, Port, Receiver, RxBuf, Transmitter, TxBuf};
+use netmap_rs::context::{BufferPool, Port, Receiver, RxBuf, Transmitter, TxBuf, TxReservation};
 use nix::sys::time::TimeVal;
+use std::cell::Cell;
 use std::mem::ManuallyDrop;
+use std::os::fd::{AsRawFd, RawFd};
 use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
 use triomphe::Arc;
 
+/// Slot flag: more slots of the same frame follow.
+const NS_MOREFRAG: u16 = 0x0020;
+
 type RefCell<T> = crate::unsafe_refcell::UnsafeRefCell<T>;
+type UnsafeRefMut<'a, T> = crate::unsafe_refcell::UnsafeRefMut<'a, T>;
 
 #[derive(Clone)]
 pub struct Ctx {
     buffer_pool: Arc<BufferPool>,
     producer: RefCell<mpsc::Producer<api::BufferRef>>,
+    /// Buffers released by `OwnedPacket`s, shared by every clone.
+    returns: api::ReturnQueue,
     index: u32,
 }
 
@@ -20,6 +29,7 @@ impl Ctx {
     fn new(buffer_pool: BufferPool, indexes: Vec<u32>) -> (Self, mpsc::Consumer<api::BufferRef>) {
         static COUNTER: AtomicU32 = AtomicU32::new(0);
         let (mut producer, cons) = mpsc::channel(indexes.len());
+        let returns = api::ReturnQueue::new(indexes.len());
         let counter = COUNTER.fetch_add(1, Ordering::SeqCst);
         let buffer_pool = Arc::new(buffer_pool);
         for idx in indexes {
@@ -29,11 +39,17 @@ impl Ctx {
         let res = Self {
             buffer_pool,
             producer: RefCell::new(producer),
+            returns,
             index: counter,
         };
         (res, cons)
     }
 
+    /// Moves the buffers released by `OwnedPacket`s to the extra buffers of the socket.
+    fn reclaim(&self) {
+        self.returns.reclaim(&mut unsafe { self.producer.borrow_mut() });
+    }
+
     unsafe fn buffer(&self, idx: api::BufferRef) -> *mut [u8] {
         unsafe { self.buffer_pool.buffer(usize::from(idx)) }
     }
@@ -46,6 +62,10 @@ impl api::Context for Ctx {
         producer_mut.push(token);
     }
 
+    fn release_detached(&self, buf_idx: api::BufferDesc) {
+        self.returns.push(buf_idx);
+    }
+
     unsafe fn unsafe_buffer(&self, buf_idx: api::BufferDesc, _size: usize) -> *mut [u8] {
         let buf_idx = api::BufferRef::from(buf_idx.0);
         unsafe { Ctx::buffer(self, buf_idx) }
@@ -61,10 +81,9 @@ struct PacketHeader {
 }
 
 pub struct Sock {
-    tx: RefCell<Transmitter>,
-    rx: RefCell<Receiver>,
-    ctx: Ctx,
-    consumer: RefCell<mpsc::Consumer<api::BufferRef>>,
+    rx: RxSock,
+    tx: TxSock,
+    ifname: String,
 }
 
 impl std::fmt::Debug for Sock {
@@ -73,83 +92,514 @@ impl std::fmt::Debug for Sock {
     }
 }
 
-impl Sock {
-    #[inline(always)]
-    fn send_inner(&self, scan: TxBuf<'_>, packet: &[u8]) -> Result<()> {
-        let TxBuf { ref slot, .. } = scan;
-        let token = slot.buf_idx();
-        let token = api::BufferRef::from(token as usize);
-        let buf = unsafe { Ctx::buffer(&self.ctx, token) };
-        let buf = unsafe { &mut (*buf) };
-        if packet.len() > buf.len() {
-            return Err(Error::TooBigPacket(packet.len()));
+/// Receive half of a netmap socket: the port's RX rings and the extra-buffer pool.
+pub struct RxSock {
+    rx: RefCell<Receiver>,
+    ctx: Ctx,
+    consumer: RefCell<mpsc::Consumer<api::BufferRef>>,
+    stats: Cell<api::Stats>,
+    clock: api::SoftwareClock,
+    filter: api::UserspaceFilter,
+    vlan_strip: bool,
+    snaplen: Option<u32>,
+    segments: api::SegmentTracker,
+    dropped: Cell<api::Dropped>,
+    metrics: api::SocketMetrics,
+}
+
+/// Transmit half of a netmap socket: the port's TX rings.
+pub struct TxSock {
+    tx: RefCell<Transmitter>,
+    ctx: Ctx,
+    stats: Cell<api::Stats>,
+    flush_state: api::FlushState,
+    pacer: api::Pacer,
+    vlan_insert: Option<api::VlanTag>,
+    completions: api::TxCompletions,
+    metrics: api::SocketMetrics,
+}
+
+impl RxSock {
+    /// Returns the next packet that passes the filter, or `None` if the rings are empty.
+    fn next_packet(&self) -> Result<Option<(Token, Meta)>> {
+        let mut rx = unsafe { self.rx.borrow_mut() };
+        let mut reset = false;
+        loop {
+            match rx.iter_mut().next() {
+                Some(buf) => {
+                    if let Some(pkt) = self.recv_inner(buf)? {
+                        return Ok(Some(pkt));
+                    }
+                }
+                None if !reset => {
+                    self.metrics.rx_sync();
+                    self.ctx.reclaim();
+                    // SAFETY: there are no `RxBuf`s, and so any `Slot`s, in use
+                    unsafe {
+                        rx.reset();
+                    }
+                    trace_event!(trace, backend = "netmap", "RX rings synced");
+                    reset = true;
+                }
+                None => return Ok(None),
+            }
         }
-        buf[..packet.len()].copy_from_slice(packet);
-        Ok(())
     }
 
+    /// Links the free extra buffers back into the interface list, so that the kernel
+    /// frees them when the port is closed.
+    fn return_extra_buffers(&self) {
+        self.ctx.reclaim();
+        unsafe { self.ctx.producer.borrow_mut() }.flush();
+        let mut consumer = unsafe { self.consumer.borrow_mut() };
+        let mut rx = unsafe { self.rx.borrow_mut() };
+        while let Some(buf_idx) = consumer.pop() {
+            // SAFETY: buffers in the release queue are not referenced by any slot or token.
+            unsafe { rx.release_extra_buffer(buf_idx as u32) };
+        }
+    }
+
+    /// Swaps the slot buffer out of the ring, unless the filter rejects the frame.
     #[inline(always)]
-    fn recv_inner(&self, buf: RxBuf<'_>) -> Result<(Token, Meta)> {
-        let RxBuf { slot, .. } = buf;
-        let free_idx = {
-            let mut consumer_mut = unsafe { self.consumer.borrow_mut() };
-            consumer_mut.pop().ok_or(Error::NoMemory)?
-        };
+    fn recv_inner(&self, buf: RxBuf<'_>) -> Result<Option<(Token, Meta)>> {
+        let RxBuf { slot, ring_idx, ts } = buf;
         let pkt_idx = slot.buf_idx();
+        let len = slot.len() as u32;
+        let frame = unsafe { &mut *Ctx::buffer(&self.ctx, api::BufferRef::from(pkt_idx as usize)) };
+        let frame = &mut frame[..(len as usize).min(frame.len())];
+        let more = slot.flags() & NS_MOREFRAG != 0;
+        // Only the first slot of a frame holds its headers.
+        let first = match self.segments.current() {
+            api::Segment::First => true,
+            api::Segment::Next => false,
+            api::Segment::Dropped => {
+                self.segments.advance(false, more);
+                return Ok(None);
+            }
+        };
+        if first && !self.filter.accepts(frame) {
+            // The buffer stays in the ring and goes back to the kernel on the next sync.
+            self.segments.advance(false, more);
+            return Ok(None);
+        }
+
+        let (wirelen, vlan) = match (first, self.vlan_strip) {
+            (false, _) => (len, None),
+            (true, true) => match api::VlanTag::strip(frame) {
+                Some((tag, stripped)) => (stripped as u32, Some(tag)),
+                None => (len, None),
+            },
+            (true, false) => (len, api::VlanTag::parse(frame)),
+        };
+        let caplen = match first {
+            true => self.snaplen.map_or(wirelen, |snaplen| wirelen.min(snaplen)),
+            false => wirelen,
+        };
+
+        let Some(free_idx) = unsafe { self.consumer.borrow_mut() }.pop() else {
+            // The slot is consumed: its packet goes back to the kernel with the buffer.
+            trace_verbose!(backend = "netmap", "no free extra buffer, packet dropped");
+            let mut dropped = self.dropped.get();
+            dropped.no_buffers += 1;
+            self.dropped.set(dropped);
+            self.metrics.dropped(1);
+            self.segments.advance(false, more);
+            return Err(Error::NoMemory);
+        };
         unsafe {
             slot.update_buffer(|x| *x = free_idx as u32);
         }
+        self.segments.advance(true, more);
 
         // let packet_token = Token::new(pkt_idx, self.ctx.index, slot.len() as u32);
         let packet_token = ManuallyDrop::new(Token {
             idx: api::BufferDesc::from(pkt_idx as usize),
-            len: slot.len() as u32,
+            len: caplen,
             buffer_pool: self.ctx.index,
         });
-        let meta = Meta {};
-        Ok((ManuallyDrop::into_inner(packet_token), meta))
+        let mut stats = self.stats.get();
+        stats.rx_packets += first as u64;
+        stats.rx_bytes += len as u64;
+        self.stats.set(stats);
+        self.metrics.rx(len as usize);
+
+        // The kernel stamps the ring with the realtime clock; 0 means no timestamp.
+        let timestamp = match ts.tv_sec() as u64 * 1_000_000 + ts.tv_usec() as u64 {
+            0 => None,
+            micros => self
+                .clock
+                .convert_realtime(api::Timestamp::from_micros(micros)),
+        };
+        let meta = Meta {
+            timestamp,
+            len: caplen,
+            wirelen,
+            ring: ring_idx,
+            vlan,
+            more,
+        };
+        Ok(Some((ManuallyDrop::into_inner(packet_token), meta)))
     }
 }
 
-impl api::Socket for Sock {
+impl api::RxSocket for RxSock {
     type Context = Ctx;
     type Metadata = Meta;
-    type Flags = NetmapFlags;
 
     fn recv_token(&self) -> Result<(Token, Self::Metadata)> {
+        self.next_packet()?.ok_or(Error::NoPacket)
+    }
+
+    fn recv_packet(&self) -> Result<(api::Packet<'_, Ctx>, Meta)> {
+        let (token, mut meta) = self.recv_token()?;
+        let mut packet = api::Packet::from(token.consume(&self.ctx));
+        // The slots of a frame are made visible in the same sync.
+        while meta.more {
+            let (token, next) = self.recv_token()?;
+            packet.push(token.consume(&self.ctx));
+            meta.more = next.more;
+        }
+        meta.len = packet.len() as u32;
+        meta.wirelen = meta.len;
+        Ok((packet, meta))
+    }
+
+    fn try_recv_token(&self) -> Result<(Token, Self::Metadata)> {
+        // Ring scans never block: an empty ring is the only way to get `NoPacket`.
+        match self.recv_token() {
+            Err(Error::NoPacket) => Err(Error::WouldBlock),
+            res => res,
+        }
+    }
+
+    fn recv_batch(&self, budget: usize) -> Result<Vec<(Token, Self::Metadata)>> {
         let mut rx = unsafe { self.rx.borrow_mut() };
-        if let Some(tmp) = rx.iter_mut().next() {
-            self.recv_inner(tmp)
-        } else {
+        let mut batch = Vec::with_capacity(budget);
+        api::fill_batch(&mut batch, rx.iter_mut().take(budget), |buf| {
+            self.recv_inner(buf)
+        })?;
+        if batch.is_empty() && budget > 0 {
+            self.metrics.rx_sync();
+            self.ctx.reclaim();
             // SAFETY: there are no `RxBuf`s, and so any `Slot`s, in use
             unsafe {
                 rx.reset();
             }
-            let tmp = rx.iter_mut().next().ok_or(Error::NoPacket)?;
-            self.recv_inner(tmp)
+            trace_event!(trace, backend = "netmap", "RX rings synced");
+            api::fill_batch(&mut batch, rx.iter_mut().take(budget), |buf| {
+                self.recv_inner(buf)
+            })?;
+            if batch.is_empty() {
+                return Err(Error::NoPacket);
+            }
         }
+        Ok(batch)
     }
 
+    fn set_filter(&self, expr: &str) -> Result<()> {
+        self.filter.set(expr)
+    }
+
+    fn dropped(&self) -> api::Dropped {
+        self.dropped.get()
+    }
+
+    fn context(&self) -> &Self::Context {
+        &self.ctx
+    }
+}
+
+impl TxSock {
+    #[inline(always)]
+    fn send_inner(&self, scan: TxBuf<'_>, packet: &[u8]) -> Result<()> {
+        let TxBuf { ref slot, .. } = scan;
+        let token = slot.buf_idx();
+        let token = api::BufferRef::from(token as usize);
+        let buf = unsafe { Ctx::buffer(&self.ctx, token) };
+        let buf = unsafe { &mut (*buf) };
+        let len = match self.vlan_insert {
+            Some(tag) => tag.write_tagged(packet, buf),
+            None => buf.get_mut(..packet.len()).map(|dst| {
+                dst.copy_from_slice(packet);
+                packet.len()
+            }),
+        }
+        .ok_or(Error::TooBigPacket(packet.len()))?;
+        unsafe {
+            slot.update(|data| data.len = len as u16);
+        }
+        count_tx(&self.stats, &self.metrics, len);
+        Ok(())
+    }
+
+    /// Records a queued packet of `len` bytes and flushes the TX rings if the pacing or
+    /// the flush policy asks for it.
+    fn queued(&self, len: usize) {
+        self.completions.queued();
+        if self.pacer.pace(len) | self.flush_state.queued() {
+            api::TxSocket::flush(self);
+        }
+    }
+}
+
+impl api::TxSocket for TxSock {
+    type Slot<'a> = TxSlot<'a>;
+
     fn send(&self, packet: &[u8]) -> Result<()> {
         let mut tx = unsafe { self.tx.borrow_mut() };
         if let Some(next) = tx.iter_mut().next() {
-            self.send_inner(next, packet)
+            self.send_inner(next, packet)?;
         } else {
             // SAFETY: there are no `TxBuf`s, and so any `Slot`s, in use
             unsafe {
                 tx.reset();
             }
             let next = tx.iter_mut().next().ok_or(Error::NoMemory)?;
-            self.send_inner(next, packet)
+            self.send_inner(next, packet)?;
         }
+        drop(tx);
+        self.queued(packet.len());
+        Ok(())
+    }
+
+    fn send_payload<C: Context>(&self, packet: api::Payload<'_, C>) -> Result<()> {
+        // A tag can only be inserted into a copy: the buffer may have no room for it.
+        let Some(ctx) = api::same_backend::<Ctx, C>(&packet)
+            .filter(|ctx| ctx.buffer_pool.mem_id() == self.ctx.buffer_pool.mem_id())
+            .filter(|_| self.vlan_insert.is_none())
+        else {
+            return self.send(&packet);
+        };
+        let mut tx = unsafe { self.tx.borrow_mut() };
+        let buf = match tx.iter_mut().next() {
+            Some(buf) => buf,
+            None => {
+                // SAFETY: there are no `TxBuf`s, and so any `Slot`s, in use
+                unsafe {
+                    tx.reset();
+                }
+                tx.iter_mut().next().ok_or(Error::NoMemory)?
+            }
+        };
+        let token = packet.into_token();
+        let len = token.size() as usize;
+        // SAFETY: the token owned the buffer, and both ports share the memory region.
+        let old = unsafe { buf.swap_buffer(usize::from(token.buffer_desc()) as u32, len as u16) };
+        drop(tx);
+        // The TX buffer takes the place of the forwarded one in the RX pool.
+        ctx.release(api::BufferDesc::from(old as usize));
+        count_tx(&self.stats, &self.metrics, len);
+        self.queued(len);
+        Ok(())
     }
 
     fn flush(&self) {
+        self.flush_state.flushed();
+        self.metrics.tx_flush();
         let mut tx = unsafe { self.tx.borrow_mut() };
         // SAFETY: Any `Slot`s is in use due to the design of the API
         unsafe {
             tx.sync();
         }
+        trace_event!(trace, backend = "netmap", "TX rings synced");
+    }
+
+    fn send_with_cookie(&self, packet: &[u8], cookie: u64) -> Result<()> {
+        self.send(packet)?;
+        self.completions.tag_last(cookie);
+        Ok(())
+    }
+
+    fn tx_completions(&self, out: &mut Vec<u64>) -> usize {
+        // The rings are not synced here: that would also push out the queued packets.
+        let in_flight = unsafe { self.tx.borrow() }.in_flight();
+        self.completions.drain(in_flight, out)
+    }
+
+    fn tx_reserve(&self, len: usize) -> Result<TxSlot<'_>> {
+        let mut tx = unsafe { self.tx.borrow_mut() };
+        let res = match tx.reserve() {
+            Some(res) => res,
+            None => {
+                // SAFETY: there are no `TxBuf`s, and so any `Slot`s, in use
+                unsafe {
+                    tx.reset();
+                }
+                tx.reserve().ok_or(Error::NoMemory)?
+            }
+        };
+        let idx = api::BufferRef::from(res.buf_idx as usize);
+        let data = unsafe { &mut *Ctx::buffer(&self.ctx, idx) };
+        if len > data.len() {
+            // SAFETY: `res` is the slot just reserved.
+            unsafe { tx.unreserve(res) };
+            return Err(Error::TooBigPacket(len));
+        }
+        Ok(TxSlot {
+            tx,
+            res: Some(res),
+            data,
+            sock: self,
+            checksum: api::TxChecksum::NONE,
+        })
+    }
+}
+
+/// Extracts the interface name from a portspec such as `netmap:eth0-1`.
+fn ifname(portspec: &str) -> &str {
+    let name = portspec.strip_prefix("netmap:").unwrap_or(portspec);
+    let end = name
+        .find(['-', '*', '^', '@', '/', '{', '}'])
+        .unwrap_or(name.len());
+    &name[..end]
+}
+
+fn count_tx(stats: &Cell<api::Stats>, metrics: &api::SocketMetrics, len: usize) {
+    let mut s = stats.get();
+    s.tx_packets += 1;
+    s.tx_bytes += len as u64;
+    stats.set(s);
+    metrics.tx(len);
+}
+
+/// Zero-copy TX slot of the netmap backend: a TX ring slot and its buffer.
+pub struct TxSlot<'a> {
+    tx: UnsafeRefMut<'a, Transmitter>,
+    res: Option<TxReservation>,
+    data: &'a mut [u8],
+    sock: &'a TxSock,
+    checksum: api::TxChecksum,
+}
+
+impl std::ops::Deref for TxSlot<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.data
+    }
+}
+
+impl std::ops::DerefMut for TxSlot<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.data
+    }
+}
+
+impl api::TxSlot for TxSlot<'_> {
+    fn commit(mut self, len: usize) -> Result<()> {
+        if len > self.data.len() {
+            return Err(Error::TooBigPacket(len));
+        }
+        api::checksum::fill(&mut self.data[..len], self.checksum);
+        let len = match self.sock.vlan_insert {
+            Some(tag) => tag
+                .insert(self.data, len)
+                .ok_or(Error::TooBigPacket(len + 4))?,
+            None => len,
+        };
+        let res = self.res.take().expect("slot committed twice");
+        // SAFETY: the transmitter stayed borrowed since `res` was reserved.
+        unsafe { self.tx.commit(res, len as u16) };
+        count_tx(&self.sock.stats, &self.sock.metrics, len);
+        // Release the transmitter before a possible flush.
+        let sock = self.sock;
+        drop(self);
+        sock.queued(len);
+        Ok(())
+    }
+
+    fn set_checksum(&mut self, csum: api::TxChecksum) {
+        self.checksum = csum;
+    }
+}
+
+impl Drop for TxSlot<'_> {
+    fn drop(&mut self) {
+        if let Some(res) = self.res.take() {
+            // SAFETY: the transmitter stayed borrowed, so this is still the last slot.
+            unsafe { self.tx.unreserve(res) };
+        }
+    }
+}
+
+impl api::Socket for Sock {
+    type Context = Ctx;
+    type Metadata = Meta;
+    type Flags = NetmapFlags;
+    type Rx = RxSock;
+    type Tx = TxSock;
+    type Slot<'a> = TxSlot<'a>;
+
+    fn recv_token(&self) -> Result<(Token, Self::Metadata)> {
+        self.rx.recv_token()
+    }
+
+    fn try_recv_token(&self) -> Result<(Token, Self::Metadata)> {
+        self.rx.try_recv_token()
+    }
+
+    fn recv_token_timeout(&self, timeout: Duration) -> Result<(Token, Self::Metadata)> {
+        api::poll::recv_token_polling(self, self.as_raw_fd(), timeout)
+    }
+
+    fn recv_packet(&self) -> Result<(api::Packet<'_, Ctx>, Meta)> {
+        self.rx.recv_packet()
+    }
+
+    fn recv_batch(&self, budget: usize) -> Result<Vec<(Token, Self::Metadata)>> {
+        self.rx.recv_batch(budget)
+    }
+
+    fn send(&self, packet: &[u8]) -> Result<()> {
+        self.tx.send(packet)
+    }
+
+    fn send_payload<C: Context>(&self, packet: api::Payload<'_, C>) -> Result<()> {
+        self.tx.send_payload(packet)
+    }
+
+    fn flush(&self) {
+        self.tx.flush();
+    }
+
+    fn send_with_cookie(&self, packet: &[u8], cookie: u64) -> Result<()> {
+        self.tx.send_with_cookie(packet, cookie)
+    }
+
+    fn tx_completions(&self, out: &mut Vec<u64>) -> usize {
+        self.tx.tx_completions(out)
+    }
+
+    fn tx_reserve(&self, len: usize) -> Result<TxSlot<'_>> {
+        self.tx.tx_reserve(len)
+    }
+
+    fn set_filter(&self, expr: &str) -> Result<()> {
+        self.rx.set_filter(expr)
+    }
+
+    fn set_promiscuous(&self, enable: bool) -> Result<()> {
+        Ok(api::netdev::set_promiscuous(&self.ifname, enable)?)
+    }
+
+    fn join_multicast(&self, mac: [u8; 6]) -> Result<()> {
+        Ok(api::netdev::set_multicast(&self.ifname, mac, true)?)
+    }
+
+    fn leave_multicast(&self, mac: [u8; 6]) -> Result<()> {
+        Ok(api::netdev::set_multicast(&self.ifname, mac, false)?)
+    }
+
+    fn link_info(&self) -> Result<api::LinkInfo> {
+        api::link_info(&self.ifname)
+    }
+
+    fn steer(&self, spec: api::FlowSpec, queue: usize) -> Result<api::FlowRule> {
+        Ok(api::ntuple_insert(&self.ifname, &spec, queue)?)
+    }
+
+    fn unsteer(&self, rule: api::FlowRule) -> Result<()> {
+        Ok(api::ntuple_delete(&self.ifname, rule)?)
     }
 
     fn create(portspec: &str, queue: Option<usize>, flags: Self::Flags) -> Result<Self> {
@@ -160,35 +610,233 @@ impl api::Socket for Sock {
         };
 
         let mut port = Port::open(p, flags.extra_buf)?;
+        if flags.promiscuous {
+            api::netdev::set_promiscuous(ifname(portspec), true)?;
+        }
+        if flags.allmulti {
+            api::netdev::set_allmulti(ifname(portspec), true)?;
+        }
         let extra_bufs = unsafe { port.extra_buffers_indexes() };
         let (tx, rx, buffer_pool) = port.split();
+        trace_event!(debug, backend = "netmap", portspec = p, "socket created");
         let (ctx, consumer) = Ctx::new(buffer_pool, extra_bufs);
+        let metrics = api::SocketMetrics::new(api::Backend::Netmap, ifname(portspec), queue);
         Ok(Self {
-            tx: RefCell::new(tx),
-            rx: RefCell::new(rx),
-            ctx,
-            consumer: RefCell::new(consumer),
+            tx: TxSock {
+                tx: RefCell::new(tx),
+                ctx: ctx.clone(),
+                stats: Cell::new(api::Stats::default()),
+                flush_state: api::FlushState::new(flags.flush_policy),
+                pacer: api::Pacer::new(flags.tx_rate),
+                vlan_insert: flags.vlan_insert,
+                completions: api::TxCompletions::new(),
+                metrics: metrics.clone(),
+            },
+            rx: RxSock {
+                rx: RefCell::new(rx),
+                ctx,
+                consumer: RefCell::new(consumer),
+                stats: Cell::new(api::Stats::default()),
+                clock: api::SoftwareClock::new(flags.timestamp_source),
+                filter: api::UserspaceFilter::default(),
+                vlan_strip: flags.vlan_strip,
+                snaplen: flags.snaplen,
+                segments: api::SegmentTracker::default(),
+                dropped: Cell::new(api::Dropped::default()),
+                metrics,
+            },
+            ifname: ifname(portspec).to_owned(),
         })
     }
 
+    fn create_group(portspec: &str, flags: Self::Flags) -> Result<api::SocketGroup<Self>> {
+        let queues = api::netdev::rx_queues(ifname(portspec))?;
+        api::SocketGroup::open(portspec, queues, flags)
+    }
+
     fn context(&self) -> &Self::Context {
-        &self.ctx
+        &self.rx.ctx
+    }
+
+    fn stats(&self) -> Result<api::Stats> {
+        // Netmap rings expose no drop counters: only the socket's own counters.
+        let rx = self.rx.stats.get();
+        let tx = self.tx.stats.get();
+        Ok(api::Stats {
+            tx_packets: tx.tx_packets,
+            tx_bytes: tx.tx_bytes,
+            ..rx
+        })
+    }
+
+    fn dropped(&self) -> api::Dropped {
+        api::RxSocket::dropped(&self.rx)
+    }
+
+    fn capabilities(&self) -> api::Capabilities {
+        api::Capabilities {
+            zero_copy_rx: true,
+            zero_copy_tx: true,
+            writable_rx: true,
+            hw_timestamps: false,
+            multi_queue: true,
+            socket_groups: true,
+            vlan_strip: self.rx.vlan_strip,
+            tx_checksum: false,
+            tso: false,
+            kernel_filter: false,
+            zero_copy_driver: true,
+            max_frame_size: self.rx.ctx.buffer_pool.buf_size(),
+        }
+    }
+
+    fn close(self) -> Result<()> {
+        let drained = api::drain_tx(|| {
+            api::TxSocket::flush(&self.tx);
+            Ok(unsafe { self.tx.tx.borrow() }.pending())
+        });
+        self.rx.return_extra_buffers();
+        drained
+    }
+
+    fn split(self) -> (RxSock, TxSock) {
+        (self.rx, self.tx)
+    }
+}
+
+impl AsRawFd for RxSock {
+    fn as_raw_fd(&self) -> RawFd {
+        unsafe { self.rx.borrow() }.fd()
+    }
+}
+
+impl AsRawFd for Sock {
+    fn as_raw_fd(&self) -> RawFd {
+        self.rx.as_raw_fd()
     }
 }
 
+impl api::Pollable for Sock {}
+
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct NetmapFlags {
     pub extra_buf: u32,
+    /// Set `IFF_PROMISC` on the interface, so frames addressed to other hosts are
+    /// received.
+    pub promiscuous: bool,
+    /// Set `IFF_ALLMULTI` on the interface, so every multicast frame is received.
+    pub allmulti: bool,
+    /// When queued TX packets are pushed to the NIC.
+    pub flush_policy: api::FlushPolicy,
+    /// Rate at which packets are transmitted.
+    pub tx_rate: api::TxRate,
+    /// Remove the outermost VLAN tag from received frames (in software).
+    pub vlan_strip: bool,
+    /// VLAN tag inserted into every transmitted frame (in software).
+    pub vlan_insert: Option<api::VlanTag>,
+    /// Maximum number of bytes of each received frame exposed to the application; the
+    /// NIC still writes the whole frame into the netmap buffer.
+    pub snaplen: Option<u32>,
+    /// Clock of the ring timestamps.
+    pub timestamp_source: api::TimestampSource,
 }
 
-impl api::Flags for NetmapFlags {}
+impl Default for NetmapFlags {
+    fn default() -> Self {
+        Self {
+            extra_buf: 1024,
+            promiscuous: false,
+            allmulti: false,
+            flush_policy: api::FlushPolicy::Manual,
+            tx_rate: api::TxRate::Unlimited,
+            vlan_strip: false,
+            vlan_insert: None,
+            snaplen: None,
+            timestamp_source: api::TimestampSource::Realtime,
+        }
+    }
+}
+
+impl api::Flags for NetmapFlags {
+    fn apply(&mut self, options: &api::SocketOptions) {
+        if let Some(count) = options.num_buffers {
+            self.extra_buf = count;
+        }
+        if let Some(enable) = options.promiscuous {
+            self.promiscuous = enable;
+        }
+        if let Some(enable) = options.allmulti {
+            self.allmulti = enable;
+        }
+        if let Some(policy) = options.flush_policy {
+            self.flush_policy = policy;
+        }
+        if let Some(rate) = options.tx_rate {
+            self.tx_rate = rate;
+        }
+        if let Some(enable) = options.vlan_strip {
+            self.vlan_strip = enable;
+        }
+        if let Some(tag) = options.vlan_insert {
+            self.vlan_insert = Some(tag);
+        }
+        if let Some(snaplen) = options.snaplen {
+            self.snaplen = Some(snaplen);
+        }
+        if let Some(source) = options.timestamp_source {
+            self.timestamp_source = source;
+        }
+    }
+}
 
-pub struct Meta {}
+/// Per-packet metadata from the netmap slot.
+///
+/// The timestamp is the RX ring's, i.e. the time of the last sync rather than of the
+/// individual packet. Netmap reports no RSS hash; `queue` is the index of the RX ring.
+///
+/// Frames larger than a buffer span several slots, received one at a time with `more`
+/// set on all but the last; the snaplen, the filter and the VLAN handling only apply to
+/// the first. [`recv_packet`](api::Socket::recv_packet) gathers them.
+pub struct Meta {
+    pub timestamp: Option<api::Timestamp>,
+    pub len: u32,
+    /// Length of the frame before the snaplen truncation.
+    pub wirelen: u32,
+    pub ring: u16,
+    pub vlan: Option<api::VlanTag>,
+    /// More slots of the same frame follow (`NS_MOREFRAG`).
+    pub more: bool,
+}
 
 impl api::Metadata for Meta {
     fn into_enum(self) -> api::MetadataType {
         api::MetadataType::Netmap(self)
     }
+
+    fn timestamp(&self) -> Option<api::Timestamp> {
+        self.timestamp
+    }
+
+    fn caplen(&self) -> u32 {
+        self.len
+    }
+
+    fn wirelen(&self) -> u32 {
+        self.wirelen
+    }
+
+    fn vlan(&self) -> Option<api::VlanTag> {
+        self.vlan
+    }
+
+    fn queue(&self) -> Option<u32> {
+        Some(self.ring as u32)
+    }
 }
 
 #[cfg(test)]
@@ -201,8 +849,34 @@ mod tests {
 
     #[test]
     fn test_send_with_flush() {
-        let socket0 = Sock::create("vale0:1", None, NetmapFlags { extra_buf: 1024 }).unwrap();
-        let socket1 = Sock::create("vale0:0", None, NetmapFlags { extra_buf: 1024 }).unwrap();
+        let socket0 = Sock::create(
+            "vale0:1",
+            None,
+            NetmapFlags {
+                extra_buf: 1024,
+                flush_policy: api::FlushPolicy::Manual,
+                tx_rate: api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                timestamp_source: api::TimestampSource::Realtime,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let socket1 = Sock::create(
+            "vale0:0",
+            None,
+            NetmapFlags {
+                extra_buf: 1024,
+                flush_policy: api::FlushPolicy::Manual,
+                tx_rate: api::TxRate::Unlimited,
+                vlan_strip: false,
+                vlan_insert: None,
+                timestamp_source: api::TimestampSource::Realtime,
+                ..Default::default()
+            },
+        )
+        .unwrap();
         socket1.send(b"Helloworldmyfriend\0\0\0\0\0\0\0").unwrap();
         socket1.flush();
         let (packet, meta) = socket0.recv().unwrap();
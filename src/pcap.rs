@@ -2,30 +2,51 @@
 //! Glue to fit the `pcap` crate into the provided Socket/Context/Token API.
 
 use std::{
-    cell::RefCell,
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    ffi::{CStr, CString},
     fs::File,
+    io::{BufRead, BufReader, Read},
+    net::IpAddr,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    path::{Path, PathBuf},
+    ptr::NonNull,
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicPtr, AtomicU16, AtomicU64, AtomicUsize, Ordering},
         Arc,
     },
+    time::{Duration, Instant},
 };
 
 use crossbeam_queue::ArrayQueue;
-use pcap::{Active, Capture, Device, Packet};
+use pcap::{
+    Active, Capture, Device, Inactive, Linktype, Packet, PacketHeader, Precision, Savefile,
+};
 use pcap_parser::{create_reader, traits::PcapReaderIterator, PcapBlockOwned, PcapError};
 
 use crate::api::{
-    BufferDesc, Context, Flags as FlagsTrait, Metadata, MetadataType, Result, Socket, Token,
+    self, netdev, poll, BufferDesc, Capabilities, Context, Direction, Dropped,
+    Flags as FlagsTrait, LinkInfo, LinkType, Metadata, MetadataType, Pacer, Payload, Pollable,
+    Result, RxFcs, RxSocket, Socket, SocketBuilder, SocketGroup, SocketMetrics, SocketOptions,
+    SoftwareClock, Stats, Timestamp, TimestampKind, TimestampSource, Token, TxChecksum,
+    TxCompletions, TxRate, TxSocket, UserspaceFilter, VlanTag,
 };
 
-/// -------- Flags ------------------------------------------------------------------
+// -------- Flags ------------------------------------------------------------------
 
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct PcapFlags {
     /// Snaplen passed to libpcap.
     pub snaplen: i32,
     /// Promiscuous mode.
     pub promiscuous: bool,
+    /// Receive every multicast frame (`PACKET_MR_ALLMULTI`, live captures).
+    pub allmulti: bool,
     /// Read timeout in milliseconds (for live captures).
     pub timeout_ms: i32,
     /// libpcap immediate mode (deliver packets as soon as they arrive).
@@ -36,6 +57,79 @@ pub struct PcapFlags {
     pub buffer_size: usize,
     /// Initial number of buffers to preallocate.
     pub buffer_count: usize,
+    /// What to do when the application holds every buffer of the pool.
+    pub pool_policy: PoolPolicy,
+    /// Captured traffic direction (live captures).
+    pub direction: Direction,
+    /// Request adapter timestamps (`PCAP_TSTAMP_ADAPTER`) for live captures; the same
+    /// as a [`timestamp_type`](PcapFlags::timestamp_type) of
+    /// [`Adapter`](TimestampType::Adapter), except that a device refusing them falls
+    /// back to host timestamps, and the socket then reports none in its capabilities.
+    pub hw_timestamps: bool,
+    /// Source of the timestamps of live captures (`pcap_set_tstamp_type`), see
+    /// [`timestamp_types`] for those a device offers; `None` leaves the libpcap
+    /// default. Takes precedence over [`hw_timestamps`](PcapFlags::hw_timestamps).
+    pub timestamp_type: Option<TimestampType>,
+    /// Clock of the software timestamps of live captures.
+    pub timestamp_source: TimestampSource,
+    /// Rate at which packets are transmitted.
+    pub tx_rate: TxRate,
+    /// Remove the outermost VLAN tag from received frames (in software).
+    pub vlan_strip: bool,
+    /// VLAN tag inserted into every transmitted frame (in software).
+    pub vlan_insert: Option<VlanTag>,
+    /// Keep (`rx-fcs` feature) or strip (in software) the FCS of the frames of live
+    /// captures; `None` leaves the interface as it is.
+    pub fcs: Option<bool>,
+    /// Put the wireless interface in monitor (rfmon) mode (live captures), to capture
+    /// every 802.11 frame in range; the frames usually come with a radiotap header,
+    /// see [`Meta::linktype`].
+    pub monitor_mode: bool,
+    /// Keep live captures in non-blocking mode (`pcap_setnonblock`): receiving never
+    /// waits and fails with `WouldBlock` instead, for event loops polling the socket
+    /// descriptor.
+    pub nonblocking: bool,
+    /// Request nanosecond timestamps from libpcap (`PCAP_TSTAMP_PRECISION_NANO`) for
+    /// live captures, instead of microsecond ones.
+    pub nanosecond_timestamps: bool,
+    /// Credentials for `rpcap://` sources; `None` uses null authentication.
+    pub remote_auth: Option<RemoteAuth>,
+    /// Replace the Linux cooked (`SLL`, `SLL2`) header of the frames captured on the
+    /// `any` device, or read from cooked capture files, with an Ethernet header, whose
+    /// source is the link-layer address of the cooked header and destination is zero.
+    pub synthesize_ethernet: bool,
+    /// Run the libpcap optimizer on the filters of live captures, which are compiled
+    /// with the IPv4 netmask of the device.
+    pub optimize_filter: bool,
+}
+
+/// Username and password for an `rpcapd` that requires authentication.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RemoteAuth {
+    /// Account on the `rpcapd` host.
+    pub username: String,
+    /// Password of the account.
+    pub password: String,
+}
+
+impl RemoteAuth {
+    /// Credentials of `username`.
+    pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Self {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+}
+
+impl std::fmt::Debug for RemoteAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemoteAuth")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
 }
 
 impl Default for PcapFlags {
@@ -43,39 +137,464 @@ impl Default for PcapFlags {
         Self {
             snaplen: 65535,
             promiscuous: true,
+            allmulti: false,
             timeout_ms: 1,
             immediate: true,
             filter: None,
             buffer_size: 2048,
             buffer_count: 32,
+            pool_policy: PoolPolicy::default(),
+            direction: Direction::InOut,
+            hw_timestamps: false,
+            timestamp_type: None,
+            timestamp_source: TimestampSource::Realtime,
+            tx_rate: TxRate::Unlimited,
+            vlan_strip: false,
+            vlan_insert: None,
+            fcs: None,
+            monitor_mode: false,
+            nonblocking: false,
+            nanosecond_timestamps: false,
+            remote_auth: None,
+            synthesize_ethernet: false,
+            optimize_filter: true,
+        }
+    }
+}
+
+impl FlagsTrait for PcapFlags {
+    fn apply(&mut self, options: &SocketOptions) {
+        if let Some(promiscuous) = options.promiscuous {
+            self.promiscuous = promiscuous;
+        }
+        if let Some(allmulti) = options.allmulti {
+            self.allmulti = allmulti;
+        }
+        if let Some(snaplen) = options.snaplen {
+            self.snaplen = snaplen.min(i32::MAX as u32) as i32;
+        }
+        if let Some(size) = options.buffer_size {
+            self.buffer_size = size as usize;
+        }
+        if let Some(count) = options.num_buffers {
+            self.buffer_count = count as usize;
+        }
+        if let Some(direction) = options.direction {
+            self.direction = direction;
+        }
+        if let Some(enable) = options.hw_timestamps {
+            self.hw_timestamps = enable;
+        }
+        if let Some(source) = options.timestamp_source {
+            self.timestamp_source = source;
+        }
+        if let Some(rate) = options.tx_rate {
+            self.tx_rate = rate;
+        }
+        if let Some(enable) = options.vlan_strip {
+            self.vlan_strip = enable;
+        }
+        if let Some(tag) = options.vlan_insert {
+            self.vlan_insert = Some(tag);
+        }
+        if let Some(keep) = options.fcs {
+            self.fcs = Some(keep);
+        }
+    }
+}
+
+impl SocketBuilder<Sock> {
+    /// Sets the read timeout in milliseconds for live captures.
+    pub fn timeout_ms(self, timeout_ms: i32) -> Self {
+        self.configure(|f| f.timeout_ms = timeout_ms)
+    }
+
+    /// Enables or disables libpcap immediate mode.
+    pub fn immediate(self, immediate: bool) -> Self {
+        self.configure(|f| f.immediate = immediate)
+    }
+
+    /// Installs a BPF filter (tcpdump syntax).
+    pub fn filter(self, filter: &str) -> Self {
+        self.configure(|f| f.filter = Some(filter.to_string()))
+    }
+
+    /// Enables or disables monitor (rfmon) mode on wireless interfaces.
+    pub fn monitor_mode(self, monitor_mode: bool) -> Self {
+        self.configure(|f| f.monitor_mode = monitor_mode)
+    }
+
+    /// Keeps live captures in non-blocking mode.
+    pub fn nonblocking(self, nonblocking: bool) -> Self {
+        self.configure(|f| f.nonblocking = nonblocking)
+    }
+
+    /// Selects the source of the timestamps of live captures.
+    pub fn timestamp_type(self, timestamp_type: TimestampType) -> Self {
+        self.configure(|f| f.timestamp_type = Some(timestamp_type))
+    }
+
+    /// Authenticates to `rpcapd` with a username and password.
+    pub fn remote_auth(self, username: &str, password: &str) -> Self {
+        self.configure(|f| f.remote_auth = Some(RemoteAuth::new(username, password)))
+    }
+
+    /// Requests nanosecond timestamps for live captures.
+    pub fn nanosecond_timestamps(self, nanosecond_timestamps: bool) -> Self {
+        self.configure(|f| f.nanosecond_timestamps = nanosecond_timestamps)
+    }
+
+    /// Enables or disables the optimizer of the filters of live captures.
+    pub fn optimize_filter(self, optimize_filter: bool) -> Self {
+        self.configure(|f| f.optimize_filter = optimize_filter)
+    }
+
+    /// Replaces the Linux cooked headers of the frames with Ethernet headers.
+    pub fn synthesize_ethernet(self, synthesize_ethernet: bool) -> Self {
+        self.configure(|f| f.synthesize_ethernet = synthesize_ethernet)
+    }
+
+    /// Sets what to do when the application holds every buffer of the pool.
+    pub fn pool_policy(self, pool_policy: PoolPolicy) -> Self {
+        self.configure(|f| f.pool_policy = pool_policy)
+    }
+}
+
+/// Source of the timestamps of a live capture (`PCAP_TSTAMP_*`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimestampType {
+    /// Taken by the host, with unspecified characteristics.
+    Host = 0,
+    /// Taken by the host, cheaply but with a low precision.
+    HostLowPrec = 1,
+    /// Taken by the host, with a high precision.
+    HostHighPrec = 2,
+    /// Taken by the capture device, and synchronized with the system clock.
+    Adapter = 3,
+    /// Taken by the capture device, and not synchronized with the system clock.
+    AdapterUnsynced = 4,
+}
+
+impl TimestampType {
+    const ALL: [Self; 5] = [
+        Self::Host,
+        Self::HostLowPrec,
+        Self::HostHighPrec,
+        Self::Adapter,
+        Self::AdapterUnsynced,
+    ];
+
+    /// Returns `true` for the timestamps taken by the capture device.
+    pub fn is_hardware(self) -> bool {
+        matches!(self, Self::Adapter | Self::AdapterUnsynced)
+    }
+
+    fn raw(self) -> libc::c_int {
+        self as libc::c_int
+    }
+}
+
+/// Returns the timestamp types `device` offers for live captures.
+///
+/// The list is empty when the device only has the default host timestamps.
+pub fn timestamp_types(device: &str) -> Result<Vec<TimestampType>> {
+    let cap = Capture::from_device(device)?;
+    let mut types = std::ptr::null_mut();
+    let count = unsafe { ffi::pcap_list_tstamp_types(cap.as_ptr().cast(), &mut types) };
+    if count < 0 {
+        return Err(crate::errors::Error::Pcap(pcap::Error::PcapError(format!(
+            "cannot list the timestamp types of {device}"
+        ))));
+    }
+    let list = if types.is_null() {
+        Vec::new()
+    } else {
+        // SAFETY: libpcap returned an array of `count` integers, freed right after.
+        let raw = unsafe { std::slice::from_raw_parts(types, count as usize) };
+        let list = raw
+            .iter()
+            .filter_map(|&raw| TimestampType::ALL.into_iter().find(|t| t.raw() == raw))
+            .collect();
+        unsafe { ffi::pcap_free_tstamp_types(types) };
+        list
+    };
+    Ok(list)
+}
+
+/// Selects the timestamp type of a capture before its activation.
+fn set_timestamp_type(cap: &Capture<Inactive>, device: &str, ts: TimestampType) -> Result<()> {
+    match unsafe { ffi::pcap_set_tstamp_type(cap.as_ptr().cast(), ts.raw()) } {
+        0 => Ok(()),
+        ffi::PCAP_WARNING_TSTAMP_TYPE_NOTSUP | ffi::PCAP_ERROR_CANTSET_TSTAMP_TYPE => {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!("{device} does not offer {ts:?} timestamps"),
+            )
+            .into())
         }
+        rc => Err(crate::errors::Error::Pcap(pcap::Error::PcapError(format!(
+            "pcap_set_tstamp_type failed on {device} ({rc})"
+        )))),
     }
 }
 
-impl FlagsTrait for PcapFlags {}
+// -------- Devices -----------------------------------------------------------------
 
-/// -------- Metadata ----------------------------------------------------------------
+/// A capture device, as returned by [`list_devices`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PcapDevice {
+    /// Device name, usable as a portspec.
+    pub name: String,
+    /// Human-readable description, if libpcap has one.
+    pub description: Option<String>,
+    /// The interface is administratively up.
+    pub up: bool,
+    /// The interface is running (it has carrier).
+    pub running: bool,
+    /// The interface is a loopback.
+    pub loopback: bool,
+    /// The interface is wireless, and may support [`monitor_mode`](PcapFlags::monitor_mode).
+    pub wireless: bool,
+    /// Addresses assigned to the interface.
+    pub addresses: Vec<DeviceAddress>,
+}
+
+/// An address of a [`PcapDevice`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceAddress {
+    /// The address.
+    pub addr: IpAddr,
+    /// Network mask.
+    pub netmask: Option<IpAddr>,
+    /// Broadcast address.
+    pub broadcast: Option<IpAddr>,
+    /// Destination address of point-to-point interfaces.
+    pub destination: Option<IpAddr>,
+}
+
+/// Lists the devices libpcap can capture from (`pcap_findalldevs`), pseudo-devices
+/// such as `any` included, in libpcap order.
+pub fn list_devices() -> Result<Vec<PcapDevice>> {
+    let devices = Device::list()?
+        .into_iter()
+        .map(|device| PcapDevice {
+            up: device.flags.is_up(),
+            running: device.flags.is_running(),
+            loopback: device.flags.is_loopback(),
+            wireless: device.flags.is_wireless(),
+            addresses: device
+                .addresses
+                .iter()
+                .map(|address| DeviceAddress {
+                    addr: address.addr,
+                    netmask: address.netmask,
+                    broadcast: address.broadcast_addr,
+                    destination: address.dst_addr,
+                })
+                .collect(),
+            name: device.name,
+            description: device.desc,
+        })
+        .collect();
+    Ok(devices)
+}
+
+/// Resolves a user-supplied interface name to the name of a libpcap device.
+///
+/// Besides device names, it accepts the friendly names libpcap reports as descriptions
+/// (on Windows, Npcap describes `\Device\NPF_{GUID}` devices with the adapter name,
+/// e.g. `Ethernet`), bare `{GUID}`s, and `lo` or `loopback` for the loopback device
+/// (`\Device\NPF_Loopback` with Npcap). Names are compared ignoring case. Fails with
+/// `NotFound` if no device matches.
+pub fn resolve_device(name: &str) -> Result<String> {
+    let devices = list_devices()?;
+    match_device(&devices, name)
+        .map(|device| device.name.clone())
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no such capture device: {name}"),
+            )
+            .into()
+        })
+}
+
+fn match_device<'a>(devices: &'a [PcapDevice], name: &str) -> Option<&'a PcapDevice> {
+    let find = |pred: &dyn Fn(&PcapDevice) -> bool| devices.iter().find(|d| pred(d));
+    find(&|d| d.name == name)
+        .or_else(|| find(&|d| d.name.eq_ignore_ascii_case(name)))
+        .or_else(|| {
+            // `{GUID}` or `NPF_{GUID}` for `\Device\NPF_{GUID}`.
+            let guid = name.trim_start_matches("NPF_");
+            if !guid.starts_with('{') {
+                return None;
+            }
+            find(&|d| {
+                d.name
+                    .rsplit_once("NPF_")
+                    .is_some_and(|(_, g)| g.eq_ignore_ascii_case(guid))
+            })
+        })
+        .or_else(|| {
+            find(&|d| {
+                d.description
+                    .as_deref()
+                    .is_some_and(|desc| desc.eq_ignore_ascii_case(name))
+            })
+        })
+        .or_else(|| {
+            let loopback = ["lo", "loopback"].iter().any(|l| name.eq_ignore_ascii_case(l));
+            loopback.then(|| find(&|d| d.loopback)).flatten()
+        })
+}
+
+// -------- Metadata ----------------------------------------------------------------
 
 // If you need per-packet pcap metadata, create a PcapMeta and add
 // a `MetadataType::Pcap(PcapMeta)` variant to your enum. For now we
 // supply unit `()` and mark conversion as unreachable.
 
 pub struct Meta {
-    pub timestamp: libc::timeval,
+    pub timestamp: Option<Timestamp>,
     pub len: u32,
     pub caplen: u32,
+    pub vlan: Option<VlanTag>,
+    /// The timestamp was taken by the capture device.
+    pub hw_timestamp: bool,
+    /// The frame ends with its FCS.
+    pub fcs: bool,
+    /// Link-layer header type of the frame, usually radiotap in monitor mode.
+    pub linktype: LinkType,
+    /// Interface the frame was captured on, from the `SLL2` header of captures on the
+    /// `any` device.
+    pub ifindex: Option<u32>,
+    /// Where the frame was going, from the `SLL` or `SLL2` header of captures on the
+    /// `any` device.
+    pub pkt_type: Option<PacketType>,
 }
 
 impl Metadata for Meta {
     fn into_enum(self) -> MetadataType {
         MetadataType::Pcap(self)
     }
+
+    fn timestamp(&self) -> Option<Timestamp> {
+        self.timestamp
+    }
+
+    fn timestamp_kind(&self) -> Option<TimestampKind> {
+        self.timestamp.map(|_| {
+            if self.hw_timestamp {
+                TimestampKind::Hardware
+            } else {
+                TimestampKind::Software
+            }
+        })
+    }
+
+    fn caplen(&self) -> u32 {
+        self.caplen
+    }
+
+    fn wirelen(&self) -> u32 {
+        self.len
+    }
+
+    fn vlan(&self) -> Option<VlanTag> {
+        self.vlan
+    }
+
+    fn has_fcs(&self) -> bool {
+        self.fcs
+    }
+
+    fn linktype(&self) -> LinkType {
+        self.linktype
+    }
+}
+
+/// Type of a frame captured on a Linux cooked (`SLL`) device such as `any`
+/// (`PACKET_*`), telling where it was going.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PacketType {
+    /// Addressed to this host.
+    Host,
+    /// Broadcast by another host.
+    Broadcast,
+    /// Multicast by another host.
+    Multicast,
+    /// Addressed to another host, seen in promiscuous mode.
+    OtherHost,
+    /// Sent by this host.
+    Outgoing,
+    /// Any other type.
+    Other(u8),
+}
+
+impl From<u8> for PacketType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Host,
+            1 => Self::Broadcast,
+            2 => Self::Multicast,
+            3 => Self::OtherHost,
+            PACKET_OUTGOING => Self::Outgoing,
+            value => Self::Other(value),
+        }
+    }
 }
 
-/// -------- Context + Pool -----------------------------------------------------------
+// -------- Context + Pool -----------------------------------------------------------
 
 static NEXT_POOL_ID: AtomicUsize = AtomicUsize::new(1);
 
+/// What a pcap socket does when every buffer of its pool is held by the application,
+/// set with [`PcapFlags::pool_policy`].
+///
+/// Each packet received as a token or payload holds a buffer until it is dropped;
+/// [`recv_into`](Socket::recv_into) needs none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PoolPolicy {
+    /// Allocates more buffers, up to this many in all (`usize::MAX` for no limit, the
+    /// default), then behaves as [`Fixed`](PoolPolicy::Fixed).
+    Grow(usize),
+    /// Never allocates more than [`buffer_count`](PcapFlags::buffer_count) buffers:
+    /// receives fail with [`NoMemory`](crate::errors::Error::NoMemory) until one is
+    /// released, and packets wait in libpcap's buffer, where the kernel drops the
+    /// newest ones once it is full.
+    Fixed,
+    /// As [`Fixed`](PoolPolicy::Fixed), but each failed receive first discards the
+    /// oldest packet waiting in libpcap's buffer, counted in
+    /// [`Dropped::no_buffers`], so that the capture keeps up with the newest traffic.
+    OverwriteOldest,
+}
+
+impl Default for PoolPolicy {
+    fn default() -> Self {
+        Self::Grow(usize::MAX)
+    }
+}
+
+/// Occupancy of the buffer pool of a pcap socket, returned by [`Sock::pool_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PoolStats {
+    /// Buffers allocated, free or not.
+    pub allocated: usize,
+    /// Buffers held by the application.
+    pub in_use: usize,
+    /// Most buffers held at once.
+    pub peak_in_use: usize,
+    /// Most buffers the pool allocates, `usize::MAX` if it grows without limit.
+    pub limit: usize,
+    /// Receives that found every buffer in use.
+    pub exhausted: u64,
+}
+
 #[derive(Clone, Debug)]
 pub struct PcapContext {
     pool_id: u32,
@@ -83,12 +602,29 @@ pub struct PcapContext {
     // Thread-safe MPMC queue for buffer recycling.
     // Stores raw pointers (as usize) to buffers.
     pool: Arc<ArrayQueue<usize>>,
+    policy: PoolPolicy,
+    usage: Arc<PoolUsage>,
+}
+
+/// Counters of a pool, shared by the clones of its context.
+#[derive(Debug, Default)]
+struct PoolUsage {
+    allocated: AtomicUsize,
+    in_use: AtomicUsize,
+    peak_in_use: AtomicUsize,
+    exhausted: AtomicU64,
 }
 
 impl PcapContext {
-    fn new(buf_size: usize, buf_count: usize) -> Self {
+    fn new(buf_size: usize, buf_count: usize, policy: PoolPolicy) -> Self {
         let pool_id = NEXT_POOL_ID.fetch_add(1, Ordering::Relaxed) as u32;
-        let pool = Arc::new(ArrayQueue::new(buf_count));
+        let buf_count = buf_count.max(1);
+        // Buffers grown up to a limit are kept for reuse; without one, the extra
+        // buffers of a burst are freed.
+        let pool = Arc::new(ArrayQueue::new(match policy {
+            PoolPolicy::Grow(max) if max != usize::MAX => max.max(buf_count),
+            _ => buf_count,
+        }));
 
         // Pre-allocate buffers
         for _ in 0..buf_count {
@@ -101,6 +637,61 @@ impl PcapContext {
             pool_id,
             buf_capacity: buf_size,
             pool,
+            policy,
+            usage: Arc::new(PoolUsage {
+                allocated: AtomicUsize::new(buf_count),
+                ..PoolUsage::default()
+            }),
+        }
+    }
+
+    /// Returns the most buffers the pool allocates.
+    fn limit(&self) -> usize {
+        match self.policy {
+            PoolPolicy::Grow(max) => max.max(self.pool.capacity()),
+            PoolPolicy::Fixed | PoolPolicy::OverwriteOldest => self.pool.capacity(),
+        }
+    }
+
+    /// Takes a buffer from the pool, or allocates a new one if it is empty and the
+    /// policy allows it.
+    fn buffer(&self) -> Option<*mut u8> {
+        let ptr = match self.pool.pop() {
+            Some(addr) => addr as *mut u8,
+            None => {
+                let limit = self.limit();
+                let grown = self.usage.allocated.fetch_update(
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                    |allocated| (allocated < limit).then_some(allocated + 1),
+                );
+                if grown.is_err() {
+                    self.usage.exhausted.fetch_add(1, Ordering::Relaxed);
+                    return None;
+                }
+                let buf = vec![0u8; self.buf_capacity].into_boxed_slice();
+                Box::into_raw(buf) as *mut u8
+            }
+        };
+        let in_use = self.usage.in_use.fetch_add(1, Ordering::Relaxed) + 1;
+        self.usage.peak_in_use.fetch_max(in_use, Ordering::Relaxed);
+        Some(ptr)
+    }
+
+    /// Returns how many buffers can be taken without exhausting the pool.
+    fn available(&self) -> usize {
+        let allocated = self.usage.allocated.load(Ordering::Relaxed);
+        self.pool.len().saturating_add(self.limit().saturating_sub(allocated))
+    }
+
+    /// Returns the occupancy of the pool.
+    pub fn pool_stats(&self) -> PoolStats {
+        PoolStats {
+            allocated: self.usage.allocated.load(Ordering::Relaxed),
+            in_use: self.usage.in_use.load(Ordering::Relaxed),
+            peak_in_use: self.usage.peak_in_use.load(Ordering::Relaxed),
+            limit: self.limit(),
+            exhausted: self.usage.exhausted.load(Ordering::Relaxed),
         }
     }
 }
@@ -120,12 +711,14 @@ impl Context for PcapContext {
 
     fn release(&self, buf_idx: BufferDesc) {
         let ptr = usize::from(buf_idx);
+        self.usage.in_use.fetch_sub(1, Ordering::Relaxed);
         // Try to return the buffer to the pool.
         if let Err(returned_ptr) = self.pool.push(ptr) {
             // If the pool is full (e.g., due to extra allocations during bursts),
             // we must deallocate the buffer to avoid leaks.
+            self.usage.allocated.fetch_sub(1, Ordering::Relaxed);
             unsafe {
-                let _ = Box::from_raw(std::slice::from_raw_parts_mut(
+                let _ = Box::from_raw(std::ptr::slice_from_raw_parts_mut(
                     returned_ptr as *mut u8,
                     self.buf_capacity,
                 ));
@@ -134,243 +727,2902 @@ impl Context for PcapContext {
     }
 }
 
-/// -------- Socket -------------------------------------------------------------------
+// -------- Compressed capture files ------------------------------------------------
 
-enum PcapInner {
-    Live(Capture<Active>),
-    Offline(Box<dyn PcapReaderIterator + Send>),
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Wraps a capture file in a decompressor if it starts with the gzip or zstd magic.
+fn decompress(file: File) -> Result<Box<dyn Read + Send>> {
+    let mut reader = BufReader::new(file);
+    let magic = reader.fill_buf()?;
+    if magic.starts_with(&GZIP_MAGIC) {
+        #[cfg(feature = "gzip")]
+        return Ok(Box::new(flate2::bufread::MultiGzDecoder::new(reader)));
+        #[cfg(not(feature = "gzip"))]
+        return Err(compression_unsupported("gzip"));
+    }
+    if magic.starts_with(&ZSTD_MAGIC) {
+        #[cfg(feature = "zstd")]
+        return Ok(Box::new(zstd::stream::read::Decoder::with_buffer(reader)?));
+        #[cfg(not(feature = "zstd"))]
+        return Err(compression_unsupported("zstd"));
+    }
+    Ok(Box::new(reader))
 }
 
-pub struct Sock {
-    ctx: PcapContext,
-    inner: RefCell<PcapInner>,
+#[cfg(any(not(feature = "gzip"), not(feature = "zstd")))]
+fn compression_unsupported(format: &str) -> crate::errors::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!("{format}-compressed capture files require the `{format}` feature"),
+    )
+    .into()
 }
 
-impl Sock {
-    fn next_packet<'a>(
-        cap: &'a mut Capture<Active>,
-    ) -> std::result::Result<Packet<'a>, pcap::Error> {
-        cap.next_packet()
-    }
+// -------- Raw libpcap ---------------------------------------------------------------
 
-    fn next_packet_offline(
-        reader: &mut Box<dyn PcapReaderIterator + Send>,
-        buffer: &mut [u8],
-    ) -> std::result::Result<(u32, Meta), crate::errors::Error> {
-        loop {
-            match reader.next() {
-                Ok((offset, block)) => {
-                    match block {
-                        PcapBlockOwned::Legacy(packet) => {
-                            let len = packet.origlen;
-                            let caplen = packet.data.len() as u32;
-                            let copy_len = std::cmp::min(caplen as usize, buffer.len());
-                            buffer[..copy_len].copy_from_slice(&packet.data[..copy_len]);
+// Entry points the `pcap` crate does not expose in a form we can use.
+mod ffi {
+    use libc::{c_char, c_int, c_uint, c_void};
 
-                            let meta = Meta {
-                                timestamp: libc::timeval {
-                                    tv_sec: packet.ts_sec as i64,
-                                    tv_usec: packet.ts_usec as i32,
-                                },
-                                len,
-                                caplen,
-                            };
-                            reader.consume(offset);
-                            return Ok((copy_len as u32, meta));
-                        }
-                        PcapBlockOwned::NG(block) => {
-                            match block {
-                                pcap_parser::Block::EnhancedPacket(packet) => {
-                                    let len = packet.origlen;
-                                    let caplen = packet.data.len() as u32;
-                                    let copy_len = std::cmp::min(caplen as usize, buffer.len());
-                                    buffer[..copy_len].copy_from_slice(&packet.data[..copy_len]);
+    pub const PCAP_ERRBUF_SIZE: usize = 256;
+    pub const PCAP_OPENFLAG_PROMISCUOUS: c_int = 1;
+    pub const PCAP_OPENFLAG_NOCAPTURE_RPCAP: c_int = 4;
+    pub const RPCAP_RMTAUTH_NULL: c_int = 0;
+    pub const RPCAP_RMTAUTH_PWD: c_int = 1;
+    pub const PCAP_WARNING_TSTAMP_TYPE_NOTSUP: c_int = 3;
+    pub const PCAP_ERROR_CANTSET_TSTAMP_TYPE: c_int = -10;
+    pub const PCAP_NETMASK_UNKNOWN: u32 = 0xffff_ffff;
 
-                                    let raw_ts = (packet.ts_high as u64) << 32 | (packet.ts_low as u64);
-                                    
-                                    // Try to determine if timestamp is microseconds or nanoseconds.
-                                    // Modern timestamps (e.g. 2024) in microseconds are ~1.7e15
-                                    // In nanoseconds they are ~1.7e18
-                                    // We use a threshold of 1e16 to distinguish.
-                                    let (tv_sec, tv_usec) = if raw_ts < 10_000_000_000_000_000 {
-                                        // Microseconds
-                                        ((raw_ts / 1_000_000) as i64, (raw_ts % 1_000_000) as i32)
-                                    } else {
-                                        // Nanoseconds
-                                        ((raw_ts / 1_000_000_000) as i64, ((raw_ts % 1_000_000_000) / 1000) as i32)
-                                    };
+    unsafe extern "C" {
+        pub fn pcap_setnonblock(p: *mut c_void, nonblock: c_int, errbuf: *mut c_char) -> c_int;
+        pub fn pcap_get_selectable_fd(p: *mut c_void) -> c_int;
+        pub fn pcap_set_tstamp_type(p: *mut c_void, tstamp_type: c_int) -> c_int;
+        pub fn pcap_list_tstamp_types(p: *mut c_void, types: *mut *mut c_int) -> c_int;
+        pub fn pcap_free_tstamp_types(types: *mut c_int);
+        pub fn pcap_open(
+            source: *const c_char,
+            snaplen: c_int,
+            flags: c_int,
+            read_timeout: c_int,
+            auth: *mut PcapRmtAuth,
+            errbuf: *mut c_char,
+        ) -> *mut c_void;
+        pub fn pcap_breakloop(p: *mut c_void);
+        pub fn pcap_dispatch(
+            p: *mut c_void,
+            cnt: c_int,
+            callback: PcapHandler,
+            user: *mut u8,
+        ) -> c_int;
+        pub fn pcap_next_ex(
+            p: *mut c_void,
+            header: *mut *mut pcap::PacketHeader,
+            data: *mut *const u8,
+        ) -> c_int;
+        pub fn pcap_stats(p: *mut c_void, stats: *mut PcapStat) -> c_int;
+        pub fn pcap_datalink(p: *mut c_void) -> c_int;
+        pub fn pcap_geterr(p: *mut c_void) -> *mut c_char;
+        pub fn pcap_close(p: *mut c_void);
+        pub fn pcap_lookupnet(
+            device: *const c_char,
+            netp: *mut u32,
+            maskp: *mut u32,
+            errbuf: *mut c_char,
+        ) -> c_int;
+        pub fn pcap_compile(
+            p: *mut c_void,
+            fp: *mut BpfProgram,
+            expr: *const c_char,
+            optimize: c_int,
+            netmask: u32,
+        ) -> c_int;
+        pub fn pcap_setfilter(p: *mut c_void, fp: *mut BpfProgram) -> c_int;
+        pub fn pcap_freecode(fp: *mut BpfProgram);
+    }
 
-                                    let meta = Meta {
-                                        timestamp: libc::timeval {
-                                            tv_sec,
-                                            tv_usec: tv_usec as _,
-                                        },
-                                        len,
-                                        caplen,
-                                    };
-                                    reader.consume(offset);
-                                    return Ok((copy_len as u32, meta));
-                                }
-                                pcap_parser::Block::SimplePacket(packet) => {
-                                    let len = packet.origlen;
-                                    let caplen = packet.data.len() as u32;
-                                    let copy_len = std::cmp::min(caplen as usize, buffer.len());
-                                    buffer[..copy_len].copy_from_slice(&packet.data[..copy_len]);
+    pub type PcapHandler =
+        extern "C" fn(user: *mut u8, header: *const pcap::PacketHeader, data: *const u8);
 
-                                    let meta = Meta {
-                                        timestamp: libc::timeval {
-                                            tv_sec: 0,
-                                            tv_usec: 0,
-                                        },
-                                        len,
-                                        caplen,
-                                    };
-                                    reader.consume(offset);
-                                    return Ok((copy_len as u32, meta));
-                                }
-                                _ => {
-                                    // Skip other blocks (headers, interfaces, stats)
-                                    reader.consume(offset);
-                                    continue;
-                                }
-                            }
-                        }
-                        PcapBlockOwned::LegacyHeader(_) => {
-                            reader.consume(offset);
-                            continue;
-                        }
-                    }
-                }
-                Err(PcapError::Eof) => {
-                    return Err(crate::errors::Error::Pcap(pcap::Error::NoMorePackets));
-                }
-                Err(PcapError::Incomplete(_)) => {
-                    reader.refill().map_err(|e| {
-                        crate::errors::Error::Pcap(pcap::Error::PcapError(format!("{:?}", e)))
-                    })?;
-                    continue;
-                }
-                Err(e) => {
-                    // Map pcap-parser error to something generic or print it
-                    eprintln!("Pcap parser error: {:?}", e);
-                    return Err(crate::errors::Error::Pcap(pcap::Error::PcapError(
-                        "Parser error".to_string(),
-                    )));
-                }
-            }
-        }
+    #[repr(C)]
+    pub struct PcapRmtAuth {
+        pub kind: c_int,
+        pub username: *mut c_char,
+        pub password: *mut c_char,
     }
-}
 
-impl Socket for Sock {
-    type Context = PcapContext;
-    type Metadata = Meta;
-    type Flags = PcapFlags;
+    #[repr(C)]
+    pub struct BpfProgram {
+        pub bf_len: c_uint,
+        pub bf_insns: *mut c_void,
+    }
 
-    fn recv_token(&self) -> Result<(Token, Self::Metadata)> {
-        let ctx = &self.ctx;
-        let mut inner = self.inner.borrow_mut();
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct PcapStat {
+        pub ps_recv: c_uint,
+        pub ps_drop: c_uint,
+        pub ps_ifdrop: c_uint,
+    }
+}
 
-        // 1. Acquire a buffer from the pool (or allocate if empty)
-        let ptr = if let Some(addr) = ctx.pool.pop() {
-            addr as *mut u8
-        } else {
-            // Fallback: pool is empty, allocate a new buffer.
-            let buf = vec![0u8; ctx.buf_capacity].into_boxed_slice();
-            Box::into_raw(buf) as *mut u8
-        };
+fn set_nonblock(cap: &Capture<Active>, nonblock: bool) -> Result<()> {
+    let mut errbuf = [0 as libc::c_char; ffi::PCAP_ERRBUF_SIZE];
+    let rc = unsafe {
+        ffi::pcap_setnonblock(
+            cap.as_ptr().cast(),
+            nonblock as libc::c_int,
+            errbuf.as_mut_ptr(),
+        )
+    };
+    if rc != 0 {
+        let msg = unsafe { CStr::from_ptr(errbuf.as_ptr()) };
+        return Err(crate::errors::Error::Pcap(pcap::Error::PcapError(
+            msg.to_string_lossy().into_owned(),
+        )));
+    }
+    Ok(())
+}
 
-        // 2. Read packet from pcap directly into buffer
-        // SAFETY: We own the buffer `ptr`.
-        let (len, meta) = unsafe {
-            let slice = std::slice::from_raw_parts_mut(ptr, ctx.buf_capacity);
-            match &mut *inner {
-                PcapInner::Live(cap) => {
-                    let pkt = Self::next_packet(cap).map_err(crate::errors::Error::from)?;
-                    let meta = Meta {
-                        timestamp: pkt.header.ts,
-                        len: pkt.header.len,
-                        caplen: pkt.header.caplen,
-                    };
-                    let copy_len = std::cmp::min(pkt.data.len(), slice.len());
-                    slice[..copy_len].copy_from_slice(&pkt.data[..copy_len]);
-                    (copy_len as u32, meta)
-                }
-                PcapInner::Offline(reader) => Self::next_packet_offline(reader, slice)?,
-            }
-        };
+/// Returns the descriptor that becomes readable when packets are ready.
+fn selectable_fd(cap: &Capture<Active>) -> Result<RawFd> {
+    let fd = unsafe { ffi::pcap_get_selectable_fd(cap.as_ptr().cast()) };
+    if fd < 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "the capture device has no selectable descriptor",
+        )
+        .into());
+    }
+    Ok(fd)
+}
 
-        // 3. Create Token
-        let buf_desc = BufferDesc(ptr as usize);
-        let token = Token::new(buf_desc, ctx.pool_id(), len);
+/// Returns the IPv4 netmask of `device`, which filters need for `ip broadcast`, or
+/// `PCAP_NETMASK_UNKNOWN` if it has no IPv4 address (or is `any`).
+fn lookup_netmask(device: &str) -> u32 {
+    let Ok(device) = CString::new(device) else {
+        return ffi::PCAP_NETMASK_UNKNOWN;
+    };
+    let (mut net, mut mask) = (0, 0);
+    let mut errbuf = [0 as libc::c_char; ffi::PCAP_ERRBUF_SIZE];
+    let rc =
+        unsafe { ffi::pcap_lookupnet(device.as_ptr(), &mut net, &mut mask, errbuf.as_mut_ptr()) };
+    if rc != 0 {
+        return ffi::PCAP_NETMASK_UNKNOWN;
+    }
+    mask
+}
 
-        Ok((token, meta))
+/// Compiles `expr` for a live capture and installs it in the kernel.
+///
+/// The `pcap` crate always compiles with an unknown netmask, with which libpcap
+/// rejects `ip broadcast`.
+fn install_filter(cap: &Capture<Active>, expr: &str, optimize: bool, netmask: u32) -> Result<()> {
+    let handle = cap.as_ptr().cast();
+    let error = || {
+        let msg = unsafe { CStr::from_ptr(ffi::pcap_geterr(handle)) };
+        crate::errors::Error::Pcap(pcap::Error::PcapError(msg.to_string_lossy().into_owned()))
+    };
+    let expr = CString::new(expr).map_err(|_| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "filter contains a NUL byte")
+    })?;
+    let mut program = ffi::BpfProgram {
+        bf_len: 0,
+        bf_insns: std::ptr::null_mut(),
+    };
+    let rc = unsafe {
+        ffi::pcap_compile(handle, &mut program, expr.as_ptr(), optimize as libc::c_int, netmask)
+    };
+    if rc != 0 {
+        return Err(error());
+    }
+    let rc = unsafe { ffi::pcap_setfilter(handle, &mut program) };
+    // libpcap keeps its own copy of the program.
+    unsafe { ffi::pcap_freecode(&mut program) };
+    if rc != 0 {
+        return Err(error());
     }
+    Ok(())
+}
 
-    fn send(&self, packet: &[u8]) -> Result<()> {
-        match &mut *self.inner.borrow_mut() {
-            PcapInner::Live(cap) => cap.sendpacket(packet).map_err(crate::errors::Error::from),
-            PcapInner::Offline(_) => {
-                // Err(err_str("pcap offline captures cannot send packets")),
-                panic!("pcap offline captures cannot send packets")
-            }
-        }
+/// Installs `expr` on the live capture `cap`, whose descriptor is `fd`, and returns
+/// where it runs.
+///
+/// On Linux, libpcap silently filters in userspace when the kernel refuses the program
+/// (or it is too long for it); the program is then compiled into `fallback` instead,
+/// so that every receive path runs it and counts what it rejects.
+fn install_live_filter(
+    cap: &Capture<Active>,
+    fd: RawFd,
+    expr: &str,
+    optimize: bool,
+    netmask: u32,
+    fallback: &UserspaceFilter,
+) -> Result<FilterStage> {
+    install_filter(cap, expr, optimize, netmask)?;
+    fallback.clear();
+    if expr.trim().is_empty() {
+        return Ok(FilterStage::None);
+    }
+    // BPF devices, on other systems, always filter in the kernel.
+    if !matches!(netdev::attached_filter_len(fd), Ok(0)) {
+        return Ok(FilterStage::Kernel);
     }
+    fallback.set_with_linktype(expr, cap.get_datalink().0)?;
+    trace_event!(
+        warn,
+        backend = "pcap",
+        expr,
+        "kernel refused the filter, running it in userspace"
+    );
+    Ok(FilterStage::Userspace)
+}
 
-    fn flush(&self) {
-        // libpcap doesn't buffer sends in a way we can flush here; no-op.
+/// State of a `pcap_dispatch` call: the socket and the packets received so far.
+struct Burst<'a> {
+    sock: &'a Sock,
+    out: &'a mut Vec<(Token, Meta)>,
+}
+
+/// Copies a packet handed out by `pcap_dispatch` into a pool buffer.
+extern "C" fn burst_handler(user: *mut u8, header: *const pcap::PacketHeader, data: *const u8) {
+    // SAFETY: `user` is the `Burst` of `recv_burst`, and libpcap passes a valid packet,
+    // which is only read during the call.
+    let (burst, pkt) = unsafe {
+        let burst = &mut *user.cast::<Burst<'_>>();
+        let header = &*header;
+        let data = std::slice::from_raw_parts(data, header.caplen as usize);
+        (burst, Packet::new(header, data))
+    };
+    let sock = burst.sock;
+    if !sock.accepts(pkt.data) {
+        return;
     }
+    let Some(ptr) = sock.ctx.buffer() else {
+        // The burst is sized to the free buffers, but another thread may have taken one.
+        sock.no_buffers.set(sock.no_buffers.get() + 1);
+        return;
+    };
+    // SAFETY: the buffer was just taken from the pool.
+    let buf = unsafe { std::slice::from_raw_parts_mut(ptr, sock.ctx.buf_capacity) };
+    let (len, meta) = sock.copy_live_packet(&pkt, buf);
+    let (len, meta) = sock.finish_packet(buf, len, meta);
+    let token = Token::new(BufferDesc(ptr as usize), sock.ctx.pool_id(), len);
+    burst.out.push((token, meta));
+}
 
-    fn create(portspec: &str, _queue: Option<usize>, flags: Self::Flags) -> Result<Self> {
-        let ctx = PcapContext::new(flags.buffer_size, flags.buffer_count);
+// -------- Remote capture -----------------------------------------------------------
 
-        // Offline path?
-        let is_file = portspec.starts_with("file:")
-            || portspec.ends_with(".pcap")
-            || portspec.ends_with(".pcapng");
+/// A capture of a remote interface served by `rpcapd`, opened with `pcap_open`, which
+/// the `pcap` crate does not wrap.
+struct RemoteCapture(NonNull<libc::c_void>);
 
-        let inner = if is_file {
-            let path = portspec.strip_prefix("file:").unwrap_or(portspec);
-            let file = File::open(path).map_err(|e| {
+// SAFETY: libpcap handles can move between threads; this one is only used through
+// `&mut self` or, for reads of immutable state, `&self`.
+unsafe impl Send for RemoteCapture {}
+
+impl RemoteCapture {
+    fn open(source: &str, flags: &PcapFlags) -> Result<Self> {
+        let invalid = |what: &str| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("invalid {what}"))
+        };
+        let source = CString::new(source).map_err(|_| invalid("rpcap source"))?;
+        let credentials = match &flags.remote_auth {
+            Some(auth) => Some((
+                CString::new(auth.username.as_str()).map_err(|_| invalid("rpcap username"))?,
+                CString::new(auth.password.as_str()).map_err(|_| invalid("rpcap password"))?,
+            )),
+            None => None,
+        };
+        // libpcap does not modify the credentials despite the `char *`.
+        let mut auth = match &credentials {
+            Some((username, password)) => ffi::PcapRmtAuth {
+                kind: ffi::RPCAP_RMTAUTH_PWD,
+                username: username.as_ptr().cast_mut(),
+                password: password.as_ptr().cast_mut(),
+            },
+            None => ffi::PcapRmtAuth {
+                kind: ffi::RPCAP_RMTAUTH_NULL,
+                username: std::ptr::null_mut(),
+                password: std::ptr::null_mut(),
+            },
+        };
+        // Keep the rpcap traffic itself out of the capture.
+        let mut open_flags = ffi::PCAP_OPENFLAG_NOCAPTURE_RPCAP;
+        if flags.promiscuous {
+            open_flags |= ffi::PCAP_OPENFLAG_PROMISCUOUS;
+        }
+        let mut errbuf = [0 as libc::c_char; ffi::PCAP_ERRBUF_SIZE];
+        let handle = unsafe {
+            ffi::pcap_open(
+                source.as_ptr(),
+                flags.snaplen,
+                open_flags,
+                flags.timeout_ms,
+                &mut auth,
+                errbuf.as_mut_ptr(),
+            )
+        };
+        let handle = NonNull::new(handle).ok_or_else(|| {
+            let msg = unsafe { CStr::from_ptr(errbuf.as_ptr()) };
+            crate::errors::Error::Pcap(pcap::Error::PcapError(msg.to_string_lossy().into_owned()))
+        })?;
+        Ok(Self(handle))
+    }
+
+    /// Reads the next packet, waiting up to the read timeout.
+    fn next_packet(&mut self) -> std::result::Result<Packet<'_>, pcap::Error> {
+        let mut header = std::ptr::null_mut();
+        let mut data = std::ptr::null();
+        match unsafe { ffi::pcap_next_ex(self.0.as_ptr(), &mut header, &mut data) } {
+            // SAFETY: the packet stays valid until the next read, which borrows `self`
+            // mutably again.
+            1 => unsafe {
+                let header = &*header;
+                let data = std::slice::from_raw_parts(data, header.caplen as usize);
+                Ok(Packet::new(header, data))
+            },
+            0 => Err(pcap::Error::TimeoutExpired),
+            -2 => Err(pcap::Error::NoMorePackets),
+            _ => Err(pcap::Error::PcapError(self.error())),
+        }
+    }
+
+    fn stats(&mut self) -> Result<pcap::Stat> {
+        let mut stats = ffi::PcapStat::default();
+        if unsafe { ffi::pcap_stats(self.0.as_ptr(), &mut stats) } != 0 {
+            return Err(crate::errors::Error::Pcap(pcap::Error::PcapError(self.error())));
+        }
+        Ok(pcap::Stat {
+            received: stats.ps_recv,
+            dropped: stats.ps_drop,
+            if_dropped: stats.ps_ifdrop,
+        })
+    }
+
+    fn linktype(&self) -> LinkType {
+        LinkType::from(unsafe { ffi::pcap_datalink(self.0.as_ptr()) } as u16)
+    }
+
+    fn error(&self) -> String {
+        let msg = unsafe { CStr::from_ptr(ffi::pcap_geterr(self.0.as_ptr())) };
+        msg.to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for RemoteCapture {
+    fn drop(&mut self) {
+        unsafe { ffi::pcap_close(self.0.as_ptr()) };
+    }
+}
+
+// -------- Interruption -------------------------------------------------------------
+
+/// Interrupts the receives of a pcap socket from another thread, obtained with
+/// [`Sock::interrupter`].
+///
+/// [`interrupt`](Interrupter::interrupt) calls `pcap_breakloop`: a receive blocked in
+/// libpcap returns at once (with libpcap 1.10 or later; older versions wait for the
+/// next packet or the read timeout) and fails with `ErrorKind::Interrupted`. If no
+/// receive is in progress, the next one fails instead. Receives with a timeout wait for
+/// the descriptor in nethuns and notice the interruption when they next read.
+///
+/// It only touches atomics and libpcap's flag, so it is safe to call from a Ctrl-C or
+/// signal handler, and does nothing once the socket is dropped or on capture files.
+///
+/// ```ignore
+/// let interrupter = socket.interrupter();
+/// ctrlc::set_handler(move || interrupter.interrupt())?;
+/// while let Ok((payload, _meta)) = socket.recv() {
+///     handle(&payload);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Interrupter(Arc<Breakloop>);
+
+impl Interrupter {
+    /// Interrupts the receive in progress, or the next one.
+    pub fn interrupt(&self) {
+        let breakloop = &self.0;
+        breakloop.busy.fetch_add(1, Ordering::SeqCst);
+        let handle = breakloop.handle.load(Ordering::SeqCst);
+        if !handle.is_null() {
+            // SAFETY: the socket waits for `busy` to drop to zero before closing it.
+            unsafe { ffi::pcap_breakloop(handle) };
+        }
+        breakloop.busy.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The libpcap handle of a socket, shared with its interrupters.
+#[derive(Debug)]
+struct Breakloop {
+    /// Null for capture files and once the socket is dropped.
+    handle: AtomicPtr<libc::c_void>,
+    /// Interrupters calling `pcap_breakloop` on the handle.
+    busy: AtomicUsize,
+}
+
+impl Breakloop {
+    fn new(handle: *mut libc::c_void) -> Self {
+        Self {
+            handle: AtomicPtr::new(handle),
+            busy: AtomicUsize::new(0),
+        }
+    }
+
+    /// Forgets the handle, waiting for the interrupters still using it.
+    fn detach(&self) {
+        self.handle.store(std::ptr::null_mut(), Ordering::SeqCst);
+        while self.busy.load(Ordering::SeqCst) != 0 {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+/// Error of a receive stopped by an [`Interrupter`].
+fn interrupted() -> crate::errors::Error {
+    std::io::Error::new(std::io::ErrorKind::Interrupted, "pcap receive interrupted").into()
+}
+
+// -------- Kernel statistics --------------------------------------------------------
+
+/// Counters of `pcap_stats` for a live capture, since the socket was opened.
+///
+/// libpcap keeps them in 32-bit counters, which wrap after a few minutes at line rate;
+/// the socket widens them to 64 bits each time they are read, so read them at least
+/// once per wrap (every 2^32 packets).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KernelStats {
+    /// Packets received by the filter (`ps_recv`), whether or not they were dropped.
+    pub received: u64,
+    /// Packets dropped because the capture buffer was full (`ps_drop`).
+    pub dropped: u64,
+    /// Packets dropped by the interface or its driver (`ps_ifdrop`).
+    pub if_dropped: u64,
+}
+
+/// Accumulates the wrapping 32-bit counters of `pcap_stats` into [`KernelStats`].
+#[derive(Debug, Default)]
+struct KernelCounters {
+    last: Cell<[u32; 3]>,
+    total: Cell<KernelStats>,
+}
+
+impl KernelCounters {
+    /// Adds the increments since the previous read and returns the totals.
+    fn update(&self, stat: pcap::Stat) -> KernelStats {
+        let current = [stat.received, stat.dropped, stat.if_dropped];
+        let last = self.last.replace(current);
+        let delta = |i: usize| current[i].wrapping_sub(last[i]) as u64;
+        let mut total = self.total.get();
+        total.received += delta(0);
+        total.dropped += delta(1);
+        total.if_dropped += delta(2);
+        self.total.set(total);
+        total
+    }
+}
+
+// -------- Interfaces ---------------------------------------------------------------
+
+/// Returns interface `id` of `interfaces`, or a default one for broken files.
+fn interface(interfaces: &[Interface], id: usize) -> Interface {
+    interfaces.get(id).copied().unwrap_or_default()
+}
+
+/// The device of a live capture, or an interface described by the headers of a capture
+/// file.
+#[derive(Clone, Copy, Debug)]
+struct Interface {
+    linktype: LinkType,
+    /// Timestamp units per second: 10^6 or 10^9 for libpcap, anything for pcapng.
+    ts_resolution: u64,
+}
+
+impl Interface {
+    fn new(linktype: LinkType, nanos: bool) -> Self {
+        Self {
+            linktype,
+            ts_resolution: if nanos { 1_000_000_000 } else { 1_000_000 },
+        }
+    }
+
+    /// Converts a timestamp in seconds and units of the interface resolution.
+    fn timestamp(&self, secs: u64, units: u64) -> Timestamp {
+        self.timestamp_units(secs * self.ts_resolution + units)
+    }
+
+    /// Converts a timestamp counted in units of the interface resolution.
+    fn timestamp_units(&self, units: u64) -> Timestamp {
+        let nanos = units as u128 * 1_000_000_000 / self.ts_resolution as u128;
+        Timestamp::from_nanos(nanos as u64)
+    }
+}
+
+impl Default for Interface {
+    fn default() -> Self {
+        Self::new(LinkType::Ethernet, false)
+    }
+}
+
+// -------- Filter stage -------------------------------------------------------------
+
+/// Where a pcap socket runs its filter, as returned by [`Sock::filter_stage`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FilterStage {
+    /// No filter is installed.
+    #[default]
+    None,
+    /// In the kernel, which only hands out the packets that match.
+    Kernel,
+    /// In nethuns, on every packet read: on capture files and remote captures, and on
+    /// live captures whose program the kernel refused.
+    Userspace,
+}
+
+// -------- Socket -------------------------------------------------------------------
+
+enum PcapInner {
+    Live(Capture<Active>),
+    Offline(Box<dyn PcapReaderIterator + Send>),
+    Remote(RemoteCapture),
+}
+
+/// A libpcap socket, capturing from a live interface or reading a capture file.
+///
+/// [`create`](Socket::create) opens a capture file instead of an interface when the
+/// portspec starts with `file:` or ends with `.pcap` or `.pcapng`, optionally followed
+/// by `.gz` or `.zst`; see also [`open_file`](Sock::open_file). Files compressed with
+/// gzip or zstd are recognized by their magic number and decompressed on the fly, with
+/// the `gzip` or `zstd` feature. Capture files are read once, to the end, with their
+/// original timestamps, after which receiving fails with
+/// `pcap::Error::NoMorePackets`. They cannot send: the send methods fail with
+/// `Unsupported`.
+///
+/// Portspecs starting with `rpcap://` (or `rpcaps://`, over TLS), such as
+/// `rpcap://probe:2002/eth0`, capture from an interface of a remote host running
+/// `rpcapd`, authenticating with [`remote_auth`](PcapFlags::remote_auth). Remote
+/// captures filter in software, always block for up to the read timeout, and cannot
+/// send.
+///
+/// Packets are sent as Ethernet frames. On devices of other link types, their Ethernet
+/// header is replaced with the one of the link: the address family on BSD loopbacks
+/// (`DLT_NULL`), nothing on raw IP links, a cooked header on Linux `SLL` devices.
+///
+/// The Linux `any` device captures on every interface at once, never in promiscuous
+/// mode, with a cooked `SLL2` header instead of the Ethernet one; [`Meta::ifindex`]
+/// and [`Meta::pkt_type`] tell the interface and the direction of each frame, and
+/// [`synthesize_ethernet`](PcapFlags::synthesize_ethernet) turns them back into
+/// Ethernet frames.
+///
+/// Live captures filter in the kernel, unless it refuses the program: the filter then
+/// runs in userspace on every packet read, as on capture files and remote captures;
+/// [`filter_stage`](Sock::filter_stage) tells which, and [`filtered`](Sock::filtered)
+/// counts the packets rejected in userspace.
+///
+/// Live captures block in `recv` for up to [`timeout_ms`](PcapFlags::timeout_ms).
+/// Event loops set [`nonblocking`](PcapFlags::nonblocking) instead, and wait for the
+/// descriptor returned by `as_raw_fd` (`pcap_get_selectable_fd`) to become readable.
+/// [`recv_batch`](Socket::recv_batch) and [`dispatch`](Socket::dispatch) read them in
+/// bursts, with one `pcap_dispatch` call per read of the kernel ring. An
+/// [`Interrupter`] stops them from another thread, e.g. on Ctrl-C.
+pub struct Sock {
+    ctx: PcapContext,
+    inner: RefCell<PcapInner>,
+    fd: RawFd,
+    /// Device name of a live capture, used to open a dedicated TX handle on split.
+    device: Option<String>,
+    counters: Cell<Stats>,
+    kernel: KernelCounters,
+    metrics: SocketMetrics,
+    max_frame_size: usize,
+    /// Software filter of offline and remote captures, and of live captures whose
+    /// filter the kernel refused.
+    filter: UserspaceFilter,
+    filter_stage: Cell<FilterStage>,
+    /// Packets rejected by the software filter.
+    filtered: Cell<u64>,
+    /// Packets discarded because every buffer was in use.
+    no_buffers: Cell<u64>,
+    /// Compile the filters of live captures with the optimizer.
+    optimize_filter: bool,
+    /// IPv4 netmask of the device of a live capture, for the filters.
+    netmask: u32,
+    /// Live capture with adapter timestamps.
+    hw_timestamps: bool,
+    /// Live capture left in non-blocking mode.
+    nonblocking: bool,
+    /// Mode the live capture is in: non-blocking reads switch it lazily, and blocking
+    /// ones switch it back, so that repeated reads of one kind make no syscall.
+    nonblock_mode: Cell<bool>,
+    clock: SoftwareClock,
+    pacer: Pacer,
+    vlan_strip: bool,
+    vlan_insert: Option<VlanTag>,
+    fcs: RxFcs,
+    /// Rewrite Linux cooked headers as Ethernet ones.
+    synthesize_ethernet: bool,
+    /// Interfaces of the capture, indexed by pcapng interface id.
+    interfaces: RefCell<Vec<Interface>>,
+    /// Cookies of the tagged packets sent; nothing is ever in flight.
+    completions: TxCompletions,
+    breakloop: Arc<Breakloop>,
+}
+
+impl Sock {
+    /// Returns a handle that interrupts the receives of this socket from any thread.
+    pub fn interrupter(&self) -> Interrupter {
+        Interrupter(self.breakloop.clone())
+    }
+
+    /// Opens the capture file at `path`, in pcap or pcapng format and possibly compressed
+    /// with gzip or zstd, as a packet source.
+    ///
+    /// The same as [`create`](Socket::create) with a `file:` portspec.
+    pub fn open_file(path: impl AsRef<Path>, flags: PcapFlags) -> Result<Self> {
+        let path = path.as_ref();
+        let path = path.to_str().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("invalid capture file path: {}", path.display()),
+            )
+        })?;
+        Self::create(&format!("file:{path}"), None, flags)
+    }
+
+    /// Returns the link-layer header type of the capture; for capture files, that of
+    /// their first interface.
+    pub fn datalink(&self) -> LinkType {
+        self.interface(0).linktype
+    }
+
+    /// Switches a live capture to another of the link types the device offers, e.g.
+    /// from [`Ieee80211`](LinkType::Ieee80211) to [`Radiotap`](LinkType::Radiotap) in
+    /// monitor mode.
+    ///
+    /// Fails with `Unsupported` on capture files and remote captures.
+    pub fn set_datalink(&self, linktype: LinkType) -> Result<()> {
+        let PcapInner::Live(cap) = &mut *self.inner.borrow_mut() else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "only local live captures can change their link type",
+            )
+            .into());
+        };
+        let dlt = match linktype {
+            // `DLT_RAW` on Linux, which libpcap does not map from `LINKTYPE_RAW`.
+            LinkType::Raw => 12,
+            linktype => linktype.value() as i32,
+        };
+        cap.set_datalink(Linktype(dlt))?;
+        self.interfaces.borrow_mut()[0].linktype = LinkType::from(cap.get_datalink().0 as u16);
+        Ok(())
+    }
+
+    /// Returns the `pcap_stats` counters of a live capture, widened to 64 bits; they
+    /// stay at zero on capture files.
+    ///
+    /// [`stats`](Socket::stats) reports their two drop counters as `rx_dropped`.
+    pub fn kernel_stats(&self) -> Result<KernelStats> {
+        match &mut *self.inner.borrow_mut() {
+            PcapInner::Live(cap) => Ok(self.kernel.update(cap.stats()?)),
+            // Counted by the remote libpcap.
+            PcapInner::Remote(remote) => Ok(self.kernel.update(remote.stats()?)),
+            PcapInner::Offline(_) => Ok(self.kernel.total.get()),
+        }
+    }
+
+    /// Returns where the filter runs: live captures fall back to userspace when the
+    /// kernel refuses it, as it may for some link types or long programs.
+    pub fn filter_stage(&self) -> FilterStage {
+        self.filter_stage.get()
+    }
+
+    /// Returns the number of packets rejected by a filter running in userspace, which,
+    /// like those rejected in the kernel, count neither as received nor as dropped.
+    pub fn filtered(&self) -> u64 {
+        self.filtered.get()
+    }
+
+    /// Runs the software filter on `frame`, counting it if rejected.
+    #[inline]
+    fn accepts(&self, frame: &[u8]) -> bool {
+        let accepted = self.filter.accepts(frame);
+        if !accepted {
+            self.filtered.set(self.filtered.get() + 1);
+        }
+        accepted
+    }
+
+    /// Returns the occupancy of the buffer pool, to size
+    /// [`buffer_count`](PcapFlags::buffer_count) and the
+    /// [`pool_policy`](PcapFlags::pool_policy) for the traffic.
+    pub fn pool_stats(&self) -> PoolStats {
+        self.ctx.pool_stats()
+    }
+
+    /// Returns the error of a receive that found every buffer in use, after discarding
+    /// the oldest waiting packet under [`PoolPolicy::OverwriteOldest`].
+    fn pool_exhausted(&self) -> crate::errors::Error {
+        if self.ctx.policy == PoolPolicy::OverwriteOldest {
+            // Nothing may be waiting: a timeout or an interruption discards nothing.
+            let discarded = match &mut *self.inner.borrow_mut() {
+                PcapInner::Live(cap) => Self::next_packet(cap).is_ok(),
+                PcapInner::Remote(remote) => remote.next_packet().is_ok(),
+                // Capture files never lose packets.
+                PcapInner::Offline(_) => false,
+            };
+            if discarded {
+                self.no_buffers.set(self.no_buffers.get() + 1);
+            }
+        }
+        crate::errors::Error::NoMemory
+    }
+
+    /// Switches a live capture to non-blocking mode for the reads that must not wait,
+    /// if it is not in that mode already.
+    fn enter_nonblock(&self) -> Result<()> {
+        self.set_nonblock_mode(true)
+    }
+
+    /// Switches a live capture back to the mode of the
+    /// [`nonblocking`](PcapFlags::nonblocking) flag before a blocking read.
+    fn leave_nonblock(&self) -> Result<()> {
+        self.set_nonblock_mode(self.nonblocking)
+    }
+
+    fn set_nonblock_mode(&self, nonblock: bool) -> Result<()> {
+        if self.nonblock_mode.get() == nonblock {
+            return Ok(());
+        }
+        if let PcapInner::Live(cap) = &*self.inner.borrow() {
+            set_nonblock(cap, nonblock)?;
+        }
+        self.nonblock_mode.set(nonblock);
+        Ok(())
+    }
+
+    /// Receives the next packet into a buffer of the pool, in the current mode.
+    fn read_token(&self) -> Result<(Token, Meta)> {
+        let ctx = &self.ctx;
+
+        // 1. Acquire a buffer from the pool (or allocate if empty)
+        let Some(ptr) = ctx.buffer() else {
+            return Err(self.pool_exhausted());
+        };
+
+        // 2. Read packet from pcap directly into buffer
+        // SAFETY: We own the buffer `ptr`.
+        let slice = unsafe { std::slice::from_raw_parts_mut(ptr, ctx.buf_capacity) };
+        let (len, meta) = match self.read_packet(slice) {
+            Ok(res) => res,
+            Err(e) => {
+                // Nothing was read: hand the buffer back instead of leaking it.
+                ctx.release(BufferDesc(ptr as usize));
+                return Err(e);
+            }
+        };
+
+        // 3. Create Token
+        let buf_desc = BufferDesc(ptr as usize);
+        let token = Token::new(buf_desc, ctx.pool_id(), len);
+
+        Ok((token, meta))
+    }
+
+    /// Receives from a remote capture, which has no descriptor to poll, reading until
+    /// a packet arrives or `timeout` expires.
+    fn recv_remote_timeout(&self, timeout: Duration) -> Result<(Token, Meta)> {
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            match self.recv_token() {
+                Err(crate::errors::Error::Pcap(pcap::Error::TimeoutExpired)) => {
+                    if std::time::Instant::now() >= deadline {
+                        return Err(crate::errors::Error::WouldBlock);
+                    }
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Returns the interface `id` of the capture, or a default one for broken files.
+    fn interface(&self, id: usize) -> Interface {
+        interface(&self.interfaces.borrow(), id)
+    }
+
+    /// Returns the interface of a live capture.
+    fn device(&self) -> std::io::Result<&str> {
+        self.device.as_deref().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "offline captures have no interface",
+            )
+        })
+    }
+
+    /// Reads the next packet that passes the filter into `buf`, truncating it to fit.
+    fn read_packet(&self, buf: &mut [u8]) -> Result<(u32, Meta)> {
+        let (len, meta) = match &mut *self.inner.borrow_mut() {
+            PcapInner::Live(cap) => loop {
+                let pkt = Self::next_packet(cap).map_err(|e| match e {
+                    pcap::Error::TimeoutExpired if self.nonblocking => {
+                        crate::errors::Error::WouldBlock
+                    }
+                    pcap::Error::NoMorePackets => interrupted(),
+                    e => e.into(),
+                })?;
+                // Only set if the kernel refused the filter.
+                if self.accepts(pkt.data) {
+                    break self.copy_live_packet(&pkt, buf);
+                }
+            },
+            PcapInner::Remote(remote) => loop {
+                let pkt = remote.next_packet().map_err(|e| match e {
+                    pcap::Error::NoMorePackets => interrupted(),
+                    e => e.into(),
+                })?;
+                if self.accepts(pkt.data) {
+                    break self.copy_live_packet(&pkt, buf);
+                }
+            },
+            PcapInner::Offline(reader) => loop {
+                let interfaces = &mut self.interfaces.borrow_mut();
+                let (len, meta) = Self::next_packet_offline(reader, interfaces, buf)?;
+                if self.accepts(&buf[..len as usize]) {
+                    break (len, meta);
+                }
+            },
+        };
+        Ok(self.finish_packet(buf, len, meta))
+    }
+
+    /// Reads the cooked header, strips or parses the VLAN tag and the FCS of a packet
+    /// read into `buf`, and counts it.
+    fn finish_packet(&self, buf: &mut [u8], len: u32, mut meta: Meta) -> (u32, Meta) {
+        let len = uncook(buf, len, &mut meta, self.synthesize_ethernet);
+        let ethernet = meta.linktype == LinkType::Ethernet;
+        let (len, vlan) = if !ethernet {
+            (len, None)
+        } else if self.vlan_strip {
+            match VlanTag::strip(&mut buf[..len as usize]) {
+                Some((tag, stripped)) => {
+                    meta.len -= 4;
+                    meta.caplen -= 4;
+                    (stripped as u32, Some(tag))
+                }
+                None => (len, None),
+            }
+        } else {
+            (len, VlanTag::parse(&buf[..len as usize]))
+        };
+        meta.vlan = vlan;
+        let (len, wirelen) = self.fcs.apply(len, meta.len);
+        meta.caplen = meta.caplen.min(wirelen);
+        meta.len = wirelen;
+        meta.fcs = self.fcs.present();
+
+        let mut counters = self.counters.get();
+        counters.rx_packets += 1;
+        counters.rx_bytes += len as u64;
+        self.counters.set(counters);
+        self.metrics.rx(len as usize);
+
+        (len, meta)
+    }
+
+    /// Receives up to `budget` packets of a live capture into `out` with a single
+    /// `pcap_dispatch` call, which hands out what one read of the kernel ring returns,
+    /// and returns how many were received.
+    ///
+    /// Returns `Ok(None)` on other captures, which read one packet at a time, and
+    /// `Ok(Some(0))` without reading if the packets already in `out` hold every buffer.
+    fn recv_burst(&self, budget: usize, out: &mut Vec<(Token, Meta)>) -> Result<Option<usize>> {
+        let available = self.ctx.available();
+        if available == 0 && matches!(&*self.inner.borrow(), PcapInner::Live(_)) {
+            // Only a receive that got nothing may discard a packet.
+            if !out.is_empty() {
+                return Ok(Some(0));
+            }
+            return Err(self.pool_exhausted());
+        }
+        let PcapInner::Live(cap) = &mut *self.inner.borrow_mut() else {
+            return Ok(None);
+        };
+        let handle = cap.as_ptr().cast();
+        let start = out.len();
+        let mut burst = Burst { sock: self, out };
+        // Never read more packets than there are buffers for.
+        let cnt = budget.min(available).min(libc::c_int::MAX as usize) as libc::c_int;
+        // SAFETY: `burst` outlives the call, the only one to run `burst_handler`.
+        let rc = unsafe {
+            ffi::pcap_dispatch(handle, cnt, burst_handler, (&raw mut burst).cast())
+        };
+        let count = burst.out.len() - start;
+        match rc {
+            -2 if count == 0 => Err(interrupted()),
+            -1 if count == 0 => {
+                let msg = unsafe { CStr::from_ptr(ffi::pcap_geterr(handle)) };
+                Err(crate::errors::Error::Pcap(pcap::Error::PcapError(
+                    msg.to_string_lossy().into_owned(),
+                )))
+            }
+            _ => Ok(Some(count)),
+        }
+    }
+
+    /// Copies a packet of a live or remote capture into `buf` and returns its metadata.
+    fn copy_live_packet(&self, pkt: &Packet<'_>, buf: &mut [u8]) -> (u32, Meta) {
+        let interface = self.interface(0);
+        let ts = interface.timestamp(pkt.header.ts.tv_sec as u64, pkt.header.ts.tv_usec as u64);
+        let meta = Meta {
+            timestamp: if self.hw_timestamps {
+                Some(ts)
+            } else {
+                self.clock.convert_realtime(ts)
+            },
+            len: pkt.header.len,
+            caplen: pkt.header.caplen,
+            vlan: None,
+            hw_timestamp: self.hw_timestamps,
+            fcs: false,
+            linktype: interface.linktype,
+            ifindex: None,
+            pkt_type: None,
+        };
+        let copy_len = std::cmp::min(pkt.data.len(), buf.len());
+        buf[..copy_len].copy_from_slice(&pkt.data[..copy_len]);
+        (copy_len as u32, meta)
+    }
+
+    fn next_packet<'a>(
+        cap: &'a mut Capture<Active>,
+    ) -> std::result::Result<Packet<'a>, pcap::Error> {
+        cap.next_packet()
+    }
+
+    /// Records the interfaces declared by a header block of a capture file.
+    fn note_header(block: &PcapBlockOwned, interfaces: &mut Vec<Interface>) -> bool {
+        match block {
+            PcapBlockOwned::LegacyHeader(header) => {
+                let linktype = LinkType::from(header.network.0 as u16);
+                *interfaces = vec![Interface::new(linktype, header.is_nanosecond_precision())];
+            }
+            // Interface ids restart with each section.
+            PcapBlockOwned::NG(pcap_parser::Block::SectionHeader(_)) => interfaces.clear(),
+            PcapBlockOwned::NG(pcap_parser::Block::InterfaceDescription(idb)) => {
+                interfaces.push(Interface {
+                    linktype: LinkType::from(idb.linktype.0 as u16),
+                    ts_resolution: idb.ts_resolution().unwrap_or(1_000_000),
+                });
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Reads the header blocks at the start of a capture file, up to the first packet.
+    fn read_headers(
+        reader: &mut Box<dyn PcapReaderIterator + Send>,
+        interfaces: &mut Vec<Interface>,
+    ) -> Result<()> {
+        loop {
+            match reader.next() {
+                Ok((offset, block)) => {
+                    if !Self::note_header(&block, interfaces) {
+                        return Ok(());
+                    }
+                    reader.consume(offset);
+                }
+                Err(PcapError::Incomplete(_)) => {
+                    reader.refill().map_err(|e| {
+                        crate::errors::Error::Pcap(pcap::Error::PcapError(format!("{:?}", e)))
+                    })?;
+                }
+                // Errors are reported by the first receive.
+                Err(_) => return Ok(()),
+            }
+        }
+    }
+
+    fn next_packet_offline(
+        reader: &mut Box<dyn PcapReaderIterator + Send>,
+        interfaces: &mut Vec<Interface>,
+        buffer: &mut [u8],
+    ) -> std::result::Result<(u32, Meta), crate::errors::Error> {
+        loop {
+            match reader.next() {
+                Ok((offset, block)) => {
+                    match block {
+                        PcapBlockOwned::Legacy(packet) => {
+                            let len = packet.origlen;
+                            let caplen = packet.data.len() as u32;
+                            let copy_len = std::cmp::min(caplen as usize, buffer.len());
+                            buffer[..copy_len].copy_from_slice(&packet.data[..copy_len]);
+
+                            // `ts_usec` holds nanoseconds in nanosecond files.
+                            let interface = interface(interfaces, 0);
+                            let meta = Meta {
+                                timestamp: Some(interface.timestamp(
+                                    packet.ts_sec as u64,
+                                    packet.ts_usec as u64,
+                                )),
+                                len,
+                                caplen,
+                                vlan: None,
+                                hw_timestamp: false,
+                                fcs: false,
+                                linktype: interface.linktype,
+                                ifindex: None,
+                                pkt_type: None,
+                            };
+                            reader.consume(offset);
+                            return Ok((copy_len as u32, meta));
+                        }
+                        PcapBlockOwned::NG(block) => {
+                            match block {
+                                pcap_parser::Block::EnhancedPacket(packet) => {
+                                    let len = packet.origlen;
+                                    let caplen = packet.data.len() as u32;
+                                    let copy_len = std::cmp::min(caplen as usize, buffer.len());
+                                    buffer[..copy_len].copy_from_slice(&packet.data[..copy_len]);
+
+                                    let raw_ts = (packet.ts_high as u64) << 32 | (packet.ts_low as u64);
+                                    // Counted in units of the interface `if_tsresol`.
+                                    let interface = interface(interfaces, packet.if_id as usize);
+
+                                    let meta = Meta {
+                                        timestamp: Some(interface.timestamp_units(raw_ts)),
+                                        len,
+                                        caplen,
+                                        vlan: None,
+                                        hw_timestamp: false,
+                                        fcs: false,
+                                        linktype: interface.linktype,
+                                        ifindex: None,
+                                        pkt_type: None,
+                                    };
+                                    reader.consume(offset);
+                                    return Ok((copy_len as u32, meta));
+                                }
+                                pcap_parser::Block::SimplePacket(packet) => {
+                                    let len = packet.origlen;
+                                    let caplen = packet.data.len() as u32;
+                                    let copy_len = std::cmp::min(caplen as usize, buffer.len());
+                                    buffer[..copy_len].copy_from_slice(&packet.data[..copy_len]);
+
+                                    // Simple packet blocks carry no timestamp at all.
+                                    let meta = Meta {
+                                        timestamp: None,
+                                        len,
+                                        caplen,
+                                        vlan: None,
+                                        hw_timestamp: false,
+                                        fcs: false,
+                                        linktype: interface(interfaces, 0).linktype,
+                                        ifindex: None,
+                                        pkt_type: None,
+                                    };
+                                    reader.consume(offset);
+                                    return Ok((copy_len as u32, meta));
+                                }
+                                block => {
+                                    // Record headers and interfaces, skip other blocks (stats, names)
+                                    Self::note_header(&PcapBlockOwned::NG(block), interfaces);
+                                    reader.consume(offset);
+                                    continue;
+                                }
+                            }
+                        }
+                        block => {
+                            Self::note_header(&block, interfaces);
+                            reader.consume(offset);
+                            continue;
+                        }
+                    }
+                }
+                Err(PcapError::Eof) => {
+                    return Err(crate::errors::Error::Pcap(pcap::Error::NoMorePackets));
+                }
+                Err(PcapError::Incomplete(_)) => {
+                    reader.refill().map_err(|e| {
+                        crate::errors::Error::Pcap(pcap::Error::PcapError(format!("{:?}", e)))
+                    })?;
+                    continue;
+                }
+                Err(e) => {
+                    // Map pcap-parser error to something generic or print it
+                    eprintln!("Pcap parser error: {:?}", e);
+                    return Err(crate::errors::Error::Pcap(pcap::Error::PcapError(
+                        "Parser error".to_string(),
+                    )));
+                }
+            }
+        }
+    }
+}
+
+impl Socket for Sock {
+    type Context = PcapContext;
+    type Metadata = Meta;
+    type Flags = PcapFlags;
+    type Rx = RxSock;
+    type Tx = TxSock;
+    type Slot<'a> = TxSlot<'a>;
+
+    fn recv_token(&self) -> Result<(Token, Self::Metadata)> {
+        self.leave_nonblock()?;
+        self.read_token()
+    }
+
+    fn recv_into(&self, buf: &mut [u8]) -> Result<(usize, Self::Metadata)> {
+        self.leave_nonblock()?;
+        // libpcap copies out of its ring anyway: copy straight into the caller's buffer.
+        let (len, meta) = self.read_packet(buf)?;
+        Ok((len as usize, meta))
+    }
+
+    fn try_recv_token(&self) -> Result<(Token, Self::Metadata)> {
+        // Offline readers never block; live captures are left in non-blocking mode
+        // until the next blocking read, so an empty buffer reports a timeout.
+        self.enter_nonblock()?;
+        match self.read_token() {
+            Err(crate::errors::Error::Pcap(pcap::Error::TimeoutExpired)) => {
+                Err(crate::errors::Error::WouldBlock)
+            }
+            res => res,
+        }
+    }
+
+    fn recv_batch(&self, budget: usize) -> Result<Vec<(Token, Self::Metadata)>> {
+        self.leave_nonblock()?;
+        let mut batch = Vec::with_capacity(budget);
+        match self.recv_burst(budget, &mut batch)? {
+            Some(0) if self.nonblocking => Err(crate::errors::Error::WouldBlock),
+            Some(0) => Err(crate::errors::Error::Pcap(pcap::Error::TimeoutExpired)),
+            Some(_) => Ok(batch),
+            None => {
+                // Stop once the batch holds every buffer rather than discard a packet.
+                while batch.len() < budget && (batch.is_empty() || self.ctx.available() > 0) {
+                    match self.read_token() {
+                        Ok(pkt) => batch.push(pkt),
+                        Err(e) if batch.is_empty() => return Err(e),
+                        Err(_) => break,
+                    }
+                }
+                Ok(batch)
+            }
+        }
+    }
+
+    fn dispatch<F>(&self, budget: usize, mut f: F) -> Result<usize>
+    where
+        F: FnMut(Payload<'_, Self::Context>, Self::Metadata),
+    {
+        // Switch to non-blocking mode once for the whole loop rather than per packet.
+        self.enter_nonblock()?;
+        if matches!(&*self.inner.borrow(), PcapInner::Live(_)) {
+            // Live captures read bursts, handed to `f` once libpcap has returned.
+            let mut batch = Vec::with_capacity(budget.min(1024));
+            let mut res = Ok(());
+            while batch.len() < budget {
+                match self.recv_burst(budget - batch.len(), &mut batch) {
+                    Ok(Some(0)) => break,
+                    Ok(_) => {}
+                    Err(e) => {
+                        if batch.is_empty() {
+                            res = Err(e);
+                        }
+                        break;
+                    }
+                }
+            }
+            let count = batch.len();
+            for (token, meta) in batch {
+                f(token.consume(&self.ctx), meta);
+            }
+            return res.map(|()| count);
+        }
+        let mut count = 0;
+        let mut res = Ok(());
+        while count < budget {
+            match self.read_token() {
+                Ok((token, meta)) => f(token.consume(&self.ctx), meta),
+                Err(
+                    crate::errors::Error::Pcap(pcap::Error::TimeoutExpired)
+                    | crate::errors::Error::WouldBlock,
+                ) => break,
+                Err(e) => {
+                    if count == 0 {
+                        res = Err(e);
+                    }
+                    break;
+                }
+            }
+            count += 1;
+        }
+        res.map(|()| count)
+    }
+
+    fn recv_token_timeout(&self, timeout: Duration) -> Result<(Token, Self::Metadata)> {
+        match &*self.inner.borrow() {
+            PcapInner::Live(_) => {}
+            PcapInner::Offline(_) => return self.recv_token(),
+            PcapInner::Remote(_) => return self.recv_remote_timeout(timeout),
+        }
+        poll::recv_token_polling(self, self.as_raw_fd(), timeout)
+    }
+
+    fn send(&self, packet: &[u8]) -> Result<()> {
+        let packet = tagged(packet, self.vlan_insert);
+        let linktype = self.interface(0).linktype;
+        match &mut *self.inner.borrow_mut() {
+            PcapInner::Live(cap) => {
+                let packet = &*encapsulate(&packet, linktype)?;
+                self.pacer.pace(packet.len());
+                let res = cap.sendpacket(packet).map_err(crate::errors::Error::from);
+                let mut counters = self.counters.get();
+                if res.is_ok() {
+                    counters.tx_packets += 1;
+                    counters.tx_bytes += packet.len() as u64;
+                    self.metrics.tx(packet.len());
+                } else {
+                    trace_verbose!(backend = "pcap", len = packet.len(), "send failed");
+                    counters.tx_errors += 1;
+                }
+                self.counters.set(counters);
+                res
+            }
+            PcapInner::Offline(_) | PcapInner::Remote(_) => Err(offline_send()),
+        }
+    }
+
+    fn flush(&self) {
+        // libpcap doesn't buffer sends in a way we can flush here; no-op.
+    }
+
+    fn send_with_cookie(&self, packet: &[u8], cookie: u64) -> Result<()> {
+        Socket::send(self, packet)?;
+        // Sent packets are complete, so only the tagged ones need counting.
+        self.completions.queued();
+        self.completions.tag_last(cookie);
+        Ok(())
+    }
+
+    fn tx_completions(&self, out: &mut Vec<u64>) -> usize {
+        self.completions.drain(0, out)
+    }
+
+    fn tx_reserve(&self, len: usize) -> Result<TxSlot<'_>> {
+        Ok(TxSlot::new(self, len))
+    }
+
+    fn set_filter(&self, expr: &str) -> Result<()> {
+        trace_event!(debug, backend = "pcap", expr, "installing filter");
+        let stage = match &mut *self.inner.borrow_mut() {
+            PcapInner::Live(cap) => install_live_filter(
+                cap,
+                self.fd,
+                expr,
+                self.optimize_filter,
+                self.netmask,
+                &self.filter,
+            )?,
+            PcapInner::Offline(_) | PcapInner::Remote(_) => {
+                self.filter.set(expr)?;
+                if self.filter.is_set() {
+                    FilterStage::Userspace
+                } else {
+                    FilterStage::None
+                }
+            }
+        };
+        self.filter_stage.set(stage);
+        Ok(())
+    }
+
+    fn set_promiscuous(&self, enable: bool) -> Result<()> {
+        // Toggle the membership libpcap itself uses for promiscuous mode, so the capture
+        // keeps its handle and fd instead of being re-activated.
+        let ifindex = netdev::ifindex(self.device()?)?;
+        Ok(netdev::set_packet_promiscuous(self.fd, ifindex, enable)?)
+    }
+
+    fn join_multicast(&self, mac: [u8; 6]) -> Result<()> {
+        let ifindex = netdev::ifindex(self.device()?)?;
+        Ok(netdev::set_packet_multicast(self.fd, ifindex, mac, true)?)
+    }
+
+    fn leave_multicast(&self, mac: [u8; 6]) -> Result<()> {
+        let ifindex = netdev::ifindex(self.device()?)?;
+        Ok(netdev::set_packet_multicast(self.fd, ifindex, mac, false)?)
+    }
+
+    fn link_info(&self) -> Result<LinkInfo> {
+        api::link_info(self.device()?)
+    }
+
+    fn steer(&self, spec: api::FlowSpec, queue: usize) -> Result<api::FlowRule> {
+        Ok(api::ntuple_insert(self.device()?, &spec, queue)?)
+    }
+
+    fn unsteer(&self, rule: api::FlowRule) -> Result<()> {
+        Ok(api::ntuple_delete(self.device()?, rule)?)
+    }
+
+    fn create(portspec: &str, queue: Option<usize>, flags: Self::Flags) -> Result<Self> {
+        let ctx = PcapContext::new(flags.buffer_size, flags.buffer_count, flags.pool_policy);
+
+        // Offline path?
+        let mut timestamp_type = flags
+            .timestamp_type
+            .or(flags.hw_timestamps.then_some(TimestampType::Adapter));
+        let is_file = portspec.starts_with("file:")
+            || [".pcap", ".pcapng"].iter().any(|ext| {
+                ["", ".gz", ".zst"].iter().any(|comp| portspec.ends_with(&format!("{ext}{comp}")))
+            });
+        let is_remote = portspec.starts_with("rpcap://") || portspec.starts_with("rpcaps://");
+
+        let (inner, fd, device, interfaces) = if is_file {
+            let path = portspec.strip_prefix("file:").unwrap_or(portspec);
+            let file = File::open(path).map_err(|e| {
                 crate::errors::Error::Pcap(pcap::Error::PcapError(e.to_string()))
             })?;
+            let fd = file.as_raw_fd();
+            let file = decompress(file)?;
+
+            // Create reader using pcap-parser's autodetection.
+            // Requires pcap-parser >= 0.16.0 (or 0.17.0) to ensure Send trait on return type.
+            let mut reader = create_reader(1000000, file).map_err(|e| {
+                crate::errors::Error::Pcap(pcap::Error::PcapError(format!("{:?}", e)))
+            })?;
+            let mut interfaces = Vec::new();
+            Self::read_headers(&mut reader, &mut interfaces)?;
+
+            (PcapInner::Offline(reader), fd, None, interfaces)
+        } else if is_remote {
+            let remote = RemoteCapture::open(portspec, &flags)?;
+            let interface = Interface::new(remote.linktype(), false);
+            // Remote captures have no descriptor to poll.
+            (PcapInner::Remote(remote), -1, None, vec![interface])
+        } else {
+            // Live device
+            // Accept both a literal device name or "any".
+            #[cfg(windows)]
+            let portspec = &*resolve_device(portspec)?;
+            let dev = Device::from(portspec);
+            // Captures on `any` cannot be promiscuous, and libpcap fails to activate them
+            // if asked to.
+            let is_any = portspec == "any";
+            let open = |timestamp_type: Option<TimestampType>| -> Result<Capture<Active>> {
+                let mut inactive =
+                    Capture::from_device(dev.clone()).map_err(crate::errors::Error::from)?;
+                inactive = inactive
+                    .promisc(flags.promiscuous && !is_any)
+                    .snaplen(flags.snaplen)
+                    .timeout(flags.timeout_ms);
+                if flags.immediate {
+                    // not all libpcap builds support immediate mode; ignore if unsupported
+                    inactive = inactive.immediate_mode(true);
+                }
+                if let Some(ts) = timestamp_type {
+                    // For adapter timestamps, libpcap enables timestamping on the NIC
+                    // itself (SIOCSHWTSTAMP).
+                    set_timestamp_type(&inactive, portspec, ts)?;
+                }
+                if flags.nanosecond_timestamps {
+                    inactive = inactive.precision(Precision::Nano);
+                }
+                if flags.monitor_mode {
+                    // Fails on activation if the interface cannot do it.
+                    inactive = inactive.rfmon(true);
+                }
+                Ok(inactive.open()?)
+            };
+            let mut cap = match open(timestamp_type) {
+                Ok(cap) => cap,
+                // `hw_timestamps` is a request, not a requirement: the device may not
+                // offer adapter timestamps, or the NIC may refuse to stamp every packet
+                // on activation, so capture with host timestamps instead.
+                Err(_e) if flags.timestamp_type.is_none() && flags.hw_timestamps => {
+                    trace_event!(
+                        warn,
+                        backend = "pcap",
+                        portspec,
+                        error = %_e,
+                        "adapter timestamps refused, using host timestamps"
+                    );
+                    timestamp_type = None;
+                    open(None)?
+                }
+                Err(e) => return Err(e),
+            };
+
+            if flags.direction != Direction::InOut {
+                cap.direction(match flags.direction {
+                    Direction::In => pcap::Direction::In,
+                    Direction::Out => pcap::Direction::Out,
+                    Direction::InOut => pcap::Direction::InOut,
+                })?;
+            }
+
+            if flags.nonblocking {
+                set_nonblock(&cap, true)?;
+            }
+            if is_any {
+                // `SLL2` headers carry the interface index; `SLL` is the fallback of
+                // older libpcap versions.
+                let _ = cap.set_datalink(Linktype(LinkType::LinuxSll2.value() as i32));
+            }
+            let fd = selectable_fd(&cap)?;
+            if flags.allmulti {
+                netdev::set_packet_allmulti(fd, netdev::ifindex(portspec)?, true)?;
+            }
+            let linktype = LinkType::from(cap.get_datalink().0 as u16);
+            let interface = Interface::new(linktype, flags.nanosecond_timestamps);
+            (PcapInner::Live(cap), fd, Some(portspec.to_owned()), vec![interface])
+        };
+        let netmask = device.as_deref().map_or(ffi::PCAP_NETMASK_UNKNOWN, lookup_netmask);
+        let filter = UserspaceFilter::default();
+        let filter_stage = match (&inner, flags.filter.as_deref()) {
+            (PcapInner::Live(cap), Some(expr)) => {
+                install_live_filter(cap, fd, expr, flags.optimize_filter, netmask, &filter)?
+            }
+            (PcapInner::Remote(_), Some(expr)) => {
+                filter.set(expr)?;
+                if filter.is_set() {
+                    FilterStage::Userspace
+                } else {
+                    FilterStage::None
+                }
+            }
+            _ => FilterStage::None,
+        };
+        let fcs = if is_file || is_remote {
+            RxFcs::Absent
+        } else {
+            netdev::rx_fcs(portspec, flags.fcs)
+        };
+
+        let inner = RefCell::new(inner);
+        // Packets are copied into the context buffers, truncated to the snaplen.
+        let max_frame_size = if is_file {
+            flags.buffer_size
+        } else {
+            flags.buffer_size.min(flags.snaplen.max(0) as usize)
+        };
+        trace_event!(
+            debug,
+            backend = "pcap",
+            portspec,
+            offline = is_file,
+            "socket created"
+        );
+
+        let local = !is_file && !is_remote;
+        let breakloop = Arc::new(Breakloop::new(match &*inner.borrow() {
+            PcapInner::Live(cap) => cap.as_ptr().cast(),
+            PcapInner::Remote(remote) => remote.0.as_ptr(),
+            PcapInner::Offline(_) => std::ptr::null_mut(),
+        }));
+        Ok(Self {
+            ctx,
+            inner,
+            fd,
+            device,
+            counters: Cell::new(Stats::default()),
+            kernel: KernelCounters::default(),
+            metrics: SocketMetrics::new(api::Backend::Pcap, portspec, queue),
+            max_frame_size,
+            filter,
+            filter_stage: Cell::new(filter_stage),
+            filtered: Cell::new(0),
+            no_buffers: Cell::new(0),
+            optimize_filter: flags.optimize_filter,
+            netmask,
+            hw_timestamps: timestamp_type.is_some_and(TimestampType::is_hardware) && local,
+            nonblocking: flags.nonblocking && local,
+            nonblock_mode: Cell::new(flags.nonblocking && local),
+            clock: SoftwareClock::new(flags.timestamp_source),
+            pacer: Pacer::new(flags.tx_rate),
+            vlan_strip: flags.vlan_strip,
+            vlan_insert: flags.vlan_insert,
+            fcs,
+            synthesize_ethernet: flags.synthesize_ethernet,
+            interfaces: RefCell::new(interfaces),
+            completions: TxCompletions::new(),
+            breakloop,
+        })
+    }
+
+    fn create_group(portspec: &str, flags: Self::Flags) -> Result<SocketGroup<Self>> {
+        // libpcap cannot bind to a queue: open one capture per RX queue and let a
+        // PACKET_FANOUT group spread the flows across them.
+        static GROUP_ID: AtomicU16 = AtomicU16::new(0);
+        let queues = netdev::rx_queues(portspec)?.max(1);
+        let group =
+            (std::process::id() as u16).wrapping_add(GROUP_ID.fetch_add(1, Ordering::Relaxed));
+        let mut sockets = Vec::with_capacity(queues);
+        for _ in 0..queues {
+            let sock = Self::create(portspec, None, flags.clone())?;
+            netdev::join_fanout(sock.fd, group)?;
+            sockets.push(sock);
+        }
+        Ok(SocketGroup::from_sockets(sockets))
+    }
+
+    fn context(&self) -> &Self::Context {
+        &self.ctx
+    }
+
+    fn stats(&self) -> Result<Stats> {
+        let mut stats = self.counters.get();
+        let kernel = self.kernel_stats()?;
+        stats.rx_dropped = kernel.dropped + kernel.if_dropped;
+        Ok(stats)
+    }
+
+    fn dropped(&self) -> Dropped {
+        // Packets are copied out of libpcap's buffer, which counts its own drops; only
+        // a full pool loses packets here.
+        Dropped {
+            no_buffers: self.no_buffers.get(),
+            ..Dropped::default()
+        }
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        let live = matches!(&*self.inner.borrow(), PcapInner::Live(_));
+        Capabilities {
+            zero_copy_rx: false,
+            zero_copy_tx: false,
+            writable_rx: true,
+            hw_timestamps: self.hw_timestamps,
+            multi_queue: false,
+            socket_groups: live,
+            vlan_strip: self.vlan_strip,
+            tx_checksum: false,
+            tso: false,
+            kernel_filter: live,
+            zero_copy_driver: false,
+            max_frame_size: self.max_frame_size,
+        }
+    }
+
+    fn split(mut self) -> (RxSock, TxSock) {
+        let tx = TxSock {
+            device: self.device.take(),
+            cap: RefCell::new(None),
+            pacer: std::mem::replace(&mut self.pacer, Pacer::new(TxRate::Unlimited)),
+            vlan_insert: self.vlan_insert,
+            completions: std::mem::take(&mut self.completions),
+            metrics: self.metrics.clone(),
+        };
+        (RxSock(self), tx)
+    }
+}
+
+impl Drop for Sock {
+    fn drop(&mut self) {
+        // The handle is closed along with `inner`, right after.
+        self.breakloop.detach();
+    }
+}
+
+impl AsRawFd for Sock {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Pollable for Sock {
+    fn poll_fd(&self) -> std::io::Result<RawFd> {
+        match &*self.inner.borrow() {
+            PcapInner::Live(_) => Ok(self.fd),
+            // epoll refuses the regular file of a savefile, and remote captures have no
+            // descriptor.
+            PcapInner::Offline(_) | PcapInner::Remote(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "only live captures can be polled",
+            )),
+        }
+    }
+}
+
+// -------- Split halves -------------------------------------------------------------
+
+/// Receive half of a split pcap socket; it keeps the original capture handle.
+pub struct RxSock(Sock);
+
+impl RxSock {
+    /// Returns the `pcap_stats` counters of the capture, see [`Sock::kernel_stats`].
+    pub fn kernel_stats(&self) -> Result<KernelStats> {
+        self.0.kernel_stats()
+    }
+
+    /// Returns a handle that interrupts the receives of the capture, see
+    /// [`Sock::interrupter`].
+    pub fn interrupter(&self) -> Interrupter {
+        self.0.interrupter()
+    }
+
+    /// Returns where the filter runs, see [`Sock::filter_stage`].
+    pub fn filter_stage(&self) -> FilterStage {
+        self.0.filter_stage()
+    }
+
+    /// Returns the number of packets rejected by a filter running in userspace, see
+    /// [`Sock::filtered`].
+    pub fn filtered(&self) -> u64 {
+        self.0.filtered()
+    }
+
+    /// Returns the occupancy of the buffer pool, see [`Sock::pool_stats`].
+    pub fn pool_stats(&self) -> PoolStats {
+        self.0.pool_stats()
+    }
+}
+
+impl RxSocket for RxSock {
+    type Context = PcapContext;
+    type Metadata = Meta;
+
+    fn recv_token(&self) -> Result<(Token, Self::Metadata)> {
+        self.0.recv_token()
+    }
+
+    fn recv_into(&self, buf: &mut [u8]) -> Result<(usize, Self::Metadata)> {
+        self.0.recv_into(buf)
+    }
+
+    fn try_recv_token(&self) -> Result<(Token, Self::Metadata)> {
+        self.0.try_recv_token()
+    }
+
+    fn recv_batch(&self, budget: usize) -> Result<Vec<(Token, Self::Metadata)>> {
+        self.0.recv_batch(budget)
+    }
+
+    fn dispatch<F>(&self, budget: usize, f: F) -> Result<usize>
+    where
+        F: FnMut(Payload<'_, Self::Context>, Self::Metadata),
+    {
+        self.0.dispatch(budget, f)
+    }
+
+    fn set_filter(&self, expr: &str) -> Result<()> {
+        self.0.set_filter(expr)
+    }
+
+    fn dropped(&self) -> Dropped {
+        self.0.dropped()
+    }
+
+    fn context(&self) -> &Self::Context {
+        &self.0.ctx
+    }
+}
+
+impl AsRawFd for RxSock {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.fd
+    }
+}
+
+/// Transmit half of a split pcap socket.
+///
+/// libpcap handles are not safe to share across threads, so the first send opens a
+/// dedicated injection handle on the same device, with a filter that drops every
+/// incoming packet in the kernel.
+pub struct TxSock {
+    device: Option<String>,
+    cap: RefCell<Option<Capture<Active>>>,
+    pacer: Pacer,
+    vlan_insert: Option<VlanTag>,
+    completions: TxCompletions,
+    metrics: SocketMetrics,
+}
+
+impl TxSock {
+    fn open_handle(device: &str) -> Result<Capture<Active>> {
+        let mut cap = Capture::from_device(Device::from(device))?
+            .snaplen(1)
+            .open()?;
+        cap.filter("less 0", true)?;
+        Ok(cap)
+    }
+}
+
+impl TxSocket for TxSock {
+    type Slot<'a> = TxSlot<'a>;
+
+    fn send(&self, packet: &[u8]) -> Result<()> {
+        let Some(device) = self.device.as_deref() else {
+            return Err(offline_send());
+        };
+        let mut cap = self.cap.borrow_mut();
+        if cap.is_none() {
+            *cap = Some(Self::open_handle(device)?);
+        }
+        let cap = cap.as_mut().expect("TX handle opened above");
+        let packet = tagged(packet, self.vlan_insert);
+        let linktype = LinkType::from(cap.get_datalink().0 as u16);
+        let packet = &*encapsulate(&packet, linktype)?;
+        self.pacer.pace(packet.len());
+        cap.sendpacket(packet)
+            .map_err(crate::errors::Error::from)?;
+        self.metrics.tx(packet.len());
+        Ok(())
+    }
+
+    fn flush(&self) {
+        // libpcap doesn't buffer sends in a way we can flush here; no-op.
+    }
+
+    fn send_with_cookie(&self, packet: &[u8], cookie: u64) -> Result<()> {
+        TxSocket::send(self, packet)?;
+        self.completions.queued();
+        self.completions.tag_last(cookie);
+        Ok(())
+    }
+
+    fn tx_completions(&self, out: &mut Vec<u64>) -> usize {
+        self.completions.drain(0, out)
+    }
+
+    fn tx_reserve(&self, len: usize) -> Result<TxSlot<'_>> {
+        Ok(TxSlot::new(self, len))
+    }
+}
 
-            // Create reader using pcap-parser's autodetection.
-            // Requires pcap-parser >= 0.16.0 (or 0.17.0) to ensure Send trait on return type.
-            let reader = create_reader(1000000, file).map_err(|e| {
-                crate::errors::Error::Pcap(pcap::Error::PcapError(format!("{:?}", e)))
-            })?;
+/// The error of the send methods of capture files and remote captures.
+fn offline_send() -> crate::errors::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "pcap offline and remote captures cannot send packets",
+    )
+    .into()
+}
 
-            PcapInner::Offline(reader)
-        } else {
-            // Live device
-            // Accept both a literal device name or "any".
-            let dev = Device::from(portspec);
-            let mut inactive = Capture::from_device(dev).map_err(crate::errors::Error::from)?;
-            inactive = inactive
-                .promisc(flags.promiscuous)
-                .snaplen(flags.snaplen)
-                .timeout(flags.timeout_ms);
-            if flags.immediate {
-                // not all libpcap builds support immediate mode; ignore if unsupported
-                inactive = inactive.immediate_mode(true);
+/// Returns `packet` with `tag` inserted after the MAC addresses, if there is a tag.
+fn tagged(packet: &[u8], tag: Option<VlanTag>) -> Cow<'_, [u8]> {
+    match tag {
+        Some(tag) => {
+            let mut buf = vec![0; packet.len() + 4];
+            tag.write_tagged(packet, &mut buf)
+                .expect("buffer sized for the tag");
+            Cow::Owned(buf)
+        }
+        None => Cow::Borrowed(packet),
+    }
+}
+
+const ETH_HLEN: usize = 14;
+const ETH_P_IP: u16 = 0x0800;
+const ETH_P_IPV6: u16 = 0x86dd;
+const ARPHRD_ETHER: u16 = 1;
+const PACKET_OUTGOING: u8 = 4;
+
+/// Rewrites the Ethernet `frame` for injection on a device of link type `linktype`,
+/// replacing its Ethernet header (and VLAN tags) with the pseudo-header of the link.
+///
+/// Fails with `Unsupported` on link types other than Ethernet, BSD loopback, raw IP and
+/// Linux cooked captures, or when the link cannot carry the EtherType of the frame.
+fn encapsulate(frame: &[u8], linktype: LinkType) -> Result<Cow<'_, [u8]>> {
+    if linktype == LinkType::Ethernet {
+        return Ok(Cow::Borrowed(frame));
+    }
+    let unsupported = |what: String| -> crate::errors::Error {
+        std::io::Error::new(std::io::ErrorKind::Unsupported, what).into()
+    };
+    // Skip the VLAN tags to reach the EtherType of the payload.
+    let mut offset = ETH_HLEN - 2;
+    let ethertype = loop {
+        let Some(bytes) = frame.get(offset..offset + 2) else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "frame shorter than its Ethernet header",
+            )
+            .into());
+        };
+        let ethertype = u16::from_be_bytes([bytes[0], bytes[1]]);
+        offset += 2;
+        if ethertype != VlanTag::TPID_8021Q && ethertype != VlanTag::TPID_8021AD {
+            break ethertype;
+        }
+        offset += 2;
+    };
+    let payload = &frame[offset..];
+    let src_mac = &frame[6..12];
+    let ip_family = || match ethertype {
+        ETH_P_IP => Ok(libc::AF_INET as u32),
+        ETH_P_IPV6 => Ok(libc::AF_INET6 as u32),
+        _ => Err(unsupported(format!(
+            "cannot send EtherType {ethertype:#06x} on a {linktype:?} link"
+        ))),
+    };
+    let header = match linktype {
+        LinkType::Raw => {
+            ip_family()?;
+            return Ok(Cow::Borrowed(payload));
+        }
+        // The address family in host byte order.
+        LinkType::Null => ip_family()?.to_ne_bytes().to_vec(),
+        LinkType::LinuxSll => {
+            let mut header = vec![0; 16];
+            header[0..2].copy_from_slice(&(PACKET_OUTGOING as u16).to_be_bytes());
+            header[2..4].copy_from_slice(&ARPHRD_ETHER.to_be_bytes());
+            header[4..6].copy_from_slice(&6u16.to_be_bytes());
+            header[6..12].copy_from_slice(src_mac);
+            header[14..16].copy_from_slice(&ethertype.to_be_bytes());
+            header
+        }
+        LinkType::LinuxSll2 => {
+            // The interface index is left to 0: the kernel knows where it sends.
+            let mut header = vec![0; 20];
+            header[0..2].copy_from_slice(&ethertype.to_be_bytes());
+            header[8..10].copy_from_slice(&ARPHRD_ETHER.to_be_bytes());
+            header[10] = PACKET_OUTGOING;
+            header[11] = 6;
+            header[12..18].copy_from_slice(src_mac);
+            header
+        }
+        linktype => return Err(unsupported(format!("cannot send on a {linktype:?} link"))),
+    };
+    Ok(Cow::Owned([&header[..], payload].concat()))
+}
+
+const SLL_HLEN: usize = 16;
+const SLL2_HLEN: usize = 20;
+
+/// Reads the interface index and packet type of a frame of a Linux cooked capture
+/// into `meta` and, with `to_ethernet`, replaces its cooked header with an Ethernet
+/// one. Returns the new length of the frame.
+///
+/// Frames of other link types, too short ones and those whose protocol is not an
+/// EtherType (e.g. raw 802.2 frames) are left as they are.
+fn uncook(buf: &mut [u8], len: u32, meta: &mut Meta, to_ethernet: bool) -> u32 {
+    let frame = &buf[..len as usize];
+    let (hlen, protocol, ifindex, pkt_type, halen, addr) = match meta.linktype {
+        LinkType::LinuxSll if frame.len() >= SLL_HLEN => (
+            SLL_HLEN,
+            u16::from_be_bytes([frame[14], frame[15]]),
+            None,
+            u16::from_be_bytes([frame[0], frame[1]]) as u8,
+            u16::from_be_bytes([frame[4], frame[5]]),
+            &frame[6..12],
+        ),
+        LinkType::LinuxSll2 if frame.len() >= SLL2_HLEN => (
+            SLL2_HLEN,
+            u16::from_be_bytes([frame[0], frame[1]]),
+            Some(u32::from_be_bytes([frame[4], frame[5], frame[6], frame[7]])),
+            frame[10],
+            frame[11] as u16,
+            &frame[12..18],
+        ),
+        _ => return len,
+    };
+    meta.ifindex = ifindex;
+    meta.pkt_type = Some(PacketType::from(pkt_type));
+    if !to_ethernet || protocol < 0x0600 {
+        return len;
+    }
+
+    let mut header = [0u8; ETH_HLEN];
+    if halen == 6 {
+        header[6..12].copy_from_slice(addr);
+    }
+    header[12..14].copy_from_slice(&protocol.to_be_bytes());
+    buf.copy_within(hlen..len as usize, ETH_HLEN);
+    buf[..ETH_HLEN].copy_from_slice(&header);
+    let shrink = (hlen - ETH_HLEN) as u32;
+    meta.len = meta.len.saturating_sub(shrink);
+    meta.caplen = meta.caplen.saturating_sub(shrink);
+    meta.linktype = LinkType::Ethernet;
+    len - shrink
+}
+
+// -------- Multiple devices ---------------------------------------------------------
+
+/// Captures from several devices at once, merging their traffic into a single receive
+/// loop, so that monitoring a whole host does not take one thread per interface.
+///
+/// Each device is opened as a non-blocking [`Sock`] with the same flags; the sockets
+/// share a buffer pool, and an `epoll` descriptor, returned by `as_raw_fd`, becomes
+/// readable when any of them has packets. Receives take packets from the devices in
+/// turn, so a busy one does not starve the others, and set [`Meta::ifindex`] to the
+/// interface each frame arrived on (as read from the `SLL2` header on `any`).
+///
+/// Blocking receives wait for up to [`timeout_ms`](PcapFlags::timeout_ms) for any
+/// device, unless [`nonblocking`](PcapFlags::nonblocking) is set. The merged capture
+/// only receives: open a [`Sock`] or [`TxSock`] on the device to send.
+///
+/// ```ignore
+/// let socket = pcap::MultiSock::open(&["eth0", "eth1", "wlan0"], PcapFlags::default())?;
+/// while let Ok((payload, meta)) = socket.recv() {
+///     println!("{} bytes on ifindex {:?}", payload.len(), meta.ifindex);
+/// }
+/// ```
+pub struct MultiSock {
+    ctx: PcapContext,
+    sources: Vec<Source>,
+    /// Watches the selectable descriptors of every source.
+    epoll: OwnedFd,
+    /// Source to read first.
+    next: Cell<usize>,
+    timeout: Duration,
+    nonblocking: bool,
+}
+
+/// A device of a [`MultiSock`].
+struct Source {
+    sock: Sock,
+    ifindex: Option<u32>,
+}
+
+impl MultiSock {
+    /// Opens live captures on `devices`, configured with `flags`.
+    ///
+    /// Fails with `InvalidInput` if `devices` is empty or names a capture file or a
+    /// remote capture.
+    pub fn open<S: AsRef<str>>(devices: &[S], flags: PcapFlags) -> Result<Self> {
+        if devices.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "no device to capture from",
+            )
+            .into());
+        }
+        let fd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        // SAFETY: `fd` was just created and is owned by nothing else.
+        let epoll = unsafe { OwnedFd::from_raw_fd(fd) };
+        let member_flags = PcapFlags {
+            nonblocking: true,
+            ..flags.clone()
+        };
+        let mut ctx: Option<PcapContext> = None;
+        let mut sources = Vec::with_capacity(devices.len());
+        for device in devices {
+            let device = device.as_ref();
+            let mut sock = Sock::create(device, None, member_flags.clone())?;
+            if !matches!(&*sock.inner.borrow(), PcapInner::Live(_)) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("{device} is not a local device"),
+                )
+                .into());
+            }
+            // Tokens of every source are released to the same pool.
+            match &ctx {
+                Some(ctx) => sock.ctx = ctx.clone(),
+                None => ctx = Some(sock.ctx.clone()),
             }
-            let mut cap = inactive.open().map_err(crate::errors::Error::from)?;
+            let mut event = libc::epoll_event {
+                events: libc::EPOLLIN as u32,
+                u64: sources.len() as u64,
+            };
+            let rc = unsafe { libc::epoll_ctl(fd, libc::EPOLL_CTL_ADD, sock.fd, &mut event) };
+            if rc != 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            let ifindex = (device != "any").then(|| netdev::ifindex(device).ok()).flatten();
+            sources.push(Source { sock, ifindex });
+        }
+        Ok(Self {
+            ctx: ctx.expect("at least one device was opened"),
+            sources,
+            epoll,
+            next: Cell::new(0),
+            timeout: match flags.timeout_ms {
+                // libpcap blocks forever with a zero timeout.
+                ..=0 => Duration::MAX,
+                ms => Duration::from_millis(ms as u64),
+            },
+            nonblocking: flags.nonblocking,
+        })
+    }
+
+    /// Returns the names of the devices, in the order they were opened.
+    pub fn devices(&self) -> impl Iterator<Item = &str> + '_ {
+        self.sources.iter().filter_map(|source| source.sock.device.as_deref())
+    }
+
+    /// Returns the counters of every device, summed.
+    pub fn stats(&self) -> Result<Stats> {
+        let mut total = Stats::default();
+        for source in &self.sources {
+            let stats = source.sock.stats()?;
+            total.rx_packets += stats.rx_packets;
+            total.rx_bytes += stats.rx_bytes;
+            total.rx_dropped += stats.rx_dropped;
+        }
+        Ok(total)
+    }
+
+    /// Returns the occupancy of the buffer pool the devices share, see
+    /// [`Sock::pool_stats`].
+    pub fn pool_stats(&self) -> PoolStats {
+        self.ctx.pool_stats()
+    }
 
-            if let Some(expr) = flags.filter.as_deref() {
-                // Optimize=true, netmask=0 lets libpcap query it
-                cap.filter(expr, true).map_err(crate::errors::Error::from)?;
+    /// Returns the `pcap_stats` counters of every device, summed.
+    pub fn kernel_stats(&self) -> Result<KernelStats> {
+        let mut total = KernelStats::default();
+        for source in &self.sources {
+            let stats = source.sock.kernel_stats()?;
+            total.received += stats.received;
+            total.dropped += stats.dropped;
+            total.if_dropped += stats.if_dropped;
+        }
+        Ok(total)
+    }
+
+    /// Iterates over the sources, starting with the one to read first.
+    fn sources(&self) -> impl Iterator<Item = (usize, &Source)> + '_ {
+        let start = self.next.get();
+        let count = self.sources.len();
+        (0..count).map(move |i| {
+            let idx = (start + i) % count;
+            (idx, &self.sources[idx])
+        })
+    }
+
+    /// Reads a burst from each source into `out`, up to `budget` packets, without
+    /// blocking.
+    fn poll_sources(&self, budget: usize, out: &mut Vec<(Token, Meta)>) -> Result<()> {
+        for (idx, source) in self.sources() {
+            if out.len() >= budget {
+                break;
+            }
+            let start = out.len();
+            match source.sock.recv_burst(budget - start, out) {
+                Ok(_) => {}
+                Err(e) if out.is_empty() => return Err(e),
+                Err(_) => break,
             }
+            for (_, meta) in &mut out[start..] {
+                meta.ifindex = meta.ifindex.or(source.ifindex);
+            }
+            self.next.set((idx + 1) % self.sources.len());
+        }
+        Ok(())
+    }
 
-            PcapInner::Live(cap)
-        };
+    /// Waits for any source to become readable, returning `false` on timeout.
+    fn wait(&self) -> Result<bool> {
+        Ok(poll::wait_readable(self.epoll.as_raw_fd(), self.timeout)?)
+    }
+}
 
-        let inner = RefCell::new(inner);
+impl RxSocket for MultiSock {
+    type Context = PcapContext;
+    type Metadata = Meta;
+
+    fn recv_token(&self) -> Result<(Token, Self::Metadata)> {
+        loop {
+            match self.try_recv_token() {
+                Err(crate::errors::Error::WouldBlock) if !self.nonblocking => {
+                    if !self.wait()? {
+                        return Err(crate::errors::Error::Pcap(pcap::Error::TimeoutExpired));
+                    }
+                }
+                res => return res,
+            }
+        }
+    }
+
+    fn try_recv_token(&self) -> Result<(Token, Self::Metadata)> {
+        for (idx, source) in self.sources() {
+            match source.sock.recv_token() {
+                Err(crate::errors::Error::WouldBlock) => continue,
+                Ok((token, mut meta)) => {
+                    meta.ifindex = meta.ifindex.or(source.ifindex);
+                    self.next.set((idx + 1) % self.sources.len());
+                    return Ok((token, meta));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(crate::errors::Error::WouldBlock)
+    }
+
+    fn recv_batch(&self, budget: usize) -> Result<Vec<(Token, Self::Metadata)>> {
+        let mut batch = Vec::with_capacity(budget);
+        loop {
+            self.poll_sources(budget, &mut batch)?;
+            if !batch.is_empty() || budget == 0 {
+                return Ok(batch);
+            }
+            if self.nonblocking {
+                return Err(crate::errors::Error::WouldBlock);
+            }
+            if !self.wait()? {
+                return Err(crate::errors::Error::Pcap(pcap::Error::TimeoutExpired));
+            }
+        }
+    }
+
+    fn dispatch<F>(&self, budget: usize, mut f: F) -> Result<usize>
+    where
+        F: FnMut(Payload<'_, Self::Context>, Self::Metadata),
+    {
+        let mut batch = Vec::with_capacity(budget.min(1024));
+        self.poll_sources(budget, &mut batch)?;
+        let count = batch.len();
+        for (token, meta) in batch {
+            f(token.consume(&self.ctx), meta);
+        }
+        Ok(count)
+    }
+
+    fn set_filter(&self, expr: &str) -> Result<()> {
+        self.sources.iter().try_for_each(|source| source.sock.set_filter(expr))
+    }
 
-        Ok(Self { ctx, inner })
+    fn dropped(&self) -> Dropped {
+        let no_buffers = self.sources.iter().map(|source| source.sock.no_buffers.get()).sum();
+        Dropped {
+            no_buffers,
+            ..Dropped::default()
+        }
     }
 
     fn context(&self) -> &Self::Context {
         &self.ctx
     }
-}
\ No newline at end of file
+}
+
+impl AsRawFd for MultiSock {
+    fn as_raw_fd(&self) -> RawFd {
+        self.epoll.as_raw_fd()
+    }
+}
+
+// -------- Dumper -------------------------------------------------------------------
+
+/// Writes packets to a pcap capture file with `pcap_dump`, along with the timestamp and
+/// the original length from their metadata.
+///
+/// ```ignore
+/// let mut dumper = pcap::Dumper::create("capture.pcap")?;
+/// while let Ok((packet, meta)) = socket.recv() {
+///     dumper.dump(&packet, &meta);
+/// }
+/// dumper.flush()?;
+/// ```
+///
+/// Files hold Ethernet frames, with microsecond timestamps unless created with
+/// [`create_nanos`](Dumper::create_nanos); packets without a timestamp are written
+/// at 0. Writes are buffered: they reach the file on
+/// [`flush`](Dumper::flush) or when the dumper is dropped. A dumper is also a
+/// [`MirrorSink`](api::MirrorSink), so that [`Socket::dump_to`] can record everything a
+/// socket of any backend receives.
+///
+/// A dumper created with [`rotating`](Dumper::rotating) spreads the packets over a
+/// series of files, see [`Rotation`].
+pub struct Dumper {
+    savefile: Savefile,
+    packets: u64,
+    nanos: bool,
+    rotator: Option<Rotator>,
+}
+
+impl Dumper {
+    /// Creates the capture file `path`, truncating it if it exists.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(create_savefile(path.as_ref(), false)?, false))
+    }
+
+    /// Creates the capture file `path` with nanosecond timestamps, as needed to keep
+    /// the precision of live captures with
+    /// [`nanosecond_timestamps`](PcapFlags::nanosecond_timestamps) or of the other
+    /// backends.
+    pub fn create_nanos(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(create_savefile(path.as_ref(), true)?, true))
+    }
+
+    /// Creates a series of capture files named after `template`, moving on to the next
+    /// one as set by `rotation`.
+    ///
+    /// The template goes through `strftime(3)` with the local time at which each file
+    /// is created, e.g. `capture-%Y%m%d-%H%M%S.pcap`. When rotating by size or packet
+    /// count, or over a fixed number of files, the index of the file is appended to
+    /// the name, as tcpdump does: `capture.pcap0`, `capture.pcap1`...
+    ///
+    /// ```ignore
+    /// let rotation = Rotation {
+    ///     max_bytes: Some(100 << 20),
+    ///     max_files: Some(10),
+    ///     ..Default::default()
+    /// };
+    /// let mut dumper = pcap::Dumper::rotating("/var/tmp/trace.pcap", rotation)?
+    ///     .on_rotate(|path| compress(path));
+    /// ```
+    pub fn rotating(template: &str, rotation: Rotation) -> Result<Self> {
+        Self::open_rotating(template, rotation, false)
+    }
+
+    /// Creates a series of capture files with nanosecond timestamps, see
+    /// [`rotating`](Dumper::rotating).
+    pub fn rotating_nanos(template: &str, rotation: Rotation) -> Result<Self> {
+        Self::open_rotating(template, rotation, true)
+    }
+
+    fn open_rotating(template: &str, rotation: Rotation, nanos: bool) -> Result<Self> {
+        let mut rotator = Rotator {
+            template: template.to_owned(),
+            rotation,
+            path: PathBuf::new(),
+            index: 0,
+            bytes: 0,
+            packets: 0,
+            opened: Instant::now(),
+            on_rotate: None,
+        };
+        let savefile = rotator.open(nanos)?;
+        let mut dumper = Self::new(savefile, nanos);
+        dumper.rotator = Some(rotator);
+        Ok(dumper)
+    }
+
+    /// Calls `f` with the path of each file of a rotating dumper once it is complete
+    /// and closed, e.g. to compress or upload it. The last file is handed to `f` by
+    /// [`close`](Dumper::close).
+    pub fn on_rotate(mut self, f: impl FnMut(&Path) + Send + 'static) -> Self {
+        if let Some(rotator) = &mut self.rotator {
+            rotator.on_rotate = Some(Box::new(f));
+        }
+        self
+    }
+
+    /// Opens the capture file `path` to add packets at its end, creating it if needed.
+    ///
+    /// libpcap refuses to append to a file with nanosecond timestamps: use
+    /// [`append_nanos`](Dumper::append_nanos) for those.
+    pub fn append(path: impl AsRef<Path>) -> Result<Self> {
+        let savefile = Capture::dead(Linktype::ETHERNET)?.savefile_append(path)?;
+        Ok(Self::new(savefile, false))
+    }
+
+    /// Opens the capture file `path`, with nanosecond timestamps, to add packets at its
+    /// end, creating it if needed.
+    pub fn append_nanos(path: impl AsRef<Path>) -> Result<Self> {
+        let dead = Capture::dead_with_precision(Linktype::ETHERNET, Precision::Nano)?;
+        Ok(Self::new(dead.savefile_append(path)?, true))
+    }
+
+    fn new(savefile: Savefile, nanos: bool) -> Self {
+        Self {
+            savefile,
+            packets: 0,
+            nanos,
+            rotator: None,
+        }
+    }
+
+    /// Writes a packet received with `meta`.
+    ///
+    /// If the next file of a rotating dumper cannot be created, the packet goes to the
+    /// current one; [`try_dump`](Dumper::try_dump) reports the error instead.
+    pub fn dump<M: Metadata>(&mut self, packet: &[u8], meta: &M) {
+        let _ = self.rotate(packet.len());
+        self.write(packet, meta);
+    }
+
+    /// Writes a packet received with `meta`, failing if the rotation to the next file
+    /// fails; the packet is not written then.
+    pub fn try_dump<M: Metadata>(&mut self, packet: &[u8], meta: &M) -> Result<()> {
+        self.rotate(packet.len())?;
+        self.write(packet, meta);
+        Ok(())
+    }
+
+    fn write<M: Metadata>(&mut self, packet: &[u8], meta: &M) {
+        let header = packet_header(packet, meta, self.nanos);
+        self.savefile.write(&Packet::new(&header, packet));
+        self.packets += 1;
+        if let Some(rotator) = &mut self.rotator {
+            rotator.bytes += (PCAP_RECORD_HLEN + packet.len()) as u64;
+            rotator.packets += 1;
+        }
+    }
+
+    /// Moves on to the next file if the current one is complete before a packet of
+    /// `len` bytes.
+    fn rotate(&mut self, len: usize) -> Result<()> {
+        let Some(rotator) = &mut self.rotator else {
+            return Ok(());
+        };
+        if !rotator.is_due(len) {
+            return Ok(());
+        }
+        let old = rotator.path.clone();
+        let savefile = rotator.open(self.nanos)?;
+        // Dropping the old savefile flushes and closes it.
+        drop(std::mem::replace(&mut self.savefile, savefile));
+        if let Some(f) = &mut rotator.on_rotate {
+            f(&old);
+        }
+        Ok(())
+    }
+
+    /// Flushes and closes the file, handing it to the [`on_rotate`](Dumper::on_rotate)
+    /// callback of a rotating dumper.
+    pub fn close(mut self) -> Result<()> {
+        self.flush()?;
+        let Self {
+            savefile, rotator, ..
+        } = self;
+        drop(savefile);
+        if let Some(Rotator {
+            path,
+            on_rotate: Some(mut f),
+            ..
+        }) = rotator
+        {
+            f(&path);
+        }
+        Ok(())
+    }
+
+    /// Returns the path of the current file of a rotating dumper.
+    pub fn path(&self) -> Option<&Path> {
+        self.rotator.as_ref().map(|r| r.path.as_path())
+    }
+
+    /// Writes the buffered packets to the file.
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.savefile.flush()?)
+    }
+
+    /// Returns the number of packets written.
+    pub fn packets(&self) -> u64 {
+        self.packets
+    }
+}
+
+/// When a rotating [`Dumper`] closes its file and moves on to the next one, like the
+/// `-C`, `-G` and `-W` options of tcpdump. A file is complete as soon as one of the
+/// limits is reached; none set means a single file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Rotation {
+    /// Size of a file, in bytes, that the next packet may not exceed (`-C`). A file
+    /// always holds at least one packet.
+    pub max_bytes: Option<u64>,
+    /// Time after which the next file is started (`-G`), counted from the creation of
+    /// the current one and checked when a packet arrives.
+    pub max_duration: Option<Duration>,
+    /// Number of packets per file.
+    pub max_packets: Option<u64>,
+    /// Number of files to cycle through, overwriting the oldest (`-W`).
+    pub max_files: Option<usize>,
+}
+
+impl Rotation {
+    /// Whether the index of the file is appended to its name.
+    fn numbered(&self) -> bool {
+        self.max_bytes.is_some() || self.max_packets.is_some() || self.max_files.is_some()
+    }
+}
+
+/// Size of the global header of a pcap file.
+const PCAP_FILE_HLEN: usize = 24;
+/// Size of the record header of a packet in a pcap file.
+const PCAP_RECORD_HLEN: usize = 16;
+
+/// State of a rotating [`Dumper`].
+struct Rotator {
+    template: String,
+    rotation: Rotation,
+    /// Path of the current file.
+    path: PathBuf,
+    /// Number of files created so far.
+    index: usize,
+    /// Size of the current file.
+    bytes: u64,
+    /// Packets in the current file.
+    packets: u64,
+    opened: Instant,
+    on_rotate: Option<RotateHook>,
+}
+
+/// Callback of [`Dumper::on_rotate`].
+type RotateHook = Box<dyn FnMut(&Path) + Send>;
+
+impl Rotator {
+    /// Whether the current file is complete before a packet of `len` bytes.
+    fn is_due(&self, len: usize) -> bool {
+        let Rotation {
+            max_bytes,
+            max_duration,
+            max_packets,
+            ..
+        } = self.rotation;
+        let next = self.bytes + (PCAP_RECORD_HLEN + len) as u64;
+        self.packets > 0
+            && (max_bytes.is_some_and(|max| next > max)
+                || max_duration.is_some_and(|max| self.opened.elapsed() >= max)
+                || max_packets.is_some_and(|max| self.packets >= max))
+    }
+
+    /// Creates the next file.
+    fn open(&mut self, nanos: bool) -> Result<Savefile> {
+        let index = match self.rotation.max_files {
+            Some(max) => self.index % max.max(1),
+            None => self.index,
+        };
+        let path = rotated_path(&self.template, &self.rotation, index, libc_now())?;
+        let savefile = create_savefile(&path, nanos)?;
+        self.path = path;
+        self.index += 1;
+        self.bytes = PCAP_FILE_HLEN as u64;
+        self.packets = 0;
+        self.opened = Instant::now();
+        Ok(savefile)
+    }
+}
+
+/// Creates the capture file `path`, holding Ethernet frames.
+fn create_savefile(path: &Path, nanos: bool) -> Result<Savefile> {
+    let dead = if nanos {
+        Capture::dead_with_precision(Linktype::ETHERNET, Precision::Nano)?
+    } else {
+        Capture::dead(Linktype::ETHERNET)?
+    };
+    Ok(dead.savefile(path)?)
+}
+
+/// Returns the current time in seconds since the Unix epoch.
+fn libc_now() -> libc::time_t {
+    // SAFETY: `time` accepts a null pointer.
+    unsafe { libc::time(std::ptr::null_mut()) }
+}
+
+/// Expands the `strftime(3)` specifiers of `template` with the local time `time`, and
+/// appends `index` if the files of `rotation` are numbered.
+fn rotated_path(
+    template: &str,
+    rotation: &Rotation,
+    index: usize,
+    time: libc::time_t,
+) -> Result<PathBuf> {
+    let invalid = || {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid file name template")
+    };
+    let format = CString::new(template).map_err(|_| invalid())?;
+    // SAFETY: zeroed `tm` is valid and filled in by `localtime_r`.
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    let mut buf = vec![0u8; template.len() * 4 + 256];
+    let len =
+        unsafe { libc::strftime(buf.as_mut_ptr().cast(), buf.len(), format.as_ptr(), &tm) };
+    if len == 0 && !template.is_empty() {
+        return Err(invalid().into());
+    }
+    buf.truncate(len);
+    let mut name = String::from_utf8(buf).map_err(|_| invalid())?;
+    if rotation.numbered() {
+        // Pad the index to the width of the largest one, so that the names sort.
+        let width = rotation
+            .max_files
+            .map_or(1, |max| max.saturating_sub(1).to_string().len());
+        name.push_str(&format!("{index:0width$}"));
+    }
+    Ok(PathBuf::from(name))
+}
+
+/// Returns the record header of `packet` in a capture file, with a timestamp in
+/// nanoseconds (in the `tv_usec` field) if the file has `nanos` precision.
+pub(crate) fn packet_header<M: Metadata>(packet: &[u8], meta: &M, nanos: bool) -> PacketHeader {
+    let ts = meta.timestamp().unwrap_or_default();
+    let frac = if nanos {
+        ts.subsec_nanos()
+    } else {
+        ts.subsec_nanos() / 1_000
+    };
+    PacketHeader {
+        ts: libc::timeval {
+            tv_sec: ts.as_secs() as libc::time_t,
+            tv_usec: frac as libc::suseconds_t,
+        },
+        caplen: packet.len() as u32,
+        len: meta.wirelen().max(packet.len() as u32),
+    }
+}
+
+// -------- Zero-copy TX -------------------------------------------------------------
+
+/// Sockets a [`TxSlot`] can hand its packet to.
+trait SendPacket {
+    fn send_packet(&self, packet: &[u8]) -> Result<()>;
+}
+
+impl SendPacket for Sock {
+    fn send_packet(&self, packet: &[u8]) -> Result<()> {
+        Socket::send(self, packet)
+    }
+}
+
+impl SendPacket for TxSock {
+    fn send_packet(&self, packet: &[u8]) -> Result<()> {
+        TxSocket::send(self, packet)
+    }
+}
+
+/// TX slot of the pcap backend.
+///
+/// libpcap has no TX ring to build packets in, so the slot is a heap buffer that
+/// `pcap_sendpacket` copies out on commit.
+pub struct TxSlot<'a> {
+    sock: &'a dyn SendPacket,
+    buf: Vec<u8>,
+    checksum: TxChecksum,
+}
+
+impl<'a> TxSlot<'a> {
+    fn new(sock: &'a dyn SendPacket, len: usize) -> Self {
+        Self {
+            sock,
+            buf: vec![0; len],
+            checksum: TxChecksum::NONE,
+        }
+    }
+}
+
+impl std::ops::Deref for TxSlot<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl std::ops::DerefMut for TxSlot<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.buf
+    }
+}
+
+impl crate::api::TxSlot for TxSlot<'_> {
+    fn commit(mut self, len: usize) -> Result<()> {
+        if len > self.buf.len() {
+            return Err(crate::errors::Error::TooBigPacket(len));
+        }
+        api::checksum::fill(&mut self.buf[..len], self.checksum);
+        self.sock.send_packet(&self.buf[..len])
+    }
+
+    fn set_checksum(&mut self, csum: TxChecksum) {
+        self.checksum = csum;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a pcap file holding `frames`, captured at 1 s, 2 s, ...
+    fn savefile(name: &str, frames: &[&[u8]]) -> std::path::PathBuf {
+        let mut data = Vec::new();
+        // Magic, version 2.4, thiszone, sigfigs, snaplen, Ethernet link type.
+        for word in [0xa1b2_c3d4u32, 0x0004_0002, 0, 0, 65535, 1] {
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+        for (i, frame) in frames.iter().enumerate() {
+            let len = frame.len() as u32;
+            for word in [i as u32 + 1, 0, len, len] {
+                data.extend_from_slice(&word.to_le_bytes());
+            }
+            data.extend_from_slice(frame);
+        }
+        let path = std::env::temp_dir().join(format!("nethuns-{}-{name}", std::process::id()));
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn interface_timestamps() {
+        let micros = Interface::new(LinkType::Ethernet, false);
+        assert_eq!(micros.timestamp(2, 5), Timestamp::from_nanos(2_000_005_000));
+        let nanos = Interface::new(LinkType::Ethernet, true);
+        assert_eq!(nanos.timestamp(2, 5), Timestamp::from_nanos(2_000_000_005));
+        // pcapng `if_tsresol` of 2^-10 seconds.
+        let binary = Interface {
+            linktype: LinkType::Ethernet,
+            ts_resolution: 1024,
+        };
+        assert_eq!(binary.timestamp_units(1536), Timestamp::from_nanos(1_500_000_000));
+    }
+
+    #[test]
+    fn matches_device_names() {
+        let device = |name: &str, description: Option<&str>, loopback| PcapDevice {
+            name: name.to_owned(),
+            description: description.map(str::to_owned),
+            up: true,
+            running: true,
+            loopback,
+            wireless: false,
+            addresses: Vec::new(),
+        };
+        let guid = "{6A3C8D52-7E0B-4F3A-9C1D-2B5E8F7A1C34}";
+        let devices = [
+            device(&format!("\\Device\\NPF_{guid}"), Some("Ethernet"), false),
+            device("\\Device\\NPF_Loopback", Some("Adapter for loopback"), true),
+        ];
+        let name = |spec: &str| match_device(&devices, spec).map(|d| d.name.clone());
+        let ethernet = Some(devices[0].name.clone());
+        assert_eq!(name("ethernet"), ethernet);
+        assert_eq!(name(guid), ethernet);
+        assert_eq!(name(&guid.to_lowercase()), ethernet);
+        let loopback = Some(devices[1].name.clone());
+        assert_eq!(name("lo"), loopback);
+        assert_eq!(name("\\Device\\NPF_Loopback"), loopback);
+        assert_eq!(name("wlan0"), None);
+    }
+
+    #[test]
+    fn encapsulates_for_link() {
+        let mut frame = vec![0xff; 6];
+        frame.extend_from_slice(&[2, 0, 0, 0, 0, 1]);
+        frame.extend_from_slice(&[0x81, 0x00, 0x00, 0x64, 0x08, 0x00]);
+        frame.extend_from_slice(&[0x45; 20]);
+
+        assert_eq!(*encapsulate(&frame, LinkType::Ethernet).unwrap(), frame[..]);
+        assert_eq!(*encapsulate(&frame, LinkType::Raw).unwrap(), [0x45; 20]);
+        let null = encapsulate(&frame, LinkType::Null).unwrap();
+        assert_eq!(null[..4], (libc::AF_INET as u32).to_ne_bytes());
+        assert_eq!(null[4..], [0x45; 20]);
+        let sll = encapsulate(&frame, LinkType::LinuxSll).unwrap();
+        assert_eq!(sll[..6], [0, 4, 0, 1, 0, 6]);
+        assert_eq!(sll[6..12], [2, 0, 0, 0, 0, 1]);
+        assert_eq!(sll[14..], [&[0x08, 0x00][..], &[0x45; 20]].concat());
+
+        // ARP has no raw IP encapsulation.
+        frame[16..18].copy_from_slice(&[0x08, 0x06]);
+        assert!(encapsulate(&frame, LinkType::Raw).is_err());
+        assert!(encapsulate(&frame, LinkType::Radiotap).is_err());
+        assert!(encapsulate(&frame[..10], LinkType::Raw).is_err());
+    }
+
+    #[test]
+    fn uncooks_sll2() {
+        let mut frame = vec![0x08, 0x00, 0, 0, 0, 0, 0, 3, 0, 1, 4, 6];
+        frame.extend_from_slice(&[2, 0, 0, 0, 0, 1, 0, 0]);
+        frame.extend_from_slice(&[0x45; 20]);
+        let len = frame.len() as u32;
+        let mut meta = Meta {
+            timestamp: None,
+            len,
+            caplen: len,
+            vlan: None,
+            hw_timestamp: false,
+            fcs: false,
+            linktype: LinkType::LinuxSll2,
+            ifindex: None,
+            pkt_type: None,
+        };
+
+        assert_eq!(uncook(&mut frame, len, &mut meta, false), len);
+        assert_eq!(meta.ifindex, Some(3));
+        assert_eq!(meta.pkt_type, Some(PacketType::Outgoing));
+        assert_eq!(meta.linktype, LinkType::LinuxSll2);
+
+        assert_eq!(uncook(&mut frame, len, &mut meta, true), len - 6);
+        assert_eq!(frame[..14], [0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 1, 0x08, 0x00]);
+        assert_eq!(frame[14..34], [0x45; 20]);
+        assert_eq!((meta.len, meta.caplen), (len - 6, len - 6));
+        assert_eq!(meta.linktype, LinkType::Ethernet);
+    }
+
+    #[test]
+    fn names_rotated_files() {
+        let single = Rotation {
+            max_duration: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let path = rotated_path("trace-%Y-100%%.pcap", &single, 3, 0).unwrap();
+        let name = path.to_str().unwrap();
+        assert!(name.starts_with("trace-19") && name.ends_with("-100%.pcap"), "{name}");
+
+        let numbered = Rotation {
+            max_bytes: Some(1 << 20),
+            max_files: Some(12),
+            ..Default::default()
+        };
+        let path = rotated_path("trace.pcap", &numbered, 3, 0).unwrap();
+        assert_eq!(path, Path::new("trace.pcap03"));
+    }
+
+    #[test]
+    fn pool_grows_to_limit() {
+        let ctx = PcapContext::new(64, 2, PoolPolicy::Grow(3));
+        let bufs: Vec<_> = (0..3).map(|_| ctx.buffer().unwrap()).collect();
+        assert!(ctx.buffer().is_none());
+        assert_eq!(ctx.available(), 0);
+        for ptr in &bufs {
+            ctx.release(BufferDesc(*ptr as usize));
+        }
+        let stats = ctx.pool_stats();
+        assert_eq!((stats.allocated, stats.in_use, stats.peak_in_use), (3, 0, 3));
+        assert_eq!((stats.limit, stats.exhausted), (3, 1));
+        assert_eq!(ctx.available(), 3);
+
+        let ctx = PcapContext::new(64, 2, PoolPolicy::Fixed);
+        let _bufs = [ctx.buffer().unwrap(), ctx.buffer().unwrap()];
+        assert!(ctx.buffer().is_none());
+        assert_eq!(ctx.pool_stats().allocated, 2);
+    }
+
+    #[test]
+    fn batch_stops_when_pool_is_full() {
+        let frames: Vec<[u8; 60]> = (1..=4).map(|i| [i; 60]).collect();
+        let frames: Vec<&[u8]> = frames.iter().map(|f| &f[..]).collect();
+        let path = savefile("pool-full.pcap", &frames);
+        let flags = PcapFlags {
+            buffer_count: 2,
+            pool_policy: PoolPolicy::OverwriteOldest,
+            ..PcapFlags::default()
+        };
+        let socket = Sock::open_file(&path, flags).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        for first in [1, 3] {
+            let packets: Vec<_> = socket
+                .recv_batch(8)
+                .unwrap()
+                .into_iter()
+                .map(|(token, _)| token.consume(socket.context()))
+                .collect();
+            assert_eq!(packets.len(), 2);
+            assert_eq!((packets[0][0], packets[1][0]), (first, first + 1));
+        }
+        assert_eq!(socket.pool_stats().exhausted, 0);
+        assert_eq!(socket.dropped().no_buffers, 0);
+    }
+
+    #[test]
+    fn dispatch_keeps_flow_order() {
+        use crate::api::dispatch::{Dispatcher, FlowHasher};
+
+        // UDP frames of 4 flows, told apart by their source port, numbered per flow.
+        let frames: Vec<Vec<u8>> = (0..64u8)
+            .map(|i| {
+                let mut frame = vec![0u8; 60];
+                frame[12..14].copy_from_slice(&[0x08, 0x00]);
+                frame[14] = 0x45;
+                frame[23] = 17;
+                frame[26..34].copy_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+                frame[34..36].copy_from_slice(&(1000 + (i % 4) as u16).to_be_bytes());
+                frame[42] = i / 4;
+                frame
+            })
+            .collect();
+        let frames: Vec<&[u8]> = frames.iter().map(|f| &f[..]).collect();
+        let path = savefile("dispatch.pcap", &frames);
+        let flags = PcapFlags {
+            buffer_count: 64,
+            pool_policy: PoolPolicy::Fixed,
+            ..PcapFlags::default()
+        };
+        let socket = Sock::open_file(&path, flags).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let (dispatcher, workers) = Dispatcher::new(3, 64, FlowHasher::Symmetric);
+        let threads: Vec<_> = workers
+            .into_iter()
+            .map(|worker| {
+                std::thread::spawn(move || {
+                    let mut received = Vec::new();
+                    while let Ok((packet, _)) = worker.recv() {
+                        received.push((packet[35], packet[42]));
+                    }
+                    received
+                })
+            })
+            .collect();
+        while dispatcher.poll(&socket, 16).unwrap_or(0) > 0 {}
+        assert_eq!(dispatcher.dropped(), 0);
+        drop(dispatcher);
+
+        let mut total = 0;
+        for thread in threads {
+            let received = thread.join().unwrap();
+            total += received.len();
+            for flow in 0..4 {
+                let seqs: Vec<u8> = received
+                    .iter()
+                    .filter(|(port, _)| *port == (1000 + flow) as u8)
+                    .map(|&(_, seq)| seq)
+                    .collect();
+                assert!(seqs.is_empty() || seqs == (0..16).collect::<Vec<_>>());
+            }
+        }
+        assert_eq!(total, 64);
+        assert_eq!(socket.pool_stats().in_use, 0);
+    }
+
+    #[test]
+    fn kernel_counters_wrap() {
+        let counters = KernelCounters::default();
+        let stat = |received, dropped| pcap::Stat {
+            received,
+            dropped,
+            if_dropped: 0,
+        };
+        counters.update(stat(u32::MAX - 1, 10));
+        let total = counters.update(stat(3, 12));
+        assert_eq!(total.received, u32::MAX as u64 + 4);
+        assert_eq!(total.dropped, 12);
+        assert_eq!(total.if_dropped, 0);
+    }
+
+    #[test]
+    fn reads_capture_file() {
+        let path = savefile("reads.pcap", &[&[1; 60], &[2; 64]]);
+        let socket = Sock::open_file(&path, PcapFlags::default()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let (packet, meta) = socket.recv().unwrap();
+        assert_eq!(&*packet, &[1; 60]);
+        assert_eq!(meta.timestamp, Some(Timestamp::from_micros(1_000_000)));
+        assert_eq!(meta.linktype, LinkType::Ethernet);
+        assert_eq!(socket.datalink(), LinkType::Ethernet);
+        drop(packet);
+        let (packet, _) = socket.recv().unwrap();
+        assert_eq!(&*packet, &[2; 64]);
+        drop(packet);
+        assert!(matches!(
+            socket.recv(),
+            Err(crate::errors::Error::Pcap(pcap::Error::NoMorePackets))
+        ));
+
+        let Err(crate::errors::Error::Generic(err)) = socket.send(&[0; 60]) else {
+            panic!("capture files cannot send");
+        };
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+        let err = socket.poll_fd().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+    }
+
+    #[cfg(all(feature = "gzip", feature = "zstd"))]
+    #[test]
+    fn reads_compressed_files() {
+        use std::io::Write;
+
+        let path = savefile("compressed.pcap", &[&[1; 60]]);
+        let plain = std::fs::read(&path).unwrap();
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        gzip.write_all(&plain).unwrap();
+        for data in [gzip.finish().unwrap(), zstd::encode_all(&plain[..], 0).unwrap()] {
+            std::fs::write(&path, data).unwrap();
+            let socket = Sock::open_file(&path, PcapFlags::default()).unwrap();
+            let (packet, _) = socket.recv().unwrap();
+            assert_eq!(&*packet, &[1; 60]);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn interrupter_outlives_socket() {
+        let path = savefile("interrupt.pcap", &[&[1; 60]]);
+        let socket = Sock::open_file(&path, PcapFlags::default()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Capture files have no libpcap handle to break out of.
+        let interrupter = socket.interrupter();
+        interrupter.interrupt();
+        assert!(socket.recv().is_ok());
+        drop(socket);
+        interrupter.interrupt();
+    }
+}
@@ -0,0 +1,21 @@
+//! Internal tracing macros, compiled out unless the `tracing` feature is enabled.
+//!
+//! Control paths, ring syncs, flushes and errors use [`trace_event!`]; anything that
+//! may run once per packet uses [`trace_verbose!`], which also needs
+//! `tracing-verbose`.
+
+/// Emits a `tracing` event at the given level, e.g. `trace_event!(debug, "...")`.
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        ::tracing::$level!($($arg)+);
+    };
+}
+
+/// Emits a `trace`-level event from a per-packet path.
+macro_rules! trace_verbose {
+    ($($arg:tt)+) => {
+        #[cfg(feature = "tracing-verbose")]
+        ::tracing::trace!($($arg)+);
+    };
+}